@@ -0,0 +1,69 @@
+#type vertex
+#version 410 core
+
+layout (location=0) in vec3 pos;
+
+uniform mat4 proj;
+uniform mat4 view;
+
+//the direction from the camera, which the sky is looked up by
+out vec3 dir;
+
+void main(){
+    dir = pos;
+    //only the camera's rotation, so the sky never gets any closer
+    vec4 clip = proj * mat4(mat3(view)) * vec4(pos, 1.0);
+    //as far away as anything can be
+    gl_Position = clip.xyww;
+}
+
+#type fragment
+#version 410 core
+
+//colour straight up and at the horizon, from the time of day
+uniform vec3 zenith;
+uniform vec3 horizon;
+uniform vec3 sun_dir;
+uniform vec3 moon_dir;
+//0 at night to 1 during the day
+uniform float daylight;
+//what the world is multiplied by, so the clouds darken with it
+uniform float sky_light;
+//turns a direction back to where it was at sunrise, so the stars go around with the moon
+uniform mat3 sky_rotation;
+
+//clouds, with alpha
+uniform samplerCube day_sky;
+//stars
+uniform samplerCube night_sky;
+
+in vec3 dir;
+
+out vec4 Color;
+
+//cosines of the angle from the middle of the sun and moon to their edge
+const float SUN_SIZE = 0.9990;
+const float MOON_SIZE = 0.9994;
+
+void main(){
+      vec3 d = normalize(dir);
+
+      //fades to the horizon colour and stays there below it
+      vec3 sky = mix(horizon, zenith, sqrt(clamp(d.y, 0.0, 1.0)));
+
+      sky += texture(night_sky, sky_rotation * d).rgb * (1.0 - daylight) * step(0.0, d.y);
+
+      //the sun turns orange as it gets close to the horizon
+      vec3 sun_color = mix(vec3(1.0, 0.55, 0.25), vec3(1.0, 0.97, 0.85), clamp(sun_dir.y * 4.0, 0.0, 1.0));
+      float sun = dot(d, sun_dir);
+      sky += sun_color * pow(max(sun, 0.0), 200.0) * 0.5;
+      sky = mix(sky, sun_color, smoothstep(SUN_SIZE - 0.0004, SUN_SIZE, sun));
+
+      float moon = dot(d, moon_dir);
+      sky = mix(sky, vec3(0.85, 0.87, 0.95), smoothstep(MOON_SIZE - 0.0003, MOON_SIZE, moon));
+
+      vec4 clouds = texture(day_sky, d);
+      sky = mix(sky, clouds.rgb * sky_light, clouds.a);
+
+      Color = vec4(sky, 1.0);
+}
\ No newline at end of file
@@ -3,17 +3,33 @@
 
 layout (location=0) in uint Core;
 layout (location=1) in uint Dims;
+layout (location=2) in uint Detail;
+//0xRRGGBB the texture is multiplied by, for grass and leaves coloured by their climate
+layout (location=3) in uint Tint;
 
 uniform mat4 proj;
 uniform mat4 view;
 
 uniform vec2 chunk_pos;
-uniform float atlas_cols;
+//row 0 is (u, v, width, height) of every texture ID on the atlas, from its top left.
+//row 1 is (frames, v distance between frames, frame time, interpolate) for animated textures
+uniform sampler2D uv_table;
+//seconds the world has been running, for animated textures
+uniform float time;
+//blocks per mesh cell. 1 for full detail chunks, 2 or 4 for downsampled ones
+uniform float lod_scale;
 
 out vec2 fuv_top;
 out vec2 fuv_width;
 out float faceID;
-out float tile_size;
+out vec2 tile_size;
+//how far to move down the atlas to the next frame, and how much of it to blend in
+flat out float next_frame;
+flat out float frame_blend;
+//clockwise quarter turns of the texture
+flat out int rotation;
+//blended between the corners of merged faces, so colours fade from column to column
+out vec3 tint;
 
 const vec2 offsets[4] = vec2[4](
     vec2(0, 0), vec2(1, 0),
@@ -21,30 +37,55 @@ const vec2 offsets[4] = vec2[4](
 );
 
 void main(){
-    // X (4), Y(4) ,   Z(8)   , TexId(8) , QuadId(2),  FaceId(3)
-    // 0000 | 0000 | 00000000 | 00000000 | 00 | 000
+    // X (4), Y(4) ,   Z(8)   , TexId(8) , QuadId(2),  FaceId(3), Animated(1), Rotation(2)
+    // 0000 | 0000 | 00000000 | 00000000 | 00 | 000 | 0 | 00
 
-    float x = float(Core & 0xFu) + chunk_pos.x * 15.0;
-    float z = float( (Core >> 4u) & 0xFu ) + chunk_pos.y * 15.0;
-    float y = float( (Core >> 8u) & 0xFFu );
+    //downsampled cells on the chunk's edge can poke past it, so clamp them back in
+    float x = min(float(Core & 0xFu) * lod_scale, 15.0) + chunk_pos.x * 15.0;
+    float z = min(float( (Core >> 4u) & 0xFu ) * lod_scale, 15.0) + chunk_pos.y * 15.0;
+    float y = float( (Core >> 8u) & 0xFFu ) * lod_scale;
 
-    vec2 fdims = vec2(float(Dims & 0xFFFFu), float((Dims >> 16u) & 0xFFFFu));
+    //scale the quad dimensions too so textures still repeat once per block
+    vec2 fdims = vec2(float(Dims & 0xFFFFu), float((Dims >> 16u) & 0xFFFFu)) * lod_scale;
 
     uint texID = (Core >> 16u) & 0xFFu; //8 bits
     uint quadID = (Core >> 24u) & 0x3u; //2 bits
     faceID = float((Core >> 26u) & 0x7u); //3 bits
+    rotation = int((Core >> 30u) & 0x3u); //2 bits
 
-    float row = floor(float(texID) / atlas_cols);
-    float col = float(texID % uint(atlas_cols));
-    float tile_dims = 1.0 / atlas_cols;
+    vec4 region = texelFetch(uv_table, ivec2(int(texID), 0), 0);
+    vec2 tile_dims = region.zw;
     tile_size = tile_dims;
 
-    vec2 top_left_uv = vec2(col * tile_dims, row * tile_dims);
+    vec2 top_left_uv = region.xy;
     //top_left_uv += offsets[quadID] * tile_dims;
     top_left_uv.y = 1.0 - top_left_uv.y;
+
+    next_frame = 0.0;
+    frame_blend = 0.0;
+    if (((Core >> 29u) & 0x1u) == 1u) {
+        vec4 animation = texelFetch(uv_table, ivec2(int(texID), 1), 0);
+        float frame = floor(time / animation.z);
+        float current = mod(frame, animation.x);
+        //frames go down the atlas image, which is down in v too since the image is flipped
+        top_left_uv.y -= current * animation.y;
+        next_frame = (mod(frame + 1.0, animation.x) - current) * animation.y;
+        frame_blend = animation.w * fract(time / animation.z);
+    }
     fuv_top = top_left_uv;
     fuv_width = offsets[quadID] * fdims * tile_dims;
 
+    //model vertices sit somewhere inside their block and say which part of the texture they show, both in 16ths
+    // X(5), Y(5), Z(5), U(5), V(5), Model(1)
+    if (((Detail >> 25u) & 0x1u) == 1u) {
+        x += float(Detail & 0x1Fu) / 16.0;
+        y += float((Detail >> 5u) & 0x1Fu) / 16.0;
+        z += float((Detail >> 10u) & 0x1Fu) / 16.0;
+        fuv_width = vec2(float((Detail >> 15u) & 0x1Fu), float((Detail >> 20u) & 0x1Fu)) / 16.0 * tile_dims;
+    }
+
+    tint = vec3(float((Tint >> 16u) & 0xFFu), float((Tint >> 8u) & 0xFFu), float(Tint & 0xFFu)) / 255.0;
+
     gl_Position = proj * view * vec4(x, y, z, 1.0);
 }
 
@@ -52,12 +93,17 @@ void main(){
 #version 410 core
 
 uniform sampler2D atlas;
-
+//how much of the sun reaches the world at this time of day
+uniform float sky_light;
 
 in vec2 fuv_top;
 in vec2 fuv_width;
-in float tile_size;
+in vec2 tile_size;
 in float faceID;
+flat in float next_frame;
+flat in float frame_blend;
+flat in int rotation;
+in vec3 tint;
 
 const float values[6] = float[6](
    0.2, 0.2, 1.0, 0.3, 0.2, 0.2
@@ -74,9 +120,20 @@ const vec3 normals[6] = vec3[6](
 out vec4 Color;
 
 void main(){
-      vec4 val = texture(atlas, vec2(fuv_top.x + mod(fuv_width.x, tile_size), fuv_top.y - mod(fuv_width.y, tile_size)));
+      //where we are in this block's copy of the texture, from 0 to 1
+      vec2 local = mod(fuv_width, tile_size) / tile_size;
+      //turn the texture clockwise by sampling it turned the other way
+      if (rotation == 1) local = vec2(local.y, 1.0 - local.x);
+      else if (rotation == 2) local = vec2(1.0 - local.x, 1.0 - local.y);
+      else if (rotation == 3) local = vec2(1.0 - local.y, local.x);
+
+      vec2 uv = vec2(fuv_top.x + local.x * tile_size.x, fuv_top.y - local.y * tile_size.y);
+      vec4 val = texture(atlas, uv);
+      if (frame_blend > 0.0) {
+            val = mix(val, texture(atlas, vec2(uv.x, uv.y - next_frame)), frame_blend);
+      }
       //vec4 val = texture(atlas, vec2(fuv_top.x, fuv_top.y));
       float mult = values[int(faceID)];
-      Color = vec4(val.x * mult, val.y * mult, val.z * mult, val.w);
+      Color = vec4(val.xyz * tint * mult * sky_light, val.w);
       //Color = vec4(0.6 * mult, 0.1 * mult, 0.3 * mult, 1.0);
 }
@@ -5,6 +5,7 @@ use std::rc::Rc;
 use crate::Dispatch;
 use crate::Event::event::*;
 use crate::Scene::sceneManager::SceneManager;
+use crate::World::contentPack::ContentPacks;
 use glium::Surface;
 use glium::glutin::Api;
 use glium::glutin::dpi::PhysicalPosition;
@@ -22,13 +23,16 @@ pub static mut WINDOW_SIZE: (u32, u32) = (800, 800);
 
 pub struct Application{
     EventBus: Rc<RefCell<Queue<Event>>>,
+    //Handed to the scene manager once the window is up
+    Packs: Option<ContentPacks>,
 
 }
 
 impl Application{
-    pub fn New() -> Self {
+    pub fn New(packs: ContentPacks) -> Self {
         Self {
             EventBus: Rc::new(RefCell::new(Queue::new())),
+            Packs: Some(packs),
         }
     }
 
@@ -74,14 +78,15 @@ impl Application{
         let mut delta = 0f32;
 
         let (eventLoop, display) = self.InitApp();
-        let mut sceneManager = SceneManager::New(&display);
+        let mut sceneManager = SceneManager::New(&display, self.Packs.take().unwrap());
 
         eventLoop.run(move |event, _, control_flow| {
 
             match event {
                 glutin::event::Event::RedrawRequested(_) => {
                     let mut target = display.draw();
-                    target.clear_color_and_depth((1.0, 1.0, 1.0, 1.0), 1.0);
+                    //the sky is drawn over the whole screen, so the colour only shows where nothing is drawn (like the main menu)
+                    target.clear_color_and_depth((0.0, 0.0, 0.0, 1.0), 1.0);
 
                     //get the delta time
                     now = std::time::SystemTime::now();
@@ -166,10 +171,12 @@ impl Application{
             
             //If the event was handled (aka the event was a window close event) or the excape key was pressed, exit the game
             if dispatcher.Handled {
+                sceneManager.Save();
                 *controlFlow = glutin::event_loop::ControlFlow::Exit;
                 return;
             }
             else if let Event::KeyPressed(KeyPressedEvent {Key: VirtualKeyCode::Escape, ..}) = ev {
+                sceneManager.Save();
                 *controlFlow = glutin::event_loop::ControlFlow::Exit;
                 return;
             }
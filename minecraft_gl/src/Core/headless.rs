@@ -0,0 +1,168 @@
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use crate::{World::{block::BlockRegistry, item::ItemRegistry, crafting::CraftingRegistry, smelting::SmeltingRegistry,
+                    world::{World, ChunkStats}, idMap::IdMap, contentPack::ContentPacks, ReadAttributes},
+            Scene::camera::Camera
+           };
+
+/*
+    Runs the world without a window or a GL context. The registries are read as usual but
+    no atlases are built, and the camera follows a list of waypoints instead of player input.
+    Meant for tests, profiling chunk generation and eventually a dedicated server
+*/
+pub struct HeadlessConfig {
+    pub Seed: u64,
+    pub RenderDistance: usize,
+    //Ticks per second. Zero runs the ticks back to back
+    pub TickRate: u32,
+    pub Ticks: u32,
+    //How often (in ticks) the chunk statistics are printed. Zero only prints the final report
+    pub ReportInterval: u32,
+    //Blocks per second the camera travels along the waypoints
+    pub CameraSpeed: f32,
+    //Points on the xz plane the camera visits in order
+    pub Waypoints: Vec<(f32, f32)>,
+    //Content packs loaded after the core pack, in order
+    pub Packs: Vec<PathBuf>,
+}
+
+impl Default for HeadlessConfig {
+    fn default() -> Self {
+        Self {
+            Seed: 0,
+            RenderDistance: 4,
+            TickRate: 20,
+            Ticks: 200,
+            ReportInterval: 20,
+            CameraSpeed: 10f32,
+            Waypoints: vec![(0f32, 0f32), (150f32, 0f32)],
+            Packs: Vec::new(),
+        }
+    }
+}
+
+impl HeadlessConfig {
+    //Flags: --seed N, --render-distance N, --tick-rate N, --ticks N, --report-interval N, --speed N, --waypoint X,Z (repeatable), --pack DIR (repeatable)
+    pub fn FromArgs(args: &[String]) -> Result<Self, String> {
+        let mut config = Self::default();
+        let mut waypoints: Vec<(f32, f32)> = Vec::new();
+
+        let mut iter = args.iter();
+        while let Some(arg) = iter.next() {
+            if arg == "--headless" {
+                continue;
+            }
+
+            let value = iter.next().ok_or(format!("Error! Missing a value for the {} flag!", arg))?;
+            let invalid = |e: &dyn std::fmt::Display| format!("Error! Invalid value '{}' for the {} flag! The error:\n{}", value, arg, e);
+
+            match arg.as_str() {
+                "--seed" => config.Seed = value.parse().map_err(|e| invalid(&e))?,
+                "--render-distance" => config.RenderDistance = value.parse().map_err(|e| invalid(&e))?,
+                "--tick-rate" => config.TickRate = value.parse().map_err(|e| invalid(&e))?,
+                "--ticks" => config.Ticks = value.parse().map_err(|e| invalid(&e))?,
+                "--report-interval" => config.ReportInterval = value.parse().map_err(|e| invalid(&e))?,
+                "--speed" => config.CameraSpeed = value.parse().map_err(|e| invalid(&e))?,
+                "--waypoint" => {
+                    let (x, z) = value.split_once(',').ok_or(invalid(&"expected a point in the form X,Z"))?;
+                    waypoints.push((x.trim().parse().map_err(|e| invalid(&e))?, z.trim().parse().map_err(|e| invalid(&e))?));
+                },
+                "--pack" => config.Packs.push(PathBuf::from(value)),
+                _ => return Err(format!("Error! Unknown headless flag {}!", arg))
+            }
+        }
+
+        if !waypoints.is_empty() {
+            config.Waypoints = waypoints;
+        }
+        Ok(config)
+    }
+}
+
+pub fn Run(config: HeadlessConfig) -> Result<ChunkStats, String> {
+    let packs = ContentPacks::Load(&config.Packs)?;
+    println!("{}", packs.Report());
+
+    let mut craftingR = CraftingRegistry::New();
+    let mut smeltingR = SmeltingRegistry::New();
+    let mut itemR = ItemRegistry::New();
+    let mut blockR = BlockRegistry::New();
+
+    //headless runs don't have a save, so the IDs are handed out fresh every time
+    ReadAttributes(&mut blockR, &mut itemR, &mut craftingR, &mut smeltingR, &packs, &mut IdMap::default())
+    .map_err(|e| format!("Error! Attribute reading failed for registries. The error:\n{}", e.to_string()))?;
+
+    let mut world = World::NewSeeded(craftingR, smeltingR, blockR, itemR, &packs, config.Seed);
+    world.RenderDistanceUpdate(config.RenderDistance)?;
+
+    let mut camera = Camera::New();
+    if let Some(start) = config.Waypoints.first() {
+        camera.Position.x = start.0;
+        camera.Position.z = start.1;
+    }
+
+    //the camera moves a fixed amount each tick, so the run is the same no matter how fast the ticks go
+    let timeStep = 1f32 / if config.TickRate == 0 { 20f32 } else { config.TickRate as f32 };
+    let tickLength = if config.TickRate == 0 { Duration::ZERO } else { Duration::from_secs_f32(timeStep) };
+    let mut nextWaypoint = 1;
+    let start = Instant::now();
+
+    for tick in 0..config.Ticks {
+        let tickStart = Instant::now();
+
+        if let Some(target) = config.Waypoints.get(nextWaypoint) {
+            let dx = target.0 - camera.Position.x;
+            let dz = target.1 - camera.Position.z;
+            let distance = f32::sqrt(dx * dx + dz * dz);
+            let step = config.CameraSpeed * timeStep;
+
+            if distance <= step {
+                camera.Position.x = target.0;
+                camera.Position.z = target.1;
+                nextWaypoint += 1;
+            } else {
+                camera.Position.x += dx / distance * step;
+                camera.Position.z += dz / distance * step;
+            }
+        }
+
+        world.Update((camera.Position.x, camera.Position.z), &camera);
+        world.TickBlocks(timeStep);
+
+        if config.ReportInterval != 0 && (tick + 1) % config.ReportInterval == 0 {
+            println!("tick {} ({:.1}, {:.1}) {}", tick + 1, camera.Position.x, camera.Position.z, world.GetChunkStats());
+        }
+
+        let elapsed = tickStart.elapsed();
+        if elapsed < tickLength {
+            std::thread::sleep(tickLength - elapsed);
+        }
+    }
+
+    //the last ticks' jobs are still out on the workers, so wait for them (and whatever they lead to) before reporting
+    while !world.IsIdle() {
+        world.Update((camera.Position.x, camera.Position.z), &camera);
+        std::thread::sleep(Duration::from_millis(1));
+    }
+
+    let stats = world.GetChunkStats();
+    println!("Finished {} ticks in {:.2}s with seed {}. {}", config.Ticks, start.elapsed().as_secs_f32(), world.GetSeed(), stats);
+    Ok(stats)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn BackToBackTicksStillGenerateChunks() {
+        let config = HeadlessConfig { RenderDistance: 1, TickRate: 0, Ticks: 3, ReportInterval: 0, Waypoints: vec![(7f32, 7f32)], ..Default::default() };
+        let stats = Run(config).unwrap();
+        assert_eq!((stats.Pending, stats.InFlight), (0, 0));
+        //the target chunk and the four around it
+        assert_eq!(stats.Meshed, 5);
+        assert!(stats.Loaded > stats.Meshed && stats.Vertices > 0);
+    }
+}
\ No newline at end of file
@@ -1,3 +1,4 @@
 pub mod window;
 pub mod application;
-pub mod input;
\ No newline at end of file
+pub mod input;
+pub mod headless;
\ No newline at end of file
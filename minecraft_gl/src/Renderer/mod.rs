@@ -1,3 +1,4 @@
 pub mod renderer;
 pub mod worldRenderer;
-pub mod spriteRenderer;
\ No newline at end of file
+pub mod spriteRenderer;
+pub mod skyRenderer;
\ No newline at end of file
@@ -6,14 +6,17 @@ use crate::Scene::camera::Camera;
 use crate::Util::atlas::TextureAtlas;
 use crate::Util::resource::ResourceManager;
 use crate::World::chunk::Chunk;
+use crate::World::dayCycle::DayCycle;
 use super::worldRenderer::WorldRenderer;
 use super::spriteRenderer::SpriteRenderer;
+use super::skyRenderer::SkyRenderer;
 
 
 pub struct Renderer{
     ResourceManager: ResourceManager,
     WorldRenderer: WorldRenderer,
     SpriteRenderer: SpriteRenderer,
+    SkyRenderer: SkyRenderer,
 }
 
 impl Renderer{
@@ -24,10 +27,13 @@ impl Renderer{
 
         let spriteRenderer = SpriteRenderer::New(&mut resourceManager, itemAtlas, display);
 
+        let skyRenderer = SkyRenderer::New(&mut resourceManager, display);
+
         let mut s = Self {
             ResourceManager: resourceManager,
             WorldRenderer: worldRenderer, 
             SpriteRenderer: spriteRenderer,
+            SkyRenderer: skyRenderer,
         };
 
         s.Init();
@@ -39,8 +45,13 @@ impl Renderer{
        // self.SpriteRenderer.Init();
     }
 
-    pub fn Render(&mut self, chunks: &HashMap<nalgebra::Vector2<i32>, Arc<Chunk>>, camera: &Camera, target: &mut glium::Frame){
-        self.WorldRenderer.Render(chunks, camera, target);
+    pub fn Update(&mut self, timeStep: f32){
+        self.WorldRenderer.Update(timeStep);
+    }
+
+    pub fn Render(&mut self, chunks: &HashMap<nalgebra::Vector2<i32>, Arc<Chunk>>, camera: &Camera, dayCycle: &DayCycle, target: &mut glium::Frame){
+        self.SkyRenderer.Render(camera, dayCycle, target);
+        self.WorldRenderer.Render(chunks, camera, dayCycle.SkyLight(), target);
         self.SpriteRenderer.Render(camera, target);
     }
 
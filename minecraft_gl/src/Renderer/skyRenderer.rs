@@ -0,0 +1,102 @@
+use std::rc::Rc;
+use glium::Surface;
+use glium::uniforms::{MinifySamplerFilter, MagnifySamplerFilter};
+use nalgebra as na;
+
+use crate::Scene::camera::Camera;
+use crate::Util::atlas::CubeMapAtlas;
+use crate::Util::resource::{ResourceManager, AssetPath};
+use crate::World::dayCycle::DayCycle;
+
+/*
+    The sky is a cube around the camera drawn before anything else. Its colour comes from the time of day,
+    with the sun and moon drawn over it. misc/sky.png holds two cube map crosses side by side: the day sky
+    (clouds, with alpha) and the night sky (stars, which turn with the moon)
+*/
+const SKY_PATH: &str = "misc/sky.png";
+const DAY_SKY: (u32, u32) = (0, 0);
+const NIGHT_SKY: (u32, u32) = (0, 1);
+
+#[derive(Copy, Clone)]
+struct Vertex {
+    pos: [f32; 3],
+}
+
+implement_vertex!(Vertex, pos);
+
+pub struct SkyRenderer{
+    VertexBuffer: glium::VertexBuffer<Vertex>,
+    IndexBuffer: glium::IndexBuffer<u16>,
+    Shader: Rc<glium::Program>,
+    DaySky: glium::texture::SrgbCubemap,
+    NightSky: glium::texture::SrgbCubemap,
+}
+
+impl SkyRenderer{
+    pub fn New(resourceManager: &mut ResourceManager, display: &glium::Display) -> Self {
+        let path = "shaders/sky.glsl";
+        let shader = resourceManager.GetShader(path, display);
+
+        let atlas = CubeMapAtlas::New(AssetPath(SKY_PATH).to_str().unwrap(), 1, 2, display)
+        .expect("Error! Could not load the sky's cube maps!");
+        let daySky = atlas.CreateCubeMap(DAY_SKY.0, DAY_SKY.1, display)
+        .expect("Error! Could not create the day sky's cube map!");
+        let nightSky = atlas.CreateCubeMap(NIGHT_SKY.0, NIGHT_SKY.1, display)
+        .expect("Error! Could not create the night sky's cube map!");
+
+        //the corners of a cube around the camera, and the two triangles of each of its faces
+        let corners: Vec<Vertex> = (0..8).map(|i| Vertex {
+            pos: [if i & 1 == 0 { -1f32 } else { 1f32 }, if i & 2 == 0 { -1f32 } else { 1f32 }, if i & 4 == 0 { -1f32 } else { 1f32 }]
+        }).collect();
+        let indices: [u16; 36] = [
+            1, 3, 7, 1, 7, 5, //+X
+            0, 4, 6, 0, 6, 2, //-X
+            2, 6, 7, 2, 7, 3, //+Y
+            0, 1, 5, 0, 5, 4, //-Y
+            4, 5, 7, 4, 7, 6, //+Z
+            0, 2, 3, 0, 3, 1, //-Z
+        ];
+
+        Self {
+            VertexBuffer: glium::VertexBuffer::new(display, &corners)
+            .expect("Sky Renderer's Vertex buffer creation failed!"),
+            IndexBuffer: glium::IndexBuffer::new(display, glium::index::PrimitiveType::TrianglesList, &indices)
+            .expect("Sky Renderer's Index buffer creation failed!"),
+            Shader: shader,
+            DaySky: daySky,
+            NightSky: nightSky,
+        }
+    }
+
+    pub fn Render(&self, camera: &Camera, dayCycle: &DayCycle, target: &mut glium::Frame){
+        let behavior = glium::uniforms::SamplerBehavior {
+            minify_filter: MinifySamplerFilter::Linear,
+            magnify_filter: MagnifySamplerFilter::Linear,
+            ..Default::default()
+        };
+
+        let gradient = dayCycle.SkyColors();
+        let sun: [f32; 3] = dayCycle.SunDirection().into();
+        let moon: [f32; 3] = dayCycle.MoonDirection().into();
+        //turns a direction in the sky back to where it was at sunrise, for the stars
+        let skyRotation: [[f32; 3]; 3] = na::Rotation3::from_axis_angle(&DayCycle::SkyAxis(), -dayCycle.SunAngle()).into_inner().into();
+
+        let uniforms = uniform! {
+            proj: camera.GetProjectionMatrix(),
+            view: camera.GetViewMatrix(),
+            zenith: gradient.Zenith,
+            horizon: gradient.Horizon,
+            sun_dir: sun,
+            moon_dir: moon,
+            daylight: dayCycle.Daylight(),
+            sky_light: dayCycle.SkyLight(),
+            sky_rotation: skyRotation,
+            day_sky: glium::uniforms::Sampler(&self.DaySky, behavior),
+            night_sky: glium::uniforms::Sampler(&self.NightSky, behavior),
+        };
+
+        //no depth test or write, everything else is drawn over it
+        target.draw(&self.VertexBuffer, &self.IndexBuffer, &self.Shader, &uniforms,
+            &Default::default()).unwrap();
+    }
+}
\ No newline at end of file
@@ -31,7 +31,7 @@ implement_vertex!(Vertex, pos);
 //TODO change all the errors to be Result<_, Str&> to avoid heap allcoation
 impl SpriteRenderer{
     pub fn New(resourceManager: &mut ResourceManager, atlas: TextureAtlas, display: &glium::Display) -> Self {
-        let path = "./minecraft_gl/assets/shaders/triangle.glsl";
+        let path = "shaders/triangle.glsl";
         let shader = resourceManager.GetShader(path, display);
       
 
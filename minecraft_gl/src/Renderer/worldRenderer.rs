@@ -8,7 +8,7 @@ use crate::Scene::camera::Camera;
 use crate::World::chunk::{CHUNK_BOUNDS_X, CHUNK_BOUNDS_Y, CHUNK_BOUNDS_Z};
 use crate::Util::atlas::TextureAtlas;
 use crate::Util::resource::ResourceManager;
-use crate::World::chunk::Chunk;
+use crate::World::chunk::{Chunk, ChunkState};
 
 pub const BLOCK_TEXTURE_RESOLUTION: u32 = 64;
 
@@ -16,6 +16,10 @@ pub const BLOCK_TEXTURE_RESOLUTION: u32 = 64;
 pub struct Vertex{
    pub Core: u32,
    pub Dims: u32,
+   //Where a model's vertex is inside its block, and its texture coordinates (see modelMeshHelper). 0 for cubes
+   pub Detail: u32,
+   //Colour the texture is multiplied by, as 0xRRGGBB. White for blocks that aren't tinted
+   pub Tint: u32,
 }
 
 pub struct WorldRenderer{
@@ -23,14 +27,16 @@ pub struct WorldRenderer{
     IndexBuffer: glium::IndexBuffer<u32>,
     Shader: Rc<glium::Program>,
     TextureAtlas: TextureAtlas,
+    //Seconds of game time, which animated textures pick their frame from
+    AnimationTime: f32,
 }
 
 //TODO change all the errors to be Result<_, Str&> to avoid heap allcoation
 impl WorldRenderer{
     pub fn New(resourceManager: &mut ResourceManager, atlas: TextureAtlas, display: &glium::Display) -> Self {
-        implement_vertex!(Vertex, Core, Dims);
+        implement_vertex!(Vertex, Core, Dims, Detail, Tint);
 
-        let path = "./minecraft_gl/assets/shaders/world.glsl";
+        let path = "shaders/world.glsl";
         let shader = resourceManager.GetShader(path, display);
       
         let mut s = Self {
@@ -41,6 +47,7 @@ impl WorldRenderer{
             .expect("Sprite Renderer's Index buffer creation failed!"),
             Shader: shader,
             TextureAtlas: atlas,
+            AnimationTime: 0f32,
         };
 
         //validate that the size of a chunk is enough to cover with 2 bytes
@@ -71,7 +78,13 @@ impl WorldRenderer{
 
     }
 
-    pub fn Render(&mut self, chunks: &HashMap<nalgebra::Vector2<i32>, Arc<Chunk>>, camera: &Camera, target: &mut glium::Frame){
+    pub fn Update(&mut self, timeStep: f32){
+        //wrapped so the time keeps its precision however long the game runs. Only animations longer than this would skip
+        self.AnimationTime = (self.AnimationTime + timeStep) % 3600f32;
+    }
+
+    //skyLight darkens the world as the sun goes down (see DayCycle::SkyLight)
+    pub fn Render(&mut self, chunks: &HashMap<nalgebra::Vector2<i32>, Arc<Chunk>>, camera: &Camera, skyLight: f32, target: &mut glium::Frame){
 
         let behavior = glium::uniforms::SamplerBehavior {
             minify_filter: MinifySamplerFilter ::Nearest,
@@ -80,6 +93,10 @@ impl WorldRenderer{
         };
 
         for chunk in chunks {
+            //generated chunks wait for their neighbours before they're meshed
+            if chunk.1.Status != ChunkState::Meshed {
+                continue;
+            }
 
             let p = nalgebra::Vector3::new(chunk.0.x as f32 * CHUNK_BOUNDS_X as f32, 0f32, chunk.0.y as f32 * CHUNK_BOUNDS_Z as f32);
             if ! camera.Fustrum.CheckChunk(&p, &(p + nalgebra::Vector3::new(CHUNK_BOUNDS_X as f32, CHUNK_BOUNDS_Y as f32, CHUNK_BOUNDS_Z as f32))) {
@@ -89,8 +106,11 @@ impl WorldRenderer{
             let uniforms = uniform! {
                 proj: camera.GetProjectionMatrix(),
                 view: camera.GetViewMatrix(),
-                atlas_cols: self.TextureAtlas.Columns as f32,
+                uv_table: glium::uniforms::Sampler(&self.TextureAtlas.UVTable, behavior),
                 chunk_pos: [chunk.1.Position.0 as f32, chunk.1.Position.1 as f32],
+                lod_scale: chunk.1.Lod.Factor() as f32,
+                time: self.AnimationTime,
+                sky_light: skyLight,
                 atlas: glium::uniforms::Sampler(&self.TextureAtlas.Texture, behavior)
             };
 
@@ -1,6 +1,8 @@
-use crate::{Renderer::renderer::Renderer, World::{block::BlockRegistry, item::ItemRegistry, crafting::CraftingRegistry, ReadAttributes}, Event::event::Event};
+use crate::{Renderer::renderer::Renderer, World::{block::BlockRegistry, item::ItemRegistry, crafting::CraftingRegistry, smelting::SmeltingRegistry, ReadAttributes,
+            idMap::{IdMap, DefaultSaveDir}, contentPack::ContentPacks}, Event::event::Event};
 use super::{worldScene::WorldScene, mainmenu::MainMenu};
 use crate::Renderer::worldRenderer::BLOCK_TEXTURE_RESOLUTION;
+use crate::Util::{atlas::AtlasCache, resource::CacheDir};
 
 #[derive(PartialEq, Eq, Debug)]
 pub enum SceneState{
@@ -21,30 +23,24 @@ pub struct SceneManager{
     CurrentSceneState: SceneState,
 
     Renderer: Renderer,
+    //Kept around so the registries can be read again when a new world is entered
+    Packs: ContentPacks,
 }
 
 impl SceneManager{
-    pub fn New(display: &glium::Display) -> Self {
+    pub fn New(display: &glium::Display, packs: ContentPacks) -> Self {
+        println!("{}", packs.Report());
+        let (blockR, itemR, craftingR, smeltingR) = ReadRegistries(&packs);
 
-        let mut craftingR = CraftingRegistry::New();
-        let mut itemR = ItemRegistry::New(); 
-        let mut blockR = BlockRegistry::New();
-
-        match ReadAttributes(&mut blockR, &mut itemR, &mut craftingR) {
-            Err(msg) => {
-                panic!("Error! Attribute reading failed for registries. The error:\n{}", msg.to_string());
-            },
-            _ => {}
-        };
-
-        let blockAtlas = match blockR.GenerateAtlas(BLOCK_TEXTURE_RESOLUTION, display) {
+        let atlasCache = AtlasCache::New(CacheDir());
+        let blockAtlas = match blockR.GenerateAtlas(BLOCK_TEXTURE_RESOLUTION, &atlasCache, display) {
             Ok(val) => val,
             Err(msg) => {
                   panic!("Error! World renderer creation failed due to block atlas creation. The error:\n{}.", msg);
             }
         };
 
-        let itemAtlas = match itemR.GenerateAtlas(BLOCK_TEXTURE_RESOLUTION, display) {
+        let itemAtlas = match itemR.GenerateAtlas(BLOCK_TEXTURE_RESOLUTION, &atlasCache, display) {
             Ok(val) => val,
             Err(msg) => {
                 panic!("Error! Sprite renderer creation failed due to item atlas creation. The error:\n{}.", msg);
@@ -52,16 +48,18 @@ impl SceneManager{
         };
 
         Self {
-            CurrentScene: Box::new(WorldScene::New(blockR, itemR,  craftingR)),
+            CurrentScene: Box::new(WorldScene::New(blockR, itemR, craftingR, smeltingR, &packs)),
             CurrentSceneState: SceneState::WorldScene,
             //TODO create the atlases here and dont worry about passing the registrys down to the renderer
             //TODO also prevent the mainMenu from having the registries, I dont care
             Renderer: Renderer::New(blockAtlas, itemAtlas, display),
+            Packs: packs,
         }
     }
 
     pub fn Update(&mut self, timeStep: f32){
         self.CurrentScene.Update(timeStep);
+        self.Renderer.Update(timeStep);
     }
 
     pub fn Render(&mut self, target: &mut glium::Frame){
@@ -76,6 +74,13 @@ impl SceneManager{
         self.Renderer.OnEvent(event);
     }
 
+    //Saves the world, if one is open. Called before the game exits
+    pub fn Save(&mut self){
+        if let Some(worldScene) = self.CurrentScene.AsAnyMut().downcast_mut::<WorldScene>() {
+            worldScene.Save();
+        }
+    }
+
     pub fn OnTransition(&mut self, state: SceneState){
         if self.CurrentSceneState == state {
             //TODO have some way of printing the scene state
@@ -94,21 +99,37 @@ impl SceneManager{
                 let mainMenu = self.CurrentScene.AsAnyMut().downcast_mut::<MainMenu>().unwrap();
                 mainMenu.Destroy();
 
-                let mut craftingR = CraftingRegistry::New();
-                let mut itemR = ItemRegistry::New(); 
-                let mut blockR = BlockRegistry::New();
-        
-                match ReadAttributes(&mut blockR, &mut itemR, &mut craftingR) {
-                    Err(msg) => {
-                        panic!("Error! Attribute reading failed for registries. The error:\n{}", msg.to_string());
-                    },
-                    _ => {}
-                };
-                
-                let worldScene = WorldScene::New(blockR, itemR,  craftingR);
+                let (blockR, itemR, craftingR, smeltingR) = ReadRegistries(&self.Packs);
+                let worldScene = WorldScene::New(blockR, itemR, craftingR, smeltingR, &self.Packs);
                 self.CurrentScene = Box::new(worldScene);
                 
             }
         };
     }
+}
+
+//Reads the registries using the runtime IDs saved with the world, then saves the IDs handed out to any new content
+fn ReadRegistries(packs: &ContentPacks) -> (BlockRegistry, ItemRegistry, CraftingRegistry, SmeltingRegistry) {
+    let saveDir = DefaultSaveDir();
+    let mut idMap = match IdMap::Load(&saveDir) {
+        Ok(val) => val,
+        Err(msg) => panic!("Error! Could not load the world's id map. The error:\n{}", msg)
+    };
+
+    let mut craftingR = CraftingRegistry::New();
+    let mut smeltingR = SmeltingRegistry::New();
+    let mut itemR = ItemRegistry::New(); 
+    let mut blockR = BlockRegistry::New();
+
+    match ReadAttributes(&mut blockR, &mut itemR, &mut craftingR, &mut smeltingR, packs, &mut idMap) {
+        Err(msg) => {
+            panic!("Error! Attribute reading failed for registries. The error:\n{}", msg.to_string());
+        },
+        _ => {}
+    };
+
+    if let Err(msg) = idMap.Save(&saveDir) {
+        panic!("Error! Could not save the world's id map. The error:\n{}", msg);
+    }
+    (blockR, itemR, craftingR, smeltingR)
 }
\ No newline at end of file
@@ -1,17 +1,24 @@
 
-use crate::{World::{block::BlockRegistry, world::World, item::ItemRegistry, crafting::CraftingRegistry}, Event::event::Event};
+use crate::{World::{block::BlockRegistry, world::World, item::ItemRegistry, crafting::CraftingRegistry, smelting::SmeltingRegistry, contentPack::ContentPacks, idMap::DefaultSaveDir}, Event::event::{Event, KeyPressedEvent, MouseButtonPressedEvent, MouseButtonReleasedEvent}};
+use glium::glutin::event::{VirtualKeyCode, MouseButton};
 use super::{sceneManager::Scene, camera::Camera};
 
+//Dropped items this close to the camera are picked up
+const PICKUP_RADIUS: f32 = 2.0;
+
 pub struct WorldScene{
     World: World,
-    Camera: Camera
+    Camera: Camera,
+    //Whether the left mouse button is held down, which keeps mining whatever the camera looks at
+    Mining: bool,
 }
 
 impl<'a> WorldScene{
-    pub fn New(blockRegistry: BlockRegistry, itemRegistry: ItemRegistry, craftingRegistry: CraftingRegistry) -> WorldScene {
+    pub fn New(blockRegistry: BlockRegistry, itemRegistry: ItemRegistry, craftingRegistry: CraftingRegistry, smeltingRegistry: SmeltingRegistry, packs: &ContentPacks) -> WorldScene {
         let mut s = Self {  
-            World: World::New(craftingRegistry, blockRegistry, itemRegistry),
+            World: World::New(craftingRegistry, smeltingRegistry, blockRegistry, itemRegistry, packs, DefaultSaveDir()),
             Camera: Camera::New(),
+            Mining: false,
         };
         
         s.Init();
@@ -19,11 +26,13 @@ impl<'a> WorldScene{
     }
 
     fn Init(&mut self) {
-        
+
     }
 
     pub fn Save(&self) {
-
+        if let Err(msg) = self.World.Save() {
+            println!("{}", msg);
+        }
     }
 
     pub fn Load(&mut self, _savePath: &str) {
@@ -36,16 +45,57 @@ impl<'a> WorldScene{
 
 }
 impl Scene for WorldScene{
-    fn Update(&mut self, _timeStep: f32) {
-        self.World.Update((self.Camera.Position.x, self.Camera.Position.z), &self.Camera)
+    fn Update(&mut self, timeStep: f32) {
+        self.World.Update((self.Camera.Position.x, self.Camera.Position.z), &self.Camera);
+        self.World.TickBlocks(timeStep);
+        self.World.UpdatePlayerPosition(self.Camera.Position);
+
+        match self.World.TargetBlock(self.Camera.Position, self.Camera.Direction) {
+            Some(hit) if self.Mining => { self.World.MineBlock(hit.Position, timeStep); },
+            _ => self.World.StopMining()
+        }
+        self.World.UpdateDroppedItems(timeStep);
+        self.World.PickUpItems(self.Camera.Position, PICKUP_RADIUS);
     }
 
     fn Render(&mut self, renderer: &mut crate::Renderer::renderer::Renderer, target: &mut glium::Frame) {
-        renderer.Render(&self.World.Chunks, &self.Camera, target);
+        renderer.Render(&self.World.Chunks, &self.Camera, self.World.GetDayCycle(), target);
     }
 
     fn OnEvent(&mut self, event: &Event) {
+        //an open block screen, like a chest, gets the input instead of the camera
+        if self.World.OnOpenBlockEvent(event) {
+            return;
+        }
         self.Camera.OnEvent(event);
+
+        match event {
+            Event::MousePressed(MouseButtonPressedEvent { MouseButton: MouseButton::Left, .. }) => self.Mining = true,
+            Event::MouseReleased(MouseButtonReleasedEvent { MouseButton: MouseButton::Left }) => self.Mining = false,
+            Event::MousePressed(MouseButtonPressedEvent { MouseButton: MouseButton::Right, .. }) => {
+                //blocks that react, like chests opening, take the click instead of the held item
+                let hit = self.World.TargetBlock(self.Camera.Position, self.Camera.Direction);
+                if !hit.map_or(false, |hit| self.World.RightClickBlock(hit.Position)) {
+                    self.World.UseSelectedItem(hit.as_ref(), self.Camera.Position, self.Camera.Direction);
+                }
+            },
+            _ => {}
+        }
+
+        if let Event::KeyPressed(KeyPressedEvent { Key, .. }) = event {
+            match Key {
+                VirtualKeyCode::Equals => self.World.RenderDistanceStep(1),
+                VirtualKeyCode::Minus => self.World.RenderDistanceStep(-1),
+                VirtualKeyCode::Key1 | VirtualKeyCode::Key2 | VirtualKeyCode::Key3 |
+                VirtualKeyCode::Key4 | VirtualKeyCode::Key5 | VirtualKeyCode::Key6 |
+                VirtualKeyCode::Key7 | VirtualKeyCode::Key8 | VirtualKeyCode::Key9 => {
+                    //the number keys come one after the other in the enum
+                    let slot = *Key as usize - VirtualKeyCode::Key1 as usize;
+                    self.World.PlayerInventory.Select(slot).ok();
+                },
+                _ => {}
+            }
+        }
     }
 
     fn AsAny(& self) -> & dyn std::any::Any{
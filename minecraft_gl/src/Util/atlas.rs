@@ -1,21 +1,105 @@
 
+use std::borrow::Cow;
+use std::path::PathBuf;
 use image::*;
 use image::io::Reader as ImageReader;
+use serde::{Serialize, Deserialize};
 
 use super::resource::GetTextureFromImage;
 
-
+//Pixels of gutter around every texture on a packed atlas. Enough for a few mip levels before neighbours bleed in
+pub const ATLAS_PADDING: u32 = 4;
+//Bump whenever the way atlases are packed changes, so cached ones are built again
+const ATLAS_FORMAT_VERSION: u32 = 2;
 
 pub struct TextureAtlas{
     pub Texture: glium::texture::SrgbTexture2d,
     pub Image: DynamicImage,
-    pub Rows: u32,
-    pub Columns: u32,
-    pub CellHeight: u32,
-    pub CellWidth: u32,
+    //Where every texture is on the image, indexed by texture ID
+    pub Layout: AtlasLayout,
+    /*
+        The layout's UV table as a two pixel tall float texture, so shaders can look a texture ID up with
+        texelFetch(table, ivec2(id, row), 0). Row 0 is (u, v, width, height) of the first frame measured from the
+        top left of the atlas, and row 1 is (frames, v distance between frames, frame time, interpolate)
+    */
+    pub UVTable: glium::texture::Texture2d,
     pub format: i32,
 }
 
+/*
+    A texture's place on a packed atlas, in pixels from the top left. Animated textures keep their frames
+    in a column, each one FrameStride pixels below the last. Still textures have a single frame
+*/
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct AtlasRegion{
+    pub X: u32,
+    pub Y: u32,
+    pub Width: u32,
+    pub Height: u32,
+    pub Frames: u32,
+    pub FrameStride: u32,
+    //Seconds each frame of an animated texture shows for, and whether it fades into the next one
+    #[serde(default)]
+    pub FrameTime: f32,
+    #[serde(default)]
+    pub Interpolate: bool,
+}
+
+//How an animated texture plays. The texture itself is a vertical strip of frames, top to bottom
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TextureAnimation{
+    //None takes the frames to be square, so a 16x64 strip is 4 frames
+    pub Frames: Option<u32>,
+    pub FrameTime: f32,
+    pub Interpolate: bool,
+}
+
+//One texture going into an atlas, by path. A path of None is an empty slot
+#[derive(Clone, Copy, Debug)]
+pub struct AtlasSource<'a>{
+    pub Path: Option<&'a str>,
+    pub Animation: Option<TextureAnimation>,
+    //(columns, rows) if the image is a sheet of tiles that each become their own texture
+    pub Sheet: Option<(u32, u32)>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct AtlasLayout{
+    pub Width: u32,
+    pub Height: u32,
+    pub Padding: u32,
+    pub Regions: Vec<AtlasRegion>,
+}
+
+//A texture waiting to be packed, one image per frame
+struct AtlasTile{
+    Frames: Vec<RgbaImage>,
+    FrameTime: f32,
+    Interpolate: bool,
+}
+
+/*
+    Packs textures of any size onto one atlas. Textures keep their own size (only shrunk down to MaxTileSize,
+    keeping their aspect ratio) and every one of them gets Padding pixels of its own edge smeared around it,
+    so linear filtering and mipmaps sample the texture's border instead of its neighbour.
+    Texture IDs are handed out in the order textures are added
+*/
+pub struct AtlasBuilder{
+    Padding: u32,
+    MaxTileSize: Option<u32>,
+    Tiles: Vec<AtlasTile>,
+}
+
+/*
+    A grid of cube maps, each cell laid out as a horizontal cross four faces wide and three tall:
+
+            Top
+    Left  Middle  Right  Back
+           Bottom
+
+    The middle row goes around the cube (-X, +Z, +X, -Z), with Top (+Y) and Bottom (-Y) lined up against
+    Middle. Every face's edges meet the faces next to it in the cross, the way most skybox tools export them
+*/
 pub struct CubeMapAtlas{
     pub Texture: glium::texture::SrgbTexture2d,
     pub Image: DynamicImage,
@@ -33,11 +117,25 @@ pub enum CubeMapFace{
     MiddleLeft,
     Middle,
     MiddleRight,
+    Back,
     Bottom
 }
 
+impl CubeMapFace{
+    //Which face of a GL cube map each part of the cross becomes
+    pub const LAYERS: [(CubeMapFace, glium::texture::CubeLayer); 6] = [
+        (CubeMapFace::Top, glium::texture::CubeLayer::PositiveY),
+        (CubeMapFace::MiddleLeft, glium::texture::CubeLayer::NegativeX),
+        (CubeMapFace::Middle, glium::texture::CubeLayer::PositiveZ),
+        (CubeMapFace::MiddleRight, glium::texture::CubeLayer::PositiveX),
+        (CubeMapFace::Back, glium::texture::CubeLayer::NegativeZ),
+        (CubeMapFace::Bottom, glium::texture::CubeLayer::NegativeY),
+    ];
+}
+
 
 impl TextureAtlas{
+    //Loads an atlas laid out as a grid of equally sized cells, like a sprite sheet
     pub fn New(path: &str, rows: u32, cols: u32, display: &glium::Display) -> Result<TextureAtlas, String>{
         //Assume that the texture passed already has its texture parameters set
         let img = ImageReader::open(path);
@@ -51,28 +149,12 @@ impl TextureAtlas{
         }
 
         let finalImage = decoded.unwrap();
-        let channels = if finalImage.color().has_alpha(){
-                                 4
-                            } else { 
-                                 3
-                            };
-        
-        let cellHeight = (finalImage.height() as f32 / rows as f32) as u32;
-        let cellWidth = (finalImage.width() as f32 / cols as f32) as u32;
-       
-        Ok(Self {
-            Texture: GetTextureFromImage(&finalImage, display)
-            .expect("Error! Could not create texture from image in 'New' function for 'TextureAtlas'"),
-            Image: finalImage,
-            Rows: rows,
-            Columns: cols,
-            CellHeight: cellHeight,
-            CellWidth:  cellWidth,
-            format: channels as i32
-        })
+        let layout = AtlasLayout::Grid(finalImage.width(), finalImage.height(), rows, cols);
+        Self::FromLayout(finalImage, layout, display)
     }
 
-    pub fn FromImage(image: DynamicImage, rows: u32, cols: u32, textureResolution: u32, display: &glium::Display) -> Self{
+    //An atlas built by AtlasBuilder (or read back from a cache of one)
+    pub fn FromLayout(image: DynamicImage, layout: AtlasLayout, display: &glium::Display) -> Result<Self, String>{
         //Assume that the texture passed already has its texture parameters set
         let channels = if image.color().has_alpha(){
                             4
@@ -80,25 +162,357 @@ impl TextureAtlas{
                             3 
                     };
 
-        Self {
-            Texture: GetTextureFromImage(&image, display)
-            .expect("Error! Could not create texture from image in 'FromImage' function for 'TextureAtlas'"),
+        Ok(Self {
+            Texture: GetTextureFromImage(&image, display)?,
+            UVTable: CreateUVTable(&layout, display)?,
             Image: image,
-            Rows: rows,
-            Columns: cols,
-            CellHeight: textureResolution,
-            CellWidth: textureResolution,
+            Layout: layout,
             format: channels as i32
+        })
+    }
+
+    //The first frame of a texture
+    pub fn GrabSubImage(&self, textureID: u32) -> Option<SubImage<&DynamicImage>>{
+        let region = self.Layout.Regions.get(textureID as usize)?;
+        Some(SubImage::new(&self.Image, region.X, region.Y, region.Width, region.Height))
+    }
+
+
+}
+
+impl AtlasRegion{
+    //The region of one frame of an animated texture. Frames past the last wrap around
+    pub fn Frame(&self, frame: u32) -> AtlasRegion{
+        let frame = frame % self.Frames.max(1);
+        AtlasRegion { Y: self.Y + frame * self.FrameStride, Frames: 1, ..*self }
+    }
+}
+
+impl AtlasLayout{
+    //A layout of rows x cols equally sized cells with no padding, numbered left to right and then top to bottom
+    pub fn Grid(width: u32, height: u32, rows: u32, cols: u32) -> Self{
+        let (cellWidth, cellHeight) = (width / cols.max(1), height / rows.max(1));
+        let regions = (0..rows * cols).map(|i| AtlasRegion {
+            X: (i % cols) * cellWidth,
+            Y: (i / cols) * cellHeight,
+            Width: cellWidth,
+            Height: cellHeight,
+            Frames: 1,
+            FrameStride: cellHeight,
+            FrameTime: 0f32,
+            Interpolate: false,
+        }).collect();
+        Self { Width: width, Height: height, Padding: 0, Regions: regions }
+    }
+
+    //(u, v, width, height) of the first frame of a texture as fractions of the atlas, measured from its top left
+    pub fn UV(&self, textureID: u32) -> Option<[f32; 4]>{
+        let region = self.Regions.get(textureID as usize)?;
+        let (width, height) = (self.Width.max(1) as f32, self.Height.max(1) as f32);
+        Some([region.X as f32 / width, region.Y as f32 / height, region.Width as f32 / width, region.Height as f32 / height])
+    }
+
+    //UV() of every texture, indexed by texture ID
+    pub fn UVTable(&self) -> Vec<[f32; 4]>{
+        (0..self.Regions.len() as u32).map(|id| self.UV(id).unwrap()).collect()
+    }
+
+    //(frames, v distance between frames, frame time, interpolate) of every texture, indexed by texture ID
+    pub fn AnimationTable(&self) -> Vec<[f32; 4]>{
+        let height = self.Height.max(1) as f32;
+        self.Regions.iter().map(|r| [r.Frames as f32, r.FrameStride as f32 / height, r.FrameTime, r.Interpolate as u32 as f32]).collect()
+    }
+}
+
+fn CreateUVTable(layout: &AtlasLayout, display: &glium::Display) -> Result<glium::texture::Texture2d, String>{
+    let width = layout.Regions.len().max(1) as u32;
+    let mut data: Vec<f32> = layout.UVTable().into_iter().chain(layout.AnimationTable()).flatten().collect();
+    //textures can't be empty
+    if data.is_empty() {
+        data = vec![0f32; 8];
+    }
+
+    let raw = glium::texture::RawImage2d {
+        width: width,
+        height: 2,
+        data: Cow::Owned(data),
+        format: glium::texture::ClientFormat::F32F32F32F32,
+    };
+    glium::texture::Texture2d::with_format(display, raw, glium::texture::UncompressedFloatFormat::F32F32F32F32, glium::texture::MipmapsOption::NoMipmap)
+    .map_err(|e| format!("Error! Could not create the UV table of a texture atlas. The error:\n{}", e.to_string()))
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+/// /////////////////////////////////////////////////////////////////////////////////////////////////////////////
+
+impl AtlasBuilder{
+    //A max tile size of None keeps every texture at its original size
+    pub fn New(padding: u32, maxTileSize: Option<u32>) -> Self{
+        Self { Padding: padding, MaxTileSize: maxTileSize, Tiles: Vec::new() }
+    }
+
+    //Adds a still texture, returning its texture ID
+    pub fn Add(&mut self, image: &DynamicImage) -> u32{
+        let frame = self.Shrink(image.to_rgba8());
+        self.Tiles.push(AtlasTile { Frames: vec![frame], FrameTime: 0f32, Interpolate: false });
+        self.Tiles.len() as u32 - 1
+    }
+
+    /*
+        Adds an animated texture made of a vertical strip of equally tall frames, top to bottom.
+        Returns its texture ID, which refers to the first frame
+    */
+    pub fn AddStrip(&mut self, image: &DynamicImage, animation: &TextureAnimation) -> Result<u32, String>{
+        let (width, height) = image.dimensions();
+        let frames = animation.Frames.unwrap_or(if width == 0 { 0 } else { height / width });
+        if frames == 0 || height % frames != 0 {
+            return Err(format!("An animated texture of height {} can't be split into {} frames of equal height", height, frames));
+        }
+        if !(animation.FrameTime > 0f32) {
+            return Err(format!("An animated texture needs a frame time above 0, not {}", animation.FrameTime));
+        }
+
+        let frameHeight = height / frames;
+        let frames = (0..frames).map(|i| self.Shrink(image.view(0, i * frameHeight, width, frameHeight).to_image())).collect();
+        self.Tiles.push(AtlasTile { Frames: frames, FrameTime: animation.FrameTime, Interpolate: animation.Interpolate });
+        Ok(self.Tiles.len() as u32 - 1)
+    }
+
+    /*
+        Adds every tile of a sheet of cols x rows equally sized tiles as its own still texture, left to right
+        and then top to bottom. Returns the texture ID of the first one, the rest follow it in order
+    */
+    pub fn AddSheet(&mut self, image: &DynamicImage, cols: u32, rows: u32) -> Result<u32, String>{
+        let (width, height) = image.dimensions();
+        if cols == 0 || rows == 0 || width % cols != 0 || height % rows != 0 {
+            return Err(format!("A sheet of size {}x{} can't be split into {}x{} tiles of equal size", width, height, cols, rows));
+        }
+
+        let (tileWidth, tileHeight) = (width / cols, height / rows);
+        let first = self.Len();
+        for i in 0..cols * rows {
+            let tile = image.view((i % cols) * tileWidth, (i / cols) * tileHeight, tileWidth, tileHeight).to_image();
+            self.Tiles.push(AtlasTile { Frames: vec![self.Shrink(tile)], FrameTime: 0f32, Interpolate: false });
+        }
+        Ok(first)
+    }
+
+    pub fn Len(&self) -> u32{
+        self.Tiles.len() as u32
+    }
+
+    /*
+        Lays the textures out with a shelf packer: tallest first, left to right in rows as wide as the atlas.
+        The atlas is a power of two wide, picked so it comes out roughly square
+    */
+    pub fn Build(&self) -> (RgbaImage, AtlasLayout){
+        let padding = self.Padding;
+        //size of each tile's slot, gutters included. Animated tiles stack their frames in one slot
+        let slots: Vec<(u32, u32)> = self.Tiles.iter().map(|tile| {
+            let (width, height) = tile.Frames[0].dimensions();
+            (width + padding * 2, (height + padding * 2) * tile.Frames.len() as u32)
+        }).collect();
+
+        let area: u64 = slots.iter().map(|(w, h)| *w as u64 * *h as u64).sum();
+        let widest = slots.iter().map(|(w, _)| *w).max().unwrap_or(1);
+        let width = u32::max((area as f64).sqrt().ceil() as u32, widest).max(1).next_power_of_two();
+
+        let mut order: Vec<usize> = (0..slots.len()).collect();
+        order.sort_by(|a, b| slots[*b].1.cmp(&slots[*a].1).then(slots[*b].0.cmp(&slots[*a].0)));
+
+        let mut positions = vec![(0u32, 0u32); slots.len()];
+        let (mut x, mut y, mut shelfHeight) = (0u32, 0u32, 0u32);
+        for idx in order {
+            let (w, h) = slots[idx];
+            if x + w > width {
+                x = 0;
+                y += shelfHeight;
+                shelfHeight = 0;
+            }
+            positions[idx] = (x, y);
+            x += w;
+            shelfHeight = shelfHeight.max(h);
+        }
+        let height = (y + shelfHeight).max(1).next_power_of_two();
+
+        let mut image = RgbaImage::new(width, height);
+        let mut regions = Vec::with_capacity(self.Tiles.len());
+        for (tile, (x, y)) in self.Tiles.iter().zip(positions) {
+            let (w, h) = tile.Frames[0].dimensions();
+            let stride = h + padding * 2;
+            for (i, frame) in tile.Frames.iter().enumerate() {
+                Extrude(&mut image, frame, x + padding, y + padding + i as u32 * stride, padding);
+            }
+            regions.push(AtlasRegion {
+                X: x + padding,
+                Y: y + padding,
+                Width: w,
+                Height: h,
+                Frames: tile.Frames.len() as u32,
+                FrameStride: stride,
+                FrameTime: tile.FrameTime,
+                Interpolate: tile.Interpolate,
+            });
+        }
+
+        (image, AtlasLayout { Width: width, Height: height, Padding: padding, Regions: regions })
+    }
+
+    //Scales an image down to fit in MaxTileSize, keeping its aspect ratio. Images already small enough are untouched
+    fn Shrink(&self, image: RgbaImage) -> RgbaImage{
+        //an empty image would take no space and break the UVs, so it stands in as a transparent pixel
+        if image.width() == 0 || image.height() == 0 {
+            return RgbaImage::new(1, 1);
+        }
+        let max = match self.MaxTileSize {
+            Some(val) if image.width() > val || image.height() > val => val.max(1),
+            _ => return image
+        };
+
+        let scale = max as f32 / u32::max(image.width(), image.height()) as f32;
+        let width = ((image.width() as f32 * scale).round() as u32).clamp(1, max);
+        let height = ((image.height() as f32 * scale).round() as u32).clamp(1, max);
+        imageops::resize(&image, width, height, imageops::FilterType::Nearest)
+    }
+}
+
+/*
+    Built atlases are kept in the cache directory under their name, along with a hash of everything that went
+    into them: the format version, resolution, gutter and each source texture's path and bytes, in atlas order.
+    Any change to those (even an edited image under the same name) gives a different hash, and the atlas is built again
+*/
+pub struct AtlasCache{
+    Dir: PathBuf,
+}
+
+impl AtlasCache{
+    pub fn New(dir: PathBuf) -> Self{
+        Self { Dir: dir }
+    }
+
+    /*
+        Hashes the sources of an atlas, in texture ID order. Missing files hash differently
+        from any real one, so adding the file later counts as a change
+    */
+    pub fn Key(sources: &[AtlasSource], maxTileSize: Option<u32>, padding: u32) -> u64{
+        let mut hash = ContentHash::New();
+        hash.WriteU32(ATLAS_FORMAT_VERSION);
+        hash.WriteU32(maxTileSize.unwrap_or(0));
+        hash.WriteU32(padding);
+        hash.WriteU32(sources.len() as u32);
+        for source in sources {
+            match source.Path {
+                Some(path) => {
+                    hash.WriteStr(path);
+                    match std::fs::read(path) {
+                        Ok(bytes) => {
+                            hash.WriteU32(bytes.len() as u32);
+                            hash.Write(&bytes);
+                        },
+                        Err(_) => hash.WriteStr("missing")
+                    }
+                },
+                None => hash.WriteStr("empty")
+            }
+
+            match source.Animation {
+                Some(animation) => {
+                    hash.WriteU32(animation.Frames.unwrap_or(0));
+                    hash.WriteU32(animation.FrameTime.to_bits());
+                    hash.WriteU32(animation.Interpolate as u32);
+                },
+                None => hash.WriteStr("still")
+            }
+
+            let (cols, rows) = source.Sheet.unwrap_or((0, 0));
+            hash.WriteU32(cols);
+            hash.WriteU32(rows);
+        }
+        hash.Finish()
+    }
+
+    //The atlas cached under this name, if it was built from sources with the same key
+    pub fn Load(&self, name: &str, key: u64) -> Option<(DynamicImage, AtlasLayout)>{
+        let dir = self.Dir.join(name);
+        let file = std::fs::File::open(dir.join("metadata.json")).ok()?;
+        let meta: AtlasMetadata = serde_json::from_reader(std::io::BufReader::new(file)).ok()?;
+        if meta.Hash != format!("{:016x}", key) {
+            return None;
+        }
+
+        let image = ImageReader::open(dir.join("atlas.png")).ok()?.decode().ok()?;
+        if image.dimensions() != (meta.Layout.Width, meta.Layout.Height) {
+            return None;
+        }
+        Some((DynamicImage::ImageRgba8(image.to_rgba8()), meta.Layout))
+    }
+
+    pub fn Store(&self, name: &str, key: u64, image: &RgbaImage, layout: &AtlasLayout) -> Result<(), String>{
+        let dir = self.Dir.join(name);
+        std::fs::create_dir_all(&dir)
+        .map_err(|e| format!("Could not create the atlas cache directory {}! The error:\n{}", dir.display(), e.to_string()))?;
+
+        //the old metadata goes first, so an image that's only half replaced is never taken for a good one
+        let metaPath = dir.join("metadata.json");
+        let _ = std::fs::remove_file(&metaPath);
+
+        image.save(dir.join("atlas.png"))
+        .map_err(|e| format!("Could not save the {} atlas to {}! The error:\n{}", name, dir.display(), e.to_string()))?;
+
+        let meta = AtlasMetadata { Hash: format!("{:016x}", key), Layout: layout.clone() };
+        let serialized = serde_json::to_string_pretty(&meta)
+        .map_err(|e| format!("Could not serialize the {} atlas metadata! The error:\n{}", name, e.to_string()))?;
+        std::fs::write(&metaPath, serialized)
+        .map_err(|e| format!("Could not write the {} atlas metadata to {}! The error:\n{}", name, metaPath.display(), e.to_string()))
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct AtlasMetadata{
+    Hash: String,
+    Layout: AtlasLayout,
+}
+
+//64 bit FNV-1a. Unlike std's DefaultHasher it never changes between Rust versions, which would throw the cache away
+pub struct ContentHash(u64);
+
+impl ContentHash{
+    pub fn New() -> Self{
+        Self(0xcbf29ce484222325)
+    }
+
+    pub fn Write(&mut self, bytes: &[u8]){
+        for byte in bytes {
+            self.0 ^= *byte as u64;
+            self.0 = self.0.wrapping_mul(0x100000001b3);
         }
     }
 
-    pub fn GrabSubImage(&self, row: u32, col: u32) ->  SubImage<&DynamicImage>{
-        let x = col * self.CellWidth;
-        let y = row * self.CellHeight;
-        SubImage::new(&self.Image, x, y, self.CellWidth, self.CellHeight) 
+    pub fn WriteU32(&mut self, val: u32){
+        self.Write(&val.to_le_bytes());
+    }
+
+    //Length prefixed, so "ab" + "c" and "a" + "bc" hash differently
+    pub fn WriteStr(&mut self, val: &str){
+        self.WriteU32(val.len() as u32);
+        self.Write(val.as_bytes());
     }
 
+    pub fn Finish(&self) -> u64{
+        self.0
+    }
+}
 
+//Copies an image onto the atlas at (x, y), repeating its outermost pixels 'padding' pixels out on every side
+fn Extrude(atlas: &mut RgbaImage, image: &RgbaImage, x: u32, y: u32, padding: u32){
+    let (width, height) = image.dimensions();
+    let padding = padding as i64;
+    for dy in -padding..height as i64 + padding {
+        for dx in -padding..width as i64 + padding {
+            let source = image.get_pixel(dx.clamp(0, width as i64 - 1) as u32, dy.clamp(0, height as i64 - 1) as u32);
+            atlas.put_pixel((x as i64 + dx) as u32, (y as i64 + dy) as u32, *source);
+        }
+    }
 }
 
 
@@ -134,7 +548,7 @@ impl CubeMapAtlas{
             CellHeight: cellHeight,
             CellWidth:  cellWidth,
             FaceHeight: (cellHeight as f32 / 3f32) as u32,
-            FaceWidth: (cellWidth as f32 / 3f32) as u32,
+            FaceWidth: (cellWidth as f32 / 4f32) as u32,
             format: channels
         })
     }
@@ -145,6 +559,7 @@ impl CubeMapAtlas{
             CubeMapFace::MiddleLeft => (0u32, self.FaceHeight),
             CubeMapFace::Middle => (self.FaceWidth, self.FaceHeight),
             CubeMapFace::MiddleRight => (self.FaceWidth * 2u32, self.FaceHeight),
+            CubeMapFace::Back => (self.FaceWidth * 3u32, self.FaceHeight),
             CubeMapFace::Bottom => (self.FaceWidth, self.FaceHeight * 2u32),
         };
 
@@ -153,4 +568,226 @@ impl CubeMapAtlas{
         SubImage::new(&self.Image, x, y, self.FaceWidth, self.FaceHeight) 
     }
 
+    /*
+        Copies the cross at (row, col) into a cube map. Faces are blitted from a texture of their own
+        since glium can't upload straight to one face of a cube map
+    */
+    pub fn CreateCubeMap(&self, row: u32, col: u32, display: &glium::Display) -> Result<glium::texture::SrgbCubemap, String>{
+        use glium::Surface;
+        if self.FaceWidth != self.FaceHeight || self.FaceWidth == 0 {
+            return Err(format!("Error! The faces of a cube map must be square, but they're {}x{}!", self.FaceWidth, self.FaceHeight));
+        }
+        if row >= self.Rows || col >= self.Columns {
+            return Err(format!("Error! There's no cube map at row {} and column {}, the atlas is {}x{}!", row, col, self.Rows, self.Columns));
+        }
+
+        let size = self.FaceWidth;
+        let cubeMap = glium::texture::SrgbCubemap::empty(display, size)
+        .map_err(|e| format!("Error! Could not create a cube map. The error:\n{}", e.to_string()))?;
+
+        for (face, layer) in &CubeMapFace::LAYERS {
+            //the first row of a cube map face is its top, so the face isn't flipped like other textures
+            let pixels = self.GrabSubImage(row, col, face).to_image();
+            let raw = glium::texture::RawImage2d::from_raw_rgba(pixels.into_raw(), (size, size));
+            let texture = glium::texture::SrgbTexture2d::new(display, raw)
+            .map_err(|e| format!("Error! Could not create a texture for a cube map face. The error:\n{}", e.to_string()))?;
+
+            let source = glium::framebuffer::SimpleFrameBuffer::new(display, &texture)
+            .map_err(|e| format!("Error! Could not read from a cube map face. The error:\n{}", e.to_string()))?;
+            let target = glium::framebuffer::SimpleFrameBuffer::new(display, cubeMap.main_level().image(*layer))
+            .map_err(|e| format!("Error! Could not write to a cube map face. The error:\n{}", e.to_string()))?;
+
+            source.blit_whole_color_to(&target, &glium::BlitTarget { left: 0, bottom: 0, width: size as i32, height: size as i32 },
+                                       glium::uniforms::MagnifySamplerFilter::Nearest);
+        }
+        Ok(cubeMap)
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //Every pixel says which texture it came from and where it was in it: (id, x, y, frame)
+    fn Tile(id: u8, width: u32, height: u32, frame: u8) -> RgbaImage {
+        RgbaImage::from_fn(width, height, |x, y| Rgba([id, x as u8, y as u8, frame]))
+    }
+
+    //A vertical strip of frames, each frame's alpha being its index
+    fn Strip(id: u8, width: u32, frameHeight: u32, frames: u32) -> DynamicImage {
+        let mut strip = RgbaImage::new(width, frameHeight * frames);
+        for i in 0..frames {
+            imageops::replace(&mut strip, &Tile(id, width, frameHeight, i as u8), 0, i * frameHeight);
+        }
+        DynamicImage::ImageRgba8(strip)
+    }
+
+    //The rectangle a texture's slot takes up on the atlas, gutters included, as (x, y, width, height)
+    fn Slot(region: &AtlasRegion, padding: u32) -> (u32, u32, u32, u32) {
+        (region.X - padding, region.Y - padding, region.Width + padding * 2, region.FrameStride * region.Frames)
+    }
+
+    fn Overlaps(a: (u32, u32, u32, u32), b: (u32, u32, u32, u32)) -> bool {
+        a.0 < b.0 + b.2 && b.0 < a.0 + a.2 && a.1 < b.1 + b.3 && b.1 < a.1 + a.3
+    }
+
+    //Checks that a frame of a texture is on the atlas as is, with its edges repeated 'padding' pixels out
+    fn AssertFrame(image: &RgbaImage, region: &AtlasRegion, frame: u32, id: u8, padding: u32) {
+        let frameRegion = region.Frame(frame);
+        let padding = padding as i64;
+        for dy in -padding..region.Height as i64 + padding {
+            for dx in -padding..region.Width as i64 + padding {
+                let sx = dx.clamp(0, region.Width as i64 - 1);
+                let sy = dy.clamp(0, region.Height as i64 - 1);
+                let pixel = image.get_pixel((frameRegion.X as i64 + dx) as u32, (frameRegion.Y as i64 + dy) as u32);
+                assert_eq!(pixel.0, [id, sx as u8, sy as u8, frame as u8], "texture {} frame {} at ({}, {})", id, frame, dx, dy);
+            }
+        }
+    }
+
+    #[test]
+    fn TilesOfDifferentSizesDontOverlap() {
+        let sizes = [(16, 16), (8, 24), (32, 4), (5, 7), (16, 16), (1, 1), (40, 12), (3, 30)];
+        let mut builder = AtlasBuilder::New(ATLAS_PADDING, None);
+        for (i, (w, h)) in sizes.iter().enumerate() {
+            assert_eq!(builder.Add(&DynamicImage::ImageRgba8(Tile(i as u8, *w, *h, 0))), i as u32);
+        }
+
+        let (image, layout) = builder.Build();
+        assert_eq!(image.dimensions(), (layout.Width, layout.Height));
+        assert!(layout.Width.is_power_of_two() && layout.Height.is_power_of_two());
+        assert_eq!(layout.Regions.len(), sizes.len());
+
+        for (i, region) in layout.Regions.iter().enumerate() {
+            assert_eq!((region.Width, region.Height, region.Frames), (sizes[i].0, sizes[i].1, 1));
+            let slot = Slot(region, ATLAS_PADDING);
+            assert!(slot.0 + slot.2 <= layout.Width && slot.1 + slot.3 <= layout.Height, "texture {} is off the atlas", i);
+            for (j, other) in layout.Regions.iter().enumerate().skip(i + 1) {
+                assert!(!Overlaps(slot, Slot(other, ATLAS_PADDING)), "textures {} and {} overlap", i, j);
+            }
+        }
+    }
+
+    #[test]
+    fn GuttersRepeatTheEdges() {
+        let mut builder = AtlasBuilder::New(ATLAS_PADDING, None);
+        builder.Add(&DynamicImage::ImageRgba8(Tile(1, 6, 3, 0)));
+        builder.Add(&DynamicImage::ImageRgba8(Tile(2, 2, 9, 0)));
+        builder.Add(&DynamicImage::ImageRgba8(Tile(3, 1, 1, 0)));
+
+        let (image, layout) = builder.Build();
+        for (i, region) in layout.Regions.iter().enumerate() {
+            AssertFrame(&image, region, 0, i as u8 + 1, ATLAS_PADDING);
+        }
+    }
+
+    #[test]
+    fn NoPaddingPacksTightly() {
+        let mut builder = AtlasBuilder::New(0, None);
+        for i in 0..4 {
+            builder.Add(&DynamicImage::ImageRgba8(Tile(i, 8, 8, 0)));
+        }
+        let (image, layout) = builder.Build();
+        assert_eq!((layout.Width, layout.Height), (16, 16));
+        for (i, region) in layout.Regions.iter().enumerate() {
+            AssertFrame(&image, region, 0, i as u8, 0);
+        }
+    }
+
+    #[test]
+    fn StripsGetASlotPerFrame() {
+        let mut builder = AtlasBuilder::New(ATLAS_PADDING, None);
+        builder.Add(&DynamicImage::ImageRgba8(Tile(1, 8, 8, 0)));
+        let square = TextureAnimation { Frames: None, FrameTime: 0.25, Interpolate: true };
+        assert_eq!(builder.AddStrip(&Strip(2, 8, 8, 4), &square).unwrap(), 1);
+        let counted = TextureAnimation { Frames: Some(3), FrameTime: 0.5, Interpolate: false };
+        assert_eq!(builder.AddStrip(&Strip(3, 10, 4, 3), &counted).unwrap(), 2);
+
+        assert!(builder.AddStrip(&Strip(4, 8, 8, 3), &TextureAnimation { Frames: Some(5), ..counted }).is_err());
+        assert!(builder.AddStrip(&Strip(4, 8, 8, 3), &TextureAnimation { FrameTime: 0f32, ..counted }).is_err());
+        assert_eq!(builder.Len(), 3);
+
+        let (image, layout) = builder.Build();
+        let (still, square, counted) = (&layout.Regions[0], &layout.Regions[1], &layout.Regions[2]);
+        assert_eq!((still.Frames, square.Frames, counted.Frames), (1, 4, 3));
+        assert_eq!((square.Width, square.Height, square.FrameStride), (8, 8, 8 + ATLAS_PADDING * 2));
+        assert_eq!((counted.Width, counted.Height, counted.FrameStride), (10, 4, 4 + ATLAS_PADDING * 2));
+        assert_eq!((square.FrameTime, square.Interpolate), (0.25, true));
+
+        //every frame has its own gutter, so frames never bleed into each other
+        for frame in 0..4 {
+            AssertFrame(&image, square, frame, 2, ATLAS_PADDING);
+        }
+        for frame in 0..3 {
+            AssertFrame(&image, counted, frame, 3, ATLAS_PADDING);
+        }
+        assert_eq!(square.Frame(5), square.Frame(1));
+
+        for (i, a) in layout.Regions.iter().enumerate() {
+            for b in layout.Regions.iter().skip(i + 1) {
+                assert!(!Overlaps(Slot(a, ATLAS_PADDING), Slot(b, ATLAS_PADDING)));
+            }
+        }
+    }
+
+    #[test]
+    fn SheetsAndShrinking() {
+        let mut builder = AtlasBuilder::New(0, Some(8));
+        let mut sheet = RgbaImage::new(8, 8);
+        for i in 0..4u32 {
+            imageops::replace(&mut sheet, &Tile(i as u8, 4, 4, 0), (i % 2) * 4, (i / 2) * 4);
+        }
+        assert_eq!(builder.AddSheet(&DynamicImage::ImageRgba8(sheet), 2, 2).unwrap(), 0);
+        assert!(builder.AddSheet(&DynamicImage::ImageRgba8(RgbaImage::new(6, 6)), 4, 4).is_err());
+        //too big, so it's scaled down keeping its shape
+        assert_eq!(builder.Add(&DynamicImage::ImageRgba8(Tile(9, 32, 16, 0))), 4);
+        builder.Add(&DynamicImage::ImageRgba8(RgbaImage::new(0, 0)));
+
+        let (image, layout) = builder.Build();
+        for i in 0..4 {
+            AssertFrame(&image, &layout.Regions[i], 0, i as u8, 0);
+        }
+        assert_eq!((layout.Regions[4].Width, layout.Regions[4].Height), (8, 4));
+        assert_eq!((layout.Regions[5].Width, layout.Regions[5].Height), (1, 1));
+    }
+
+    #[test]
+    fn UVTablePointsAtTheTextures() {
+        let mut builder = AtlasBuilder::New(ATLAS_PADDING, None);
+        builder.Add(&DynamicImage::ImageRgba8(Tile(1, 12, 5, 0)));
+        builder.AddStrip(&Strip(2, 6, 6, 2), &TextureAnimation { Frames: None, FrameTime: 0.1, Interpolate: true }).unwrap();
+        builder.Add(&DynamicImage::ImageRgba8(Tile(3, 3, 20, 0)));
+
+        let (image, layout) = builder.Build();
+        let (width, height) = (layout.Width as f32, layout.Height as f32);
+        let uvs = layout.UVTable();
+        let animations = layout.AnimationTable();
+        assert_eq!(uvs.len(), 3);
+        assert!(layout.UV(3).is_none());
+
+        for (i, [u, v, w, h]) in uvs.iter().enumerate() {
+            //back to pixels, the rectangle holds exactly the first frame of the texture
+            let (x, y) = ((u * width).round() as u32, (v * height).round() as u32);
+            let (rw, rh) = ((w * width).round() as u32, (h * height).round() as u32);
+            let region = &layout.Regions[i];
+            assert_eq!((x, y, rw, rh), (region.X, region.Y, region.Width, region.Height));
+            assert_eq!(image.get_pixel(x, y).0, [i as u8 + 1, 0, 0, 0]);
+            assert_eq!(image.get_pixel(x + rw - 1, y + rh - 1).0, [i as u8 + 1, rw as u8 - 1, rh as u8 - 1, 0]);
+        }
+
+        assert_eq!(animations[0], [1f32, (5 + ATLAS_PADDING * 2) as f32 / height, 0f32, 0f32]);
+        assert_eq!(animations[1], [2f32, (6 + ATLAS_PADDING * 2) as f32 / height, 0.1, 1f32]);
+        //the next frame is one stride further down
+        let [_, v, _, _] = uvs[1];
+        let next = ((v + animations[1][1]) * height).round() as u32;
+        assert_eq!(image.get_pixel(layout.Regions[1].X, next).0, [2, 0, 0, 1]);
+    }
+
+    #[test]
+    fn GridLayout() {
+        let layout = AtlasLayout::Grid(64, 32, 2, 4);
+        assert_eq!(layout.Regions.len(), 8);
+        assert_eq!(layout.UV(5).unwrap(), [0.25, 0.5, 0.25, 0.5]);
+    }
 }
\ No newline at end of file
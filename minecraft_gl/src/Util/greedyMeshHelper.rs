@@ -1,6 +1,6 @@
 use std::sync::Arc;
 use crate::{World::{chunk::{Chunk, CHUNK_BOUNDS_X, CHUNK_BOUNDS_Z}, 
-            block::{Block, TextureData, BlockRegistry}}, 
+            block::{Block, TextureData, BlockRegistry}, colormap::{Tint, ColumnTints, NO_TINT}}, 
             Renderer::worldRenderer::Vertex
            };
 
@@ -17,10 +17,26 @@ const Z_AXIS: usize = 2;
 enum BlockStateType {
     Solid,
     Air,
-    Flora,
+    Model,
     Empty
 }
 
+//Everything that decides how a face looks. Neighbouring faces are only merged into one quad if these are equal
+#[derive(PartialEq, Clone, Copy, Debug)]
+struct FaceInfo {
+    Block: Block,
+    FaceID: i32,
+    TextureID: i32,
+    Rotation: u8,
+    Animated: bool,
+    Tint: Option<Tint>,
+    /*
+        The tint at the face's corners, which the quad blends between. Merging only faces with the same corners
+        means a merged quad's colour doesn't change along the direction it was merged in, so it looks the same as the faces would
+    */
+    Corners: [u32; 4],
+}
+
 impl Into<bool> for BlockStateType {
     fn into(self) -> bool {
         match self {
@@ -31,9 +47,15 @@ impl Into<bool> for BlockStateType {
 }
 
 
-pub fn SweepVolume(chunk: &mut Chunk, dimensions: &[usize; 3], 
+/*
+    Origin is the world position of the grid's first block and scale is how many blocks wide each cell
+    of the grid is (more than 1 for downsampled grids). They're used to pick random texture variants
+    by world position, so a block keeps its variant whenever its chunk is meshed. Tinted faces take
+    the colour of the column at each of their corners from 'tints'
+*/
+pub fn SweepVolume(blocks: &Vec<Block>, mesh: &mut Vec<Vertex>, dimensions: &[usize; 3], 
                currentDimension: usize, adjacencyChunks: &[Option<Arc<Chunk>>; 4],
-               blockRegistry: &BlockRegistry) 
+               blockRegistry: &BlockRegistry, origin: [i32; 3], scale: i32, tints: &ColumnTints) 
 {
 
     /*
@@ -94,20 +116,21 @@ pub fn SweepVolume(chunk: &mut Chunk, dimensions: &[usize; 3],
      while indexHolder[currentDimension] < dimensions[currentDimension] as i32 {
 
         //Fill our mask for this plane slice
-        SweepPlane(chunk, &mut mask, axis1, axis2, dimensions, currentDimension, 
+        SweepPlane(blocks, &mut mask, axis1, axis2, dimensions, currentDimension, 
                    &mut indexHolder, &normalVector, adjacencyChunks, blockRegistry);
 
         //advance 
         indexHolder[currentDimension] += 1;
 
         //construct mesh
-        ConstructMeshFromMask(chunk, &mut mask, axis1, axis2, dimensions, 
-                              currentDimension, &mut indexHolder, blockRegistry);
+        ConstructMeshFromMask(blocks, mesh, &mut mask, axis1, axis2, dimensions, 
+                              currentDimension, &mut indexHolder, adjacencyChunks, blockRegistry,
+                              origin, scale, tints);
      }
      
 }
 
-fn SweepPlane(chunk: &mut Chunk, mask: &mut Vec<bool>, axis1: usize, axis2: usize, 
+fn SweepPlane(blocks: &Vec<Block>, mask: &mut Vec<bool>, axis1: usize, axis2: usize, 
               dimensions: &[usize; 3], currentDimension: usize, indexHolder: &mut [i32; 3], 
               normalVector: &[i32; 3], adjacencyChunks: &[Option<Arc<Chunk>>; 4],
               blockRegistry: &BlockRegistry) {
@@ -158,10 +181,10 @@ fn SweepPlane(chunk: &mut Chunk, mask: &mut Vec<bool>, axis1: usize, axis2: usiz
                 vector yields nothing back
             */
             let currentBlock = IsSolid(indexHolder.clone(), dimensions, 
-                                                    currentDimension, adjacencyChunks, &chunk.Blocks, blockRegistry);
+                                                    currentDimension, adjacencyChunks, blocks, blockRegistry);
 
             let nextBlock = IsSolid(AddArrayVector(indexHolder, normalVector).clone(), 
-                                                     dimensions, currentDimension, adjacencyChunks, &chunk.Blocks,
+                                                     dimensions, currentDimension, adjacencyChunks, blocks,
                                                      blockRegistry);
             
                
@@ -196,9 +219,10 @@ fn SweepPlane(chunk: &mut Chunk, mask: &mut Vec<bool>, axis1: usize, axis2: usiz
     }
 }
 
-fn ConstructMeshFromMask(chunk: &mut Chunk, mask: &mut Vec<bool>, axis1: usize, axis2: usize, 
+fn ConstructMeshFromMask(blocks: &Vec<Block>, mesh: &mut Vec<Vertex>, mask: &mut Vec<bool>, axis1: usize, axis2: usize, 
     dimensions: &[usize; 3], currentDimension: usize, indexHolder: &mut [i32; 3], 
-    blockRegistry: &BlockRegistry) 
+    adjacencyChunks: &[Option<Arc<Chunk>>; 4], blockRegistry: &BlockRegistry, origin: [i32; 3], scale: i32,
+    tints: &ColumnTints) 
 {
 
         /*
@@ -226,19 +250,18 @@ fn ConstructMeshFromMask(chunk: &mut Chunk, mask: &mut Vec<bool>, axis1: usize,
                 indexHolder[axis2] = i as i32;
                 indexHolder[axis1] = j as i32;
 
-                //Get the current block and pass it to the following functions
-                let currBlock =  GetBlock(indexHolder.clone(), currentDimension, 
-                                                    &chunk.Blocks, dimensions, blockRegistry);
+                //Get the current face and pass it to the following functions
+                let currFace = GetFace(indexHolder.clone(), currentDimension, axis1, axis2, blocks, 
+                                       dimensions, adjacencyChunks, blockRegistry, origin, scale, tints);
 
                 //Get the width and height of this quad        
                 let (width, height) = GetFaceDimensions(mask, axis1, axis2, dimensions, 
-                                                        currentDimension, indexHolder, i, j, n, currBlock,
-                                                        &chunk.Blocks, blockRegistry);
+                                                        currentDimension, indexHolder, i, j, n, currFace,
+                                                        blocks, adjacencyChunks, blockRegistry, origin, scale, tints);
                     
                 //Construct the face mesh and put it into our chunk
-                ConstructFace(chunk, indexHolder.clone(), currentDimension, dimensions, 
-                              blockRegistry, currBlock, width, height, axis1,
-                              axis2);
+                ConstructFace(mesh, indexHolder.clone(), currentDimension, currFace, 
+                              width, height, axis1, axis2, tints, scale);
 
                 //clear the mask to prevent the creation of duplicate faces
                 for l in 0..height {
@@ -257,7 +280,8 @@ fn ConstructMeshFromMask(chunk: &mut Chunk, mask: &mut Vec<bool>, axis1: usize,
 
 fn GetFaceDimensions(mask: &Vec<bool>, axis1: usize, axis2: usize, dimensions: &[usize; 3], 
     currentDimension: usize, indexHolder: &[i32; 3], i: usize, j: usize, n: usize, 
-    currBlock: Block, blocks: &Vec<Block>, blockRegistry: &BlockRegistry) -> (usize, usize)
+    currFace: FaceInfo, blocks: &Vec<Block>, adjacencyChunks: &[Option<Arc<Chunk>>; 4],
+    blockRegistry: &BlockRegistry, origin: [i32; 3], scale: i32, tints: &ColumnTints) -> (usize, usize)
 {
     //Make a copy of indexholder
     let mut holderCopy = indexHolder.clone();
@@ -265,8 +289,8 @@ fn GetFaceDimensions(mask: &Vec<bool>, axis1: usize, axis2: usize, dimensions: &
     /*  
         Sweep over the width axis (axis 1). Extend out more
         and more until we reach an empty block (mask is false),
-        reach out of bounds, or the next block isn't equal to the
-        current block
+        reach out of bounds, or the next face doesn't look the same
+        as the current face (another block, texture variant or rotation)
 
         Note that all quads are automatically width and height = 1,
         so we start by defining that before the loop
@@ -274,8 +298,8 @@ fn GetFaceDimensions(mask: &Vec<bool>, axis1: usize, axis2: usize, dimensions: &
     let mut width = 1;
     holderCopy[axis1] += 1;
     while j + width < dimensions[axis1] && mask[n + width] &&
-          currBlock == GetBlock(holderCopy.clone(), currentDimension, 
-                                blocks, dimensions, blockRegistry)
+          currFace == GetFace(holderCopy.clone(), currentDimension, axis1, axis2, blocks, 
+                              dimensions, adjacencyChunks, blockRegistry, origin, scale, tints)
     {
         width += 1;
         holderCopy[axis1] += 1;
@@ -301,8 +325,8 @@ fn GetFaceDimensions(mask: &Vec<bool>, axis1: usize, axis2: usize, dimensions: &
 
             //if there isn't a solid face present, the quad has a hole in it. Exit
             if !mask[k + n + height * dimensions[axis1]] || 
-                currBlock != GetBlock(holderCopy.clone(), currentDimension, 
-                                      blocks, dimensions, blockRegistry) 
+                currFace != GetFace(holderCopy.clone(), currentDimension, axis1, axis2, blocks, 
+                                    dimensions, adjacencyChunks, blockRegistry, origin, scale, tints) 
             {  
                 return (width, height);
             }
@@ -316,9 +340,8 @@ fn GetFaceDimensions(mask: &Vec<bool>, axis1: usize, axis2: usize, dimensions: &
     (width, height)
 }
 
-fn ConstructFace(chunk: &mut Chunk, indexHolder: [i32; 3], currentDimension: usize,  
-    dimensions: &[usize; 3], blockRegistry: &BlockRegistry, currBlock: Block,
-    mut width: usize, mut height: usize, axis1: usize, axis2: usize) 
+fn ConstructFace(mesh: &mut Vec<Vertex>, indexHolder: [i32; 3], currentDimension: usize, face: FaceInfo,
+    mut width: usize, mut height: usize, axis1: usize, axis2: usize, tints: &ColumnTints, scale: i32) 
 {
     //Array vectors to store the width and height
     let mut dimensionsQuadWidth = [0; 3];
@@ -326,6 +349,40 @@ fn ConstructFace(chunk: &mut Chunk, indexHolder: [i32; 3], currentDimension: usi
     dimensionsQuadWidth[axis1] = width as i32;
     dimensionsQuadHeight[axis2] = height as i32;
 
+    /*
+        For some reason the x axis is oreinted sideways so we
+        have to do this to fix it (swap width height)
+    */
+    if currentDimension == X_AXIS {
+        let temp = dimensionsQuadWidth;
+        dimensionsQuadWidth = dimensionsQuadHeight;
+        dimensionsQuadHeight = temp;
+
+        let tmp = width;
+        width = height;
+        height = tmp;
+    }
+
+    //a merged face blends between the colours at its corners
+    let tintAt = |point: &[i32; 3]| match face.Tint {
+        Some(tint) => tints.At(tint, point[X_AXIS] * scale, point[Z_AXIS] * scale),
+        None => NO_TINT
+    };
+
+    ConstructStandardFace(mesh, &indexHolder, face.TextureID, face.Rotation, face.Animated, face.FaceID,
+                             width, height, &dimensionsQuadWidth,
+                             &dimensionsQuadHeight, &tintAt);
+
+
+}
+
+fn GetFace(point: [i32; 3], currentDimension: usize, axis1: usize, axis2: usize, blocks: &Vec<Block>,
+    dimensions: &[usize; 3], adjacencyChunks: &[Option<Arc<Chunk>>; 4], blockRegistry: &BlockRegistry, 
+    origin: [i32; 3], scale: i32, tints: &ColumnTints) -> FaceInfo
+{
+    let (block, position) = GetBlockAndPosition(point.clone(), currentDimension, 
+                                                blocks, dimensions, blockRegistry);
+
     /*
         The face ID tells us which face we are currently looking at
         There are 6 faces in total distributed as so...
@@ -338,62 +395,120 @@ fn ConstructFace(chunk: &mut Chunk, indexHolder: [i32; 3], currentDimension: usi
         depending on whether the block in the next plane slice 
         (plus the normal vector) is the same as the current block
     */
-    let mut tmp = indexHolder.clone();
+    let mut tmp = point.clone();
     tmp[currentDimension] += 1;
 
     let block1 = GetBlock(tmp.clone(), currentDimension,
-                        &chunk.Blocks, dimensions, blockRegistry);
-    let cond = block1 == currBlock;
+                        blocks, dimensions, blockRegistry);
+    let cond = block1 == block;
 
     let fid: i32 = cond as i32 + currentDimension as i32 * 2;
 
-
-    let mut texid = -1;
+    let mut face = FaceInfo { Block: block, FaceID: fid, TextureID: -1, Rotation: 0, Animated: false, Tint: None, Corners: [NO_TINT; 4] };
+    let tint = blockRegistry.GetAttributesOf(&block).Tint;
     if let TextureData::SixSided(data) = 
-           &blockRegistry.GetAttributesOf(&currBlock).TextureData 
+           &blockRegistry.GetAttributesOf(&block).TextureData 
     {
-            texid = data.TextureID as i32 + data.Offsets[fid as usize] as i32;
+        let texture = &data.Faces[fid as usize];
+        let connections = if texture.IsConnected() {
+            Connections(position, block, currentDimension, axis1, axis2, blocks, dimensions, adjacencyChunks)
+        } else { 0 };
+
+        let world = [origin[0] + position[0] * scale, origin[1] + position[1] * scale, origin[2] + position[2] * scale];
+        let (texid, rotation, animated) = texture.Pick(data.TextureID, world, fid, connections);
+        face.TextureID = texid as i32;
+        face.Rotation = rotation;
+        face.Animated = animated;
+        face.Tint = tint.filter(|_| data.Tinted[fid as usize]);
     } 
     else if let TextureData::Single(data) = 
-                  &blockRegistry.GetAttributesOf(&currBlock).TextureData 
+                  &blockRegistry.GetAttributesOf(&block).TextureData 
     {
-        texid = data.TextureID as i32;
+        face.TextureID = data.TextureID as i32;
+        face.Animated = data.Animated;
+        face.Tint = tint;
     }
-        
 
+    //the same corners ConstructFace samples for a one block quad
+    if let Some(tint) = face.Tint {
+        let corner = |a: i32, b: i32| {
+            let mut corner = point;
+            corner[axis1] += a;
+            corner[axis2] += b;
+            tints.At(tint, corner[X_AXIS] * scale, corner[Z_AXIS] * scale)
+        };
+        face.Corners = [corner(0, 0), corner(1, 0), corner(0, 1), corner(1, 1)];
+    }
 
-    /*
-        For some reason the x axis is oreinted sideways so we
-        have to do this to fix it (swap width height)
-    */
-    if currentDimension == X_AXIS {
-        let temp = dimensionsQuadWidth;
-        dimensionsQuadWidth = dimensionsQuadHeight;
-        dimensionsQuadHeight = temp;
+    face
+}
 
-        let tmp = width;
-        width = height;
-        height = tmp;
+/*
+    Which sides of a face have the same block next to them, as the bits 1 up, 2 right, 4 down and 8 left
+    of the face's texture. Going up the texture is going along the quad's height and going right is going
+    back along its width (see the uv math in world.glsl)
+*/
+fn Connections(position: [i32; 3], block: Block, currentDimension: usize, axis1: usize, axis2: usize,
+               blocks: &Vec<Block>, dimensions: &[usize; 3], adjacencyChunks: &[Option<Arc<Chunk>>; 4]) -> u8
+{
+    //The quad's width and height axes, swapped on the x axis like in ConstructFace
+    let (widthAxis, heightAxis) = if currentDimension == X_AXIS { (axis2, axis1) } else { (axis1, axis2) };
+    let directions = [(heightAxis, 1), (widthAxis, -1), (heightAxis, -1), (widthAxis, 1)];
+
+    let mut connections = 0;
+    for (i, (axis, step)) in directions.iter().enumerate() {
+        let mut neighbour = position;
+        neighbour[*axis] += step;
+        if BlockAt(neighbour, blocks, dimensions, adjacencyChunks) == Some(block) {
+            connections |= 1 << i;
+        }
     }
+    connections
+}
 
-    ConstructStandardFace(chunk, &indexHolder, texid, fid,
-                             width, height, &dimensionsQuadWidth,
-                             &dimensionsQuadHeight);
+//The block at a point that may be one block into a neighbouring chunk. None if there's no block there
+pub fn BlockAt(mut point: [i32; 3], blocks: &Vec<Block>, dimensions: &[usize; 3], 
+           adjacencyChunks: &[Option<Arc<Chunk>>; 4]) -> Option<Block>
+{
+    if point[Y_AXIS] < 0 || point[Y_AXIS] >= dimensions[Y_AXIS] as i32 {
+        return None;
+    }
 
+    let mut adjacentChunkIdx = -1;
+    if point[X_AXIS] == -1 {
+        point[X_AXIS] = dimensions[X_AXIS] as i32 - 1;
+        adjacentChunkIdx = ADJACENT_LEFT;
+    }
+    else if point[X_AXIS] == dimensions[X_AXIS] as i32 {
+        point[X_AXIS] = 0;
+        adjacentChunkIdx = ADJACENT_RIGHT;
+    }
+    else if point[Z_AXIS] == -1 {
+        point[Z_AXIS] = dimensions[Z_AXIS] as i32 - 1;
+        adjacentChunkIdx = ADJACENT_UP;
+    }
+    else if point[Z_AXIS] == dimensions[Z_AXIS] as i32 {
+        point[Z_AXIS] = 0;
+        adjacentChunkIdx = ADJACENT_DOWN;
+    }
 
+    if adjacentChunkIdx == -1 {
+        return Some(blocks[To1DWithDims(&point, dimensions)]);
+    }
+    adjacencyChunks[adjacentChunkIdx as usize].as_ref().map(|chunk| chunk.Blocks[To1DWithDims(&point, dimensions)])
 }
 
-fn ConstructStandardFace(chunk: &mut Chunk, indexHolder: &[i32; 3], texId: i32, fId: i32,
+fn ConstructStandardFace(mesh: &mut Vec<Vertex>, indexHolder: &[i32; 3], texId: i32, rotation: u8, animated: bool, fId: i32,
                          width: usize, height: usize, dimensionsQuadWidth: &[i32; 3],
-                         dimensionsQuadHeight: &[i32; 3]) 
+                         dimensionsQuadHeight: &[i32; 3], tintAt: &dyn Fn(&[i32; 3]) -> u32) 
 {
     //vertex ID offsets
     let offsets = [3, 2, 1, 0];
 
     //index holder is the top left of our quad
     //Top-left vertice position
-    AddVertex(&indexHolder, texId, offsets[0], 
-        fId, width, height, &mut chunk.Mesh); 
+    AddVertex(&indexHolder, texId, rotation, animated, offsets[0], 
+        fId, width, height, tintAt(indexHolder), mesh); 
 
 
     //Top right vertice position
@@ -402,8 +517,8 @@ fn ConstructStandardFace(chunk: &mut Chunk, indexHolder: &[i32; 3], texId: i32,
                 indexHolder[1] + dimensionsQuadWidth[1], 
                 indexHolder[2] + dimensionsQuadWidth[2]
             ];
-    AddVertex(&arr, texId, offsets[1], 
-            fId, width, height, &mut chunk.Mesh); 
+    AddVertex(&arr, texId, rotation, animated, offsets[1], 
+            fId, width, height, tintAt(&arr), mesh); 
                          
     //Bottom left vertice position
     let arr = [
@@ -411,8 +526,8 @@ fn ConstructStandardFace(chunk: &mut Chunk, indexHolder: &[i32; 3], texId: i32,
                 indexHolder[1] + dimensionsQuadHeight[1], 
                 indexHolder[2] + dimensionsQuadHeight[2]
             ];  
-    AddVertex(&arr, texId, offsets[2], 
-            fId, width, height, &mut chunk.Mesh);   
+    AddVertex(&arr, texId, rotation, animated, offsets[2], 
+            fId, width, height, tintAt(&arr), mesh);   
 
     //Bottom right vertice position
     let arr = [
@@ -421,40 +536,8 @@ fn ConstructStandardFace(chunk: &mut Chunk, indexHolder: &[i32; 3], texId: i32,
                 indexHolder[2] + dimensionsQuadWidth[2] + dimensionsQuadHeight[2]
             ];
 
-    AddVertex(&arr, texId, offsets[3], 
-        fId, width, height, &mut chunk.Mesh);    
-}
-
-pub fn ConstructFloraFaces(chunk: &mut Chunk, indexHolder: &[i32; 3], texId: i32) 
-{   
-    let offsets = [3, 2, 1, 0];
-    let fId = 0;
-
-    let start1 = *indexHolder;
-    let start2 = AddArrayVector(indexHolder, &[1, 0, 0]);
-    let starts = [(start1, 1), (start2, -1)];
-
-    for (start, sign) in starts {
-        let arr = AddArrayVector(&start, &[0, -1, 0]);
-        AddVertex(&arr, texId, offsets[1], 
-            fId, 1, 1, &mut chunk.Mesh); 
-    
-        //+1 on height
-        let arr= AddArrayVector(&start, &[0, 0, 0]);
-        AddVertex(&arr, texId, offsets[3], 
-                fId, 1, 1, &mut chunk.Mesh); 
-                             
-        //+-1 across
-        let arr = AddArrayVector(&start, &[sign, -1, 1]);
-        AddVertex(&arr, texId, offsets[0], 
-                fId, 1, 1, &mut chunk.Mesh);   
-    
-        //+-1 across and +1 height
-        let arr = AddArrayVector(&start, &[sign, 0, 1]);
-        AddVertex(&arr, texId, offsets[2], 
-            fId, 1, 1, &mut chunk.Mesh); 
-    }
-
+    AddVertex(&arr, texId, rotation, animated, offsets[3], 
+        fId, width, height, tintAt(&arr), mesh);    
 }
 
 fn IsSolid(mut point: [i32; 3], dimensions: &[usize; 3], currentDimension: usize, 
@@ -506,42 +589,53 @@ fn IsSolid(mut point: [i32; 3], dimensions: &[usize; 3], currentDimension: usize
 
     //If we don't need to sample an adjacent chunk then...
     if adjacentChunkIdx == -1 {
-        let block = blocks[To1D(&point)] ;
+        let block = blocks[To1DWithDims(&point, dimensions)];
         let air =  block == Block::Air();
 
         if air {return BlockStateType::Air}
 
-        let isFlora = IsFlora(block, blockRegistry);
-        if isFlora {return BlockStateType::Flora} else {return BlockStateType::Solid};
+        let isModel = HasModel(block, blockRegistry);
+        if isModel {return BlockStateType::Model} else {return BlockStateType::Solid};
     }
 
     //Else we must sample an adjacent chunk, but only if it's Some()...
     assert!(adjacentChunkIdx >= 0 && adjacentChunkIdx < 4);
     if let Some(chunk) = adjacencyChunks[adjacentChunkIdx as usize].clone() {
-        let block = (*chunk).Blocks[To1D(&point)];
+        let block = (*chunk).Blocks[To1DWithDims(&point, dimensions)];
         let air = block == Block::Air();
 
         if air {return BlockStateType::Air}
 
-        let isFlora = IsFlora(block, blockRegistry);
-        if isFlora {return BlockStateType::Flora} else {return BlockStateType::Solid};
+        let isModel = HasModel(block, blockRegistry);
+        if isModel {return BlockStateType::Model} else {return BlockStateType::Solid};
     }
 
     BlockStateType::Empty
 }
 
-fn AddVertex(point: &[i32; 3], textureId: i32, vertexId: i32, faceId: i32,
-             width: usize, height: usize, mesh: &mut Vec<Vertex>)
+/*
+    The animated bit tells the shader to look up the texture's current frame. Rotation is in clockwise quarter turns
+    and the tint is the 0xRRGGBB colour the texture is multiplied by
+*/
+fn AddVertex(point: &[i32; 3], textureId: i32, rotation: u8, animated: bool, vertexId: i32, faceId: i32,
+             width: usize, height: usize, tint: u32, mesh: &mut Vec<Vertex>)
 {
     let core = point[0] | point[2] << 4 | point[1] << 8 | textureId << 16 | 
-               vertexId << 24 | faceId << 26;
+               vertexId << 24 | faceId << 26 | (animated as i32) << 29 | (rotation as i32 & 0x3) << 30;
     let dims = width | height << 16;
 
-    mesh.push(Vertex {Core: core as u32, Dims: dims as u32} );
+    mesh.push(Vertex {Core: core as u32, Dims: dims as u32, Detail: 0, Tint: tint} );
 }
 
-fn GetBlock(mut point: [i32; 3], currentDimension: usize, blocks: &Vec<Block>,
+fn GetBlock(point: [i32; 3], currentDimension: usize, blocks: &Vec<Block>,
             dimensions: &[usize; 3], blockRegistry: &BlockRegistry) -> Block 
+{
+    GetBlockAndPosition(point, currentDimension, blocks, dimensions, blockRegistry).0
+}
+
+//Same as GetBlock, but also gives where the block is in the grid
+fn GetBlockAndPosition(mut point: [i32; 3], currentDimension: usize, blocks: &Vec<Block>,
+            dimensions: &[usize; 3], blockRegistry: &BlockRegistry) -> (Block, [i32; 3]) 
 {
     /*
         Imagine we have 2 blocks and 3 faces oriented as such
@@ -575,10 +669,10 @@ fn GetBlock(mut point: [i32; 3], currentDimension: usize, blocks: &Vec<Block>,
         1. Else we return block 0
     */
     point[currentDimension] = (point[currentDimension] - 1).max(0);
-    let mut block = blocks[To1D(&point)].clone();
+    let mut block = blocks[To1DWithDims(&point, dimensions)].clone();
 
-    //treat flora as air
-    if IsFlora(block, blockRegistry) {
+    //blocks with models are meshed on their own, so treat them as air
+    if HasModel(block, blockRegistry) {
         block = Block::Air()
     }
 
@@ -590,10 +684,10 @@ fn GetBlock(mut point: [i32; 3], currentDimension: usize, blocks: &Vec<Block>,
        point[currentDimension] < dimensions[currentDimension] as i32 -1
     {
         point[currentDimension] += 1;
-        block = blocks[To1D(&point)].clone();
+        block = blocks[To1DWithDims(&point, dimensions)].clone();
     }
 
-    block
+    (block, point)
 }
 
 pub fn AddArrayVector(v1: &[i32; 3], v2: &[i32; 3]) -> [i32; 3] {
@@ -605,7 +699,98 @@ pub fn To1D(cord: &[i32; 3]) -> usize {
     t as usize
 }
 
-pub fn IsFlora(block: Block, blockRegistry: &BlockRegistry) -> bool {
-    blockRegistry.NameToID("Flower").filter(|e| block.ID == *e).is_some() ||
-    blockRegistry.NameToID("tallGrass").filter(|e| block.ID == *e).is_some()
+//Same as To1D, but for voxel grids that aren't chunk sized (such as downsampled level of detail grids)
+pub fn To1DWithDims(cord: &[i32; 3], dimensions: &[usize; 3]) -> usize {
+    let t = cord[0] + dimensions[0] as i32 * (cord[2] + cord[1] * dimensions[2] as i32);
+    t as usize
+}
+
+//Blocks drawn with a model instead of as a cube (flowers, slabs, fences...). See modelMeshHelper
+pub fn HasModel(block: Block, blockRegistry: &BlockRegistry) -> bool {
+    blockRegistry.GetAttributesOf(&block).Model.is_some()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::World::{ReadAttributes, contentPack::ContentPacks, idMap::IdMap, block::BlockRegistry, item::ItemRegistry,
+                       crafting::CraftingRegistry, smelting::SmeltingRegistry};
+
+    const SIZE: usize = 4;
+
+    fn Registry() -> BlockRegistry {
+        let packs = ContentPacks::Core().unwrap();
+        let (mut blocks, mut items, mut crafting, mut smelting) = (BlockRegistry::New(), ItemRegistry::New(), CraftingRegistry::New(), SmeltingRegistry::New());
+        ReadAttributes(&mut blocks, &mut items, &mut crafting, &mut smelting, &packs, &mut IdMap::default()).unwrap();
+        blocks
+    }
+
+    //The top faces of a layer of leaves, as (min x, min z, max x, max z) and the tint of each corner by position
+    fn TopQuads(registry: &BlockRegistry, tints: &ColumnTints) -> Vec<([i32; 4], Vec<([i32; 2], u32)>)> {
+        let leaves = Block { ID: registry.NameToID("core:leaves").unwrap() };
+        let dimensions = [SIZE, 2, SIZE];
+        let mut blocks = vec![leaves; SIZE * SIZE];
+        blocks.resize(SIZE * SIZE * 2, Block::Air());
+
+        let mut mesh = Vec::new();
+        SweepVolume(&blocks, &mut mesh, &dimensions, Y_AXIS, &[None, None, None, None], registry, [0; 3], 1, tints);
+
+        mesh.chunks(4).filter_map(|quad| {
+            let corners: Vec<([i32; 2], u32)> = quad.iter().map(|v| ([(v.Core & 0xF) as i32, (v.Core >> 4 & 0xF) as i32], v.Tint)).collect();
+            if quad.iter().any(|v| v.Core >> 8 & 0xFF != 1) {
+                return None;
+            }
+            let (xs, zs): (Vec<i32>, Vec<i32>) = corners.iter().map(|(p, _)| (p[0], p[1])).unzip();
+            Some(([*xs.iter().min().unwrap(), *zs.iter().min().unwrap(), *xs.iter().max().unwrap(), *zs.iter().max().unwrap()], corners))
+        }).collect()
+    }
+
+    //What the quad blends to at a point, as the shader would
+    fn Blended(rect: [i32; 4], corners: &Vec<([i32; 2], u32)>, x: i32, z: i32) -> [f32; 3] {
+        let at = |cx: i32, cz: i32| corners.iter().find(|(p, _)| *p == [cx, cz]).unwrap().1;
+        let (u, v) = ((x - rect[0]) as f32 / (rect[2] - rect[0]) as f32, (z - rect[1]) as f32 / (rect[3] - rect[1]) as f32);
+        [16, 8, 0].map(|shift| {
+            let channel = |c: u32| (c >> shift & 0xFF) as f32;
+            let top = channel(at(rect[0], rect[1])) * (1f32 - u) + channel(at(rect[2], rect[1])) * u;
+            let bottom = channel(at(rect[0], rect[3])) * (1f32 - u) + channel(at(rect[2], rect[3])) * u;
+            top * (1f32 - v) + bottom * v
+        })
+    }
+
+    #[test]
+    fn EvenTintsMerge() {
+        let registry = Registry();
+        let tints = ColumnTints::Blend(registry.GetColormaps(), (0, 0), &mut |_, _| (0.7, 0.4));
+        let quads = TopQuads(&registry, &tints);
+        assert_eq!(quads.len(), 1);
+        assert_eq!(quads[0].0, [0, 0, SIZE as i32, SIZE as i32]);
+    }
+
+    #[test]
+    fn MergedFacesKeepEveryColumnsTint() {
+        let registry = Registry();
+        //a climate that changes unevenly from column to column, even after blending
+        let tints = ColumnTints::Blend(registry.GetColormaps(), (0, 0), &mut |x, z| (((x * x + z * 3) % 11) as f32 / 10f32, 0.6));
+        let quads = TopQuads(&registry, &tints);
+
+        let mut covered = 0;
+        for (rect, corners) in &quads {
+            covered += (rect[2] - rect[0]) * (rect[3] - rect[1]);
+            for (p, tint) in corners {
+                assert_eq!(*tint, tints.At(Tint::Foliage, p[0], p[1]));
+            }
+            //every column corner the quad covers ends up the colour of that column
+            for x in rect[0]..=rect[2] {
+                for z in rect[1]..=rect[3] {
+                    let expected = tints.At(Tint::Foliage, x, z);
+                    let blended = Blended(*rect, corners, x, z);
+                    for (channel, shift) in [16, 8, 0].iter().enumerate() {
+                        assert!((blended[channel] - (expected >> shift & 0xFF) as f32).abs() < 0.5,
+                                "the quad {:?} blends to {:?} at ({}, {}) instead of {:06X}", rect, blended, x, z, expected);
+                    }
+                }
+            }
+        }
+        assert_eq!(covered, (SIZE * SIZE) as i32);
+    }
 }
\ No newline at end of file
@@ -0,0 +1,276 @@
+use crate::World::{chunk::{CHUNK_BOUNDS_X, CHUNK_BOUNDS_Y, CHUNK_BOUNDS_Z},
+                   block::{Block, BlockRegistry}
+                  };
+use super::greedyMeshHelper::{self, HasModel};
+
+/*
+    Rings of chunks (in chebyshev distance from the target chunk) that are meshed
+    at each level of detail. Anything past the quarter ring becomes a heightmap impostor
+*/
+pub const LOD_FULL_RING: u32 = 2;
+pub const LOD_HALF_RING: u32 = 5;
+pub const LOD_QUARTER_RING: u32 = 10;
+
+//The order of the adjacency array used by the mesher. Left, right, up, down
+pub const ADJACENT_OFFSETS: [(i32, i32); 4] = [(-1, 0), (1, 0), (0, -1), (0, 1)];
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash)]
+pub enum LodLevel {
+    Full,
+    Half,
+    Quarter,
+    HeightMap,
+}
+
+impl LodLevel {
+    pub fn ForRing(ring: u32) -> Self {
+        match ring {
+            _ if ring <= LOD_FULL_RING => LodLevel::Full,
+            _ if ring <= LOD_HALF_RING => LodLevel::Half,
+            _ if ring <= LOD_QUARTER_RING => LodLevel::Quarter,
+            _ => LodLevel::HeightMap
+        }
+    }
+
+    pub fn ForChunk(chunkPos: (i32, i32), targetPos: (i32, i32)) -> Self {
+        Self::ForRing(RingDistance(chunkPos, targetPos))
+    }
+
+    //The levels of detail of the four chunks adjacent to chunkPos, in the order of ADJACENT_OFFSETS
+    pub fn ForNeighbours(chunkPos: (i32, i32), targetPos: (i32, i32)) -> [Self; 4] {
+        let mut lods = [LodLevel::Full; 4];
+        for i in 0..4 {
+            let pos = (chunkPos.0 + ADJACENT_OFFSETS[i].0, chunkPos.1 + ADJACENT_OFFSETS[i].1);
+            lods[i] = Self::ForChunk(pos, targetPos);
+        }
+        lods
+    }
+
+    //How many blocks (on each axis) one cell of this level of detail covers
+    pub fn Factor(&self) -> usize {
+        match self {
+            LodLevel::Full => 1,
+            LodLevel::Half => 2,
+            LodLevel::Quarter | LodLevel::HeightMap => 4,
+        }
+    }
+
+    //Dimensions of the downsampled voxel grid. Partial cells on the edges are rounded up
+    pub fn Dimensions(&self) -> [usize; 3] {
+        let f = self.Factor();
+        [
+            (CHUNK_BOUNDS_X as usize + f - 1) / f,
+            (CHUNK_BOUNDS_Y as usize + f - 1) / f,
+            (CHUNK_BOUNDS_Z as usize + f - 1) / f,
+        ]
+    }
+}
+
+pub fn RingDistance(chunkPos: (i32, i32), targetPos: (i32, i32)) -> u32 {
+    i32::max((chunkPos.0 - targetPos.0).abs(), (chunkPos.1 - targetPos.1).abs()) as u32
+}
+
+pub fn Downsample(blocks: &Vec<Block>, lod: LodLevel, blockRegistry: &BlockRegistry) -> Vec<Block> {
+    match lod {
+        LodLevel::Full => blocks.clone(),
+        LodLevel::HeightMap => DownsampleHeightMap(blocks, lod, blockRegistry),
+        _ => DownsampleVoxels(blocks, lod, blockRegistry)
+    }
+}
+
+fn DownsampleVoxels(blocks: &Vec<Block>, lod: LodLevel, blockRegistry: &BlockRegistry) -> Vec<Block> {
+    let f = lod.Factor();
+    let dims = lod.Dimensions();
+    let mut out = vec![Block::Air(); dims[0] * dims[1] * dims[2]];
+
+    for cy in 0..dims[1] {
+        for cz in 0..dims[2] {
+            for cx in 0..dims[0] {
+
+                /*
+                    A cell is solid if at least half of the blocks it covers are solid. It takes
+                    the type of its highest solid block, since that is the one visible from above
+                    (so grass stays on top of dirt instead of being voted away)
+                */
+                let mut solid = 0;
+                let mut total = 0;
+                let mut top = Block::Air();
+                for y in cy * f..usize::min((cy + 1) * f, CHUNK_BOUNDS_Y as usize) {
+                    for z in cz * f..usize::min((cz + 1) * f, CHUNK_BOUNDS_Z as usize) {
+                        for x in cx * f..usize::min((cx + 1) * f, CHUNK_BOUNDS_X as usize) {
+                            total += 1;
+                            let block = blocks[greedyMeshHelper::To1D(&[x as i32, y as i32, z as i32])];
+                            if block == Block::Air() || HasModel(block, blockRegistry) {
+                                continue;
+                            }
+                            solid += 1;
+                            top = block;
+                        }
+                    }
+                }
+
+                if solid * 2 >= total && solid > 0 {
+                    out[greedyMeshHelper::To1DWithDims(&[cx as i32, cy as i32, cz as i32], &dims)] = top;
+                }
+            }
+        }
+    }
+    out
+}
+
+fn DownsampleHeightMap(blocks: &Vec<Block>, lod: LodLevel, blockRegistry: &BlockRegistry) -> Vec<Block> {
+    let f = lod.Factor();
+    let dims = lod.Dimensions();
+    let mut out = vec![Block::Air(); dims[0] * dims[1] * dims[2]];
+
+    for cz in 0..dims[2] {
+        for cx in 0..dims[0] {
+
+            //Find the tallest surface within the cell's columns. Caves and overhangs are thrown away
+            let mut height = 0;
+            let mut surface = Block::Air();
+            for z in cz * f..usize::min((cz + 1) * f, CHUNK_BOUNDS_Z as usize) {
+                for x in cx * f..usize::min((cx + 1) * f, CHUNK_BOUNDS_X as usize) {
+                    for y in (0..CHUNK_BOUNDS_Y as usize).rev() {
+                        let block = blocks[greedyMeshHelper::To1D(&[x as i32, y as i32, z as i32])];
+                        if block == Block::Air() || HasModel(block, blockRegistry) {
+                            continue;
+                        }
+                        if y + 1 > height {
+                            height = y + 1;
+                            surface = block;
+                        }
+                        break;
+                    }
+                }
+            }
+
+            //Fill the column up to the (rounded) surface height with the surface block
+            let cells = usize::min((height + f / 2) / f, dims[1]);
+            for cy in 0..cells {
+                out[greedyMeshHelper::To1DWithDims(&[cx as i32, cy as i32, cz as i32], &dims)] = surface;
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::World::{ReadAttributes, contentPack::ContentPacks, idMap::IdMap, item::ItemRegistry,
+                       crafting::CraftingRegistry, smelting::SmeltingRegistry};
+
+    fn Registry() -> BlockRegistry {
+        let packs = ContentPacks::Core().unwrap();
+        let (mut blocks, mut items, mut crafting, mut smelting) = (BlockRegistry::New(), ItemRegistry::New(), CraftingRegistry::New(), SmeltingRegistry::New());
+        ReadAttributes(&mut blocks, &mut items, &mut crafting, &mut smelting, &packs, &mut IdMap::default()).unwrap();
+        blocks
+    }
+
+    //A chunk of stone up to the height of each column with grass on top, and air above
+    fn Terrain(registry: &BlockRegistry, height: impl Fn(usize, usize) -> usize) -> Vec<Block> {
+        let stone = Block { ID: registry.NameToID("core:stone").unwrap() };
+        let grass = Block { ID: registry.NameToID("core:grass").unwrap() };
+        let mut blocks = vec![Block::Air(); (CHUNK_BOUNDS_X * CHUNK_BOUNDS_Y * CHUNK_BOUNDS_Z) as usize];
+        for z in 0..CHUNK_BOUNDS_Z as usize {
+            for x in 0..CHUNK_BOUNDS_X as usize {
+                let h = height(x, z);
+                for y in 0..h {
+                    blocks[greedyMeshHelper::To1D(&[x as i32, y as i32, z as i32])] = if y + 1 == h { grass } else { stone };
+                }
+            }
+        }
+        blocks
+    }
+
+    fn CellAt(blocks: &[Block], lod: LodLevel, cell: [usize; 3]) -> Block {
+        blocks[greedyMeshHelper::To1DWithDims(&[cell[0] as i32, cell[1] as i32, cell[2] as i32], &lod.Dimensions())]
+    }
+
+    #[test]
+    fn RingsPickTheLevelOfDetail() {
+        let expected = [(0, LodLevel::Full), (LOD_FULL_RING, LodLevel::Full), (LOD_FULL_RING + 1, LodLevel::Half),
+                        (LOD_HALF_RING, LodLevel::Half), (LOD_HALF_RING + 1, LodLevel::Quarter),
+                        (LOD_QUARTER_RING, LodLevel::Quarter), (LOD_QUARTER_RING + 1, LodLevel::HeightMap)];
+        for (ring, lod) in expected {
+            assert_eq!(LodLevel::ForRing(ring), lod, "ring {}", ring);
+        }
+
+        //rings are squares, not circles
+        assert_eq!(RingDistance((3, -5), (1, -1)), 4);
+        assert_eq!(LodLevel::ForChunk((-3, 3), (0, 0)), LodLevel::Half);
+        assert_eq!(LodLevel::ForNeighbours((LOD_FULL_RING as i32, 0), (0, 0)), [LodLevel::Full, LodLevel::Half, LodLevel::Full, LodLevel::Full]);
+    }
+
+    #[test]
+    fn FactorsAndDimensionsCoverTheChunk() {
+        let bounds = [CHUNK_BOUNDS_X as usize, CHUNK_BOUNDS_Y as usize, CHUNK_BOUNDS_Z as usize];
+        for (lod, factor) in [(LodLevel::Full, 1), (LodLevel::Half, 2), (LodLevel::Quarter, 4), (LodLevel::HeightMap, 4)] {
+            assert_eq!(lod.Factor(), factor);
+            for (axis, dim) in lod.Dimensions().iter().enumerate() {
+                //just enough cells to cover the chunk, with the last one partly outside it
+                assert!(dim * factor >= bounds[axis] && (dim - 1) * factor < bounds[axis], "{:?} axis {}", lod, axis);
+            }
+        }
+    }
+
+    #[test]
+    fn DownsampledVoxelsKeepTheGroundAndItsTop() {
+        let registry = Registry();
+        let grass = Block { ID: registry.NameToID("core:grass").unwrap() };
+        let blocks = Terrain(&registry, |_, _| 8);
+        assert!(Downsample(&blocks, LodLevel::Full, &registry) == blocks);
+
+        for lod in [LodLevel::Half, LodLevel::Quarter] {
+            let f = lod.Factor();
+            let dims = lod.Dimensions();
+            let out = Downsample(&blocks, lod, &registry);
+            assert_eq!(out.len(), dims[0] * dims[1] * dims[2]);
+
+            //every column is solid up to the ground, including the partial cells on the edges, and the top cell is grass
+            let cells = 8 / f;
+            for cz in 0..dims[2] {
+                for cx in 0..dims[0] {
+                    for cy in 0..dims[1] {
+                        let cell = CellAt(&out, lod, [cx, cy, cz]);
+                        assert_eq!(cell != Block::Air(), cy < cells, "{:?} cell {:?}", lod, (cx, cy, cz));
+                    }
+                    assert_eq!(CellAt(&out, lod, [cx, cells - 1, cz]), grass);
+                }
+            }
+        }
+
+        //a cell that's mostly air stays air
+        let sparse = Terrain(&registry, |x, z| if x == 0 && z == 0 { CHUNK_BOUNDS_Y as usize } else { 0 });
+        assert!(Downsample(&sparse, LodLevel::Half, &registry).iter().all(|b| *b == Block::Air()));
+    }
+
+    #[test]
+    fn HeightMapsKeepTheTallestSurface() {
+        let registry = Registry();
+        let grass = Block { ID: registry.NameToID("core:grass").unwrap() };
+        let lod = LodLevel::HeightMap;
+        let f = lod.Factor();
+        let dims = lod.Dimensions();
+
+        //a hill that rises along x with a cave dug under it
+        let height = |x: usize, _: usize| 10 + x;
+        let mut blocks = Terrain(&registry, height);
+        for y in 2..6 {
+            blocks[greedyMeshHelper::To1D(&[5, y, 5])] = Block::Air();
+        }
+        let out = Downsample(&blocks, lod, &registry);
+
+        for cz in 0..dims[2] {
+            for cx in 0..dims[0] {
+                let tallest = height(usize::min((cx + 1) * f, CHUNK_BOUNDS_X as usize) - 1, 0);
+                let cells = (tallest + f / 2) / f;
+                for cy in 0..dims[1] {
+                    let expected = if cy < cells { grass } else { Block::Air() };
+                    assert_eq!(CellAt(&out, lod, [cx, cy, cz]), expected, "cell {:?}", (cx, cy, cz));
+                }
+            }
+        }
+    }
+}
@@ -1,4 +1,6 @@
 pub mod resource;
 pub mod atlas;
 pub mod fustrum;
-pub mod greedyMeshHelper;
\ No newline at end of file
+pub mod greedyMeshHelper;
+pub mod lodMeshHelper;
+pub mod modelMeshHelper;
\ No newline at end of file
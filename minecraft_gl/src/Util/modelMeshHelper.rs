@@ -0,0 +1,49 @@
+use crate::{World::{blockModel::ModelQuad, world::NEIGHBOR_OFFSETS, colormap::NO_TINT}, 
+            Renderer::worldRenderer::Vertex
+           };
+
+/*
+    Blocks with models don't fit the greedy mesher, so each of their quads is added as is.
+    Model vertices have the model bit of Detail set, which tells the shader to move the vertex
+    inside its block and use the texture coordinates it carries instead of tiling the texture
+
+    Detail bits are
+    X (5), Y (5), Z (5), U (5), V (5), Model (1)
+    in 16ths of a block and 16ths of the texture
+*/
+
+const MODEL_BIT: u32 = 1 << 25;
+
+/*
+    Adds the quads of a block's model, with the block at 'position' in the chunk. 'isFull' says whether
+    the block at a position covers the faces of a model next to it, so those faces can be left out.
+    The tinted quads are multiplied by 'tint'
+*/
+pub fn ConstructModel<'a>(mesh: &mut Vec<Vertex>, position: [i32; 3], textureID: u32, tint: u32,
+                          quads: impl Iterator<Item = &'a ModelQuad>, isFull: impl Fn([i32; 3]) -> bool) 
+{
+    for quad in quads {
+        if let Some(face) = quad.Cull {
+            let offset = NEIGHBOR_OFFSETS[face as usize];
+            if isFull([position[0] + offset.0, position[1] + offset.1, position[2] + offset.2]) {
+                continue;
+            }
+        }
+
+        for i in 0..4 {
+            AddModelVertex(&position, quad, i, textureID, if quad.Tinted { tint } else { NO_TINT }, mesh);
+        }
+    }
+}
+
+fn AddModelVertex(point: &[i32; 3], quad: &ModelQuad, corner: usize, textureID: u32, tint: u32, mesh: &mut Vec<Vertex>) {
+    let textureId = (textureID + quad.Offset) as i32;
+    let core = point[0] | point[2] << 4 | point[1] << 8 | textureId << 16 | 
+               (quad.FaceID as i32) << 26 | (quad.Animated as i32) << 29 | (quad.Rotation as i32 & 0x3) << 30;
+
+    let [x, y, z] = quad.Corners[corner].map(|v| v as u32);
+    let [u, v] = quad.UVs[corner].map(|v| v as u32);
+    let detail = x | y << 5 | z << 10 | u << 15 | v << 20 | MODEL_BIT;
+
+    mesh.push(Vertex { Core: core as u32, Dims: 1 | 1 << 16, Detail: detail, Tint: tint });
+}
\ No newline at end of file
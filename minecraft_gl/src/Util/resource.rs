@@ -1,6 +1,6 @@
 use std::collections::HashMap;
 use std::io::BufRead;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::rc::Rc;
 
 use image::{self, DynamicImage, GenericImageView};
@@ -21,11 +21,13 @@ impl ResourceManager{
     }
 
 
+    //Paths are relative to the asset directory (see AssetDir)
     pub fn GetShader(&mut self, path: &'static str, display: &glium::Display) -> Rc<glium::Program> {
         if self.Shaders.contains_key(path) {
             return Rc::clone(&self.Shaders[path]);
         }
-        self.Shaders.insert(path, Rc::new(GetShaderFromPath(path, display).unwrap()));
+        let fullPath = AssetPath(path);
+        self.Shaders.insert(path, Rc::new(GetShaderFromPath(fullPath.to_str().unwrap(), display).unwrap()));
         Rc::clone(&self.Shaders[path])
     }
 
@@ -33,13 +35,55 @@ impl ResourceManager{
         if self.Textures.contains_key(path) {
             return Rc::clone(&self.Textures[path]);
         }
-        self.Textures.insert(path, Rc::new(GetTextureFromPath(path, display).unwrap()));
+        let fullPath = AssetPath(path);
+        self.Textures.insert(path, Rc::new(GetTextureFromPath(fullPath.to_str().unwrap(), display).unwrap()));
         Rc::clone(&self.Textures[path])
     }
 }
 
 
 
+/*
+    The directory the game's files (assets, cache and saves) live in, worked out at runtime so a built game can be
+    moved anywhere. It's the working directory if the assets are there, otherwise the closest directory at or above
+    the executable that has them, which finds the crate when running out of target/. Falls back to the working directory
+*/
+pub fn InstallDir() -> PathBuf {
+    let workingDir = std::env::current_dir().unwrap_or_default();
+    let exeDirs: Vec<PathBuf> = match std::env::current_exe() {
+        Ok(exe) => exe.ancestors().skip(1).map(Path::to_path_buf).collect(),
+        Err(_) => Vec::new()
+    };
+
+    std::iter::once(workingDir.clone()).chain(exeDirs)
+    .find(|dir| dir.join("assets").is_dir())
+    .unwrap_or(workingDir)
+}
+
+//The bundled assets. MINECRAFT_GL_ASSETS points somewhere else (like a shared install)
+pub fn AssetDir() -> PathBuf {
+    match std::env::var_os("MINECRAFT_GL_ASSETS") {
+        Some(dir) => PathBuf::from(dir),
+        None => InstallDir().join("assets")
+    }
+}
+
+//Path of a bundled asset, like "shaders/world.glsl"
+pub fn AssetPath(relative: &str) -> PathBuf {
+    AssetDir().join(relative)
+}
+
+/*
+    Where generated files (like the texture atlases) are kept between runs, so nothing is written into the assets.
+    Anything in it can be deleted, it's rebuilt as needed. MINECRAFT_GL_CACHE points somewhere else
+*/
+pub fn CacheDir() -> PathBuf {
+    match std::env::var_os("MINECRAFT_GL_CACHE") {
+        Some(dir) => PathBuf::from(dir),
+        None => InstallDir().join("cache")
+    }
+}
+
 pub fn GetImageFromPath(path: &str) -> Result<DynamicImage, String> {
 
     let img = ImageReader::open(path);
@@ -60,7 +104,7 @@ pub fn GetImageOrNull(path: &str) -> Result<DynamicImage, DynamicImage> {
        return Ok(GetImageFromPath(path).unwrap());
     }
 
-    Err(GetImageFromPath("./minecraft_gl/assets/data/block/img/nullTexture.png").unwrap())
+    Err(GetImageFromPath(AssetPath("data/block/img/nullTexture.png").to_str().unwrap()).unwrap())
 }
 
 pub fn GetShaderFromPath(path: &str, display: &glium::Display) -> Result<glium::Program, String>{
@@ -1,13 +1,27 @@
 
 use bracket_noise::prelude::{FastNoise, NoiseType};
-use rand::{Rng, prelude::ThreadRng};
+use rand::{Rng, SeedableRng, rngs::StdRng};
 use super::{chunk::{CHUNK_BOUNDS_X, CHUNK_BOUNDS_Z, CHUNK_BOUNDS_Y, To1D}, block::Block};
 
 
+/*
+    Generators are shared by every chunk worker at once, so generating only reads them.
+    Anything random comes from ChunkRng, and the noise is set up once when the generator is made
+*/
 pub trait BiomeGenerator {
-    fn Generate(&mut self, blocks: &mut Vec<Block>, chunkX: i32, chunkZ: i32);
+    //Lays down the terrain (crust, mantle, core and water)
+    fn Generate(&self, blocks: &mut Vec<Block>, chunkX: i32, chunkZ: i32);
+    //Places trees and flora on top of already generated terrain
+    fn Decorate(&self, blocks: &mut Vec<Block>, chunkX: i32, chunkZ: i32);
+    //Temperature and humidity (from 0 to 1) of the column at a world position, which tinted blocks are coloured by
+    fn Climate(&self, x: i32, z: i32) -> (f32, f32);
 }
 
+//How far the climate drifts from the biome's own temperature and humidity
+const CLIMATE_VARIATION: f32 = 0.25;
+//Humidity is read from the same noise as temperature, just far enough away that the two don't line up
+const HUMIDITY_OFFSET: f32 = 10000.0;
+
 #[derive(Eq, Hash, PartialEq, Clone, Debug)]
 pub enum Biome{
     Forest,
@@ -95,6 +109,14 @@ impl NoiseParameters {
         noise.set_fractal_lacunarity(self.Lacunarity);
         noise.set_seed(self.Seed);
     }
+
+    //A simplex fractal noise with these parameters applied. Sampling it doesn't change it
+    pub fn Build(&self) -> FastNoise {
+        let mut noise = FastNoise::new();
+        noise.set_noise_type(NoiseType::SimplexFractal);
+        self.Apply(&mut noise);
+        noise
+    }
 }
 
 #[derive(Debug)]
@@ -109,25 +131,35 @@ pub struct GenerationData{
     pub SurfaceAmplitude: u32,
     pub SeaLevel: u32,
 
+    pub Temperature: f32,
+    pub Humidity: f32,
+
     pub CaveModifier: HeightModifier,
     pub CaveCutoff: f32,
 
     pub OreCutoff: f32,
+
+    //Blocks the generator places itself instead of picking them by noise
+    pub Water: Block,
+    pub Log: Block,
+    pub Leaves: Block,
+    pub Flower: Block,
+    pub TallGrass: Block,
 }
 
 pub struct ForestGenerator {
-    Noise: FastNoise,
-    HeightMapNoise: NoiseParameters,
-    SelectionNoise: NoiseParameters,
-    CaveNoise: NoiseParameters,
-    OreNoise: NoiseParameters,
+    HeightMapNoise: FastNoise,
+    SelectionNoise: FastNoise,
+    CaveNoise: FastNoise,
+    OreNoise: FastNoise,
+    ClimateNoise: FastNoise,
     GenData: GenerationData,
-
+    Seed: u64,
 }
 
 impl ForestGenerator {
-    pub fn New(genData: GenerationData) -> Self {
-        let mut rng = rand::thread_rng();
+    pub fn New(genData: GenerationData, seed: u64) -> Self {
+        let mut rng = StdRng::seed_from_u64(seed);
         let height = NoiseParameters{
             Octaves: 6,
             Seed: rng.gen_range(0..10000),
@@ -161,33 +193,50 @@ impl ForestGenerator {
             Persistance: 0.5f32,
         };
 
-        let mut noise = FastNoise::new();
-        noise.set_noise_type(NoiseType::SimplexFractal);
+        //much smoother than the terrain, so the colour of the grass changes over hundreds of blocks
+        let climate = NoiseParameters{
+            Octaves: 3,
+            Seed: rng.gen_range(0..10000),
+            Frequency: 0.004f32,
+            Lacunarity: 2f32,
+            Persistance: 0.5f32,
+        };
 
         Self {
-            Noise: noise,
-            HeightMapNoise: height,
-            SelectionNoise: selection,
-            CaveNoise: cave,
-            OreNoise: ore,
+            HeightMapNoise: height.Build(),
+            SelectionNoise: selection.Build(),
+            CaveNoise: cave.Build(),
+            OreNoise: ore.Build(),
+            ClimateNoise: climate.Build(),
             GenData: genData,
+            Seed: seed,
         }
     }
 }
 
+/*
+    Chunks are generated on worker threads in whatever order they finish, so anything random
+    has to come from an rng seeded by the chunk position for the same seed to give the same world
+*/
+pub fn ChunkRng(seed: u64, chunkX: i32, chunkZ: i32) -> StdRng {
+    let x = (chunkX as i64 as u64).wrapping_mul(0x9E3779B97F4A7C15);
+    let z = (chunkZ as i64 as u64).wrapping_mul(0xC2B2AE3D27D4EB4F);
+    StdRng::seed_from_u64(seed ^ x ^ z.rotate_left(32))
+}
+
 impl ForestGenerator {
-    pub fn Decorate(&self, pos: (u32, u32, u32), blocks: &mut Vec<Block>, rng: &mut ThreadRng){
+    pub fn DecorateColumn(&self, pos: (u32, u32, u32), blocks: &mut Vec<Block>, rng: &mut StdRng){
         if rng.gen_ratio(1, 100) {
             self.GenerateTree(pos, blocks, rng);
         } else if rng.gen_ratio(1, 100) {
             let idx = To1D((pos.0, pos.1 + 1, pos.2)) as usize;
-            blocks[idx] = Block { ID: 9 };
+            blocks[idx] = self.GenData.Flower;
         } else if rng.gen_ratio(1, 15) {
             let idx = To1D((pos.0, pos.1 + 1, pos.2)) as usize;
-            blocks[idx] = Block { ID: 10 };
+            blocks[idx] = self.GenData.TallGrass;
         }
     }
-    pub fn GenerateTree(&self, pos: (u32, u32, u32), blocks: &mut Vec<Block>, rng: &mut ThreadRng){
+    pub fn GenerateTree(&self, pos: (u32, u32, u32), blocks: &mut Vec<Block>, rng: &mut StdRng){
          
         let trunkLength = rng.gen_range(3..6);
         let leaveDims: (i32, i32, i32) = (5, 3, 5);
@@ -197,7 +246,7 @@ impl ForestGenerator {
 
         for i in 0..trunkLength{
             let idx = To1D((pos.0, pos.1 + i + 1, pos.2)) as usize;
-            blocks[idx] = Block { ID: 3 };
+            blocks[idx] = self.GenData.Log;
         }
 
         for x in -leaveDims.0/2..=leaveDims.0/2 {
@@ -210,32 +259,30 @@ impl ForestGenerator {
                            continue;
                     }
                     let idx = To1D(((pos.0 as i32 + x) as u32 , (pos.1 as i32 + y + 1) as u32 + trunkLength, (pos.2 as i32 + z) as u32)) as usize;
-                    blocks[idx] = Block { ID: 8 };
+                    blocks[idx] = self.GenData.Leaves;
                 }
             }
         }
         let idx = To1D((pos.0, pos.1 + trunkLength + leaveDims.1 as u32 + 1, pos.2)) as usize;
-        blocks[idx] = Block { ID: 8 };
+        blocks[idx] = self.GenData.Leaves;
     }
 }
 
 impl BiomeGenerator for ForestGenerator {
-    fn Generate(&mut self, blocks: &mut Vec<Block>, chunkX: i32, chunkZ: i32) {
+    fn Generate(&self, blocks: &mut Vec<Block>, chunkX: i32, chunkZ: i32) {
 
         let mut heightMap =  [0; (CHUNK_BOUNDS_X * CHUNK_BOUNDS_Z) as usize];
         let mut crust = [Block::Air(); (CHUNK_BOUNDS_X * CHUNK_BOUNDS_Z) as usize];
 
         for x in 0..CHUNK_BOUNDS_X {
             for z in 0..CHUNK_BOUNDS_Z {
-                self.HeightMapNoise.Apply(&mut self.Noise);
-                let heightNoisenoise = self.Noise.get_noise((x as i32 + chunkX * CHUNK_BOUNDS_X as i32) as f32, (z as i32 + chunkZ * CHUNK_BOUNDS_Z as i32) as f32);
+                let heightNoisenoise = self.HeightMapNoise.get_noise((x as i32 + chunkX * CHUNK_BOUNDS_X as i32) as f32, (z as i32 + chunkZ * CHUNK_BOUNDS_Z as i32) as f32);
                 let heightNoiseNormalized = (heightNoisenoise + 1f32) / 2f32;
                 let height = (self.GenData.HeightLevel as f32 + 
                                    self.GenData.SurfaceAmplitude as f32 * heightNoiseNormalized) as u32;
                 heightMap[(z * CHUNK_BOUNDS_X + x) as usize] = height;
 
-                self.SelectionNoise.Apply(&mut self.Noise);
-                let crustNoise = self.Noise.get_noise3d(
+                let crustNoise = self.SelectionNoise.get_noise3d(
                     (x as i32 + chunkX * CHUNK_BOUNDS_X as i32) as f32, 
                     (z as i32 + chunkZ * CHUNK_BOUNDS_Z as i32) as f32,
                     height as f32
@@ -247,7 +294,7 @@ impl BiomeGenerator for ForestGenerator {
 
         }
 
-        let mut rng = rand::thread_rng();
+        let mut rng = ChunkRng(self.Seed, chunkX, chunkZ);
         for x in 0..CHUNK_BOUNDS_X {
             for z in 0..CHUNK_BOUNDS_Z {
                 let mapIdx  = (x + z * CHUNK_BOUNDS_X) as usize;
@@ -259,12 +306,9 @@ impl BiomeGenerator for ForestGenerator {
                     let idx = To1D((x, y, z)) as usize;
 
                     match y {
-                         _ if y > height && y <= self.GenData.SeaLevel =>  blocks[idx] = Block { ID: 7 },
+                         _ if y > height && y <= self.GenData.SeaLevel =>  blocks[idx] = self.GenData.Water,
                         _ if y > height => continue,
-                        _ if y == height => {
-                            blocks[idx] = crustBlock;
-                            self.Decorate((x, y, z), blocks, &mut rng);
-                          },
+                        _ if y == height => blocks[idx] = crustBlock,
                         _ if y >= height - mantleLength => {
                             if let Some(block_) = self.GenData.Mantle {
                                 blocks[idx] = block_;
@@ -279,9 +323,39 @@ impl BiomeGenerator for ForestGenerator {
             }
         }
     }
-  
 
- 
+    fn Decorate(&self, blocks: &mut Vec<Block>, chunkX: i32, chunkZ: i32) {
+        //find every surface before placing anything, so trees don't get decorated on top of their neighbour's leaves
+        let mut surfaces: Vec<(u32, u32, u32)> = Vec::new();
+        for x in 0..CHUNK_BOUNDS_X {
+            for z in 0..CHUNK_BOUNDS_Z {
+                for y in (0..CHUNK_BOUNDS_Y - 1).rev() {
+                    let block = blocks[To1D((x, y, z)) as usize];
+                    if block == Block::Air() {
+                        continue;
+                    }
+                    //nothing grows underwater
+                    if block != self.GenData.Water {
+                        surfaces.push((x, y, z));
+                    }
+                    break;
+                }
+            }
+        }
+
+        //offset the seed so decorations don't correlate with the terrain rolls
+        let mut rng = ChunkRng(self.Seed.wrapping_add(1), chunkX, chunkZ);
+        for pos in surfaces {
+            self.DecorateColumn(pos, blocks, &mut rng);
+        }
+    }
+
+    fn Climate(&self, x: i32, z: i32) -> (f32, f32) {
+        let temperature = self.ClimateNoise.get_noise(x as f32, z as f32);
+        let humidity = self.ClimateNoise.get_noise(x as f32 + HUMIDITY_OFFSET, z as f32 + HUMIDITY_OFFSET);
+        ((self.GenData.Temperature + temperature * CLIMATE_VARIATION).clamp(0f32, 1f32), 
+         (self.GenData.Humidity + humidity * CLIMATE_VARIATION).clamp(0f32, 1f32))
+    }
 }
 
 // struct MountainGenerator {
@@ -1,5 +1,5 @@
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Block{
     pub ID: u8
 }
@@ -16,53 +16,163 @@ impl Block{
 ////////////////////////////////////////////////////////////////////////////////////////////////////////////////
 //////////////////////////////////////////////////////////////////////////////////////////////////////////////// 
 use std::error::Error;
-use std::io::{BufReader, Write};
 use std::collections::{HashMap, HashSet};
-use std::fs::{self, File};
-use std::path::{PathBuf, Path};
-use crate::Util::atlas::TextureAtlas;
-use super::{State, GenericError};
-use super::item::{Item, ItemID, ItemStack};
-use super::blockBehavior::{BlockBehavior, BlockBindingFunction};
-use crate::Event::event::Event;
+use std::path::PathBuf;
+use crate::Util::atlas::{TextureAtlas, AtlasBuilder, AtlasCache, AtlasSource, TextureAnimation, ATLAS_PADDING};
+use super::{State, GenericError, schema::{BlockJson, FaceTextureJson, FaceJson, QuarterTurns}, idMap::{IdMap, AIR_IDENTIFIER, ValidateIdentifier}, contentPack::ContentPacks};
+use super::item::ItemID;
+use super::blockBehavior::{BlockBehavior, DefaultBehavior, BlockBindingFunction};
+use super::blockModel::{BlockModel, ReadBlockModel};
+use super::colormap::{Tint, Colormaps};
 use super::super::Util::resource;
 use image;
-use serde_json::Value;
-use serde_json;
+use serde::{Serialize, Deserialize};
 
 #[derive(Clone)]
 pub enum TextureData{
     SixSided(TextureSix),
     Single(TextureSingle),
+    Model(TextureModel),
     PlaceHolder
 }
 
-#[derive(Clone)]
+#[derive(Clone, Debug)]
 pub struct TextureSix{
-    //might possibly need the texture paths to debug later on
-    pub Textures: [String; 6],
+    //Every texture the block uses, in the order they're put on the atlas starting at TextureID
+    pub Textures: Vec<BlockTexture>,
     /*
         Block ID's will not always line up perfectly with the position of the block's 
         texture on the atlas. This is for that
     */
     pub TextureID: u32,
+    //What each face shows, indexed by the face ID (+X, -X, +Y, -Y, +Z, -Z)
+    pub Faces: [FaceTexture; 6],
+    //Which faces the block's Tint colours
+    pub Tinted: [bool; 6],
+}
+
+#[derive(Clone, Debug)]
+pub struct BlockTexture{
+    //Path of the texture, already resolved against the content packs
+    pub Path: String,
+    //Connected textures are a sheet of CONNECTED_TILES tiles, each taking a texture ID of its own
+    pub Connected: bool,
+}
+
+//Connected texture sheets are CONNECTED_SHEET_SIZE x CONNECTED_SHEET_SIZE tiles
+pub const CONNECTED_SHEET_SIZE: u32 = 4;
+pub const CONNECTED_TILES: u32 = CONNECTED_SHEET_SIZE * CONNECTED_SHEET_SIZE;
+
+//Texture IDs are 8 bits in the mesh, so that's as many as the atlas can have
+pub const MAX_BLOCK_TEXTURES: u32 = 256;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FaceVariant{
     /*  
         The texture ID is the 1D coordinate of a block's texture on the texture atlas. However,
-        a block may have multiple textures AFTER that position. The offsets array is to be indexed
-        by the face ID (all faces numbered 0-5) of a block. This tells us how far a face's desired
-        texture is away from the start of a block's texture on the atlas
+        a block may have multiple textures AFTER that position. The offset tells us how far this
+        face's texture is away from the start of a block's texture on the atlas
     */
-    pub Offsets: [u32; 6],
+    pub Offset: u32,
+    //Clockwise quarter turns (0-3)
+    pub Rotation: u8,
+    //Only used by random variants
+    pub Weight: u32,
+    //Whether the texture is animated, so the mesher can flag it for the shader
+    pub Animated: bool,
+}
+
+#[derive(Clone, Debug)]
+pub enum FaceTexture{
+    Fixed(FaceVariant),
+    //One of these is picked by the block's position
+    Random(Vec<FaceVariant>),
+    //The first of CONNECTED_TILES tiles, picked by which sides have the same block next to them
+    Connected(FaceVariant),
+}
+
+impl FaceTexture{
+    /*
+        The texture ID, rotation and animated flag to draw a face with. The position is in world space
+        so that a random variant never changes when the chunk is meshed again. Connections are the bits
+        1 up, 2 right, 4 down and 8 left, and only matter for connected textures
+    */
+    pub fn Pick(&self, textureID: u32, position: [i32; 3], face: i32, connections: u8) -> (u32, u8, bool) {
+        let variant = match self {
+            FaceTexture::Fixed(variant) => *variant,
+            FaceTexture::Connected(variant) => {
+                return (textureID + variant.Offset + (connections as u32 % CONNECTED_TILES), 0, false);
+            },
+            FaceTexture::Random(variants) => {
+                let total: u32 = variants.iter().map(|v| v.Weight).sum();
+                let mut roll = PositionHash(position, face) % total.max(1);
+                let mut picked = variants[0];
+                for variant in variants {
+                    if roll < variant.Weight {
+                        picked = *variant;
+                        break;
+                    }
+                    roll -= variant.Weight;
+                }
+                picked
+            }
+        };
+        (textureID + variant.Offset, variant.Rotation, variant.Animated)
+    }
+
+    pub fn IsConnected(&self) -> bool {
+        matches!(self, FaceTexture::Connected(_))
+    }
+}
+
+impl TextureSix{
+    //How many texture IDs the block takes up on the atlas
+    pub fn Slots(&self) -> u32 {
+        self.Textures.iter().map(|t| t.Slots()).sum()
+    }
+}
+
+impl TextureModel{
+    pub fn Slots(&self) -> u32 {
+        self.Textures.iter().map(|t| t.Slots()).sum()
+    }
+}
+
+impl BlockTexture{
+    pub fn Slots(&self) -> u32 {
+        if self.Connected { CONNECTED_TILES } else { 1 }
+    }
+}
+
+//Scrambles a block position into a number that doesn't look like it came from one
+fn PositionHash(position: [i32; 3], face: i32) -> u32 {
+    let mut hash = (position[0] as u32).wrapping_mul(73856093) ^ (position[1] as u32).wrapping_mul(19349663) ^
+                   (position[2] as u32).wrapping_mul(83492791) ^ (face as u32).wrapping_mul(2654435761);
+    hash ^= hash >> 16;
+    hash = hash.wrapping_mul(0x45d9f3b);
+    hash ^= hash >> 16;
+    hash
+}
+
+//The textures of a block drawn with models. The model's quads say which of them they use
+#[derive(Clone, Debug)]
+pub struct TextureModel{
+    pub Textures: Vec<BlockTexture>,
+    pub TextureID: u32,
 }
 
 #[derive(Clone)]
 pub struct TextureSingle{
+    //Path of the texture, already resolved against the content packs
     pub Texture: String,
-    pub TextureID: u32
+    pub TextureID: u32,
+    pub Animated: bool,
 }
 
 #[derive(Clone)]
 pub struct BlockAttribute{
+    //Namespaced identifier (core:grass). Unlike the numeric ID, this is the same in every world
+    pub Identifier: String,
     pub Name: String,
     //Think of this as the 'hitpoints' of a block. This informs how hard the block is to mine
     pub Toughness: f32,
@@ -74,23 +184,36 @@ pub struct BlockAttribute{
     pub EffectiveTool: Option<ItemID>,
     //If the user did not define a proper texture, the null texture is used
     pub TextureData: TextureData,
+    //Animated textures, keyed by their resolved path like the paths in TextureData
+    pub Animations: HashMap<String, TextureAnimation>,
     //Custom attributes are for more lossly defined attributes. Not every attribute can be covered by this struct
     pub CustomAttributes: HashMap<String, State>,
     //Whether or not the block is decoration (tall grass, flowers, etc)
     pub Decor: bool,
+    //Whether the block is a fluid like water, which isn't solid (see BlockRegistry::IsSolid)
+    pub Fluid: bool,
+    //Blocks that aren't cubes (slabs, fences, flowers) are drawn with a model
+    pub Model: Option<BlockModel>,
+    //Grass, leaves and water are coloured by the climate they're in
+    pub Tint: Option<Tint>,
 }
 
 impl Default for BlockAttribute{
     fn default() -> Self {
         Self { 
+            Identifier: String::from(""),
             Name: String::from(""),
             Toughness: 1f32,
             Friction: 1f32, 
             DropItem: None, 
             EffectiveTool: None,
             TextureData: TextureData::PlaceHolder,
+            Animations: HashMap::new(),
             CustomAttributes: HashMap::new(),
             Decor: false,
+            Fluid: false,
+            Model: None,
+            Tint: None,
         }
     }
 }
@@ -109,10 +232,12 @@ pub struct BlockRegistry{
     //TODO otherwise remove stringtoID hashmap and replace the u8 ID's with strings
     //TODO Consider changing the visibility of these too, as there are already a bunch of interface functions to access them
     pub BlocksAttributes: HashMap<u8, BlockAttribute>,
-    pub BlockBehaviors: HashMap<u8, BlockBehavior>,
+    pub BlockBehaviors: HashMap<u8, Box<dyn BlockBehavior>>,
     StringToID: HashMap<String, u8>,
     NumRegisteredBlocks: u32,
     NumRegisteredTextures: u32,
+    //What tinted blocks are coloured with, by the climate of their column
+    Colormaps: Colormaps,
 }
 
 impl BlockRegistry{
@@ -123,10 +248,11 @@ impl BlockRegistry{
             StringToID: HashMap::new(),
             NumRegisteredBlocks: 0, 
             NumRegisteredTextures: 0,
+            Colormaps: Colormaps::None(),
         }
     }
 
-    pub fn ReadBlockAttributes(&mut self) -> Result<(Vec<(u8, String)>, Vec<(u8, String)>), Box<dyn Error>>{
+    pub fn ReadBlockAttributes(&mut self, packs: &ContentPacks, idMap: &mut IdMap) -> Result<(Vec<(u8, String)>, Vec<(u8, String)>), Box<dyn Error>>{
          //Keep track of the number of blocks and textures for those blocks
          let mut blockCount = 0;
          let mut textureCount = 0;
@@ -145,225 +271,149 @@ impl BlockRegistry{
          let mut dropItems: Vec<(u8, String)> = Vec::new();
          let mut effectiveMiningItems: Vec<(u8, String)> = Vec::new();
 
-         //keep a list of all json files in the given directory
-         let mut jsonFiles: Vec<serde_json::Value> = Vec::new();
+         //keep a list of all json files across the content packs, along with their paths for error messages
+         let mut jsonFiles: Vec<(PathBuf, BlockJson)> = packs.ReadBlocks().IntoResult()?;
 
-         let path = std::path::Path::new("./minecraft_gl/assets/data/block/json/");
-         for file in fs::read_dir(path)
-            .map_err(|e| format!("Error! Could not find ./minecraft_gl/assets/data/block/json/ directory! The error:\n{}", e.to_string()))? {
+         self.Colormaps = Colormaps::Load(packs).map_err(GenericError::NewBoxed)?;
 
-            let path = file
-            .map_err(|e| format!("Error! Could not retrieve file in ../../assets/data/block/json/ directory! The error:\n{}", e.to_string()))?.path();
+         //Hand out runtime IDs in identifier order so a new world always gets the same IDs for the same content
+         jsonFiles.sort_by(|a, b| a.1.ID.cmp(&b.1.ID));
 
-            let file = File::open(path)
-            .map_err(|e| GenericError::NewBoxed(format!("Error! Could not open file of path in ../../assets/data/block/json/ directory! The error:\n{}", e.to_string())))?;
+         //reserve block ID #0 to air
+         self.StringToID.insert(AIR_IDENTIFIER.to_owned(), 0);
+         self.BlocksAttributes.insert(0, BlockAttribute { Identifier: AIR_IDENTIFIER.to_owned(), Name: "Air".to_owned(), ..Default::default() });
+
+         let mut identifiers: HashSet<String> = HashSet::new();
+         let mut assigned: Vec<(u8, PathBuf, BlockJson)> = Vec::with_capacity(jsonFiles.len());
+         for (path, json) in jsonFiles {
+            let file = path.display();
+            ValidateIdentifier(&json.ID)
+            .map_err(|e| format!("Invalid block identifier. {}. Error in {}", e, file))?;
+
+            if json.ID == AIR_IDENTIFIER {
+                return Err(GenericError::NewBoxed(format!("The identifier {} is reserved for the air block. Error in {}", AIR_IDENTIFIER, file)));
+            }
+            //Disabled blocks still claim their identifier, so turning one on later can't cause a conflict
+            if ! identifiers.insert(json.ID.clone()) {
+                return Err(GenericError::NewBoxed(format!("Duplicate block identifier {} found! Error in {}", json.ID, file)));
+            }
 
-            let json: Value = serde_json::from_reader(BufReader::new(file))?;
-            jsonFiles.push(json);
+            if ! json.Enabled {
+                continue;
+            }
+
+            let id = idMap.AssignBlock(&json.ID)?;
+            assigned.push((id, path, json));
          }
 
          /*
             Sort the Json files by their ID. This is due to the running 'textureCount' variable, which is used to determine a blocks
             position in the texture atlas. Block's with lower ID's should be first in the atlas, followed by higher ID's
          */
-         jsonFiles.sort_by(|a, b| a["ID"].as_u64().unwrap().partial_cmp(&b["ID"].as_u64().unwrap()).unwrap());
-
-         //reserve block ID #0 to air
-         self.StringToID.insert("Air".to_owned(), 0);
-         self.BlocksAttributes.insert(0, BlockAttribute::default());
+         assigned.sort_by_key(|(id, _, _)| *id);
 
          //Iterate through each Json, extracting its attributes
-         for json in jsonFiles {
-            let id: u8;
-            if let Some(val) = json.get("ID") {
-                id = val.as_u64().unwrap() as u8;
-                if id == 0 {
-                    return Err(GenericError::NewBoxed(format!("ID #0 is reserved for the air block. Start your block ID's at #1. The Json:\n\n{}", json.to_string())));
-                }
-                else if self.BlocksAttributes.contains_key(&id) {
-                    return Err(GenericError::NewBoxed(format!("Duplicate block ID found! Conflict with block '{}' of id {}. The Json:\n\n{}", self.BlocksAttributes[&id].Name, id, json.to_string())));
-                }
-            }
-            else {
-                return Err(GenericError::NewBoxed(format!("No 'ID' attribute found for block whilst reading block attributes. The Json:\n\n{}", json.to_string())));
-            }
-          
-            let name: &str;
-            if let Some(val) = json.get("Name") {
-                name = val.as_str().unwrap();
-                if name == "Air" {
-                    return Err(GenericError::NewBoxed(format!("The block 'Air' is reserved. Rename block ID #{} to something else. The Json:\n\n{}", id, json.to_string())));
-                }
-                else if self.StringToID.contains_key(name) {
-                    return Err(GenericError::NewBoxed(format!("Duplicate block name found! Conflict with block '{}' of id {}. The Json:\n\n{}", self.BlocksAttributes[&id].Name, id, json.to_string())));
-                }
-
-            }
-            else {
-                return Err(GenericError::NewBoxed(format!("No 'Name' attribute found for block of id {} whilst reading block attributes. The Json:\n\n{}", id, json.to_string())));
-            }
+         for (id, path, json) in assigned {
+            let name = json.Name.as_str();
+            let file = path.display();
 
-            if let Some(val) = json.get("Enabled") {
-                if ! val.as_bool().unwrap() {
-                    continue;
-                }
-            }
-            else {
-                return Err(GenericError::NewBoxed(format!("No 'Enabled' attribute found for block of type {} and id {} whilst reading block attributes. The Json:\n{}", name, id, json.to_string())));
-            }
-
-            //add the name and id to the String -> BlockID hashmap
-            self.StringToID.insert(String::from(name), id);
+            //add the identifier and id to the String -> BlockID hashmap
+            self.StringToID.insert(json.ID.clone(), id);
 
             //construct the block attribute struct with default values
             let mut blockAttribs = BlockAttribute::default();
+            blockAttribs.Identifier = json.ID.clone();
             blockAttribs.Name = String::from(name); //add the name
 
-            /*
-                A 'Decor' block is a decoration block such as tall grass or a flower
-                These blocks are limited and won't have the same attributes as other more extensive blocks
-            */
-            if let Some(val) = json.get("Decor") { //TODO just pass this through the rest, don't continue
-                if val.as_bool().unwrap() {
-                    blockAttribs.Decor = true;
-                    //get the texture
-                    if let Some(tex) = json.get("Texture") {
-                        blockAttribs.TextureData = TextureData::Single(TextureSingle {
-                            Texture: tex.as_str().unwrap().to_owned(),
-                            TextureID: textureCount
-                        });
-                    } else {
-                        return Err(GenericError::NewBoxed(format!("Error in block registry creation! Texture attribute for decoration block {} of id {} does not exist.", name, id)));
-                    }
-
-                    self.BlocksAttributes.insert(id, blockAttribs);
-                    self.BlockBehaviors.insert(id, BlockBehavior::default());
-                    textureCount += 1;
-                    blockCount += 1;
-
-                    continue;
+            //Animations name a texture the same way Texture and Textures do, so it has to be one of those
+            for (texture, animation) in &json.Animations {
+                if ! json.TextureNames().contains(&texture.as_str()) {
+                    return Err(GenericError::NewBoxed(format!("Block {} of id {} has an animation for the texture {}, which it doesn't use. Error in {}", name, id, texture, file)));
                 }
+                let val = animation.ToAnimation()
+                .map_err(|e| format!("Invalid animation for the texture {} of block {} of id {}. {}. Error in {}", texture, name, id, e, file))?;
+                blockAttribs.Animations.insert(ResolveTexture(packs, texture), val);
             }
 
+            //A 'Decor' block is a decoration block such as tall grass or a flower, which the player can walk through
+            blockAttribs.Decor = json.Decor;
+            blockAttribs.Fluid = json.Fluid;
+            blockAttribs.Tint = json.Tint;
+
             /*
                 Gather the custom attributes. These are varying attributes that are two specific to put in
                 as individual data members for the block attribute struct. As such, a hashmap is used to grab
                 these highly specialized values. The idea is for the user to grab these attributes via their 
                 name in the block behavior functions later on defined
              */
-            if let Some(attributes) = json.get("Attributes") {
-
-                let obj = attributes.as_object().unwrap();
-                for pair in obj.iter() {
-
-                    /*
-                        his is for when the user wants to specify a data type, but doesn't want to provide a default value.
-                        Imagine a furnace, which uses a floating point number to represent the amount of fuel left inside of it.
-                        It won't make much sense for the user to give that a default value, so they can just specify a data type.
-                    */
-                    if pair.1.is_string() {
-                        let val = State::StateType(pair.1.as_str().unwrap()) 
-                        .map_err(|e| format!("Error reading custom attribute '{}' for block of type {} and ID {}. The error:\n{}", pair.1.to_string(), name, id, e.to_string()))?;
-                        blockAttribs.CustomAttributes.insert(pair.0.clone(), val);
-                    }
-                    //For containers of specific row and column dimensions
-                    else if pair.1.is_object() {
-                        let rows = pair.1.get("Rows").unwrap().as_u64().unwrap() as u32;
-                        let cols = pair.1.get("Cols").unwrap().as_u64().unwrap() as u32;
-                        let vec: Vec<ItemStack> = Vec::with_capacity((rows * cols) as usize);
-                        blockAttribs.CustomAttributes.insert(pair.0.clone(), State::Container((vec, rows, cols)));
-                    }
-                    //integer types with a default value
-                    else if pair.1.is_i64() {
-                        let val = pair.1.as_i64().unwrap() as i32;
-                        blockAttribs.CustomAttributes.insert(pair.0.clone(), State::IntAttribute(val));
-                    }
-                    //floating point types with a default value
-                    else if pair.1.is_f64() {
-                        let val = pair.1.as_f64().unwrap() as f32;
-                        blockAttribs.CustomAttributes.insert(pair.0.clone(), State::FloatAttribute(val));
-                    }
-                    //boolean types with a default value
-                    else if pair.1.is_boolean() {
-                        let val = pair.1.as_bool().unwrap();
-                        blockAttribs.CustomAttributes.insert(pair.0.clone(), State::BoolAttribute(val));
-                    }
-                    else {
-                        return Err(GenericError::NewBoxed(format!("Error! Item attribute {} for Item {} is not a valid type!\n
-                        The only valid types are...\n
-                        StateType:\n
-                            Dynamic Container\n
-                            Int\n
-                            Float\n
-                            Bool\n
-                        List: {{Rows, Cols}}\n
-                        Integer: int\n
-                        Float: float\n
-                        Bool: boolean\n", pair.0, name)));
-                    }  
-                }
+            for (key, attribute) in &json.Attributes {
+                let val = attribute.ToState()
+                .map_err(|e| format!("Error reading custom attribute '{}' for block of type {} and ID {} in {}. The error:\n{}", key, name, id, file, e))?;
+                blockAttribs.CustomAttributes.insert(key.clone(), val);
             }
 
             //Now start to retrieve the concrete attributes that every block must have...
-            if let Some(val) = json.get("Toughness") { blockAttribs.Toughness = val.as_f64().unwrap() as f32; }
-            if let Some(val) = json.get("Friction") { blockAttribs.Friction = val.as_f64().unwrap() as f32; }
-            //TODO figure out a way to fix this
-            //TODO implement a function in mod.rs that checks if the dropItems of the block registry are valid, as well as the place items of the item registry
-            if let Some(val) = json.get("DropItem") {  dropItems.push((id, val.as_str().unwrap().to_owned())); }
-            if let Some(val) = json.get("EffectiveTool") { effectiveMiningItems.push((id, val.as_str().unwrap().to_owned())) }
-
-            if let Some(textures) = json.get("Textures") {
-                 //Must have an array of 6 textures, one for each block face
-                 if ! textures.is_array() {
-                    return Err(GenericError::NewBoxed(format!("Error in block registry creation! Texture attribute for {} of id {} must be an array of 6 elements", name, id)));
-                 }
+            blockAttribs.Toughness = json.Toughness;
+            blockAttribs.Friction = json.Friction;
+            //The item identifiers are checked against the item registry in ReadAttributes (mod.rs)
+            if let Some(val) = &json.DropItem {  dropItems.push((id, val.clone())); }
+            if let Some(val) = &json.EffectiveTool { effectiveMiningItems.push((id, val.clone())) }
+
+            if json.HasModel() {
+                if json.Textures.is_some() {
+                    return Err(GenericError::NewBoxed(format!("Block {} of id {} has both a model and 'Textures'. The textures of a model go in 'Model Textures'. Error in {}", name, id, file)));
+                }
 
-                 //Construct an empty texData object to be written into
-                 let paths = textures.as_array().unwrap();  
-                 let texs = [String::new(), String::new(), String::new(), String::new(), String::new(), String::new()];
-                 let mut texData = TextureSix { Textures: texs, TextureID: textureCount, Offsets: [0; 6] };
-
-                 /*
-                    This piece of code determines the offset for each texture
-                    What we do is we query if a texture has already been seen before,
-                    which with the use of a hashmap is O(1). The we get the offset of that
-                    particular texture, which is the value of the hashmap.
-                 */
-                 let mut map: HashMap<&str, u32> = HashMap::new(); //&str to avoid heap allocation
-                 let mut cumul = 0;
-                 for i in 0..6 {
-                    texData.Textures[i] = String::from(paths[i].as_str().unwrap());
-
-                    if ! map.contains_key(&texData.Textures[i].as_str()) {
-                        /*
-                            on the first iteration i will not be incremented, as the first
-                            first texture location should be = textureID which means offsets[0] 
-                            should = 0
-                        */
-                        cumul += 1 * (i != 0) as u32;
-                        map.insert(paths[i].as_str().unwrap(), cumul);
-                    }
-                    //What is the offset of this texture?
-                    texData.Offsets[i] = map[&texData.Textures[i].as_str()];
+                let mut blockTextures: Vec<BlockTexture> = Vec::new();
+                let animations = &blockAttribs.Animations;
+                let model = ReadBlockModel(packs, &json, &mut |key| {
+                    let texture = ModelTexture(&json, key)?;
+                    let path = ResolveTexture(packs, texture);
+                    let animated = animations.contains_key(&path);
+                    Ok((OffsetOf(&mut blockTextures, path, false), animated))
+                })
+                .map_err(|e| format!("Invalid model for block {} of id {}. {}. Error in {}", name, id, e, file))?;
+
+                let texData = TextureModel { Textures: blockTextures, TextureID: textureCount };
+                textureCount += texData.Slots();
+                blockAttribs.TextureData = TextureData::Model(texData);
+                blockAttribs.Model = Some(model);
+            }
+            else if let Some(textures) = &json.Textures {
+                 //Faces that share a texture share its place on the atlas too
+                 let mut blockTextures: Vec<BlockTexture> = Vec::new();
+                 let mut faces: Vec<FaceTexture> = Vec::with_capacity(6);
+                 for (i, face) in textures.iter().enumerate() {
+                    let face = ReadFace(packs, face, &mut blockTextures, &blockAttribs.Animations)
+                    .map_err(|e| format!("Invalid texture for face {} of block {} of id {}. {}. Error in {}", i, name, id, e, file))?;
+                    faces.push(face);
                  }
-                 textureCount += cumul + 1;
+
+                 let tinted = [0, 1, 2, 3, 4, 5].map(|i| textures[i].IsTinted());
+                 let texData = TextureSix { Textures: blockTextures, TextureID: textureCount, Faces: faces.try_into().unwrap(), Tinted: tinted };
+                 textureCount += texData.Slots();
                  blockAttribs.TextureData = TextureData::SixSided(texData);
             }
             else {
                 blockAttribs.TextureData = TextureData::Single(TextureSingle {
-                    Texture: "./minecraft_gl/assets/data/block/img/nullTexture.png".to_owned(),
-                    TextureID: textureCount
-                    
+                    Texture: ResolveTexture(packs, "nullTexture.png"),
+                    TextureID: textureCount,
+                    Animated: false,
+
                 });
                 textureCount += 1; 
             }
 
             //add the attribute to the attributes hashmap, keyed by the block ID
             self.BlocksAttributes.insert(id, blockAttribs);
-            self.BlockBehaviors.insert(id, BlockBehavior::default());
+            self.BlockBehaviors.insert(id, Box::new(DefaultBehavior));
             blockCount += 1;
          }
          
+         if textureCount > MAX_BLOCK_TEXTURES {
+            return Err(GenericError::NewBoxed(format!("The blocks use {} textures, but there's only room for {} on the atlas. Connected textures take up {} each", textureCount, MAX_BLOCK_TEXTURES, CONNECTED_TILES)));
+         }
+
          //finish up the loop
          self.NumRegisteredBlocks = blockCount;
          self.NumRegisteredTextures = textureCount;
@@ -371,174 +421,101 @@ impl BlockRegistry{
          Ok((dropItems, effectiveMiningItems))
     }
 
-    fn ValidatePreviousAtlas(&self, dims: u32, textureResolution: u32) -> Result<bool, String> {
-        //If a texture atlas already exsits...
-        if Path::new("./minecraft_gl/assets/data/block/atlas/atlas.png").exists() {
-            
-            //The metaData file contains data about the atlas. We want to check if that data is the same as our atlas
-            let file = std::fs::File::open("./minecraft_gl/assets/data/block/atlas/metaData.json")
-            .map_err(|_| format!("Could not open metaData file of path ./minecraft_gl/assets/data/block/atlas/metaData.json for the block atlas."))?;
-            
-            let json: Value = serde_json::from_reader(BufReader::new(file))
-            .map_err(|_| format!("Could not read json meta data file for the block atlas!"))?;
-
-            //check if the dimensions are different
-            let rows = json.get("Rows").unwrap().as_u64().unwrap() as u32;
-            let cols = json.get("Cols").unwrap().as_u64().unwrap() as u32;
-            if rows != dims || cols != dims {
-                return Ok(false);
-            }
-
-            //check if the resolution is the same
-            let res = json.get("Texture Resolution").unwrap().as_u64().unwrap() as u32;
-            if textureResolution != res {
-                return Ok(false);
-            }
-
-            //check if the items in the atlas are different than our current collection of items
-            let items = json.get("Items").unwrap().as_array().unwrap();
-            let strItems: Vec<&str> = items.into_iter().map(|x| x.as_str().unwrap()).collect();
-            for string in self.StringToID.keys() {
-                
-                let mut found = false;
-                for string2 in &strItems {
-                    if string2 == string {
-                        found = true;
-                        break;
-                    }
-                }
-                
-                if ! found {
-                    return Ok(false);
+    //Paths of every block texture in texture ID order, along with the block that first uses it and whether it's a connected sheet
+    fn AtlasTextures(&self) -> Vec<(&str, u8, Option<TextureAnimation>, bool)> {
+        let mut textures = Vec::with_capacity(self.NumRegisteredTextures as usize);
+        let mut keys: Vec<&u8> = self.BlocksAttributes.keys().collect();
+        keys.sort();
+        for id in keys {
+            //If there is texture data...
+            let blockTextures = match &self.BlocksAttributes[id].TextureData {
+                TextureData::SixSided(texData) => Some(&texData.Textures),
+                TextureData::Model(texData) => Some(&texData.Textures),
+                _ => None
+            };
+            if let Some(blockTextures) = blockTextures {
+                //The textures are already unique and in the order their offsets were handed out in
+                for texture in blockTextures {
+                    let animation = self.BlocksAttributes[id].Animations.get(&texture.Path).copied();
+                    textures.push((texture.Path.as_str(), *id, animation, texture.Connected));
                 }
-
             }
-            return Ok(true);
+            else if let TextureData::Single(texData) = &self.BlocksAttributes[id].TextureData {
+                let animation = self.BlocksAttributes[id].Animations.get(&texData.Texture).copied();
+                textures.push((texData.Texture.as_str(), *id, animation, false));
+            }
         }
-        Ok(false)
+        textures
     }
 
-    pub fn GenerateAtlas(&self, textureResolution: u32, display: &glium::Display) -> Result<TextureAtlas, String> {
+    /*
+        Packs the block textures onto an atlas, in the order the texture IDs were handed out in ReadBlockAttributes.
+        Textures bigger than textureResolution are scaled down to fit it. The atlas is only built again when
+        one of its textures (or their order) changed since it was cached
+    */
+    pub fn GenerateAtlas(&self, textureResolution: u32, cache: &AtlasCache, display: &glium::Display) -> Result<TextureAtlas, String> {
         //TODO Create a loading bar when creating a new texture atlas
         //TODO Make a loading bar thing in resource which takes a percentage and prints a bar for you and some metadata
-        //attemp to make a square image out of the atlas...
-        let dims = f32::ceil(f32::sqrt(self.NumRegisteredTextures as f32)) as u32;
+        let textures = self.AtlasTextures();
+        let sources: Vec<AtlasSource> = textures.iter().map(|(path, _, animation, connected)| AtlasSource { 
+            Path: Some(*path), 
+            Animation: *animation,
+            Sheet: connected.then(|| (CONNECTED_SHEET_SIZE, CONNECTED_SHEET_SIZE)),
+        }).collect();
+        let key = AtlasCache::Key(&sources, Some(textureResolution), ATLAS_PADDING);
 
         //First check if the atlas already exists...
-        if self.ValidatePreviousAtlas(dims, textureResolution)? {
-            let res = resource::GetImageFromPath("./minecraft_gl/assets/data/block/atlas/atlas.png")
-            .map_err(|e| format!("Could not open the pre-existing block atlas in ./minecraft_gl/assets/block/atlas/atlas.png! The error:\n{}", e.to_string()))?;
-            
-            //open the json again, would be sloppy to return the rows and cols from validatePreviousAtlas()
-            let file = std::fs::File::open("./minecraft_gl/assets/data/block/atlas/metaData.json")
-            .map_err(|_| format!("Could not open metaData file of path {} for the block atlas.", "./minecraft_gl/assets/data/block/atlas/metaData.json"))?;
-            
-            let json: Value = serde_json::from_reader(BufReader::new(file))
-            .map_err(|_| "Could not open block atlas metadata json!")?;
-            let rows = json.get("Rows").unwrap().as_u64().unwrap() as u32;
-            let cols = json.get("Cols").unwrap().as_u64().unwrap() as u32;
-
-            return Ok(TextureAtlas::FromImage(res, rows, cols, textureResolution, display))
+        if let Some((image, layout)) = cache.Load("block", key) {
+            return TextureAtlas::FromLayout(image, layout, display);
         }
 
-        //the master texture atlas image. We will paste texture - sub images onto this
-        let mut img = image::RgbaImage::new(textureResolution * dims, textureResolution * dims);
-
-        let mut runningTextureCount = 0;
-        let mut keys: Vec<&u8> = self.BlocksAttributes.keys().collect();
-        keys.sort();
-        for id in keys {
-            if *id == 0 {
-                //skip air, it will have no texture
-                continue;
-            }
-
-            //If there is texture data...
-            if let TextureData::SixSided(texData) = &self.BlocksAttributes[id].TextureData {
-
-                let mut set: HashSet<&str> = HashSet::new(); //&str to prevent heap allocation
-                for i in 0..6 {
-                    //Only add a new texture if we haven't encountered it before
-                    if ! set.contains(&texData.Textures[i].as_str()) {
-                        set.insert(texData.Textures[i].as_str());
-                 
-                        let mut pathBuf = PathBuf::new();
-                        pathBuf.push("./minecraft_gl/assets/data/block/img");
-                        pathBuf.push(texData.Textures[i].as_str());
-                   
-                        //If the path is invalid, use the null texture and print an error response
-                        let mut texture = match resource::GetImageOrNull(pathBuf.as_path().as_os_str().to_str().unwrap()) {
-                            Ok(tex) => tex,
-                            Err(tex) => {
-                                eprintln!("Image of path {} is invalid! Using null texture for block {} of id {}", pathBuf.as_os_str().to_str().unwrap(), self.BlocksAttributes[id].Name, id);
-                                tex
-                            }
-                        };
-
-                        //resize the image
-                        texture = image::DynamicImage::ImageRgba8(image::imageops::resize(&texture, textureResolution, textureResolution, image::imageops::FilterType::Nearest));
-                        let coords = ((runningTextureCount % dims) * textureResolution, (runningTextureCount / dims) * textureResolution);
-                        image::imageops::overlay(&mut img, &mut texture, coords.0, coords.1);
-                        runningTextureCount += 1;
-                    }
+        let mut builder = AtlasBuilder::New(ATLAS_PADDING, Some(textureResolution));
+        for (path, id, animation, connected) in textures {
+            //If the path is invalid, use the null texture and print an error response
+            let texture = match resource::GetImageOrNull(path) {
+                Ok(tex) => tex,
+                Err(tex) => {
+                    eprintln!("Image of path {} is invalid! Using null texture for block {} of id {}", path, self.BlocksAttributes[&id].Name, id);
+                    tex
                 }
+            };
+            if connected {
+                builder.AddSheet(&texture, CONNECTED_SHEET_SIZE, CONNECTED_SHEET_SIZE)
+                .map_err(|e| format!("Could not split the connected texture {} of block {} of id {}. {}", path, self.BlocksAttributes[&id].Name, id, e))?;
+                continue;
             }
-            else if let TextureData::Single(texData) = &self.BlocksAttributes[id].TextureData {
-                let mut pathBuf = PathBuf::new();
-                pathBuf.push("./minecraft_gl/assets/data/block/img/");
-                pathBuf.push(texData.Texture.as_str());
-
-                let mut texture = resource::GetImageFromPath(pathBuf.as_os_str().to_str().unwrap())?;
-                texture = image::DynamicImage::ImageRgba8(image::imageops::resize(&mut texture, textureResolution, textureResolution, image::imageops::FilterType::Nearest));
-                let coords = ((runningTextureCount % dims) * textureResolution, (runningTextureCount / dims) * textureResolution);
-                image::imageops::overlay(&mut img, &texture, coords.0, coords.1);
-                runningTextureCount += 1;
+            match animation {
+                Some(animation) => {
+                    builder.AddStrip(&texture, &animation)
+                    .map_err(|e| format!("Could not animate the texture {} of block {} of id {}. {}", path, self.BlocksAttributes[&id].Name, id, e))?;
+                },
+                None => { builder.Add(&texture); }
             }
- 
         }
         
-        let image = image::DynamicImage::ImageRgba8(img);
-        
-        //Save the image and some metadata about it
-        image.save("./minecraft_gl/assets/data/block/atlas/atlas.png").expect("Could not save block atlas png!");
-        let mut file = File::create("./minecraft_gl/assets/data/block/atlas/metadata.json").expect("Could not create block atlas metadata file!");
-
-        //generate a list of all the block names
-        let mut blocks: Vec<&str> = Vec::with_capacity(self.NumRegisteredBlocks as usize);
-        for attrib in self.StringToID.keys() {
-            blocks.push(&attrib);
-        }
-        //generate a serialized json string of these values and write it to the file
-        let serialized = serde_json::to_string(&blocks).expect("Could not serialize block names to json fromat!");
-        let finalStr = format!("{{\n\"Items\": {},\n\"Rows\": {},\n\"Cols\": {},\n\"Texture Resolution\": {}\n}}", serialized, dims, dims, textureResolution);
-        file.write_all(finalStr.as_bytes()).expect("Could not write to block atlas metadata file!");
+        let (img, layout) = builder.Build();
+        cache.Store("block", key, &img, &layout)?;
 
-        Ok(TextureAtlas::FromImage(image, dims, dims, textureResolution, display))
+        TextureAtlas::FromLayout(image::DynamicImage::ImageRgba8(img), layout, display)
     }
 
-    pub fn OnLeftClickWithName(&self, blockName: &str, hit: Item) {
-        let blockID = self.StringToID[blockName];
-        let behavior =  &self.BlockBehaviors[&blockID];
-        (behavior.OnLeftClick)(self.GetAttributesOfID(blockID), hit)
-    }
-
-    pub fn OnLeftClickWithID(&self, blockID: u8, hit: Item) {
-        let behavior =  &self.BlockBehaviors[&blockID];
-        (behavior.OnLeftClick)(self.GetAttributesOfID(blockID), hit)
-
-    }
-
-    pub fn OnRightClickWithName(&self, blockName: &str) -> Option<fn(&BlockAttribute, Event) -> bool> {
-        let blockID = self.StringToID[blockName];
-        let behavior =  &self.BlockBehaviors[&blockID];
-        (behavior.OnRightClick)(self.GetAttributesOfID(blockID))
-        
-
+    //Blocks without a behavior, like air, act like DefaultBehavior
+    pub fn GetBehavior(&self, blockID: u8) -> &dyn BlockBehavior {
+        match self.BlockBehaviors.get(&blockID) {
+            Some(behavior) => behavior.as_ref(),
+            None => &DefaultBehavior
+        }
     }
 
-    pub fn OnRightClickWithID(&self, blockID: u8) -> Option<fn(&BlockAttribute, Event) -> bool> {
-        let behavior =  &self.BlockBehaviors[&blockID];
-        (behavior.OnRightClick)(self.GetAttributesOfID(blockID))
+    //Gives a block its behavior. Returns false if no block has the identifier
+    pub fn Bind(&mut self, blockName: &str, behavior: Box<dyn BlockBehavior>) -> bool {
+        match self.NameToID(blockName) {
+            Some(id) => {
+                self.BlockBehaviors.insert(id, behavior);
+                true
+            },
+            None => false
+        }
     }
 
     pub fn GetAttributesOf(&self, block: &Block) -> &BlockAttribute{
@@ -556,14 +533,28 @@ impl BlockRegistry{
         Ok(&self.BlocksAttributes[&id].Name)
     }
 
+    //Takes a namespaced identifier (core:grass), not the display name
     pub fn NameToID(&self, blockName: &str) -> Option<u8>{
-        //TODO Convert the whole string to lowercase, then the first charactet to uppercase
         if ! self.StringToID.contains_key(blockName) {
             return None
         }
         Some(self.StringToID[blockName])
     }
 
+    pub fn GetColormaps(&self) -> &Colormaps {
+        &self.Colormaps
+    }
+
+    //Whether a block is a whole cube, which covers the faces of models next to it
+    pub fn IsFullBlock(&self, block: Block) -> bool{
+        block != Block::Air() && self.BlocksAttributes.get(&block.ID).map_or(false, |b| b.Model.is_none())
+    }
+
+    //Whether a block stops rays and falling items, and can be mined or built against. Air and fluids aren't
+    pub fn IsSolid(&self, block: Block) -> bool{
+        block != Block::Air() && self.BlocksAttributes.get(&block.ID).is_some_and(|b| !b.Fluid)
+    }
+
     pub fn HasBlock(&self, blockName: &str) -> bool{
         self.StringToID.contains_key(blockName)
     }
@@ -571,4 +562,61 @@ impl BlockRegistry{
     pub fn InitBehaviors(&mut self){
         BlockBindingFunction(self);
     }
+}
+
+//Finds a block texture in the content packs. Missing textures keep a path so the atlas can report them and use the null texture
+fn ResolveTexture(packs: &ContentPacks, texture: &str) -> String {
+    packs.ResolveFileOrCore(&format!("block/img/{}", texture)).to_string_lossy().into_owned()
+}
+
+//The texture file a texture key of a block's model stands for
+pub fn ModelTexture<'a>(json: &'a BlockJson, key: &str) -> Result<&'a str, String> {
+    match json.ModelTextures.get(key).or(json.Texture.as_ref()) {
+        Some(texture) => Ok(texture.as_str()),
+        None => Err(format!("the texture key {} isn't in 'Model Textures' and the block has no 'Texture'", key))
+    }
+}
+
+//Turns one face of a block's json into the textures it can show, adding them to the block's textures
+fn ReadFace(packs: &ContentPacks, face: &FaceTextureJson, textures: &mut Vec<BlockTexture>, animations: &HashMap<String, TextureAnimation>) -> Result<FaceTexture, String> {
+    face.Validate()?;
+
+    let mut variant = |texture: &str, rotation: u32, weight: u32, connected: bool| -> Result<FaceVariant, String> {
+        let path = ResolveTexture(packs, texture);
+        let animated = animations.contains_key(&path);
+        if connected && animated {
+            return Err(format!("the connected texture {} can't be animated", texture));
+        }
+        Ok(FaceVariant { 
+            Rotation: QuarterTurns(rotation)?, 
+            Weight: weight, 
+            Animated: animated, 
+            Offset: OffsetOf(textures, path, connected) 
+        })
+    };
+
+    match face {
+        FaceTextureJson::Name(texture) => Ok(FaceTexture::Fixed(variant(texture, 0, 1, false)?)),
+        FaceTextureJson::Face(FaceJson { Texture: Some(texture), Rotation, .. }) => Ok(FaceTexture::Fixed(variant(texture, *Rotation, 1, false)?)),
+        FaceTextureJson::Face(FaceJson { Variants: Some(variants), .. }) => {
+            let variants = variants.iter().map(|v| variant(&v.Texture, v.Rotation, v.Weight, false)).collect::<Result<Vec<_>, String>>()?;
+            Ok(FaceTexture::Random(variants))
+        },
+        FaceTextureJson::Face(FaceJson { Connected: Some(texture), .. }) => Ok(FaceTexture::Connected(variant(texture, 0, 1, true)?)),
+        //Validate makes sure one of them is there
+        FaceTextureJson::Face(_) => Err(format!("a face needs exactly one of 'Texture', 'Variants' or 'Connected'"))
+    }
+}
+
+//Where a texture sits on the atlas relative to the block's TextureID, adding it to the block's textures the first time it's seen
+fn OffsetOf(textures: &mut Vec<BlockTexture>, path: String, connected: bool) -> u32 {
+    let mut offset = 0;
+    for texture in textures.iter() {
+        if texture.Path == path && texture.Connected == connected {
+            return offset;
+        }
+        offset += texture.Slots();
+    }
+    textures.push(BlockTexture { Path: path, Connected: connected });
+    offset
 }
\ No newline at end of file
@@ -1,62 +1,261 @@
-use crate::Event::event::Event;
-use crate::World::block::BlockAttribute;
-use super::item::Item;
+use std::collections::HashMap;
+use glium::glutin::event::VirtualKeyCode;
+use crate::Event::event::{Event, KeyPressedEvent};
+use crate::Ecs::ecs::Entity;
+use crate::World::block::{Block, BlockAttribute};
+use super::item::{Item, ItemStack};
+use super::inventory::ContainerContents;
 use super::block::BlockRegistry;
+use super::smelting::TickFurnace;
+use super::mining::Placement;
+use super::world::World;
+use super::State;
 
+pub const CHEST_ITEMS: &str = "Items";
+//State the models of placed blocks are picked by (see the block jsons). Directions are face IDs
+pub const FACING: &str = "Facing";
+pub const TOP: &str = "Top";
+pub const WALL: &str = "Wall";
+pub const OPEN: &str = "Open";
+pub const UPPER: &str = "Upper";
+//The sides a fence connects on, in face ID order skipping up and down
+pub const FENCE_SIDES: [(&str, (i32, i32, i32)); 4] = [("+X", (1, 0, 0)), ("-X", (-1, 0, 0)), ("+Z", (0, 0, 1)), ("-Z", (0, 0, -1))];
 
-pub struct BlockBehavior{
-    //TODO add an OnPlace() function which handles if the block should be placed in a chunk's State
-    //TODO and also the initialization of the attribute hashmap's fields
-    //TODO perhaps make it return an Option<> of its hashmap. If some, add to chunk State
-    pub OnLeftClick: fn(attributes: &BlockAttribute, hit: Item),
-    pub OnRightClick: fn(attributes: &BlockAttribute) -> Option<fn(attributes: &BlockAttribute, Event) -> bool>,
-    //the bool is for if the window / behavior should close / stop
-    CustomBehavor: Option<fn(attributes: &BlockAttribute, Event) -> bool>, //returned in onRightClick
+/*
+    Every hook gets the world, the position of the block it's running for and that block's state.
+    The state is taken out of its chunk while the hook runs and put back afterwards, so the hook is free
+    to use the world (even on the same chunk). Whatever is left in the map is what the block keeps,
+    an empty map means the block has no state. If the hook replaces its own block, the state is dropped
+*/
+pub struct BlockContext<'a> {
+    pub World: &'a mut World,
+    pub Position: (i32, i32, i32),
+    pub Block: Block,
+    pub Attributes: &'a BlockAttribute,
 }
 
-impl Default for BlockBehavior{
-    fn default() -> Self {
+//What came of right clicking a block
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BlockUse {
+    //The block doesn't react, so the held item gets used on it instead (like placing a block against it)
+    Pass,
+    Used,
+    //The block opened a screen, which gets the input events through OnScreenEvent until it closes
+    OpenScreen,
+}
 
-        #[allow(unused)]
-        fn onLeft(attributes: &BlockAttribute, hit: Item){
+//Whatever stepped onto a block. The player isn't an entity of its own yet
+#[derive(Clone)]
+pub enum Stepper {
+    Player,
+    Entity(Entity),
+}
 
-        }
+/*
+    How a type of block reacts to the world. Every hook has a default, so a behavior only
+    implements what it needs. Implementations are registered by block identifier in CreateBinding! below
+*/
+#[allow(unused)]
+pub trait BlockBehavior: Send + Sync {
+    //The block was just placed. Blocks with custom attributes start out with a copy of them as their state
+    fn OnPlace(&self, context: &mut BlockContext, state: &mut HashMap<String, State>) {
+        state.extend(context.Attributes.CustomAttributes.clone());
+    }
 
-        #[allow(unused)]
-        fn onRight(attributes: &BlockAttribute) -> Option<fn(attributes: &BlockAttribute, Event) -> bool>{
-            None
-        }
+    //The block is about to be broken. Returns the items it spills, by default whatever its containers held
+    fn OnBreak(&self, context: &mut BlockContext, state: &mut HashMap<String, State>) -> Vec<ItemStack> {
+        ContainerContents(state)
+    }
+
+    fn OnUse(&self, context: &mut BlockContext, state: &mut HashMap<String, State>) -> BlockUse {
+        BlockUse::Pass
+    }
+
+    //The player started hitting the block with an item (an empty stack's item for a bare hand)
+    fn OnLeftClick(&self, context: &mut BlockContext, state: &mut HashMap<String, State>, hit: &Item) {
+
+    }
+
+    //Input while the block's screen is open. Returns true once the screen should close
+    fn OnScreenEvent(&self, context: &mut BlockContext, state: &mut HashMap<String, State>, event: &Event) -> bool {
+        true
+    }
+
+    //Called every world tick for each placed block of this type that has state
+    fn OnTick(&self, context: &mut BlockContext, state: &mut HashMap<String, State>, deltaTime: f32) {
+
+    }
+
+    //The player placed the block, right after OnPlace. Blocks placed by the world itself never get this
+    fn OnPlacedBy(&self, context: &mut BlockContext, state: &mut HashMap<String, State>, placement: &Placement) {
+
+    }
+
+    //The block at 'neighbor', one of the six touching this one, was placed, broken or replaced
+    fn OnNeighborChanged(&self, context: &mut BlockContext, state: &mut HashMap<String, State>, neighbor: (i32, i32, i32)) {
+
+    }
+
+    fn OnEntityStep(&self, context: &mut BlockContext, state: &mut HashMap<String, State>, stepper: &Stepper) {
 
-        Self { OnLeftClick: onLeft, OnRightClick: onRight, CustomBehavor: None }
     }
 }
 
+//Blocks without a binding of their own
+pub struct DefaultBehavior;
+
+impl BlockBehavior for DefaultBehavior {}
+
 
 ////////////////////////////////////////////////////////////////////////////////////////////////////
 // ///////////////////////////////////////////////////////////////////////////////////////////////./
 
-crate::CreateBinding!(BlockBindingFunction,
+crate::CreateBinding!(BlockBindingFunction, BlockRegistry,
+    "core:crafting_table" => CraftingTable,
+    "core:furnace" => Furnace,
+    "core:chest" => Chest,
+    "core:stone_slab" => Slab,
+    "core:oak_stairs" => Stairs,
+    "core:oak_fence" => Fence,
+    "core:torch" => Torch,
+    "core:oak_door" => Door,
+);
 
-    pub fn BindCraftingTable(registry: &mut BlockRegistry) {
-        let craftingTableID = registry.NameToID("craftingTable").expect("could not find crafting table");
-        #[allow(unused)]
-        fn onLeft(attributes: &BlockAttribute, hit: Item){
+//The crafting screen isn't drawn yet, so the table acts like any other block for now
+pub struct CraftingTable;
 
-        }
-        
-        #[allow(unused)]
-        fn onRight(attributes: &BlockAttribute) -> Option<fn(attributes: &BlockAttribute, Event) -> bool>{
-            None
-        }
+impl BlockBehavior for CraftingTable {}
+
+//The furnace's slots and timers are its custom attributes (see smelting.rs)
+pub struct Furnace;
+
+impl BlockBehavior for Furnace {
+    fn OnTick(&self, context: &mut BlockContext, state: &mut HashMap<String, State>, deltaTime: f32) {
+        TickFurnace(state, context.World.GetItemRegistry(), context.World.GetSmeltingRegistry(), deltaTime);
+    }
+}
+
+//The chest's items are the container attribute CHEST_ITEMS. Its screen reaches them through World::OpenedContainer
+pub struct Chest;
+
+impl BlockBehavior for Chest {
+    fn OnUse(&self, _: &mut BlockContext, _: &mut HashMap<String, State>) -> BlockUse {
+        BlockUse::OpenScreen
+    }
+
+    //Closes on E, like the inventory (escape already quits the game)
+    fn OnScreenEvent(&self, _: &mut BlockContext, _: &mut HashMap<String, State>, event: &Event) -> bool {
+        matches!(event, Event::KeyPressed(KeyPressedEvent { Key: VirtualKeyCode::E, .. }))
+    }
+}
+
+//Sits in the top half of the cell when put against a ceiling or the top half of a wall
+pub struct Slab;
+
+impl BlockBehavior for Slab {
+    fn OnPlacedBy(&self, _: &mut BlockContext, state: &mut HashMap<String, State>, placement: &Placement) {
+        state.insert(TOP.to_owned(), State::BoolAttribute(placement.Upper));
+    }
+}
+
+//Climbs away from the player who placed it, and hangs upside down like a slab does
+pub struct Stairs;
+
+impl BlockBehavior for Stairs {
+    fn OnPlacedBy(&self, _: &mut BlockContext, state: &mut HashMap<String, State>, placement: &Placement) {
+        state.insert(FACING.to_owned(), State::IntAttribute(placement.Facing as i32));
+        state.insert(TOP.to_owned(), State::BoolAttribute(placement.Upper));
+    }
+}
+
+//Connects to other fences and to full blocks beside it
+pub struct Fence;
 
-        #[allow(unused)]
-        fn CustomBehavior(attributes: &BlockAttribute, inputEvent: Event) -> bool{
-            false
+impl Fence {
+    fn Connect(context: &mut BlockContext, state: &mut HashMap<String, State>) {
+        let (x, y, z) = context.Position;
+        for (side, offset) in FENCE_SIDES {
+            let connected = match context.World.GetBlock((x + offset.0, y + offset.1, z + offset.2)) {
+                Some(block) => block == context.Block || context.World.GetBlockRegistry().IsFullBlock(block),
+                None => false
+            };
+            state.insert(side.to_owned(), State::BoolAttribute(connected));
         }
+    }
+}
 
-        let behavior = BlockBehavior { OnLeftClick: onLeft, OnRightClick: onRight, CustomBehavor: Some(CustomBehavior) };
-        registry.BlockBehaviors.insert(craftingTableID, behavior); //move into the vector
+impl BlockBehavior for Fence {
+    fn OnPlace(&self, context: &mut BlockContext, state: &mut HashMap<String, State>) {
+        state.extend(context.Attributes.CustomAttributes.clone());
+        Fence::Connect(context, state);
     }
 
-);
+    fn OnNeighborChanged(&self, context: &mut BlockContext, state: &mut HashMap<String, State>, _: (i32, i32, i32)) {
+        Fence::Connect(context, state);
+    }
+}
+
+//Leans against the wall it was put on, or stands upright on the floor
+pub struct Torch;
+
+impl BlockBehavior for Torch {
+    fn OnPlacedBy(&self, _: &mut BlockContext, state: &mut HashMap<String, State>, placement: &Placement) {
+        let wall = placement.Face != 2 && placement.Face != 3;
+        state.insert(WALL.to_owned(), State::BoolAttribute(wall));
+        state.insert(FACING.to_owned(), State::IntAttribute(if wall { placement.Face as i32 } else { 0 }));
+    }
+}
+
+/*
+    Two blocks tall, the bottom half being the one the player placed. Both halves keep the same facing and open state,
+    and breaking either one breaks the other
+*/
+pub struct Door;
+
+impl Door {
+    fn Partner(context: &BlockContext, state: &HashMap<String, State>) -> (i32, i32, i32) {
+        let (x, y, z) = context.Position;
+        if state.get(UPPER).and_then(|s| s.AsBool()).copied().unwrap_or(false) { (x, y - 1, z) } else { (x, y + 1, z) }
+    }
+}
+
+impl BlockBehavior for Door {
+    fn OnPlacedBy(&self, context: &mut BlockContext, state: &mut HashMap<String, State>, placement: &Placement) {
+        state.insert(FACING.to_owned(), State::IntAttribute(placement.Facing as i32));
+        state.insert(OPEN.to_owned(), State::BoolAttribute(false));
+        state.insert(UPPER.to_owned(), State::BoolAttribute(false));
+
+        //Without room for the top half there's no door at all
+        let above = Door::Partner(context, state);
+        if !context.World.PlaceBlock(above, context.Block, None) {
+            context.World.BreakBlock(context.Position);
+            return;
+        }
+        //and if the top half couldn't be set up, the bottom half goes too rather than leaving half a door
+        let mut upper = state.clone();
+        upper.insert(UPPER.to_owned(), State::BoolAttribute(true));
+        if !context.World.SetBlockState(above, upper) {
+            context.World.BreakBlock(above);
+            context.World.BreakBlock(context.Position);
+        }
+    }
+
+    fn OnUse(&self, context: &mut BlockContext, state: &mut HashMap<String, State>) -> BlockUse {
+        let open = !state.get(OPEN).and_then(|s| s.AsBool()).copied().unwrap_or(false);
+        state.insert(OPEN.to_owned(), State::BoolAttribute(open));
 
+        let partner = Door::Partner(context, state);
+        if context.World.GetBlock(partner) == Some(context.Block) {
+            let mut other = state.clone();
+            other.insert(UPPER.to_owned(), State::BoolAttribute(partner.1 > context.Position.1));
+            context.World.SetBlockState(partner, other);
+        }
+        BlockUse::Used
+    }
+
+    fn OnNeighborChanged(&self, context: &mut BlockContext, state: &mut HashMap<String, State>, neighbor: (i32, i32, i32)) {
+        if neighbor == Door::Partner(context, state) && context.World.GetBlock(neighbor) != Some(context.Block) {
+            context.World.BreakBlock(context.Position);
+        }
+    }
+}
@@ -0,0 +1,223 @@
+use std::collections::HashMap;
+use super::State;
+use super::contentPack::ContentPacks;
+use super::schema::{BlockJson, ModelJson, ModelVariantJson, StateValueJson, QuarterTurns, ReadJsonFile};
+
+/*
+    Block models are boxes measured in 16ths of a block (see ModelJson). They're turned into quads once when the
+    block registry is read, and the mesher copies the quads of the parts a block's state selects. Unlike cubes,
+    model faces are never merged with their neighbours
+*/
+
+pub const MODEL_UNITS: u32 = 16;
+//The parts drawn for a state are kept as a bitmask (see BlockModel::Selected), so a block can't have more
+pub const MAX_MODEL_PARTS: usize = 64;
+//Decoration blocks without a model of their own use this one
+pub const DEFAULT_DECOR_MODEL: &str = "cross.json";
+//Face names in face ID order
+pub const FACE_NAMES: [&str; 6] = ["+X", "-X", "+Y", "-Y", "+Z", "-Z"];
+const CROSS_FACE: &str = "Cross";
+
+/*
+    Which corner of an element each corner of a face is at, 1 meaning the element's 'To' on that axis.
+    Corners go top left, top right, bottom left, bottom right as seen from in front of the face,
+    with the top of side faces being up and the top of the +Y and -Y faces being towards -Z
+*/
+const FACE_CORNERS: [[[u32; 3]; 4]; 6] = [
+    [[1, 1, 1], [1, 1, 0], [1, 0, 1], [1, 0, 0]],
+    [[0, 1, 0], [0, 1, 1], [0, 0, 0], [0, 0, 1]],
+    [[0, 1, 0], [1, 1, 0], [0, 1, 1], [1, 1, 1]],
+    [[0, 0, 1], [1, 0, 1], [0, 0, 0], [1, 0, 0]],
+    [[0, 1, 1], [1, 1, 1], [0, 0, 1], [1, 0, 1]],
+    [[1, 1, 0], [0, 1, 0], [1, 0, 0], [0, 0, 0]],
+];
+//The two diagonal planes of a cross
+const CROSS_CORNERS: [[[u32; 3]; 4]; 2] = [
+    [[0, 1, 0], [1, 1, 1], [0, 0, 0], [1, 0, 1]],
+    [[0, 1, 1], [1, 1, 0], [0, 0, 1], [1, 0, 0]],
+];
+//The face each face ends up as after a quarter turn (+X to +Z)
+const TURNED_FACE: [u8; 6] = [4, 5, 2, 3, 1, 0];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ModelQuad {
+    //Corners in 16ths of a block, in the order of FACE_CORNERS
+    pub Corners: [[u8; 3]; 4],
+    //Texture coordinates of the corners in 16ths of the texture
+    pub UVs: [[u8; 2]; 4],
+    //How far the texture is away from the block's TextureID on the atlas
+    pub Offset: u32,
+    //Clockwise quarter turns of the texture
+    pub Rotation: u8,
+    pub Animated: bool,
+    //Which way the quad faces, for shading. Cross planes are shaded like +X
+    pub FaceID: u8,
+    //Whether the block's Tint colours the quad
+    pub Tinted: bool,
+    //Hidden when a full block is next to the block on this side (a face ID)
+    pub Cull: Option<u8>,
+}
+
+#[derive(Clone, Debug)]
+pub struct ModelPart {
+    pub When: Vec<(String, StateValueJson)>,
+    pub Quads: Vec<ModelQuad>,
+}
+
+#[derive(Clone, Debug)]
+pub struct BlockModel {
+    pub Parts: Vec<ModelPart>,
+}
+
+impl BlockModel {
+    //Bitmask of the parts drawn for a block's state. A block without state draws the parts that match 0 and false
+    pub fn Selected(&self, state: Option<&HashMap<String, State>>) -> u64 {
+        let mut selected = 0;
+        for (i, part) in self.Parts.iter().enumerate() {
+            if part.When.iter().all(|(key, value)| value.Matches(state.and_then(|s| s.get(key)))) {
+                selected |= 1 << i;
+            }
+        }
+        selected
+    }
+
+    pub fn Quads<'a>(&'a self, state: Option<&HashMap<String, State>>) -> impl Iterator<Item = &'a ModelQuad> {
+        let selected = self.Selected(state);
+        self.Parts.iter().enumerate()
+        .filter(move |(i, _)| selected & (1 << i) != 0)
+        .flat_map(|(_, part)| part.Quads.iter())
+    }
+}
+
+/*
+    Reads the models a block's json draws it with. 'texture' turns a texture key of a model into where the
+    texture is relative to the block's TextureID, and whether it's animated
+*/
+pub fn ReadBlockModel(packs: &ContentPacks, json: &BlockJson, texture: &mut dyn FnMut(&str) -> Result<(u32, bool), String>) -> Result<BlockModel, String> {
+    let mut variants: Vec<ModelVariantJson> = Vec::new();
+    match &json.Model {
+        Some(model) => variants.push(ModelVariantJson { When: HashMap::new(), Model: model.clone(), Rotation: 0 }),
+        None if json.Models.is_empty() => variants.push(ModelVariantJson { When: HashMap::new(), Model: DEFAULT_DECOR_MODEL.to_owned(), Rotation: 0 }),
+        None => {}
+    }
+    variants.extend(json.Models.iter().cloned());
+
+    if variants.len() > MAX_MODEL_PARTS {
+        return Err(format!("a block can have at most {} models, not {}", MAX_MODEL_PARTS, variants.len()));
+    }
+
+    //Blocks tend to use the same model many times over with different rotations, so each file is only read once
+    let mut files: HashMap<String, ModelJson> = HashMap::new();
+    let mut parts = Vec::with_capacity(variants.len());
+    for variant in variants {
+        if !files.contains_key(&variant.Model) {
+            files.insert(variant.Model.clone(), ReadModelFile(packs, &variant.Model)?);
+        }
+
+        let quads = CompileModel(&files[&variant.Model], QuarterTurns(variant.Rotation)?, texture)
+        .map_err(|e| format!("{} in the model {}", e, variant.Model))?;
+
+        let mut when: Vec<(String, StateValueJson)> = variant.When.into_iter().collect();
+        when.sort_by(|a, b| a.0.cmp(&b.0));
+        parts.push(ModelPart { When: when, Quads: quads });
+    }
+    Ok(BlockModel { Parts: parts })
+}
+
+//Reads a model by file name from the last content pack that has it
+pub fn ReadModelFile(packs: &ContentPacks, model: &str) -> Result<ModelJson, String> {
+    let path = packs.ResolveFile(&format!("block/model/{}", model))
+    .ok_or(format!("the model {} is not in any content pack", model))?;
+    ReadJsonFile(&path)
+}
+
+//Turns a model into quads, turned around the vertical axis by some quarter turns
+fn CompileModel(json: &ModelJson, turns: u8, texture: &mut dyn FnMut(&str) -> Result<(u32, bool), String>) -> Result<Vec<ModelQuad>, String> {
+    let mut quads = Vec::new();
+    for element in &json.Elements {
+        let (from, to) = (element.From, element.To);
+        for axis in 0..3 {
+            if from[axis] > to[axis] || to[axis] > MODEL_UNITS {
+                return Err(format!("an element goes from {:?} to {:?}, which isn't a box inside the block", from, to));
+            }
+        }
+
+        //Sorted, so the quads come out in the same order every time
+        let mut faces: Vec<&String> = element.Faces.keys().collect();
+        faces.sort();
+        for name in faces {
+            let face = &element.Faces[name];
+            let (offset, animated) = texture(&face.Texture)?;
+            let rotation = QuarterTurns(face.Rotation)?;
+            if let Some(uv) = face.UV {
+                if uv.iter().any(|v| *v > MODEL_UNITS) {
+                    return Err(format!("the UV {:?} of the face {} goes past the texture", uv, name));
+                }
+            }
+
+            if name == CROSS_FACE {
+                let uv = face.UV.unwrap_or([0, MODEL_UNITS - to[1], MODEL_UNITS, MODEL_UNITS - from[1]]);
+                for corners in CROSS_CORNERS {
+                    quads.push(ModelQuad {
+                        Corners: Corners(&corners, from, to), UVs: CornerUVs(uv), Offset: offset,
+                        Rotation: rotation, Animated: animated, FaceID: 0, Tinted: face.Tint, Cull: None
+                    });
+                }
+                continue;
+            }
+
+            let faceID = match FACE_NAMES.iter().position(|n| n == name) {
+                Some(val) => val,
+                None => return Err(format!("there's no face called {}. Faces are {} or {}", name, FACE_NAMES.join(", "), CROSS_FACE))
+            };
+            let uv = face.UV.unwrap_or(DefaultUV(faceID, from, to));
+
+            //Only a face on the edge of the block can be covered up by its neighbour
+            let onEdge = match faceID {
+                0 => to[0] == MODEL_UNITS, 1 => from[0] == 0,
+                2 => to[1] == MODEL_UNITS, 3 => from[1] == 0,
+                4 => to[2] == MODEL_UNITS, _ => from[2] == 0,
+            };
+
+            quads.push(ModelQuad {
+                Corners: Corners(&FACE_CORNERS[faceID], from, to), UVs: CornerUVs(uv), Offset: offset,
+                Rotation: rotation, Animated: animated, FaceID: faceID as u8, Tinted: face.Tint,
+                Cull: if face.Cull && onEdge { Some(faceID as u8) } else { None }
+            });
+        }
+    }
+
+    for quad in quads.iter_mut() {
+        for _ in 0..turns {
+            for corner in quad.Corners.iter_mut() {
+                *corner = [MODEL_UNITS as u8 - corner[2], corner[1], corner[0]];
+            }
+            quad.FaceID = TURNED_FACE[quad.FaceID as usize];
+            quad.Cull = quad.Cull.map(|face| TURNED_FACE[face as usize]);
+        }
+    }
+    Ok(quads)
+}
+
+fn Corners(corners: &[[u32; 3]; 4], from: [u32; 3], to: [u32; 3]) -> [[u8; 3]; 4] {
+    corners.map(|c| [0, 1, 2].map(|axis| if c[axis] == 1 { to[axis] as u8 } else { from[axis] as u8 }))
+}
+
+//[u1, v1, u2, v2] to the texture coordinates of each corner
+fn CornerUVs(uv: [u32; 4]) -> [[u8; 2]; 4] {
+    let [u1, v1, u2, v2] = uv.map(|v| v as u8);
+    [[u1, v1], [u2, v1], [u1, v2], [u2, v2]]
+}
+
+//The part of the texture a full cube would show where the face is, so neighbouring elements line up
+fn DefaultUV(faceID: usize, from: [u32; 3], to: [u32; 3]) -> [u32; 4] {
+    let m = MODEL_UNITS;
+    match faceID {
+        0 => [m - to[2], m - to[1], m - from[2], m - from[1]],
+        1 => [from[2], m - to[1], to[2], m - from[1]],
+        2 => [from[0], from[2], to[0], to[2]],
+        3 => [from[0], m - to[2], to[0], m - from[2]],
+        4 => [from[0], m - to[1], to[0], m - from[1]],
+        _ => [m - to[0], m - to[1], m - from[0], m - from[1]],
+    }
+}
\ No newline at end of file
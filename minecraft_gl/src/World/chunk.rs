@@ -1,7 +1,8 @@
-use std::{collections::HashMap, sync::Arc};
-use crate::{Renderer::worldRenderer::Vertex, Util::greedyMeshHelper};
+use std::{collections::HashMap, sync::Arc, path::{Path, PathBuf}};
+use serde::{Serialize, Deserialize};
+use crate::{Renderer::worldRenderer::Vertex, Util::{greedyMeshHelper, modelMeshHelper, lodMeshHelper::{self, LodLevel}}};
 use super::{block::{Block, BlockRegistry, TextureData}, State, 
-            biomeGenerator::{Biome, BiomeGenerator}
+            biomeGenerator::{Biome, BiomeGenerator}, colormap::{Colormaps, ColumnTints, NO_TINT}
            };
 
 //TODO GET THE MATH WORKING OUT BETTER
@@ -11,6 +12,28 @@ pub const CHUNK_BOUNDS_Y: u32 = 60;
 pub const CHUNK_BOUNDS_Z: u32 = 15;
 pub const TOTAL_CHUNK_SIZE: u32 = CHUNK_BOUNDS_X * CHUNK_BOUNDS_Y * CHUNK_BOUNDS_Z;
 
+/*
+    The stages a chunk goes through, in order. A chunk is only meshed once all four
+    of its neighbours are at least decorated, so its borders are meshed against the
+    final blocks of its neighbours and never need to be redone
+*/
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum ChunkState {
+    Empty,
+    Generated,
+    Decorated,
+    Meshed,
+}
+
+//Which of a chunk's maps a block's state is kept in. Only dynamic state is ticked and saved
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum StateStore {
+    //New state is dynamic
+    #[default]
+    Dynamic,
+    Static,
+}
+
 #[derive(Clone)]
 pub struct Chunk{
     pub Blocks: Vec<Block>,
@@ -21,6 +44,21 @@ pub struct Chunk{
 
     pub Biome: Biome,
     pub BiomeValue: f32,
+    //Colours of the tinted blocks in each column, worked out from the climate when the chunk is generated
+    pub Tints: ColumnTints,
+
+    //The level of detail the current mesh was built at. The renderer scales the mesh by this
+    pub Lod: LodLevel,
+    pub Status: ChunkState,
+    //Changed since it was generated or loaded, so it has to be saved
+    pub Modified: bool,
+}
+
+//What gets written to disk. Everything else is regenerated from the seed
+#[derive(Serialize, Deserialize)]
+struct ChunkSave {
+    Blocks: Vec<u8>,
+    DynamicState: HashMap<u32, HashMap<String, State>>,
 }
 
 impl Chunk{
@@ -34,19 +72,23 @@ impl Chunk{
             Position: chunkPos,
 
             Biome: Biome::None, 
-            BiomeValue: biomeValue
+            BiomeValue: biomeValue,
+            Tints: ColumnTints::default(),
+            Lod: LodLevel::Full,
+            Status: ChunkState::Empty,
+            Modified: false,
         }
     }
 
-    pub fn OfHeight(heightLevel: u32, chunkPos: (i32, i32)) -> Self {
+    pub fn OfHeight(heightLevel: u32, chunkPos: (i32, i32), fill: Block) -> Self {
         let mut blocks = Vec::with_capacity(TOTAL_CHUNK_SIZE as usize);
         let offset = CHUNK_BOUNDS_X * (heightLevel) * CHUNK_BOUNDS_Z;
         for i in 0..TOTAL_CHUNK_SIZE {
             if i < offset {
-                blocks.push(Block { ID: 2 } );
+                blocks.push(fill);
             }
             else {
-               blocks.push(Block { ID: 0 } );
+               blocks.push(Block::Air());
             }
         }
 
@@ -58,12 +100,26 @@ impl Chunk{
             Position: chunkPos,
 
             Biome: Biome::None, 
-            BiomeValue: -1f32
+            BiomeValue: -1f32,
+            Tints: ColumnTints::default(),
+            Lod: LodLevel::Full,
+            //blocks are filled in right away
+            Status: ChunkState::Decorated,
+            Modified: false,
         }
     }
 
+    pub fn GetBlockAt(&self, coordinate: (u32, u32, u32)) -> Block {
+        self.Blocks[To1D(coordinate) as usize]
+    }
+
+    pub fn GetBlockState(&self, coordinate: (u32, u32, u32)) -> Option<&HashMap<String, State>> {
+        let idx = To1D(coordinate);
+        self.DynamicState.get(&idx).or_else(|| self.StaticState.get(&idx))
+    }
+
     pub fn GetBlockStateAt(&mut self, coordinate: (u32, u32, u32)) -> Option<&mut HashMap<String, State>> {
-        let idx = coordinate.0 + CHUNK_BOUNDS_X * (coordinate.2 + coordinate.1 * CHUNK_BOUNDS_Y);
+        let idx = To1D(coordinate);
         if self.DynamicState.contains_key(&idx) {
             return Some(self.DynamicState.get_mut(&idx).unwrap());
         }
@@ -75,30 +131,117 @@ impl Chunk{
         }
     }
 
-    pub fn DestroyBlock(&mut self, coordinate: (u32, u32, u32)){
-        //TODO make an onDestroy() function in blockBehaviors and call it here. 
-        //TODO It should take in the block state as a parameter before you delete it
-        //Remove the block from the blocks array and the state
+    /*
+        Replaces a block with air and drops its state. Returns false if it was already air.
+        The block's behavior is run by the world beforehand, since it needs to see the world
+    */
+    pub fn DestroyBlock(&mut self, coordinate: (u32, u32, u32)) -> bool{
         let idx = To1D(coordinate);
+        if self.Blocks[idx as usize] == Block::Air() {
+            return false;
+        }
+
         self.Blocks[idx as usize] = Block::Air();
-        if self.DynamicState.contains_key(&idx) {
-            self.DynamicState.remove(&idx);
+        self.Modified = true;
+        self.DynamicState.remove(&idx);
+        self.StaticState.remove(&idx);
+        true
+    }
+
+    //Places a block where there's air. Returns false if the spot is taken
+    pub fn EmplaceBlock(&mut self, coordinate: (u32, u32, u32), block: &Block) -> bool{
+        let idx = To1D(coordinate);
+        if self.Blocks[idx as usize] != Block::Air() {
+            return false;
         }
-        else if self.StaticState.contains_key(&idx) {
-            self.StaticState.remove(&idx);
+
+        self.Blocks[idx as usize] = *block;
+        self.Modified = true;
+        true
+    }
+
+    //Removes a block's state so a behavior can work on it while it has the world borrowed. PutBlockState puts it back
+    pub fn TakeBlockState(&mut self, coordinate: (u32, u32, u32)) -> Option<(HashMap<String, State>, StateStore)> {
+        let idx = To1D(coordinate);
+        if let Some(state) = self.DynamicState.remove(&idx) {
+            return Some((state, StateStore::Dynamic));
         }
+        self.StaticState.remove(&idx).map(|state| (state, StateStore::Static))
+    }
+
+    //An empty state is the same as no state
+    pub fn PutBlockState(&mut self, coordinate: (u32, u32, u32), state: HashMap<String, State>, store: StateStore) {
+        let idx = To1D(coordinate);
+        let map = match store {
+            StateStore::Dynamic => &mut self.DynamicState,
+            StateStore::Static => &mut self.StaticState,
+        };
+        if state.is_empty() {
+            map.remove(&idx);
+            return;
+        }
+        map.insert(idx, state);
+        self.Modified = true;
+    }
+
+    //Replaces a block's state, keeping it in the map it's already in
+    pub fn SetBlockState(&mut self, coordinate: (u32, u32, u32), state: HashMap<String, State>) {
+        let store = if self.StaticState.contains_key(&To1D(coordinate)) { StateStore::Static } else { StateStore::Dynamic };
+        self.PutBlockState(coordinate, state, store);
     }
 
-    pub fn EmplaceBlock(&mut self, _coordinate: (u32, u32, u32), _block: &Block) -> bool{
-        //call blockBehaviors[idx].onPlace() -> Option<Hashmap> and add that hashmap to block states
-        //bool for success
-        false
+    //Local coordinates of every block with dynamic state, like furnaces and chests
+    pub fn StatefulBlocks(&self) -> Vec<(u32, u32, u32)> {
+        self.DynamicState.keys().map(|idx| From1D(*idx)).collect()
     }
 
     pub fn PropogateBlockUpdate(&mut self, _origin: (u32, u32, u32)){
 
     }
 
+    pub fn Save(&self, saveDir: &Path) -> Result<(), String> {
+        let path = ChunkSavePath(saveDir, self.Position);
+        std::fs::create_dir_all(path.parent().unwrap())
+        .map_err(|e| format!("Error! Could not create the chunk directory for {}! The error:
+{}", path.display(), e.to_string()))?;
+
+        let save = ChunkSave { Blocks: self.Blocks.iter().map(|b| b.ID).collect(), DynamicState: self.DynamicState.clone() };
+        let serialized = serde_json::to_string(&save)
+        .map_err(|e| format!("Error! Could not serialize chunk {:?}! The error:
+{}", self.Position, e.to_string()))?;
+        std::fs::write(&path, serialized)
+        .map_err(|e| format!("Error! Could not write chunk {}! The error:
+{}", path.display(), e.to_string()))
+    }
+
+    /*
+        Swaps the generated blocks for the saved ones, if the chunk was ever saved. Returns whether it was.
+        Blocks whose content was removed since the save turn into air
+    */
+    pub fn LoadSaved(&mut self, saveDir: &Path, blockRegistry: &BlockRegistry) -> Result<bool, String> {
+        let path = ChunkSavePath(saveDir, self.Position);
+        if !path.exists() {
+            return Ok(false);
+        }
+
+        let file = std::fs::File::open(&path)
+        .map_err(|e| format!("Error! Could not open chunk {}! The error:
+{}", path.display(), e.to_string()))?;
+        let save: ChunkSave = serde_json::from_reader(std::io::BufReader::new(file))
+        .map_err(|e| format!("Error! The chunk {} is corrupted! The error:
+{}", path.display(), e.to_string()))?;
+        if save.Blocks.len() != TOTAL_CHUNK_SIZE as usize {
+            return Err(format!("Error! The chunk {} has {} blocks instead of {}!", path.display(), save.Blocks.len(), TOTAL_CHUNK_SIZE));
+        }
+
+        self.Blocks = save.Blocks.into_iter()
+                      .map(|id| if blockRegistry.BlocksAttributes.contains_key(&id) { Block { ID: id } } else { Block::Air() })
+                      .collect();
+        let blocks = &self.Blocks;
+        self.DynamicState = save.DynamicState.into_iter().filter(|(idx, _)| (*idx as usize) < blocks.len() && blocks[*idx as usize] != Block::Air()).collect();
+        Ok(true)
+    }
+
     pub fn Clear(&mut self){
         self.Blocks.clear();
         self.Mesh.clear();
@@ -108,61 +251,130 @@ impl Chunk{
         self.Mesh.clear();
     }
 
-    pub fn GenerateBlocks(&mut self, generator: &mut Box<dyn BiomeGenerator + Send>){
+    pub fn GenerateBlocks(&mut self, generator: &dyn BiomeGenerator){
 
         //TODO maybe change surface ampltidue in json file to max height and when making heightmap do
         //TODO Surface level + (max_height - surface level) * noise_normalized
         self.Blocks.resize(TOTAL_CHUNK_SIZE as usize, Block::Air());
         generator.Generate(&mut self.Blocks, self.Position.0, self.Position.1);
-        self.Blocks[To1DUsize((0, CHUNK_BOUNDS_Y as usize - 5, 0))] = Block { ID: 6 };
+        self.Status = ChunkState::Generated;
+    }
+
+    pub fn Decorate(&mut self, generator: &dyn BiomeGenerator){
+        generator.Decorate(&mut self.Blocks, self.Position.0, self.Position.1);
+        self.Status = ChunkState::Decorated;
+    }
 
-        //get rid of straggler stone block
-        //TODO figure out why the straggler is being generated
-        self.Blocks[To1Di((0, 55,0)) as usize] = Block::Air();
+    //Blends the colours of tinted blocks from the generator's climate. Saved chunks keep the tints of the terrain they replace
+    pub fn GenerateTints(&mut self, generator: &dyn BiomeGenerator, colormaps: &Colormaps){
+        self.Tints = ColumnTints::Blend(colormaps, self.Position, &mut |x, z| generator.Climate(x, z));
     }
 
     pub fn GreedyMesh(&mut self, adj: &[Option<Arc<Chunk>>; 4], blockRegistry: &BlockRegistry){
-        self.ClearMesh();
-        let dimensions = [CHUNK_BOUNDS_X as usize, CHUNK_BOUNDS_Y as usize, CHUNK_BOUNDS_Z as usize];
+        self.GreedyMeshLod(LodLevel::Full, adj, &[LodLevel::Full; 4], blockRegistry);
+    }
+
+    pub fn GreedyMeshLod(&mut self, lod: LodLevel, adj: &[Option<Arc<Chunk>>; 4], adjLods: &[LodLevel; 4], 
+                         blockRegistry: &BlockRegistry)
+    {
+        self.Mesh = self.BuildMesh(lod, adj, adjLods, blockRegistry);
+        self.Lod = lod;
+        self.Status = ChunkState::Meshed;
+    }
 
+    //Builds the mesh without touching the chunk, so workers can mesh a shared chunk without cloning its blocks
+    pub fn BuildMesh(&self, lod: LodLevel, adj: &[Option<Arc<Chunk>>; 4], adjLods: &[LodLevel; 4], 
+                     blockRegistry: &BlockRegistry) -> Vec<Vertex>
+    {
+        //approcimation of surface area
+        let mut mesh = Vec::with_capacity(f32::powf(TOTAL_CHUNK_SIZE as f32, 2f32 / 3f32) as usize * 6);
+        let dimensions = lod.Dimensions();
+
+        /*
+            Neighbours have to be sampled at the same resolution as us. If a neighbour is at a
+            different level of detail, its surface won't line up with ours and there would be cracks
+            in between. So we pretend that neighbour is all air, which makes both chunks draw their
+            border walls. These walls act as skirts that cover up the seam
+        */
+        let mut neighbours: [Option<Arc<Chunk>>; 4] = [None, None, None, None];
+        for i in 0..4 {
+            if adjLods[i] != lod {
+                let mut skirt = Chunk::New(self.Position, self.BiomeValue);
+                skirt.Blocks.resize(dimensions[0] * dimensions[1] * dimensions[2], Block::Air());
+                neighbours[i] = Some(Arc::new(skirt));
+            }
+            else if let Some(chunk) = &adj[i] {
+                neighbours[i] = if lod == LodLevel::Full { 
+                    Some(chunk.clone()) 
+                } else {
+                    let mut downsampled = Chunk::New(chunk.Position, chunk.BiomeValue);
+                    downsampled.Blocks = lodMeshHelper::Downsample(&chunk.Blocks, lod, blockRegistry);
+                    Some(Arc::new(downsampled))
+                };
+            }
+        }
+
+        //World position of our first block, for picking random texture variants
+        let origin = [self.Position.0 * CHUNK_BOUNDS_X as i32, 0, self.Position.1 * CHUNK_BOUNDS_Z as i32];
+
+        if lod == LodLevel::Full {
+            for dim in 0..3 {
+                greedyMeshHelper::SweepVolume(&self.Blocks, &mut mesh, &dimensions, 
+                                dim, &neighbours, blockRegistry, origin, 1, &self.Tints);
+            }
+            self.MeshModels(&mut mesh, &neighbours, blockRegistry);
+            return mesh;
+        }
+
+        //Mesh a downsampled copy of our blocks. Models are too small to bother with
+        let downsampled = lodMeshHelper::Downsample(&self.Blocks, lod, blockRegistry);
         for dim in 0..3 {
-            greedyMeshHelper::SweepVolume(self, &dimensions, 
-                            dim, adj, blockRegistry);
+            greedyMeshHelper::SweepVolume(&downsampled, &mut mesh, &dimensions, 
+                            dim, &neighbours, blockRegistry, origin, lod.Factor() as i32, &self.Tints);
         }
-        self.MeshFlora(blockRegistry);
+        mesh
     }
 
-    pub fn MeshFlora(&mut self, blockRegistry: &BlockRegistry) {
+    //Adds the blocks drawn with models, like flowers and slabs. Their faces are hidden by full blocks next to them, even in other chunks
+    pub fn MeshModels(&self, mesh: &mut Vec<Vertex>, adj: &[Option<Arc<Chunk>>; 4], blockRegistry: &BlockRegistry) {
         //TODO for this to not have those wierd white squares show up we must have proper draw order
-        //TODO drawing the flora LAST ensures proper draw in order within the chunk but not 
+        //TODO drawing the models LAST ensures proper draw in order within the chunk but not 
         //TODO in regards to other chunks. Draw the other chunks in order of distance from camera
+        let dimensions = LodLevel::Full.Dimensions();
         for x in 0..CHUNK_BOUNDS_X {
             for y in 0..CHUNK_BOUNDS_Y {
                 for z in 0..CHUNK_BOUNDS_Z {
 
+                    let block = self.Blocks[To1D((x, y, z)) as usize];
+                    let attributes = blockRegistry.GetAttributesOf(&block);
+                    let (model, texData) = match (&attributes.Model, &attributes.TextureData) {
+                        (Some(model), TextureData::Model(texData)) => (model, texData),
+                        _ => continue
+                    };
+
                     let p = [x as i32, y as i32, z as i32];
-                    let block = self.Blocks[greedyMeshHelper::To1D(&p) as usize];
-                    
-                    //TODO fix flower rendering
-                    let mut texID = -1;
-                    if let TextureData::Single(data) = &blockRegistry.GetAttributesOf(&block).TextureData {
-                        texID = data.TextureID as i32;
-                    } 
-
-                    if greedyMeshHelper::IsFlora(block, blockRegistry) {
-                        let pp = greedyMeshHelper::AddArrayVector(&p, &[0, 1, 0]);
-                        greedyMeshHelper::ConstructFloraFaces(self, &pp, texID);
-                    }
+                    let tint = attributes.Tint.map_or(NO_TINT, |tint| self.Tints.At(tint, p[0], p[2]));
+                    modelMeshHelper::ConstructModel(mesh, p, texData.TextureID, tint, model.Quads(self.GetBlockState((x, y, z))), |n| {
+                        greedyMeshHelper::BlockAt(n, &self.Blocks, &dimensions, adj).map_or(false, |b| blockRegistry.IsFullBlock(b))
+                    });
                 }
             }
         }
     }
 }
 
+fn ChunkSavePath(saveDir: &Path, position: (i32, i32)) -> PathBuf {
+    saveDir.join("chunks").join(format!("{}_{}.json", position.0, position.1))
+}
+
 pub fn To1D(cord: (u32, u32, u32)) -> u32 {
     cord.0 + CHUNK_BOUNDS_X * (cord.2 + cord.1 * CHUNK_BOUNDS_Z)
 }
 
+pub fn From1D(idx: u32) -> (u32, u32, u32) {
+    (idx % CHUNK_BOUNDS_X, idx / (CHUNK_BOUNDS_X * CHUNK_BOUNDS_Z), (idx / CHUNK_BOUNDS_X) % CHUNK_BOUNDS_Z)
+}
+
 pub fn To1Di(cord: (i32, i32, i32)) -> i32 {
     cord.0 + CHUNK_BOUNDS_X as i32 * (cord.2 + cord.1 * CHUNK_BOUNDS_Z as i32)
 }
@@ -0,0 +1,457 @@
+use nalgebra as na;
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap};
+use std::sync::{Arc, mpsc::{self, Receiver, Sender},
+                atomic::{AtomicBool, Ordering as AtomicOrdering}
+               };
+
+use crate::Util::lodMeshHelper::{LodLevel, ADJACENT_OFFSETS};
+use crate::Renderer::worldRenderer::Vertex;
+use super::{block::BlockRegistry, chunk::{Chunk, ChunkState},
+            biomeGenerator::{BiomeGenerator, Biome},
+            world::InRadius
+           };
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum JobKind {
+    //Generate and decorate the chunk's blocks
+    Generate,
+    //Build the mesh of a loaded chunk. Only dispatched once all four neighbours are decorated
+    Mesh,
+}
+
+pub struct MeshResult {
+    pub Position: (i32, i32),
+    pub Lod: LodLevel,
+    pub Mesh: Vec<Vertex>,
+}
+
+pub enum FinishedJob {
+    //A new chunk in the Decorated state
+    Generated(Chunk),
+    //Meshing only reads the chunk, so only the mesh comes back
+    Meshed(MeshResult),
+}
+
+impl FinishedJob {
+    pub fn Position(&self) -> (i32, i32) {
+        match self {
+            FinishedJob::Generated(chunk) => chunk.Position,
+            FinishedJob::Meshed(result) => result.Position,
+        }
+    }
+}
+
+enum JobResult {
+    Finished(FinishedJob),
+    Cancelled((i32, i32)),
+}
+
+#[derive(PartialEq, Eq)]
+struct PendingJob {
+    Position: (i32, i32),
+    Kind: JobKind,
+    //Squared distance to the target chunk
+    Distance: i32,
+}
+
+//BinaryHeap is a max heap, so flip the ordering to pop the closest job first
+impl Ord for PendingJob {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.Distance.cmp(&self.Distance)
+    }
+}
+
+impl PartialOrd for PendingJob {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+pub struct ChunkScheduler {
+    /*
+        The heap can hold stale entries (a job that was cancelled or upgraded from a mesh to a generate).
+        The 'PendingKinds' map is the source of truth, and heap entries that disagree with it are skipped
+    */
+    Pending: BinaryHeap<PendingJob>,
+    PendingKinds: HashMap<(i32, i32), JobKind>,
+    //Each job in flight has a flag the worker checks so it can bail out early
+    InFlight: HashMap<(i32, i32), Arc<AtomicBool>>,
+    MaxInFlight: usize,
+
+    Target: (i32, i32),
+    Radius: i32,
+
+    Pool: rayon::ThreadPool,
+    Sender: Sender<JobResult>,
+    Reciever: Receiver<JobResult>,
+
+    BlockRegistry: Arc<BlockRegistry>,
+    BiomeGenerators: Arc<HashMap<Biome, Box<dyn BiomeGenerator + Send + Sync>>>,
+}
+
+impl ChunkScheduler {
+    pub fn New(blockRegistry: Arc<BlockRegistry>,
+               biomeGenerators: Arc<HashMap<Biome, Box<dyn BiomeGenerator + Send + Sync>>>) -> Self
+    {
+        //leave a core for the main thread
+        let threads = std::thread::available_parallelism().map(|n| n.get()).unwrap_or(4).saturating_sub(1).max(1);
+        let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .thread_name(|i| format!("chunk worker {}", i))
+        .build()
+        .expect("Error! Could not create the chunk worker thread pool!");
+
+        let (tx, rx) = mpsc::channel();
+        Self {
+            Pending: BinaryHeap::new(),
+            PendingKinds: HashMap::new(),
+            InFlight: HashMap::new(),
+            //enough to keep every worker busy without flooding the pool with jobs that may be cancelled
+            MaxInFlight: threads * 2,
+
+            Target: (0, 0),
+            Radius: 0,
+
+            Pool: pool,
+            Sender: tx,
+            Reciever: rx,
+
+            BlockRegistry: blockRegistry,
+            BiomeGenerators: biomeGenerators,
+        }
+    }
+
+    pub fn Schedule(&mut self, position: (i32, i32), kind: JobKind) {
+        match self.PendingKinds.get(&position) {
+            //the chunk will be meshed once it arrives, so a pending generate covers everything
+            Some(JobKind::Generate) => return,
+            Some(JobKind::Mesh) if kind == JobKind::Mesh => return,
+            _ => {}
+        }
+
+        self.PendingKinds.insert(position, kind);
+        self.Pending.push(PendingJob { Position: position, Kind: kind, Distance: SquaredDistance(position, self.Target) });
+    }
+
+    pub fn Cancel(&mut self, position: (i32, i32)) {
+        //the heap entry becomes stale and is skipped when popped
+        self.PendingKinds.remove(&position);
+        if let Some(flag) = self.InFlight.get(&position) {
+            flag.store(true, AtomicOrdering::Relaxed);
+        }
+    }
+
+    //Re-prioritise around a new target and cancel anything that fell out of range
+    pub fn Retarget(&mut self, target: (i32, i32), radius: i32) {
+        if target == self.Target && radius == self.Radius {
+            return;
+        }
+        self.Target = target;
+        self.Radius = radius;
+
+        let old = std::mem::take(&mut self.Pending);
+        for job in old.into_vec() {
+            if self.PendingKinds.get(&job.Position) != Some(&job.Kind) {
+                continue;
+            }
+            if !InRadius(job.Position, target, radius) {
+                self.PendingKinds.remove(&job.Position);
+                continue;
+            }
+            self.Pending.push(PendingJob { Distance: SquaredDistance(job.Position, target), ..job });
+        }
+
+        for (pos, flag) in &self.InFlight {
+            if !InRadius(*pos, target, radius) {
+                flag.store(true, AtomicOrdering::Relaxed);
+            }
+        }
+    }
+
+    //Hand out the closest jobs to the worker pool until the in flight cap is hit
+    pub fn Dispatch(&mut self, chunks: &HashMap<na::Vector2<i32>, Arc<Chunk>>) {
+        //jobs for chunks that already have a job in flight wait until that one comes back
+        let mut deferred: Vec<PendingJob> = Vec::new();
+
+        while self.InFlight.len() < self.MaxInFlight {
+            let job = match self.Pending.pop() {
+                Some(job) => job,
+                None => break
+            };
+
+            if self.PendingKinds.get(&job.Position) != Some(&job.Kind) {
+                continue;
+            }
+            if self.InFlight.contains_key(&job.Position) {
+                deferred.push(job);
+                continue;
+            }
+            self.PendingKinds.remove(&job.Position);
+
+            let pos = job.Position;
+            let cancelled = Arc::new(AtomicBool::new(false));
+            let tx = self.Sender.clone();
+
+            match job.Kind {
+                JobKind::Generate => {
+                    self.InFlight.insert(pos, cancelled.clone());
+                    let biomeGens = self.BiomeGenerators.clone();
+                    let blockReg = self.BlockRegistry.clone();
+
+                    self.Pool.spawn(move || {
+                        if cancelled.load(AtomicOrdering::Relaxed) {
+                            tx.send(JobResult::Cancelled(pos)).ok();
+                            return;
+                        }
+
+                        //generators only read themselves, so every worker generates at once
+                        let generator = biomeGens.get(&Biome::Forest).unwrap().as_ref();
+                        let mut chunk = Chunk::New(pos, 0.0f32);
+                        chunk.GenerateBlocks(generator);
+                        chunk.Decorate(generator);
+                        chunk.GenerateTints(generator, blockReg.GetColormaps());
+                        tx.send(JobResult::Finished(FinishedJob::Generated(chunk))).ok();
+                    });
+                },
+                JobKind::Mesh => {
+                    /*
+                        The chunk or one of its neighbours was unloaded since this was scheduled (or a neighbour
+                        hasn't arrived yet). The world schedules the mesh again once the last neighbour arrives
+                    */
+                    let chunk = match chunks.get(&na::Vector2::new(pos.0, pos.1)) {
+                        Some(chunk) => chunk.clone(),
+                        None => continue
+                    };
+                    let adj = GatherAdjacent(pos, chunks);
+                    if !NeighboursReady(&adj) {
+                        continue;
+                    }
+
+                    self.InFlight.insert(pos, cancelled.clone());
+                    let lod = LodLevel::ForChunk(pos, self.Target);
+                    let adjLods = LodLevel::ForNeighbours(pos, self.Target);
+                    let blockReg = self.BlockRegistry.clone();
+
+                    self.Pool.spawn(move || {
+                        if cancelled.load(AtomicOrdering::Relaxed) {
+                            tx.send(JobResult::Cancelled(pos)).ok();
+                            return;
+                        }
+
+                        let mesh = chunk.BuildMesh(lod, &adj, &adjLods, &blockReg);
+                        tx.send(JobResult::Finished(FinishedJob::Meshed(MeshResult { Position: pos, Lod: lod, Mesh: mesh }))).ok();
+                    });
+                }
+            }
+        }
+
+        for job in deferred {
+            self.PendingKinds.insert(job.Position, job.Kind);
+            self.Pending.push(job);
+        }
+    }
+
+    //Collect the jobs that finished since the last poll. Cancelled jobs are dropped
+    pub fn Poll(&mut self) -> Vec<FinishedJob> {
+        let mut finished = Vec::new();
+
+        while let Ok(result) = self.Reciever.try_recv() {
+            match result {
+                JobResult::Cancelled(pos) => {
+                    self.InFlight.remove(&pos);
+                },
+                JobResult::Finished(job) => {
+                    let flag = self.InFlight.remove(&job.Position());
+                    //it may have been cancelled after the worker's last check
+                    if flag.map_or(false, |f| f.load(AtomicOrdering::Relaxed)) {
+                        continue;
+                    }
+                    finished.push(job);
+                }
+            }
+        }
+        finished
+    }
+
+    pub fn IsPending(&self, position: (i32, i32)) -> bool {
+        self.PendingKinds.contains_key(&position) || self.InFlight.contains_key(&position)
+    }
+
+    pub fn NumPending(&self) -> usize {
+        self.PendingKinds.len()
+    }
+
+    pub fn NumInFlight(&self) -> usize {
+        self.InFlight.len()
+    }
+}
+
+pub fn GatherAdjacent(position: (i32, i32), chunks: &HashMap<na::Vector2<i32>, Arc<Chunk>>) -> [Option<Arc<Chunk>>; 4] {
+    let mut adj = [None, None, None, None];
+    for i in 0..4 {
+        let pos = na::Vector2::new(position.0 + ADJACENT_OFFSETS[i].0, position.1 + ADJACENT_OFFSETS[i].1);
+        adj[i] = chunks.get(&pos).cloned();
+    }
+    adj
+}
+
+//A chunk can be meshed once all four of its neighbours have their final blocks
+pub fn NeighboursReady(adj: &[Option<Arc<Chunk>>; 4]) -> bool {
+    adj.iter().all(|c| c.as_ref().map_or(false, |c| c.Status >= ChunkState::Decorated))
+}
+
+fn SquaredDistance(a: (i32, i32), b: (i32, i32)) -> i32 {
+    (a.0 - b.0) * (a.0 - b.0) + (a.1 - b.1) * (a.1 - b.1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::World::{block::Block, item::ItemRegistry, crafting::CraftingRegistry, smelting::SmeltingRegistry, contentPack::ContentPacks,
+                       idMap::IdMap, ReadAttributes, ReadBiomeGenerators};
+
+    //A scheduler for the core content that only lets 'maxInFlight' jobs out at once
+    fn TestScheduler(maxInFlight: usize) -> ChunkScheduler {
+        let packs = ContentPacks::Core().unwrap();
+        let (mut blocks, mut items, mut crafting, mut smelting) = (BlockRegistry::New(), ItemRegistry::New(), CraftingRegistry::New(), SmeltingRegistry::New());
+        ReadAttributes(&mut blocks, &mut items, &mut crafting, &mut smelting, &packs, &mut IdMap::default()).unwrap();
+        let generators = ReadBiomeGenerators(&blocks, &packs, 1).unwrap();
+
+        let mut scheduler = ChunkScheduler::New(Arc::new(blocks), Arc::new(generators));
+        scheduler.MaxInFlight = maxInFlight;
+        scheduler
+    }
+
+    //Polls until nothing is in flight, returning the positions of the jobs that finished in the order they came back
+    fn Drain(scheduler: &mut ChunkScheduler) -> Vec<(i32, i32)> {
+        let start = std::time::Instant::now();
+        let mut finished = Vec::new();
+        while scheduler.NumInFlight() > 0 {
+            finished.extend(scheduler.Poll().iter().map(|job| job.Position()));
+            assert!(start.elapsed().as_secs() < 60, "jobs never came back");
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+        finished
+    }
+
+    //Dispatches one job at a time, returning the order they were handed out in
+    fn DispatchOrder(scheduler: &mut ChunkScheduler) -> Vec<(i32, i32)> {
+        let mut order = Vec::new();
+        loop {
+            scheduler.Dispatch(&HashMap::new());
+            if scheduler.NumInFlight() == 0 {
+                return order;
+            }
+            order.extend(Drain(scheduler));
+        }
+    }
+
+    fn ChunkIn(state: ChunkState, pos: (i32, i32)) -> Arc<Chunk> {
+        let mut chunk = Chunk::OfHeight(0, pos, Block::Air());
+        chunk.Status = state;
+        Arc::new(chunk)
+    }
+
+    #[test]
+    fn ClosestJobsGoFirst() {
+        let mut scheduler = TestScheduler(1);
+        scheduler.Retarget((0, 0), 10);
+        for pos in [(3, 0), (0, 0), (-2, 2), (0, -1), (5, 5)] {
+            scheduler.Schedule(pos, JobKind::Generate);
+        }
+        //scheduling something that's already pending doesn't queue it twice
+        scheduler.Schedule((3, 0), JobKind::Generate);
+        scheduler.Schedule((3, 0), JobKind::Mesh);
+        assert_eq!(scheduler.NumPending(), 5);
+
+        assert_eq!(DispatchOrder(&mut scheduler), vec![(0, 0), (0, -1), (-2, 2), (3, 0), (5, 5)]);
+        assert_eq!(scheduler.NumPending(), 0);
+    }
+
+    #[test]
+    fn RetargetingReprioritisesAndDropsWhatsOutOfRange() {
+        let mut scheduler = TestScheduler(1);
+        scheduler.Retarget((0, 0), 10);
+        for pos in [(0, 0), (2, 0), (4, 0), (6, 0), (-3, 0)] {
+            scheduler.Schedule(pos, JobKind::Generate);
+        }
+
+        scheduler.Retarget((6, 0), 4);
+        assert_eq!(scheduler.NumPending(), 3);
+        assert!(!scheduler.IsPending((0, 0)) && !scheduler.IsPending((-3, 0)));
+        assert_eq!(DispatchOrder(&mut scheduler), vec![(6, 0), (4, 0), (2, 0)]);
+    }
+
+    #[test]
+    fn CancellingFlagsJobsInFlightAndDropsPendingOnes() {
+        let mut scheduler = TestScheduler(1);
+        scheduler.Retarget((0, 0), 10);
+        scheduler.Schedule((0, 0), JobKind::Generate);
+        scheduler.Schedule((1, 0), JobKind::Generate);
+        scheduler.Dispatch(&HashMap::new());
+        assert!(scheduler.InFlight.contains_key(&(0, 0)));
+
+        scheduler.Cancel((0, 0));
+        scheduler.Cancel((1, 0));
+        assert!(scheduler.InFlight[&(0, 0)].load(AtomicOrdering::Relaxed));
+        assert!(!scheduler.IsPending((1, 0)));
+        assert_eq!(scheduler.NumPending(), 0);
+
+        //the cancelled job never comes back, and nothing else is handed out
+        assert!(Drain(&mut scheduler).is_empty());
+        scheduler.Dispatch(&HashMap::new());
+        assert_eq!(scheduler.NumInFlight(), 0);
+    }
+
+    #[test]
+    fn OnlyMaxInFlightJobsAreHandedOut() {
+        let mut scheduler = TestScheduler(3);
+        scheduler.Retarget((0, 0), 10);
+        for x in 0..10 {
+            scheduler.Schedule((x, 0), JobKind::Generate);
+        }
+
+        scheduler.Dispatch(&HashMap::new());
+        assert_eq!((scheduler.NumInFlight(), scheduler.NumPending()), (3, 7));
+        scheduler.Dispatch(&HashMap::new());
+        assert_eq!((scheduler.NumInFlight(), scheduler.NumPending()), (3, 7));
+
+        let mut finished = Drain(&mut scheduler);
+        while scheduler.NumPending() > 0 {
+            scheduler.Dispatch(&HashMap::new());
+            assert!(scheduler.NumInFlight() <= 3);
+            finished.extend(Drain(&mut scheduler));
+        }
+        finished.sort();
+        assert_eq!(finished, (0..10).map(|x| (x, 0)).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn MeshesWaitForDecoratedNeighbours() {
+        let mut chunks: HashMap<na::Vector2<i32>, Arc<Chunk>> = HashMap::new();
+        chunks.insert(na::Vector2::new(0, 0), ChunkIn(ChunkState::Decorated, (0, 0)));
+        for offset in ADJACENT_OFFSETS {
+            chunks.insert(na::Vector2::new(offset.0, offset.1), ChunkIn(ChunkState::Decorated, offset));
+        }
+        assert!(NeighboursReady(&GatherAdjacent((0, 0), &chunks)));
+
+        //a neighbour that's only generated can still get trees from its own neighbours
+        let (x, z) = ADJACENT_OFFSETS[2];
+        chunks.insert(na::Vector2::new(x, z), ChunkIn(ChunkState::Generated, (x, z)));
+        assert!(!NeighboursReady(&GatherAdjacent((0, 0), &chunks)));
+        chunks.remove(&na::Vector2::new(x, z));
+        assert!(!NeighboursReady(&GatherAdjacent((0, 0), &chunks)));
+
+        //a mesh job for it is dropped until the world schedules it again
+        let mut scheduler = TestScheduler(1);
+        scheduler.Schedule((0, 0), JobKind::Mesh);
+        scheduler.Dispatch(&chunks);
+        assert!(!scheduler.IsPending((0, 0)));
+
+        chunks.insert(na::Vector2::new(x, z), ChunkIn(ChunkState::Meshed, (x, z)));
+        scheduler.Schedule((0, 0), JobKind::Mesh);
+        scheduler.Dispatch(&chunks);
+        assert_eq!(Drain(&mut scheduler), vec![(0, 0)]);
+    }
+}
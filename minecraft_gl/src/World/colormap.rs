@@ -0,0 +1,155 @@
+use serde::Deserialize;
+use schemars::JsonSchema;
+use super::contentPack::ContentPacks;
+use super::chunk::{CHUNK_BOUNDS_X, CHUNK_BOUNDS_Z};
+
+/*
+    Grass, leaves and water take their colour from the climate they're in. Each kind of tint has a colormap
+    image in block/colormap, looked up by temperature and humidity like Minecraft's: temperature goes from hot
+    on the left to cold on the right, and humidity (scaled by temperature, so the image is a triangle) from
+    wet at the top to dry at the bottom. The colour multiplies whatever the block's texture shows, so tinted
+    textures are best kept grey
+*/
+
+//How many columns to each side a column's tint is averaged over, so colours fade across biome borders
+pub const TINT_BLEND_RADIUS: i32 = 3;
+pub const TINT_KINDS: usize = 3;
+//Multiplying by white leaves a texture as it is
+pub const NO_TINT: u32 = 0xFFFFFF;
+/*
+    Tints are kept for the columns at every corner of the chunk's blocks, one more than the chunk is wide.
+    The last row is the first row of the next chunk over, so colours line up across chunk borders
+*/
+const TINT_COLUMNS_X: i32 = CHUNK_BOUNDS_X as i32 + 1;
+const TINT_COLUMNS_Z: i32 = CHUNK_BOUNDS_Z as i32 + 1;
+
+#[derive(Deserialize, JsonSchema, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Tint {
+    Grass,
+    Foliage,
+    Water,
+}
+
+impl Tint {
+    pub const ALL: [Tint; TINT_KINDS] = [Tint::Grass, Tint::Foliage, Tint::Water];
+
+    pub fn FileName(&self) -> &'static str {
+        match self {
+            Tint::Grass => "grass.png",
+            Tint::Foliage => "foliage.png",
+            Tint::Water => "water.png",
+        }
+    }
+}
+
+pub struct Colormap {
+    Width: u32,
+    Height: u32,
+    Colors: Vec<[u8; 3]>,
+}
+
+impl Colormap {
+    pub fn Load(path: &std::path::Path) -> Result<Self, String> {
+        let img = image::open(path)
+        .map_err(|e| format!("Error! Could not read the colormap {}! The error:\n{}", path.display(), e.to_string()))?
+        .to_rgb8();
+        Ok(Self { Width: img.width(), Height: img.height(), Colors: img.pixels().map(|p| p.0).collect() })
+    }
+
+    //A colormap that doesn't change anything, for tints without an image
+    pub fn White() -> Self {
+        Self { Width: 1, Height: 1, Colors: vec![[255; 3]] }
+    }
+
+    //Both go from 0 to 1
+    pub fn Sample(&self, temperature: f32, humidity: f32) -> [u8; 3] {
+        let temperature = temperature.clamp(0f32, 1f32);
+        let humidity = humidity.clamp(0f32, 1f32) * temperature;
+        let x = ((1f32 - temperature) * (self.Width - 1) as f32).round() as u32;
+        let y = ((1f32 - humidity) * (self.Height - 1) as f32).round() as u32;
+        self.Colors[(x + y * self.Width) as usize]
+    }
+}
+
+//One colormap per kind of tint, in the order of Tint::ALL
+pub struct Colormaps {
+    Maps: Vec<Colormap>,
+}
+
+impl Colormaps {
+    //A tint without a colormap in any pack doesn't colour its blocks
+    pub fn Load(packs: &ContentPacks) -> Result<Self, String> {
+        let mut maps = Vec::with_capacity(TINT_KINDS);
+        for tint in Tint::ALL {
+            maps.push(match packs.ResolveFile(&format!("block/colormap/{}", tint.FileName())) {
+                Some(path) => Colormap::Load(&path)?,
+                None => Colormap::White()
+            });
+        }
+        Ok(Self { Maps: maps })
+    }
+
+    pub fn None() -> Self {
+        Self { Maps: Tint::ALL.iter().map(|_| Colormap::White()).collect() }
+    }
+
+    pub fn Sample(&self, tint: Tint, temperature: f32, humidity: f32) -> [u8; 3] {
+        self.Maps[tint as usize].Sample(temperature, humidity)
+    }
+}
+
+//The blended tints of a chunk's columns, packed as 0xRRGGBB
+#[derive(Clone, Debug, Default)]
+pub struct ColumnTints {
+    Colors: Vec<[u32; TINT_KINDS]>,
+}
+
+impl ColumnTints {
+    /*
+        Averages the colour of every column within TINT_BLEND_RADIUS of each of the chunk's columns. 'climate'
+        gives the temperature and humidity of a column by its world position, which can be outside the chunk
+    */
+    pub fn Blend(colormaps: &Colormaps, chunkPos: (i32, i32), climate: &mut dyn FnMut(i32, i32) -> (f32, f32)) -> Self {
+        let origin = (chunkPos.0 * CHUNK_BOUNDS_X as i32 - TINT_BLEND_RADIUS, chunkPos.1 * CHUNK_BOUNDS_Z as i32 - TINT_BLEND_RADIUS);
+        let width = TINT_COLUMNS_X + TINT_BLEND_RADIUS * 2;
+        let depth = TINT_COLUMNS_Z + TINT_BLEND_RADIUS * 2;
+
+        let mut samples: Vec<[[u8; 3]; TINT_KINDS]> = Vec::with_capacity((width * depth) as usize);
+        for z in 0..depth {
+            for x in 0..width {
+                let (temperature, humidity) = climate(origin.0 + x, origin.1 + z);
+                samples.push(Tint::ALL.map(|tint| colormaps.Sample(tint, temperature, humidity)));
+            }
+        }
+
+        let mut colors = Vec::with_capacity((TINT_COLUMNS_X * TINT_COLUMNS_Z) as usize);
+        let count = ((TINT_BLEND_RADIUS * 2 + 1) * (TINT_BLEND_RADIUS * 2 + 1)) as u32;
+        for z in 0..TINT_COLUMNS_Z {
+            for x in 0..TINT_COLUMNS_X {
+                let mut sum = [[0u32; 3]; TINT_KINDS];
+                for dz in 0..=TINT_BLEND_RADIUS * 2 {
+                    for dx in 0..=TINT_BLEND_RADIUS * 2 {
+                        let sample = &samples[(x + dx + (z + dz) * width) as usize];
+                        for kind in 0..TINT_KINDS {
+                            for channel in 0..3 {
+                                sum[kind][channel] += sample[kind][channel] as u32;
+                            }
+                        }
+                    }
+                }
+                colors.push(sum.map(|c| (c[0] / count) << 16 | (c[1] / count) << 8 | c[2] / count));
+            }
+        }
+        Self { Colors: colors }
+    }
+
+    //The tint at the corner (x, z) of the chunk's blocks. Chunks that were never tinted (like LOD skirts) aren't coloured
+    pub fn At(&self, tint: Tint, x: i32, z: i32) -> u32 {
+        if self.Colors.is_empty() {
+            return NO_TINT;
+        }
+        let (x, z) = (x.clamp(0, TINT_COLUMNS_X - 1), z.clamp(0, TINT_COLUMNS_Z - 1));
+        self.Colors[(x + z * TINT_COLUMNS_X) as usize][tint as usize]
+    }
+}
\ No newline at end of file
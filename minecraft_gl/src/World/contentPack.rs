@@ -0,0 +1,251 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, de::DeserializeOwned};
+use schemars::JsonSchema;
+use crate::Util::resource::AssetPath;
+use super::schema::{BlockJson, ItemJson, BiomeJson, RecipeJson, SmeltingJson, ReadJsonFile};
+
+/*
+    Content is loaded from an ordered list of packs. A pack is a directory laid out like assets/data
+    (block/json, block/img, block/model, block/colormap, item/json, item/img, biome, recipe, smelting) with a pack.json manifest at its root. Later packs
+    extend the earlier ones, and a block, item, biome or recipe with the same identifier as an earlier one replaces
+    it outright. Textures, block models and colormaps are overridden by file name the same way.
+    The bundled content is the 'core' pack and is always loaded first
+*/
+
+const MANIFEST_FILE: &str = "pack.json";
+
+#[derive(Deserialize, JsonSchema, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct PackManifest {
+    pub Name: String,
+    pub Version: String,
+    #[serde(default)]
+    pub Description: String,
+    //Names of packs that have to be loaded before this one
+    #[serde(default)]
+    pub Dependencies: Vec<String>,
+}
+
+pub struct ContentPack {
+    pub Root: PathBuf,
+    pub Manifest: PackManifest,
+}
+
+//Something from one pack that a later pack replaced
+pub struct Override {
+    //The directory it lives in, like block/json or item/img
+    pub Kind: String,
+    //Identifier, biome name or file name
+    pub Key: String,
+    pub Overridden: String,
+    pub By: String,
+}
+
+impl std::fmt::Display for Override {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{} {} from '{}' is overridden by '{}'", self.Kind, self.Key, self.Overridden, self.By)
+    }
+}
+
+pub struct JsonDir<T> {
+    //The entries left after overriding, in load order
+    pub Entries: Vec<(PathBuf, T)>,
+    //Files that failed to parse
+    pub Errors: Vec<(PathBuf, String)>,
+    pub Overrides: Vec<Override>,
+    pub FilesRead: usize,
+}
+
+impl<T> JsonDir<T> {
+    //Fails on the first file that couldn't be read, like the registries want
+    pub fn IntoResult(self) -> Result<Vec<(PathBuf, T)>, String> {
+        match self.Errors.into_iter().next() {
+            Some((_, msg)) => Err(msg),
+            None => Ok(self.Entries)
+        }
+    }
+}
+
+pub struct ContentPacks {
+    Packs: Vec<ContentPack>,
+}
+
+impl ContentPacks {
+    //The core pack followed by the packs at the given roots, in that order
+    pub fn Load(roots: &[PathBuf]) -> Result<Self, String> {
+        let mut packs: Vec<ContentPack> = Vec::with_capacity(roots.len() + 1);
+
+        for root in std::iter::once(AssetPath("data")).chain(roots.iter().cloned()) {
+            let manifest: PackManifest = ReadJsonFile(&root.join(MANIFEST_FILE))
+            .map_err(|e| format!("Error! Could not load the content pack at {}. The error:\n{}", root.display(), e))?;
+
+            if let Some(other) = packs.iter().find(|p| p.Manifest.Name == manifest.Name) {
+                return Err(format!("Error! Two content packs are named '{}': {} and {}", manifest.Name, other.Root.display(), root.display()));
+            }
+            for dependency in &manifest.Dependencies {
+                if !packs.iter().any(|p| &p.Manifest.Name == dependency) {
+                    return Err(format!("Error! The content pack '{}' depends on '{}', which has to be loaded before it", manifest.Name, dependency));
+                }
+            }
+
+            packs.push(ContentPack { Root: root, Manifest: manifest });
+        }
+
+        Ok(Self { Packs: packs })
+    }
+
+    //Only the bundled content
+    pub fn Core() -> Result<Self, String> {
+        Self::Load(&[])
+    }
+
+    pub fn Packs(&self) -> &Vec<ContentPack> {
+        &self.Packs
+    }
+
+    //The bundled pack, which files missing from every other pack fall back to
+    pub fn CoreRoot(&self) -> &Path {
+        &self.Packs[0].Root
+    }
+
+    pub fn ReadBlocks(&self) -> JsonDir<BlockJson> {
+        self.ReadJsonDir("block/json", |json: &BlockJson| json.ID.as_str())
+    }
+
+    pub fn ReadItems(&self) -> JsonDir<ItemJson> {
+        self.ReadJsonDir("item/json", |json: &ItemJson| json.ID.as_str())
+    }
+
+    pub fn ReadBiomes(&self) -> JsonDir<BiomeJson> {
+        self.ReadJsonDir("biome", |json: &BiomeJson| json.Name.as_str())
+    }
+
+    pub fn ReadRecipes(&self) -> JsonDir<RecipeJson> {
+        self.ReadJsonDir("recipe", |json: &RecipeJson| json.ID())
+    }
+
+    pub fn ReadSmelting(&self) -> JsonDir<SmeltingJson> {
+        self.ReadJsonDir("smelting", |json: &SmeltingJson| json.ID.as_str())
+    }
+
+    /*
+        Reads every json file in a directory of every pack. An entry with the same key as one from an earlier
+        pack takes its place. Duplicates within a single pack are all kept, so the caller can report them
+    */
+    pub fn ReadJsonDir<T: DeserializeOwned>(&self, dir: &str, key: fn(&T) -> &str) -> JsonDir<T> {
+        let mut out = JsonDir { Entries: Vec::new(), Errors: Vec::new(), Overrides: Vec::new(), FilesRead: 0 };
+        //key -> (index into the entries, index of the pack it came from)
+        let mut seen: HashMap<String, (usize, usize)> = HashMap::new();
+
+        for (packIdx, pack) in self.Packs.iter().enumerate() {
+            for path in ListFiles(&pack.Root.join(dir), Some("json")) {
+                out.FilesRead += 1;
+                let json: T = match ReadJsonFile(&path) {
+                    Ok(val) => val,
+                    Err(msg) => {
+                        out.Errors.push((path, msg));
+                        continue;
+                    }
+                };
+
+                let k = key(&json).to_owned();
+                match seen.get(&k) {
+                    Some(&(idx, otherPack)) if otherPack != packIdx => {
+                        out.Overrides.push(Override {
+                            Kind: dir.to_owned(), Key: k.clone(),
+                            Overridden: self.Packs[otherPack].Manifest.Name.clone(), By: pack.Manifest.Name.clone()
+                        });
+                        out.Entries[idx] = (path, json);
+                        seen.insert(k, (idx, packIdx));
+                    },
+                    _ => {
+                        seen.insert(k, (out.Entries.len(), packIdx));
+                        out.Entries.push((path, json));
+                    }
+                }
+            }
+        }
+        out
+    }
+
+    //Finds a file like "block/img/grass.png" in the last pack that has it
+    pub fn ResolveFile(&self, relative: &str) -> Option<PathBuf> {
+        self.Packs.iter().rev().map(|p| p.Root.join(relative)).find(|p| p.is_file())
+    }
+
+    //Same as ResolveFile, but a missing file still gets a path (in the core pack) for error messages and null textures to work with
+    pub fn ResolveFileOrCore(&self, relative: &str) -> PathBuf {
+        self.ResolveFile(relative).unwrap_or(self.CoreRoot().join(relative))
+    }
+
+    //Files in a directory, like block/img, that a later pack replaced
+    pub fn FileOverrides(&self, dir: &str) -> Vec<Override> {
+        let mut overrides = Vec::new();
+        let mut seen: HashMap<String, usize> = HashMap::new();
+
+        for (packIdx, pack) in self.Packs.iter().enumerate() {
+            for path in ListFiles(&pack.Root.join(dir), None) {
+                let name = path.file_name().unwrap().to_string_lossy().into_owned();
+                if let Some(otherPack) = seen.insert(name.clone(), packIdx) {
+                    overrides.push(Override {
+                        Kind: dir.to_owned(), Key: name,
+                        Overridden: self.Packs[otherPack].Manifest.Name.clone(), By: pack.Manifest.Name.clone()
+                    });
+                }
+            }
+        }
+        overrides
+    }
+
+    //The load order and everything the packs override in each other
+    pub fn Report(&self) -> PackReport {
+        let mut overrides = self.ReadBlocks().Overrides;
+        overrides.extend(self.ReadItems().Overrides);
+        overrides.extend(self.ReadBiomes().Overrides);
+        overrides.extend(self.ReadRecipes().Overrides);
+        overrides.extend(self.ReadSmelting().Overrides);
+        overrides.extend(self.FileOverrides("block/img"));
+        overrides.extend(self.FileOverrides("block/model"));
+        overrides.extend(self.FileOverrides("block/colormap"));
+        overrides.extend(self.FileOverrides("item/img"));
+
+        PackReport {
+            Order: self.Packs.iter().map(|p| format!("{} {} ({})", p.Manifest.Name, p.Manifest.Version, p.Root.display())).collect(),
+            Overrides: overrides,
+        }
+    }
+}
+
+pub struct PackReport {
+    pub Order: Vec<String>,
+    pub Overrides: Vec<Override>,
+}
+
+impl std::fmt::Display for PackReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        writeln!(f, "Content packs in load order:")?;
+        for (i, pack) in self.Order.iter().enumerate() {
+            writeln!(f, "  {}. {}", i + 1, pack)?;
+        }
+        for o in &self.Overrides {
+            writeln!(f, "  {}", o)?;
+        }
+        write!(f, "{} overrides", self.Overrides.len())
+    }
+}
+
+//Sorted, since directory order isn't stable across platforms. A missing directory just has no files
+fn ListFiles(dir: &Path, extension: Option<&str>) -> Vec<PathBuf> {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(val) => val,
+        Err(_) => return Vec::new()
+    };
+
+    let mut paths: Vec<PathBuf> = entries.filter_map(|e| e.ok().map(|e| e.path()))
+                                  .filter(|p| p.is_file())
+                                  .filter(|p| extension.map_or(true, |ext| p.extension().map_or(false, |e| e == ext)))
+                                  .collect();
+    paths.sort();
+    paths
+}
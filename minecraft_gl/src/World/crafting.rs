@@ -1,6 +1,13 @@
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use super::item::{ItemID, ItemRegistry};
+use super::idMap::ValidateIdentifier;
+use super::contentPack::ContentPacks;
+use super::schema::RecipeJson;
+use super::GenericError;
 
-use std::collections::HashMap;
-use super::item::ItemID;
+//The crafting table grid is 3x3, so no recipe can be bigger than that
+pub const MAX_CRAFTING_GRID: u32 = 3;
 
 pub enum MatchType{
     TotalMatch,
@@ -8,102 +15,178 @@ pub enum MatchType{
     ZeroMatch
 }
 
-//TODO add support for variable length crafting recipes
-#[derive(Clone)]
-pub struct CraftingRecipe{
-    Grid: Vec<ItemID>,
-    Rows: u32,
-    Cols: u32
+/*
+    What a player has put into a crafting grid. None is an empty slot.
+    The grid can be any size, a 2x2 inventory grid works the same as a 3x3 table
+*/
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct CraftingGrid{
+    pub Slots: Vec<Option<ItemID>>,
+    pub Rows: u32,
+    pub Cols: u32
 }
 
-impl CraftingRecipe{
-    pub fn New(grid: Vec<u8>, rows: u32, cols: u32) -> Result<Self, String> {
-        if rows != cols || rows <= 0 || cols <= 0 {
-            return Err(format!("Error! Invalid crafting grid dimensions of {} rows and {} columns!", rows, cols));
+impl CraftingGrid{
+    pub fn New(slots: Vec<Option<ItemID>>, rows: u32, cols: u32) -> Result<Self, String> {
+        if rows == 0 || cols == 0 || slots.len() != (rows * cols) as usize {
+            return Err(format!("Error! Invalid crafting grid of {} slots with {} rows and {} columns!", slots.len(), rows, cols));
         }
+        Ok( Self { Slots: slots, Rows: rows, Cols: cols } )
+    }
 
-        let itemGrid: Vec<ItemID> = grid.into_iter().map(|val| ItemID::New(val) ).collect();
+    pub fn Empty(rows: u32, cols: u32) -> Self {
+        Self { Slots: vec![None; (rows * cols) as usize], Rows: rows, Cols: cols }
+    }
 
-        Ok (
-            Self {
-                Grid: itemGrid, //move assignment
-                Rows: rows,
-                Cols: cols,
-            }
-        )
+    pub fn Get(&self, row: u32, col: u32) -> Option<ItemID> {
+        self.Slots[(row * self.Cols + col) as usize]
     }
 
-    pub fn MatchType(&self, other: &CraftingRecipe) -> MatchType {
-        if self.Rows != other.Rows || self.Cols != other.Cols { return MatchType::ZeroMatch; }
-        let mut selfSet: HashMap<ItemID, u16> = HashMap::new();
-        let mut otherSet: HashMap<ItemID, u16> = HashMap::new();
+    pub fn IsEmpty(&self) -> bool {
+        self.Slots.iter().all(|slot| slot.is_none())
+    }
 
-        let mut matches = true;
-        let mut matchCount = 0;
-        for r in 0..self.Rows {
-            for c in 0..self.Cols {
-                let idx = r * self.Cols + c;
-                let item1 = self.Grid[idx as usize];
-                let item2 = other.Grid[idx as usize];
+    /*
+        Cuts the grid down to the smallest rectangle holding every non empty slot.
+        Comparing trimmed grids is what makes shaped recipes work wherever they are placed
+    */
+    pub fn Trimmed(&self) -> Option<CraftingGrid> {
+        let filled = (0..self.Rows).flat_map(|r| (0..self.Cols).map(move |c| (r, c)))
+                                   .filter(|&(r, c)| self.Get(r, c).is_some());
 
-                if !selfSet.contains_key(&item1) {
-                    selfSet.insert(item1, 1);
-                }
-                else {
-                    *selfSet.get_mut(&item1).unwrap() += 1;
-                }
+        let (mut minR, mut minC, mut maxR, mut maxC) = (u32::MAX, u32::MAX, 0, 0);
+        let mut any = false;
+        for (r, c) in filled {
+            minR = minR.min(r); minC = minC.min(c);
+            maxR = maxR.max(r); maxC = maxC.max(c);
+            any = true;
+        }
+        if !any {
+            return None;
+        }
 
-                if !otherSet.contains_key(&item2) {
-                    otherSet.insert(item2, 1);
-                }
-                else {
-                    *otherSet.get_mut(&item2).unwrap() += 1;
-                }
+        let rows = maxR - minR + 1;
+        let cols = maxC - minC + 1;
+        let slots = (minR..=maxR).flat_map(|r| (minC..=maxC).map(move |c| (r, c)))
+                                 .map(|(r, c)| self.Get(r, c))
+                                 .collect();
+        Some( Self { Slots: slots, Rows: rows, Cols: cols } )
+    }
 
-                if item1 != item2 {
-                    matches = false;
-                }
-                else{
-                    //at the end of the loop, this is incremented by a total of 2
-                    //Makes it more favorable to have matches AND matches at the same index
-                    matchCount += 1; 
-                }
+    //Every item in the grid, sorted so two grids with the same items compare equal
+    pub fn Ingredients(&self) -> Vec<ItemID> {
+        let mut items: Vec<ItemID> = self.Slots.iter().flatten().cloned().collect();
+        items.sort_by_key(|item| item.ID);
+        items
+    }
+}
 
-                if selfSet.contains_key(&item2) {
-                    matchCount += 1;
+//A multiset of items as (item, count) pairs sorted by item ID
+pub type IngredientCounts = Vec<(ItemID, u32)>;
 
-                    *selfSet.get_mut(&item2).unwrap() -= 1;
-                    if selfSet[&item2] == 0 { selfSet.remove(&item2); }
-                    *otherSet.get_mut(&item2).unwrap() -= 1;
-                    if otherSet[&item2] == 0 { otherSet.remove(&item2); }
-                }
-                else if otherSet.contains_key(&item1) {
-                    matchCount += 1;
+//Expects the items sorted, like CraftingGrid::Ingredients returns them
+pub fn CountIngredients(sorted: &[ItemID]) -> IngredientCounts {
+    let mut counts: IngredientCounts = Vec::new();
+    for item in sorted {
+        match counts.last_mut() {
+            Some((last, count)) if last == item => *count += 1,
+            _ => counts.push((*item, 1))
+        }
+    }
+    counts
+}
 
-                    *selfSet.get_mut(&item1).unwrap() -= 1;
-                    if selfSet[&item1] == 0 { selfSet.remove(&item1); }
-                    *otherSet.get_mut(&item1).unwrap() -= 1;
-                    if otherSet[&item1] == 0 { otherSet.remove(&item1); }
-                }
-                
-            }
+//How many items two multisets have in common. Both are sorted, so it's a single merge pass
+pub fn IngredientOverlap(a: &IngredientCounts, b: &IngredientCounts) -> u32 {
+    let (mut i, mut j, mut overlap) = (0, 0, 0);
+    while i < a.len() && j < b.len() {
+        let (itemA, countA) = a[i];
+        let (itemB, countB) = b[j];
+        if itemA.ID < itemB.ID { i += 1; }
+        else if itemA.ID > itemB.ID { j += 1; }
+        else {
+            overlap += countA.min(countB);
+            i += 1;
+            j += 1;
         }
+    }
+    overlap
+}
 
-        if matches {
-            return MatchType::TotalMatch;
+#[derive(Clone, PartialEq, Eq)]
+pub enum RecipeShape{
+    //A pattern that has to appear as is (but anywhere) in the grid. Already trimmed, so it has no empty outer rows or columns
+    Shaped(CraftingGrid),
+    //Sorted ingredients. Their placement doesn't matter
+    Shapeless(Vec<ItemID>),
+}
+
+#[derive(Clone)]
+pub struct CraftingRecipe{
+    pub Identifier: String,
+    pub Shape: RecipeShape,
+    pub Output: ItemID,
+    pub Count: u32,
+    //Everything the recipe uses, for partial matching
+    pub Ingredients: IngredientCounts,
+}
+
+impl CraftingRecipe{
+    pub fn NewShaped(identifier: &str, pattern: CraftingGrid, output: ItemID, count: u32) -> Result<Self, String> {
+        let trimmed = pattern.Trimmed()
+        .ok_or(format!("Error! The shaped recipe {} has an empty pattern!", identifier))?;
+
+        if trimmed.Rows > MAX_CRAFTING_GRID || trimmed.Cols > MAX_CRAFTING_GRID {
+            return Err(format!("Error! The recipe {} is {}x{}, bigger than the {}x{} crafting grid!",
+                       identifier, trimmed.Rows, trimmed.Cols, MAX_CRAFTING_GRID, MAX_CRAFTING_GRID));
         }
-        MatchType::PartialMatch(matchCount)
+        Self::New(identifier, RecipeShape::Shaped(trimmed), output, count)
+    }
 
+    pub fn NewShapeless(identifier: &str, mut ingredients: Vec<ItemID>, output: ItemID, count: u32) -> Result<Self, String> {
+        if ingredients.is_empty() || ingredients.len() > (MAX_CRAFTING_GRID * MAX_CRAFTING_GRID) as usize {
+            return Err(format!("Error! The shapeless recipe {} has {} ingredients, it needs between 1 and {}!",
+                       identifier, ingredients.len(), MAX_CRAFTING_GRID * MAX_CRAFTING_GRID));
+        }
+
+        ingredients.sort_by_key(|item| item.ID);
+        Self::New(identifier, RecipeShape::Shapeless(ingredients), output, count)
     }
-}
 
-impl PartialEq for CraftingRecipe{
-    fn eq(&self, other: &Self) -> bool {
-        self.Rows == other.Rows && self.Cols == other.Cols && ! self.Grid.iter().zip(other.Grid.iter()).any(|x| *x.0 != *x.1)
+    fn New(identifier: &str, shape: RecipeShape, output: ItemID, count: u32) -> Result<Self, String> {
+        if count == 0 {
+            return Err(format!("Error! The recipe {} has to make at least one item!", identifier));
+        }
+        let ingredients = match &shape {
+            RecipeShape::Shaped(pattern) => CountIngredients(&pattern.Ingredients()),
+            RecipeShape::Shapeless(items) => CountIngredients(items),
+        };
+        Ok( Self { Identifier: identifier.to_owned(), Shape: shape, Output: output, Count: count, Ingredients: ingredients } )
+    }
+
+    pub fn Matches(&self, grid: &CraftingGrid) -> bool {
+        match &self.Shape {
+            RecipeShape::Shaped(pattern) => grid.Trimmed().map_or(false, |trimmed| trimmed == *pattern),
+            RecipeShape::Shapeless(ingredients) => grid.Ingredients() == *ingredients,
+        }
     }
-}
 
-impl Eq for CraftingRecipe {}
+    /*
+        How close a grid is to this recipe, for suggesting recipes to the player.
+        A partial match counts the ingredients the grid and the recipe have in common, ignoring placement
+    */
+    pub fn MatchType(&self, grid: &CraftingGrid) -> MatchType {
+        if self.Matches(grid) {
+            return MatchType::TotalMatch;
+        }
+
+        let matchCount = IngredientOverlap(&self.Ingredients, &CountIngredients(&grid.Ingredients()));
+        if matchCount == 0 {
+            return MatchType::ZeroMatch;
+        }
+        MatchType::PartialMatch(matchCount)
+    }
+}
 
 
 ////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
@@ -113,33 +196,417 @@ impl Eq for CraftingRecipe {}
 ////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
 /// ////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
 
+/*
+    Recipes are indexed so matching a grid is a couple of hash lookups instead of a scan over every recipe.
+    Shaped recipes are keyed by their trimmed pattern and shapeless ones by their sorted ingredients, which is
+    exactly what a grid normalises to. Partial matches go through an index of which recipes use which item
+*/
 pub struct CraftingRegistry{
-    //Hashmap is used so we can easily query for a specific item's recipe without having to loop over all recipes 
-    Recipes: HashMap<u8, CraftingRecipe>
+    Recipes: Vec<CraftingRecipe>,
+    //Output item -> indices into Recipes. An item can be made by more than one recipe
+    ByOutput: HashMap<u8, Vec<usize>>,
+    //Only the first recipe with a given pattern or ingredients is kept, the same one a scan would find
+    ShapedIndex: HashMap<CraftingGrid, usize>,
+    ShapelessIndex: HashMap<Vec<ItemID>, usize>,
+    //Item -> recipes that use it
+    ByIngredient: HashMap<ItemID, Vec<usize>>,
 }
 
 impl CraftingRegistry{
     pub fn New() -> Self {
-        Self { Recipes: HashMap::new() }
+        Self { Recipes: Vec::new(), ByOutput: HashMap::new(), ShapedIndex: HashMap::new(), ShapelessIndex: HashMap::new(), ByIngredient: HashMap::new() }
     }
 
-    pub fn AddRecipe(&mut self, itemID: u8, recipe: CraftingRecipe){
-        self.Recipes.insert(itemID, recipe);
+    pub fn AddRecipe(&mut self, recipe: CraftingRecipe){
+        let idx = self.Recipes.len();
+        self.ByOutput.entry(recipe.Output.ID).or_insert(Vec::new()).push(idx);
+
+        match &recipe.Shape {
+            RecipeShape::Shaped(pattern) => { self.ShapedIndex.entry(pattern.clone()).or_insert(idx); },
+            RecipeShape::Shapeless(items) => { self.ShapelessIndex.entry(items.clone()).or_insert(idx); },
+        }
+        for (item, _) in &recipe.Ingredients {
+            self.ByIngredient.entry(*item).or_insert(Vec::new()).push(idx);
+        }
+
+        self.Recipes.push(recipe);
     }
 
     pub fn DoesRecipeExistFor(&self, item: &ItemID) -> bool{
-        self.Recipes.contains_key(&item.ID)
+        self.ByOutput.contains_key(&item.ID)
+    }
+
+    pub fn GetRecipesFor(&self, item: &ItemID) -> Vec<&CraftingRecipe> {
+        match self.ByOutput.get(&item.ID) {
+            Some(indices) => indices.iter().map(|idx| &self.Recipes[*idx]).collect(),
+            None => Vec::new()
+        }
+    }
+
+    pub fn Recipes(&self) -> &Vec<CraftingRecipe> {
+        &self.Recipes
+    }
+
+    //The recipe the grid makes, if any. When two recipes fit, the one loaded first wins
+    pub fn MatchRecipe(&self, grid: &CraftingGrid) -> Option<&CraftingRecipe> {
+        let trimmed = grid.Trimmed()?;
+
+        let shaped = self.ShapedIndex.get(&trimmed);
+        let shapeless = self.ShapelessIndex.get(&trimmed.Ingredients());
+        let idx = match (shaped, shapeless) {
+            (Some(a), Some(b)) => *a.min(b),
+            (Some(a), None) | (None, Some(a)) => *a,
+            (None, None) => return None
+        };
+        Some(&self.Recipes[idx])
+    }
+
+    /*
+        Recipes that share at least one ingredient with the grid, best first, for a recipe book to suggest.
+        The score is how many of the grid's items the recipe uses. Ties go to the recipe loaded first
+    */
+    pub fn PartialMatches(&self, grid: &CraftingGrid) -> Vec<(&CraftingRecipe, u32)> {
+        let gridCounts = CountIngredients(&grid.Ingredients());
+
+        let mut scores: HashMap<usize, u32> = HashMap::new();
+        for (item, count) in &gridCounts {
+            for idx in self.ByIngredient.get(item).into_iter().flatten() {
+                let needed = self.Recipes[*idx].Ingredients.iter().find(|(i, _)| i == item).map_or(0, |(_, c)| *c);
+                *scores.entry(*idx).or_insert(0) += needed.min(*count);
+            }
+        }
+
+        let mut matches: Vec<(usize, u32)> = scores.into_iter().collect();
+        matches.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        matches.into_iter().map(|(idx, score)| (&self.Recipes[idx], score)).collect()
     }
 
-    pub fn GetRecipeFor(&self, item: &ItemID) -> Option<&CraftingRecipe> {
-        self.Recipes.get(&item.ID)
+    /*
+        Reads every recipe of every content pack. Has to run after the item registry is read,
+        since recipes refer to their ingredients and results by item identifier
+    */
+    pub fn ReadRecipes(&mut self, packs: &ContentPacks, itemRegistry: &ItemRegistry) -> Result<(), Box<dyn Error>> {
+        let mut seen: HashSet<String> = HashSet::new();
+
+        for (path, json) in packs.ReadRecipes().IntoResult()? {
+            let identifier = json.ID();
+            ValidateIdentifier(identifier)
+            .map_err(|e| GenericError::NewBoxed(format!("Error! Invalid recipe identifier in {}. {}", path.display(), e)))?;
+            if !seen.insert(identifier.to_owned()) {
+                return Err(GenericError::NewBoxed(format!("Error! Duplicate recipe identifier '{}' in {}", identifier, path.display())));
+            }
+
+            let resolve = |name: &str, property: &str| -> Result<ItemID, Box<dyn Error>> {
+                if !itemRegistry.HasItem(name) {
+                    return Err(GenericError::NewBoxed(
+                    format!("Error! '{}' of the recipe {} refers to the item '{}', which does not exist", property, identifier, name)));
+                }
+                Ok(ItemID::New(itemRegistry.NameToID(name)))
+            };
+
+            let output = resolve(json.Result(), "Result")?;
+            let recipe = match &json {
+                RecipeJson::Shaped { Pattern, Key, .. } => {
+                    let grid = ParsePattern(Pattern, identifier)?;
+                    let mut slots: Vec<Option<ItemID>> = Vec::with_capacity(grid.len());
+                    for symbol in grid {
+                        slots.push(match symbol {
+                            None => None,
+                            Some(c) => match Key.get(&c) {
+                                Some(name) => Some(resolve(name, "Key")?),
+                                None => return Err(GenericError::NewBoxed(
+                                        format!("Error! The pattern of the recipe {} uses '{}', which is not in its 'Key'", identifier, c)))
+                            }
+                        });
+                    }
+                    let rows = Pattern.len() as u32;
+                    let pattern = CraftingGrid::New(slots, rows, (Pattern[0].chars().count()) as u32)?;
+                    CraftingRecipe::NewShaped(identifier, pattern, output, json.Count())?
+                },
+                RecipeJson::Shapeless { Ingredients, .. } => {
+                    let mut items: Vec<ItemID> = Vec::with_capacity(Ingredients.len());
+                    for name in Ingredients {
+                        items.push(resolve(name, "Ingredients")?);
+                    }
+                    CraftingRecipe::NewShapeless(identifier, items, output, json.Count())?
+                }
+            };
+
+            self.AddRecipe(recipe);
+        }
+        Ok(())
+    }
+}
+
+//Flattens the rows of a pattern into slots, row by row. A space is an empty slot
+pub fn ParsePattern(pattern: &Vec<String>, identifier: &str) -> Result<Vec<Option<char>>, String> {
+    let cols = match pattern.first() {
+        Some(row) => row.chars().count(),
+        None => return Err(format!("Error! The recipe {} has an empty pattern!", identifier))
+    };
+
+    if cols == 0 || pattern.iter().any(|row| row.chars().count() != cols) {
+        return Err(format!("Error! Every row of the pattern of the recipe {} has to be the same, non zero, length!", identifier));
+    }
+    if pattern.len() as u32 > MAX_CRAFTING_GRID || cols as u32 > MAX_CRAFTING_GRID {
+        return Err(format!("Error! The pattern of the recipe {} is bigger than the {}x{} crafting grid!", identifier, MAX_CRAFTING_GRID, MAX_CRAFTING_GRID));
+    }
+
+    Ok(pattern.iter().flat_map(|row| row.chars()).map(|c| if c == ' ' { None } else { Some(c) }).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    use crate::World::idMap::IdMap;
+
+    const PLANK: u8 = 1;
+    const STICK: u8 = 2;
+    const STONE: u8 = 3;
+    const PICKAXE: u8 = 4;
+    const TORCH: u8 = 5;
+    const COAL: u8 = 6;
+
+    fn Id(id: u8) -> ItemID {
+        ItemID::New(id)
+    }
+
+    //A grid from rows of item IDs, 0 being an empty slot
+    fn Grid(rows: &[&[u8]]) -> CraftingGrid {
+        let slots = rows.iter().flat_map(|row| row.iter()).map(|id| if *id == 0 { None } else { Some(Id(*id)) }).collect();
+        CraftingGrid::New(slots, rows.len() as u32, rows[0].len() as u32).unwrap()
+    }
+
+    //Sticks are two planks on top of each other, torches are coal over a stick in any order
+    fn Book() -> CraftingRegistry {
+        let mut registry = CraftingRegistry::New();
+        registry.AddRecipe(CraftingRecipe::NewShaped("test:sticks", Grid(&[&[PLANK], &[PLANK]]), Id(STICK), 4).unwrap());
+        registry.AddRecipe(CraftingRecipe::NewShaped("test:pickaxe", Grid(&[&[STONE, STONE, STONE], &[0, STICK, 0], &[0, STICK, 0]]), Id(PICKAXE), 1).unwrap());
+        registry.AddRecipe(CraftingRecipe::NewShapeless("test:torch", vec![Id(COAL), Id(STICK)], Id(TORCH), 4).unwrap());
+        registry.AddRecipe(CraftingRecipe::NewShapeless("test:torch_from_planks", vec![Id(PLANK), Id(COAL)], Id(TORCH), 1).unwrap());
+        registry
+    }
+
+    fn Matched<'a>(registry: &'a CraftingRegistry, grid: &CraftingGrid) -> Option<&'a str> {
+        registry.MatchRecipe(grid).map(|recipe| recipe.Identifier.as_str())
+    }
+
+    //A pack holding a single recipe, written to a fresh temporary directory
+    fn PackWithRecipe(name: &str, recipe: &str) -> PathBuf {
+        let root = std::env::temp_dir().join(format!("minecraft_gl_crafting_{}", name));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(root.join("recipe")).unwrap();
+        std::fs::write(root.join("pack.json"), format!("{{ \"Name\": \"{}\", \"Version\": \"0.1.0\", \"Dependencies\": [\"core\"] }}", name)).unwrap();
+        std::fs::write(root.join("recipe/recipe.json"), recipe).unwrap();
+        root
+    }
+
+    fn ReadPack(root: PathBuf) -> Result<CraftingRegistry, String> {
+        let packs = ContentPacks::Load(&[root])?;
+        let mut items = ItemRegistry::New();
+        items.ReadItemAttributes(&packs, &mut IdMap::default()).map_err(|e| e.to_string())?;
+        let mut registry = CraftingRegistry::New();
+        registry.ReadRecipes(&packs, &items).map_err(|e| e.to_string())?;
+        Ok(registry)
+    }
+
+    #[test]
+    fn TrimmedCutsEmptyEdges() {
+        let grid = Grid(&[&[0, 0, 0], &[0, PLANK, 0], &[0, PLANK, STICK]]);
+        let trimmed = grid.Trimmed().unwrap();
+        assert_eq!((trimmed.Rows, trimmed.Cols), (2, 2));
+        assert_eq!(trimmed.Slots.iter().map(|slot| slot.map(|item| item.ID)).collect::<Vec<_>>(), vec![Some(PLANK), None, Some(PLANK), Some(STICK)]);
+        assert!(CraftingGrid::Empty(3, 3).Trimmed().is_none());
+        assert!(CraftingGrid::New(vec![None; 4], 3, 3).is_err());
+    }
+
+    #[test]
+    fn ShapedMatchesAnywhereInTheGrid() {
+        let registry = Book();
+        for col in 0..3 {
+            for row in 0..2 {
+                let mut grid = CraftingGrid::Empty(3, 3);
+                grid.Slots[(row * 3 + col) as usize] = Some(Id(PLANK));
+                grid.Slots[((row + 1) * 3 + col) as usize] = Some(Id(PLANK));
+                assert_eq!(Matched(&registry, &grid), Some("test:sticks"));
+            }
+        }
+        //a 2x2 inventory grid works too
+        assert_eq!(Matched(&registry, &Grid(&[&[0, PLANK], &[0, PLANK]])), Some("test:sticks"));
+        assert_eq!(Matched(&registry, &Grid(&[&[STONE, STONE, STONE], &[0, STICK, 0], &[0, STICK, 0]])), Some("test:pickaxe"));
     }
 
-    pub fn MatchRecipe(&self, recipe: &CraftingRecipe) -> Option<ItemID> {
-        let result = self.Recipes.iter().find(|&x| x.1 == recipe);
-        if let Some(val) = result {
-            return Some(ItemID::New(*val.0));
+    #[test]
+    fn ShapedNeedsTheExactShape() {
+        let registry = Book();
+        assert_eq!(Matched(&registry, &Grid(&[&[PLANK, PLANK, 0], &[0, 0, 0], &[0, 0, 0]])), None);
+        assert_eq!(Matched(&registry, &Grid(&[&[PLANK, 0, 0], &[0, PLANK, 0], &[0, 0, 0]])), None);
+        assert_eq!(Matched(&registry, &Grid(&[&[PLANK, 0, 0], &[PLANK, 0, 0], &[PLANK, 0, 0]])), None);
+        assert_eq!(Matched(&registry, &Grid(&[&[STONE, STONE, STONE], &[STICK, 0, 0], &[STICK, 0, 0]])), None);
+        assert_eq!(Matched(&registry, &CraftingGrid::Empty(3, 3)), None);
+    }
+
+    #[test]
+    fn ShapelessMatchesInAnyOrder() {
+        let registry = Book();
+        assert_eq!(Matched(&registry, &Grid(&[&[COAL, STICK]])), Some("test:torch"));
+        assert_eq!(Matched(&registry, &Grid(&[&[STICK, 0, 0], &[0, 0, 0], &[0, 0, COAL]])), Some("test:torch"));
+        assert_eq!(Matched(&registry, &Grid(&[&[0, 0, 0], &[PLANK, 0, 0], &[0, COAL, 0]])), Some("test:torch_from_planks"));
+        //exactly the ingredients, no more
+        assert_eq!(Matched(&registry, &Grid(&[&[COAL, STICK, STICK]])), None);
+        assert_eq!(Matched(&registry, &Grid(&[&[COAL, 0, 0]])), None);
+    }
+
+    #[test]
+    fn SeveralRecipesForOneOutput() {
+        let registry = Book();
+        let torches: Vec<&str> = registry.GetRecipesFor(&Id(TORCH)).iter().map(|r| r.Identifier.as_str()).collect();
+        assert_eq!(torches, vec!["test:torch", "test:torch_from_planks"]);
+        assert!(registry.DoesRecipeExistFor(&Id(STICK)));
+        assert!(!registry.DoesRecipeExistFor(&Id(COAL)));
+        assert!(registry.GetRecipesFor(&Id(COAL)).is_empty());
+    }
+
+    #[test]
+    fn OutputCounts() {
+        let registry = Book();
+        let sticks = registry.MatchRecipe(&Grid(&[&[PLANK], &[PLANK]])).unwrap();
+        assert_eq!((sticks.Output.ID, sticks.Count), (STICK, 4));
+        let torch = registry.MatchRecipe(&Grid(&[&[STICK, COAL]])).unwrap();
+        assert_eq!((torch.Output.ID, torch.Count), (TORCH, 4));
+        assert!(CraftingRecipe::NewShapeless("test:nothing", vec![Id(COAL)], Id(TORCH), 0).is_err());
+    }
+
+    #[test]
+    fn FirstLoadedRecipeWins() {
+        let mut registry = Book();
+        registry.AddRecipe(CraftingRecipe::NewShapeless("test:coal_torch", vec![Id(STICK), Id(COAL)], Id(COAL), 1).unwrap());
+        assert_eq!(Matched(&registry, &Grid(&[&[COAL, STICK]])), Some("test:torch"));
+    }
+
+    #[test]
+    fn PartialMatchesBestFirst() {
+        let registry = Book();
+        let matches: Vec<(&str, u32)> = registry.PartialMatches(&Grid(&[&[STICK, STICK, STONE]]))
+                                                .into_iter().map(|(r, score)| (r.Identifier.as_str(), score)).collect();
+        assert_eq!(matches, vec![("test:pickaxe", 3), ("test:torch", 1)]);
+        assert!(registry.PartialMatches(&Grid(&[&[PICKAXE]])).is_empty());
+
+        let recipe = registry.MatchRecipe(&Grid(&[&[PLANK], &[PLANK]])).unwrap();
+        assert!(matches!(recipe.MatchType(&Grid(&[&[PLANK, PLANK]])), MatchType::PartialMatch(2)));
+        assert!(matches!(recipe.MatchType(&Grid(&[&[PLANK], &[PLANK]])), MatchType::TotalMatch));
+        assert!(matches!(recipe.MatchType(&Grid(&[&[COAL]])), MatchType::ZeroMatch));
+    }
+
+    #[test]
+    fn ParsePatternRows() {
+        let pattern = vec!["XXX".to_owned(), " | ".to_owned()];
+        assert_eq!(ParsePattern(&pattern, "test:p").unwrap(), vec![Some('X'), Some('X'), Some('X'), None, Some('|'), None]);
+
+        assert!(ParsePattern(&Vec::new(), "test:p").is_err());
+        assert!(ParsePattern(&vec![String::new()], "test:p").is_err());
+        assert!(ParsePattern(&vec!["XX".to_owned(), "X".to_owned()], "test:p").is_err());
+        assert!(ParsePattern(&vec!["XXXX".to_owned()], "test:p").is_err());
+        assert!(ParsePattern(&vec!["X".to_owned(); 4], "test:p").is_err());
+    }
+
+    #[test]
+    fn ReadsRecipesFromPacks() {
+        let registry = ReadPack(PackWithRecipe("read", r#"{ "Type": "Shaped", "ID": "read:dirt", "Pattern": ["d ", "d "], "Key": { "d": "core:dirt" }, "Result": "core:apple", "Count": 3 }"#)).unwrap();
+        let recipe = registry.Recipes().iter().find(|r| r.Identifier == "read:dirt").unwrap();
+        assert_eq!(recipe.Count, 3);
+        assert_eq!(recipe.Ingredients.len(), 1);
+        assert_eq!(recipe.Ingredients[0].1, 2);
+        match &recipe.Shape {
+            //the empty column of the pattern is trimmed off
+            RecipeShape::Shaped(pattern) => assert_eq!((pattern.Rows, pattern.Cols), (2, 1)),
+            RecipeShape::Shapeless(_) => panic!("read:dirt should be shaped")
         }
-        None
+
+        let dirt = Some(recipe.Ingredients[0].0);
+        let grid = CraftingGrid::New(vec![None, dirt, None, dirt], 2, 2).unwrap();
+        assert_eq!(Matched(&registry, &grid), Some("read:dirt"));
+    }
+
+    #[test]
+    fn UnknownNamesAreErrors() {
+        let unknownResult = ReadPack(PackWithRecipe("result", r#"{ "Type": "Shapeless", "ID": "result:r", "Ingredients": ["core:dirt"], "Result": "core:nothing" }"#));
+        assert!(unknownResult.err().unwrap().contains("'Result' of the recipe result:r refers to the item 'core:nothing'"));
+
+        let unknownIngredient = ReadPack(PackWithRecipe("ingredient", r#"{ "Type": "Shapeless", "ID": "ingredient:r", "Ingredients": ["core:nothing"], "Result": "core:dirt" }"#));
+        assert!(unknownIngredient.err().unwrap().contains("'Ingredients' of the recipe ingredient:r"));
+
+        let unknownKey = ReadPack(PackWithRecipe("key", r#"{ "Type": "Shaped", "ID": "key:r", "Pattern": ["dx"], "Key": { "d": "core:dirt" }, "Result": "core:dirt" }"#));
+        assert!(unknownKey.err().unwrap().contains("uses 'x', which is not in its 'Key'"));
+
+        let badKeyItem = ReadPack(PackWithRecipe("keyitem", r#"{ "Type": "Shaped", "ID": "keyitem:r", "Pattern": ["d"], "Key": { "d": "core:nothing" }, "Result": "core:dirt" }"#));
+        assert!(badKeyItem.err().unwrap().contains("'Key' of the recipe keyitem:r"));
+
+        let badIdentifier = ReadPack(PackWithRecipe("ident", r#"{ "Type": "Shapeless", "ID": "NotAnIdentifier", "Ingredients": ["core:dirt"], "Result": "core:dirt" }"#));
+        assert!(badIdentifier.is_err());
+    }
+
+    /*
+        Times the indexed lookups against scanning every recipe, with thousands of made up recipes.
+        Ignored since it's slow in debug builds. Run it with --release -- --ignored --nocapture
+    */
+    #[test]
+    #[ignore]
+    fn IndexedLookupsBeatAScan() {
+        use rand::{Rng, SeedableRng, rngs::StdRng};
+        const RECIPES: usize = 5000;
+        const GRIDS: usize = 2000;
+
+        let mut rng = StdRng::seed_from_u64(7);
+        let randomGrid = |rng: &mut StdRng| {
+            let slots = (0..9).map(|_| if rng.gen_bool(0.4) { Some(Id(rng.gen_range(1..=255))) } else { None }).collect();
+            CraftingGrid::New(slots, 3, 3).unwrap()
+        };
+
+        let mut registry = CraftingRegistry::New();
+        let mut grids = Vec::with_capacity(GRIDS);
+        for i in 0..RECIPES {
+            let grid = randomGrid(&mut rng);
+            if grid.IsEmpty() {
+                continue;
+            }
+            //half of the grids craft something, the others are noise
+            if grids.len() < GRIDS / 2 && i % 3 == 0 {
+                grids.push(grid.clone());
+            }
+            let recipe = if i % 2 == 0 {
+                CraftingRecipe::NewShaped(&format!("bench:{}", i), grid, Id(1), 1)
+            } else {
+                CraftingRecipe::NewShapeless(&format!("bench:{}", i), grid.Ingredients(), Id(1), 1)
+            };
+            registry.AddRecipe(recipe.unwrap());
+        }
+        while grids.len() < GRIDS {
+            grids.push(randomGrid(&mut rng));
+        }
+
+        let timed = |name: &str, f: &mut dyn FnMut(&CraftingGrid) -> usize| {
+            let start = std::time::Instant::now();
+            let total: usize = grids.iter().map(|grid| f(grid)).sum();
+            let elapsed = start.elapsed();
+            println!("{:<26} {:>10.3?} ({} found)", name, elapsed, total);
+            (elapsed, total)
+        };
+
+        let recipes = registry.Recipes();
+        let (indexedMatch, found) = timed("MatchRecipe", &mut |grid| registry.MatchRecipe(grid).is_some() as usize);
+        let (scannedMatch, scanned) = timed("MatchRecipe (scan)", &mut |grid| recipes.iter().any(|r| r.Matches(grid)) as usize);
+        assert_eq!(found, scanned);
+
+        let (indexedPartial, found) = timed("PartialMatches", &mut |grid| registry.PartialMatches(grid).len());
+        let (scannedPartial, scanned) = timed("PartialMatches (scan)", &mut |grid| {
+            recipes.iter().filter(|r| !matches!(r.MatchType(grid), MatchType::ZeroMatch)).count()
+        });
+        assert_eq!(found, scanned);
+
+        println!("{} recipes, {} grids", recipes.len(), grids.len());
+        assert!(indexedMatch < scannedMatch);
+        assert!(indexedPartial < scannedPartial);
     }
 }
\ No newline at end of file
@@ -0,0 +1,148 @@
+use nalgebra as na;
+use std::path::Path;
+use serde::{Serialize, Deserialize};
+
+/*
+    The world's time of day. It moves forward with the block ticks and wraps around every DAY_LENGTH seconds.
+    The sun rises in the east (+X) at the start of the day, is highest a quarter of the way through and sets in
+    the west half way through, with the moon always on the other side of the sky. The sky's colours and how
+    bright the world is drawn both follow the height of the sun
+*/
+
+//Seconds in a full day, like Minecraft's 20 minutes
+pub const DAY_LENGTH: f32 = 1200.0;
+//New worlds start a little after sunrise
+const NEW_WORLD_TIME: f32 = DAY_LENGTH / 24.0;
+//How far the sun's path leans away from straight overhead, in radians
+const SUN_TILT: f32 = 0.35;
+//How bright the world is drawn at midnight, so it can still be seen
+pub const MIN_SKY_LIGHT: f32 = 0.2;
+//The sun's height where night turns into day. Twilight is in between
+const NIGHT_SUN_HEIGHT: f32 = -0.15;
+const DAY_SUN_HEIGHT: f32 = 0.2;
+//How far above or below the horizon the sun still turns the horizon orange
+const DUSK_SUN_HEIGHT: f32 = 0.3;
+
+const DAY_ZENITH: [f32; 3] = [0.30, 0.55, 0.95];
+const DAY_HORIZON: [f32; 3] = [0.70, 0.84, 1.00];
+const NIGHT_ZENITH: [f32; 3] = [0.01, 0.01, 0.04];
+const NIGHT_HORIZON: [f32; 3] = [0.04, 0.05, 0.10];
+const DUSK_HORIZON: [f32; 3] = [0.95, 0.45, 0.20];
+
+const TIME_FILE: &str = "time.json";
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct DayCycle {
+    //Full days since the world was created
+    pub Day: u64,
+    //Seconds into the current day
+    pub Time: f32,
+}
+
+//The colour straight up and at the horizon. The sky fades from one to the other
+#[derive(Clone, Copy, Debug)]
+pub struct SkyGradient {
+    pub Zenith: [f32; 3],
+    pub Horizon: [f32; 3],
+}
+
+impl Default for DayCycle {
+    fn default() -> Self {
+        Self { Day: 0, Time: NEW_WORLD_TIME }
+    }
+}
+
+impl DayCycle {
+    //A world without a saved time starts in the morning of its first day
+    pub fn Load(saveDir: &Path) -> Result<Self, String> {
+        let path = saveDir.join(TIME_FILE);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let file = std::fs::File::open(&path)
+        .map_err(|e| format!("Error! Could not open the world time {}! The error:\n{}", path.display(), e.to_string()))?;
+        let cycle: Self = serde_json::from_reader(std::io::BufReader::new(file))
+        .map_err(|e| format!("Error! The world time {} is corrupted! The error:\n{}", path.display(), e.to_string()))?;
+
+        if !(0f32..DAY_LENGTH).contains(&cycle.Time) {
+            return Err(format!("Error! The world time {} has a time of {}, but it has to be between 0 and {}!", path.display(), cycle.Time, DAY_LENGTH));
+        }
+        Ok(cycle)
+    }
+
+    pub fn Save(&self, saveDir: &Path) -> Result<(), String> {
+        std::fs::create_dir_all(saveDir)
+        .map_err(|e| format!("Error! Could not create the save directory {}! The error:\n{}", saveDir.display(), e.to_string()))?;
+
+        let path = saveDir.join(TIME_FILE);
+        let serialized = serde_json::to_string_pretty(self)
+        .map_err(|e| format!("Error! Could not serialize the world time! The error:\n{}", e.to_string()))?;
+        std::fs::write(&path, serialized)
+        .map_err(|e| format!("Error! Could not write the world time {}! The error:\n{}", path.display(), e.to_string()))
+    }
+
+    pub fn Advance(&mut self, deltaTime: f32) {
+        self.Time += deltaTime.max(0f32);
+        if self.Time >= DAY_LENGTH {
+            let days = (self.Time / DAY_LENGTH).floor();
+            self.Day += days as u64;
+            self.Time = (self.Time - days * DAY_LENGTH).clamp(0f32, DAY_LENGTH - f32::EPSILON);
+        }
+    }
+
+    //How far through the day it is, from 0 (sunrise) to 1. Noon is at 0.25 and midnight at 0.75
+    pub fn Fraction(&self) -> f32 {
+        self.Time / DAY_LENGTH
+    }
+
+    //How far the sun has gone around the world since sunrise, in radians
+    pub fn SunAngle(&self) -> f32 {
+        self.Fraction() * std::f32::consts::TAU
+    }
+
+    //The sun and moon go around this, so anything turning with the sky (like the stars) turns around it too
+    pub fn SkyAxis() -> na::Unit<na::Vector3<f32>> {
+        na::Unit::new_normalize(na::Vector3::new(0f32, -SUN_TILT.sin(), SUN_TILT.cos()))
+    }
+
+    //Points from the world towards the sun
+    pub fn SunDirection(&self) -> na::Vector3<f32> {
+        let angle = self.SunAngle();
+        na::Vector3::new(angle.cos(), angle.sin() * SUN_TILT.cos(), angle.sin() * SUN_TILT.sin())
+    }
+
+    pub fn MoonDirection(&self) -> na::Vector3<f32> {
+        -self.SunDirection()
+    }
+
+    //How much of the sky the sun lights, from 0 at night to 1 during the day
+    pub fn Daylight(&self) -> f32 {
+        SmoothStep(NIGHT_SUN_HEIGHT, DAY_SUN_HEIGHT, self.SunDirection().y)
+    }
+
+    //What the world's colours are multiplied by
+    pub fn SkyLight(&self) -> f32 {
+        MIN_SKY_LIGHT + (1f32 - MIN_SKY_LIGHT) * self.Daylight()
+    }
+
+    //The sky goes from night to day with the sun, and the horizon glows while the sun is close to it
+    pub fn SkyColors(&self) -> SkyGradient {
+        let daylight = self.Daylight();
+        let dusk = (1f32 - self.SunDirection().y.abs() / DUSK_SUN_HEIGHT).max(0f32);
+
+        let zenith = Lerp(NIGHT_ZENITH, DAY_ZENITH, daylight);
+        let horizon = Lerp(Lerp(NIGHT_HORIZON, DAY_HORIZON, daylight), DUSK_HORIZON, dusk * dusk);
+        SkyGradient { Zenith: zenith, Horizon: horizon }
+    }
+}
+
+fn SmoothStep(from: f32, to: f32, x: f32) -> f32 {
+    let t = ((x - from) / (to - from)).clamp(0f32, 1f32);
+    t * t * (3f32 - 2f32 * t)
+}
+
+fn Lerp(from: [f32; 3], to: [f32; 3], t: f32) -> [f32; 3] {
+    [0, 1, 2].map(|i| from[i] + (to[i] - from[i]) * t)
+}
\ No newline at end of file
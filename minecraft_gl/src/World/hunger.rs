@@ -0,0 +1,58 @@
+/*
+    The player's hunger. Food is eaten up to MAX_FOOD, and the saturation that comes with it is used up before
+    the food is. Exhaustion from working (like breaking blocks) builds up and costs a point every EXHAUSTION_PER_POINT
+*/
+
+pub const MAX_FOOD: u32 = 20;
+pub const EXHAUSTION_PER_POINT: f32 = 4.0;
+//Exhaustion from breaking one block
+pub const BREAK_EXHAUSTION: f32 = 0.025;
+
+pub struct Hunger{
+    Food: u32,
+    //Never more than the food
+    Saturation: f32,
+    Exhaustion: f32,
+}
+
+impl Hunger{
+    //Full, like a new player
+    pub fn New() -> Self {
+        Self { Food: MAX_FOOD, Saturation: 5f32, Exhaustion: 0f32 }
+    }
+
+    pub fn Food(&self) -> u32 {
+        self.Food
+    }
+
+    pub fn Saturation(&self) -> f32 {
+        self.Saturation
+    }
+
+    pub fn IsFull(&self) -> bool {
+        self.Food >= MAX_FOOD
+    }
+
+    //Returns false if the player is too full to eat
+    pub fn Eat(&mut self, food: u32, saturation: f32) -> bool {
+        if self.IsFull() {
+            return false;
+        }
+        self.Food = (self.Food + food).min(MAX_FOOD);
+        self.Saturation = f32::min(self.Saturation + saturation, self.Food as f32);
+        true
+    }
+
+    pub fn Exhaust(&mut self, amount: f32) {
+        self.Exhaustion += amount;
+        while self.Exhaustion >= EXHAUSTION_PER_POINT {
+            self.Exhaustion -= EXHAUSTION_PER_POINT;
+            if self.Saturation > 0f32 {
+                self.Saturation = f32::max(self.Saturation - 1f32, 0f32);
+            }
+            else {
+                self.Food = self.Food.saturating_sub(1);
+            }
+        }
+    }
+}
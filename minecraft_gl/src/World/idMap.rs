@@ -0,0 +1,92 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use serde::{Serialize, Deserialize};
+use crate::Util::resource::InstallDir;
+
+/*
+    Blocks and items are named by namespaced identifiers (core:grass) in json, but chunks store a
+    single byte per block. The numeric runtime IDs are handed out here the first time an identifier
+    is seen and then saved with the world, so a block keeps its ID for the lifetime of a save no
+    matter what content is added or removed later. IDs of removed content are never handed out again
+*/
+
+const ID_MAP_FILE: &str = "ids.json";
+
+//Next to the assets, wherever they were found at runtime
+pub fn DefaultSaveDir() -> PathBuf {
+    InstallDir().join("saves/world")
+}
+
+//Block ID 0 is air and item ID 0 is an empty slot
+pub const AIR_IDENTIFIER: &str = "core:air";
+
+#[derive(Serialize, Deserialize, Default, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct IdMap {
+    //BTreeMaps so the saved file has a stable order
+    pub Blocks: BTreeMap<String, u8>,
+    pub Items: BTreeMap<String, u8>,
+}
+
+impl IdMap {
+    //A world without a saved mapping starts with an empty one
+    pub fn Load(saveDir: &Path) -> Result<Self, String> {
+        let path = saveDir.join(ID_MAP_FILE);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let file = std::fs::File::open(&path)
+        .map_err(|e| format!("Error! Could not open the id map {}! The error:\n{}", path.display(), e.to_string()))?;
+        serde_json::from_reader(std::io::BufReader::new(file))
+        .map_err(|e| format!("Error! The id map {} is corrupted! The error:\n{}", path.display(), e.to_string()))
+    }
+
+    pub fn Save(&self, saveDir: &Path) -> Result<(), String> {
+        std::fs::create_dir_all(saveDir)
+        .map_err(|e| format!("Error! Could not create the save directory {}! The error:\n{}", saveDir.display(), e.to_string()))?;
+
+        let path = saveDir.join(ID_MAP_FILE);
+        let serialized = serde_json::to_string_pretty(self)
+        .map_err(|e| format!("Error! Could not serialize the id map! The error:\n{}", e.to_string()))?;
+        std::fs::write(&path, serialized)
+        .map_err(|e| format!("Error! Could not write the id map {}! The error:\n{}", path.display(), e.to_string()))
+    }
+
+    pub fn AssignBlock(&mut self, identifier: &str) -> Result<u8, String> {
+        Assign(&mut self.Blocks, identifier)
+    }
+
+    pub fn AssignItem(&mut self, identifier: &str) -> Result<u8, String> {
+        Assign(&mut self.Items, identifier)
+    }
+}
+
+fn Assign(map: &mut BTreeMap<String, u8>, identifier: &str) -> Result<u8, String> {
+    if let Some(id) = map.get(identifier) {
+        return Ok(*id);
+    }
+
+    //hand out the lowest ID nobody has ever had. 0 is reserved
+    let used: std::collections::HashSet<u8> = map.values().cloned().collect();
+    let id = (1..=u8::MAX).find(|id| !used.contains(id))
+    .ok_or(format!("Error! Could not assign an ID to {}. All {} IDs are taken!", identifier, u8::MAX))?;
+
+    map.insert(identifier.to_owned(), id);
+    Ok(id)
+}
+
+//Identifiers look like namespace:path, both made of lowercase letters, digits and underscores (the path may also use '/' and '.')
+pub fn ValidateIdentifier(identifier: &str) -> Result<(), String> {
+    let (namespace, path) = identifier.split_once(':')
+    .ok_or(format!("'{}' is not a namespaced identifier. It should look like core:stone", identifier))?;
+
+    let valid = |c: char, extra: &str| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_' || extra.contains(c);
+    if namespace.is_empty() || !namespace.chars().all(|c| valid(c, "")) {
+        return Err(format!("The namespace of '{}' must be made of lowercase letters, digits and underscores", identifier));
+    }
+    if path.is_empty() || !path.chars().all(|c| valid(c, "/.")) {
+        return Err(format!("The path of '{}' must be made of lowercase letters, digits, underscores, '/' and '.'", identifier));
+    }
+    Ok(())
+}
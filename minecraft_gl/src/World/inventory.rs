@@ -0,0 +1,493 @@
+use std::collections::HashMap;
+use super::item::{ItemID, ItemRegistry, ItemStack};
+use super::State;
+
+/*
+    Inventories are a fixed number of slots, each holding an item stack or ItemStack::Empty().
+    The slot operations are free functions over slices, so the same rules (stack sizes, merging,
+    leftovers) apply to a player inventory and to a block's State::Container, like a chest.
+    The first slots of a player inventory are the hotbar
+*/
+
+pub const PLAYER_INVENTORY_SIZE: usize = 36;
+pub const HOTBAR_SIZE: usize = 9;
+
+pub struct Inventory{
+    pub Slots: Vec<ItemStack>,
+    HotbarSize: usize,
+    //Index into the hotbar
+    Selected: usize,
+}
+
+impl Inventory{
+    pub fn New(size: usize, hotbarSize: usize) -> Result<Self, String> {
+        if size == 0 || hotbarSize > size {
+            return Err(format!("Error! Invalid inventory of {} slots with a hotbar of {} slots!", size, hotbarSize));
+        }
+        Ok( Self { Slots: vec![ItemStack::Empty(); size], HotbarSize: hotbarSize, Selected: 0 } )
+    }
+
+    pub fn Player() -> Self {
+        //the sizes are constants that are known to be valid
+        Self::New(PLAYER_INVENTORY_SIZE, HOTBAR_SIZE).unwrap()
+    }
+
+    pub fn Size(&self) -> usize {
+        self.Slots.len()
+    }
+
+    pub fn Get(&self, slot: usize) -> Option<&ItemStack> {
+        self.Slots.get(slot).filter(|stack| !stack.IsEmpty())
+    }
+
+    pub fn IsEmpty(&self) -> bool {
+        self.Slots.iter().all(|stack| stack.IsEmpty())
+    }
+
+    //Total count of an item across every slot
+    pub fn CountOf(&self, item: &ItemID) -> u32 {
+        self.Slots.iter().filter(|stack| !stack.IsEmpty() && stack.Item.ItemID == *item).map(|stack| stack.Count).sum()
+    }
+
+    //Fills existing stacks of the item first, then empty slots. Returns whatever didn't fit
+    pub fn Insert(&mut self, stack: ItemStack, itemRegistry: &ItemRegistry) -> Option<ItemStack> {
+        InsertInto(&mut self.Slots, stack, itemRegistry)
+    }
+
+    //Puts as much of the stack into one slot as fits. Returns whatever didn't fit
+    pub fn InsertAt(&mut self, slot: usize, mut stack: ItemStack, itemRegistry: &ItemRegistry) -> Option<ItemStack> {
+        match self.Slots.get_mut(slot) {
+            Some(dst) => {
+                MoveInto(dst, &mut stack, itemRegistry);
+                Leftover(stack)
+            },
+            None => Leftover(stack)
+        }
+    }
+
+    //Takes up to count items out of a slot
+    pub fn Take(&mut self, slot: usize, count: u32) -> Option<ItemStack> {
+        let stack = self.Slots.get_mut(slot).filter(|stack| !stack.IsEmpty())?;
+        let count = count.min(stack.Count);
+        if count == 0 {
+            return None;
+        }
+
+        stack.Count -= count;
+        let taken = ItemStack::WithCount(stack.Item.clone(), count);
+        if stack.Count == 0 {
+            *stack = ItemStack::Empty();
+        }
+        Some(taken)
+    }
+
+    //Takes the bigger half of a stack, like right clicking a slot
+    pub fn Split(&mut self, slot: usize) -> Option<ItemStack> {
+        let count = self.Get(slot)?.Count;
+        self.Take(slot, (count + 1) / 2)
+    }
+
+    pub fn Swap(&mut self, a: usize, b: usize) -> Result<(), String> {
+        if a >= self.Slots.len() || b >= self.Slots.len() {
+            return Err(format!("Error! Cannot swap slots {} and {} in an inventory of {} slots!", a, b, self.Slots.len()));
+        }
+        self.Slots.swap(a, b);
+        Ok(())
+    }
+
+    /*
+        Moves as much as fits from one slot onto another. Different items swap places instead, the
+        same as dropping a stack onto another in the inventory screen. Returns false if nothing changed
+    */
+    pub fn Merge(&mut self, from: usize, to: usize, itemRegistry: &ItemRegistry) -> bool {
+        if from == to || from >= self.Slots.len() || to >= self.Slots.len() || self.Slots[from].IsEmpty() {
+            return false;
+        }
+
+        let (src, dst) = TwoSlots(&mut self.Slots, from, to);
+        if !dst.IsEmpty() && dst.Item.ItemID != src.Item.ItemID {
+            std::mem::swap(src, dst);
+            return true;
+        }
+        MoveInto(dst, src, itemRegistry) > 0
+    }
+
+    pub fn HotbarSize(&self) -> usize {
+        self.HotbarSize
+    }
+
+    pub fn SelectedSlot(&self) -> usize {
+        self.Selected
+    }
+
+    pub fn Select(&mut self, hotbarSlot: usize) -> Result<(), String> {
+        if hotbarSlot >= self.HotbarSize {
+            return Err(format!("Error! Hotbar slot {} is out of range, the hotbar has {} slots!", hotbarSlot, self.HotbarSize));
+        }
+        self.Selected = hotbarSlot;
+        Ok(())
+    }
+
+    //Moves the selection like a scroll wheel, wrapping around the ends of the hotbar
+    pub fn Scroll(&mut self, delta: i32) {
+        if self.HotbarSize == 0 {
+            return;
+        }
+        self.Selected = (self.Selected as i32 + delta).rem_euclid(self.HotbarSize as i32) as usize;
+    }
+
+    pub fn Selected(&self) -> Option<&ItemStack> {
+        if self.HotbarSize == 0 {
+            return None;
+        }
+        self.Get(self.Selected)
+    }
+
+    pub fn TakeSelected(&mut self, count: u32) -> Option<ItemStack> {
+        if self.HotbarSize == 0 {
+            return None;
+        }
+        self.Take(self.Selected, count)
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+/// ////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+/// ////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+
+//Fills existing stacks of the item first, then empty slots. Returns whatever didn't fit
+pub fn InsertInto(slots: &mut [ItemStack], mut stack: ItemStack, itemRegistry: &ItemRegistry) -> Option<ItemStack> {
+    if stack.IsEmpty() {
+        return None;
+    }
+
+    for dst in slots.iter_mut().filter(|dst| !dst.IsEmpty()) {
+        if MoveInto(dst, &mut stack, itemRegistry) > 0 && stack.IsEmpty() {
+            return None;
+        }
+    }
+    for dst in slots.iter_mut().filter(|dst| dst.IsEmpty()) {
+        MoveInto(dst, &mut stack, itemRegistry);
+        if stack.IsEmpty() {
+            return None;
+        }
+    }
+    Some(stack)
+}
+
+/*
+    Moves the stack in one slot into another container, like shift clicking between a chest and the
+    player inventory. Whatever doesn't fit stays in the slot. Returns false if nothing moved
+*/
+pub fn Transfer(from: &mut [ItemStack], slot: usize, to: &mut [ItemStack], itemRegistry: &ItemRegistry) -> bool {
+    let stack = match from.get_mut(slot) {
+        Some(stack) if !stack.IsEmpty() => std::mem::replace(stack, ItemStack::Empty()),
+        _ => return false
+    };
+
+    let count = stack.Count;
+    match InsertInto(to, stack, itemRegistry) {
+        Some(leftover) => {
+            let moved = leftover.Count != count;
+            from[slot] = leftover;
+            moved
+        },
+        None => true
+    }
+}
+
+/*
+    The slots of a container state, padded out to rows * cols with empty stacks in case
+    the state was built by hand with fewer stacks than slots
+*/
+pub fn ContainerSlots(state: &mut State) -> Option<&mut Vec<ItemStack>> {
+    match state {
+        State::Container((slots, rows, cols)) => {
+            let size = (*rows * *cols) as usize;
+            if slots.len() < size {
+                slots.resize(size, ItemStack::Empty());
+            }
+            Some(slots)
+        },
+        State::DynamicContainer(slots) => Some(slots),
+        _ => None
+    }
+}
+
+//Every stack in every container of a block's state, like what a broken chest spills
+pub fn ContainerContents(state: &HashMap<String, State>) -> Vec<ItemStack> {
+    let mut stacks = Vec::new();
+    for val in state.values() {
+        let slots = match val {
+            State::Container((slots, _, _)) => slots,
+            State::DynamicContainer(slots) => slots,
+            _ => continue
+        };
+        stacks.extend(slots.iter().filter(|stack| !stack.IsEmpty()).cloned());
+    }
+    stacks
+}
+
+//Moves as much of src into dst as dst has room for. Returns the number of items moved
+fn MoveInto(dst: &mut ItemStack, src: &mut ItemStack, itemRegistry: &ItemRegistry) -> u32 {
+    if src.IsEmpty() {
+        return 0;
+    }
+    if dst.IsEmpty() {
+        *dst = ItemStack::WithCount(src.Item.clone(), 0);
+    }
+    else if dst.Item.ItemID != src.Item.ItemID {
+        return 0;
+    }
+
+    let room = dst.MaxCount(itemRegistry).saturating_sub(dst.Count);
+    let moved = room.min(src.Count);
+    dst.Count += moved;
+    src.Count -= moved;
+
+    if dst.Count == 0 {
+        *dst = ItemStack::Empty();
+    }
+    if src.Count == 0 {
+        *src = ItemStack::Empty();
+    }
+    moved
+}
+
+fn Leftover(stack: ItemStack) -> Option<ItemStack> {
+    if stack.IsEmpty() { None } else { Some(stack) }
+}
+
+//Mutable references to two different slots
+fn TwoSlots(slots: &mut [ItemStack], a: usize, b: usize) -> (&mut ItemStack, &mut ItemStack) {
+    if a < b {
+        let (left, right) = slots.split_at_mut(b);
+        (&mut left[a], &mut right[0])
+    } else {
+        let (left, right) = slots.split_at_mut(a);
+        (&mut right[0], &mut left[b])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::World::item::{Item, ItemAttribute};
+
+    const DIRT: u8 = 1;
+    const PEARL: u8 = 2;
+    const SWORD: u8 = 3;
+
+    //Dirt stacks to 64, pearls to 16 and swords don't stack
+    fn Registry() -> ItemRegistry {
+        let mut registry = ItemRegistry::New();
+        for (id, stackSize) in [(DIRT, 64), (PEARL, 16), (SWORD, 1)] {
+            registry.ItemAttributes.insert(id, ItemAttribute { StackSize: stackSize, ..Default::default() });
+        }
+        registry
+    }
+
+    fn Stack(id: u8, count: u32) -> ItemStack {
+        ItemStack::WithCount(Item { ItemID: ItemID::New(id), Attributes: None }, count)
+    }
+
+    fn Chest(rows: u32, cols: u32) -> State {
+        State::Container((Vec::new(), rows, cols))
+    }
+
+    #[test]
+    fn InsertFillsStacksBeforeEmptySlots() {
+        let registry = Registry();
+        let mut inventory = Inventory::New(3, 3).unwrap();
+        inventory.Slots[2] = Stack(DIRT, 60);
+
+        assert!(inventory.Insert(Stack(DIRT, 10), &registry).is_none());
+        assert_eq!(inventory.Slots[2].Count, 64);
+        assert_eq!(inventory.Slots[0].Count, 6);
+        assert!(inventory.Slots[1].IsEmpty());
+        assert_eq!(inventory.CountOf(&ItemID::New(DIRT)), 70);
+    }
+
+    #[test]
+    fn InsertReturnsLeftovers() {
+        let registry = Registry();
+        let mut inventory = Inventory::New(2, 2).unwrap();
+
+        let leftover = inventory.Insert(Stack(PEARL, 40), &registry).unwrap();
+        assert_eq!(leftover.Count, 8);
+        assert_eq!(leftover.Item.ItemID.ID, PEARL);
+        assert_eq!(inventory.Slots[0].Count, 16);
+        assert_eq!(inventory.Slots[1].Count, 16);
+
+        //full of something else, nothing goes in
+        let leftover = inventory.Insert(Stack(DIRT, 5), &registry).unwrap();
+        assert_eq!(leftover.Count, 5);
+        assert!(inventory.Insert(ItemStack::Empty(), &registry).is_none());
+    }
+
+    #[test]
+    fn StackSizeLimitsSlots() {
+        let registry = Registry();
+        let mut inventory = Inventory::New(3, 3).unwrap();
+
+        assert!(inventory.Insert(Stack(SWORD, 2), &registry).is_none());
+        assert_eq!(inventory.Slots[0].Count, 1);
+        assert_eq!(inventory.Slots[1].Count, 1);
+
+        let leftover = inventory.InsertAt(2, Stack(DIRT, 70), &registry).unwrap();
+        assert_eq!(inventory.Slots[2].Count, 64);
+        assert_eq!(leftover.Count, 6);
+        assert!(inventory.InsertAt(7, Stack(DIRT, 1), &registry).is_some());
+    }
+
+    #[test]
+    fn PushStopsAtMaxCount() {
+        let registry = Registry();
+        let item = Item { ItemID: ItemID::New(DIRT), Attributes: None };
+
+        let mut stack = Stack(DIRT, 63);
+        assert!(stack.Push(&item, &registry));
+        assert_eq!(stack.Count, 64);
+        assert!(!stack.Push(&item, &registry));
+        assert_eq!(stack.Count, 64);
+
+        let mut sword = Stack(SWORD, 1);
+        assert!(!sword.Push(&Item { ItemID: ItemID::New(SWORD), Attributes: None }, &registry));
+        assert!(!Stack(PEARL, 1).Push(&item, &registry));
+    }
+
+    #[test]
+    fn TakeAndSplit() {
+        let mut inventory = Inventory::New(2, 2).unwrap();
+        inventory.Slots[0] = Stack(DIRT, 5);
+
+        let half = inventory.Split(0).unwrap();
+        assert_eq!(half.Count, 3);
+        assert_eq!(inventory.Slots[0].Count, 2);
+
+        assert_eq!(inventory.Take(0, 10).unwrap().Count, 2);
+        assert!(inventory.Slots[0].IsEmpty());
+        assert!(inventory.Split(0).is_none());
+        assert!(inventory.Take(1, 1).is_none());
+
+        inventory.Slots[1] = Stack(DIRT, 1);
+        assert_eq!(inventory.Split(1).unwrap().Count, 1);
+        assert!(inventory.IsEmpty());
+    }
+
+    #[test]
+    fn MergeMovesWhatFits() {
+        let registry = Registry();
+        let mut inventory = Inventory::New(3, 3).unwrap();
+        inventory.Slots[0] = Stack(PEARL, 10);
+        inventory.Slots[1] = Stack(PEARL, 12);
+
+        assert!(inventory.Merge(0, 1, &registry));
+        assert_eq!(inventory.Slots[1].Count, 16);
+        assert_eq!(inventory.Slots[0].Count, 6);
+
+        //the destination is full
+        assert!(!inventory.Merge(0, 1, &registry));
+        //onto an empty slot moves the whole stack
+        assert!(inventory.Merge(0, 2, &registry));
+        assert!(inventory.Slots[0].IsEmpty());
+        assert_eq!(inventory.Slots[2].Count, 6);
+
+        assert!(!inventory.Merge(0, 1, &registry));
+        assert!(!inventory.Merge(1, 1, &registry));
+        assert!(!inventory.Merge(1, 9, &registry));
+    }
+
+    #[test]
+    fn MergeSwapsDifferentItems() {
+        let registry = Registry();
+        let mut inventory = Inventory::New(2, 2).unwrap();
+        inventory.Slots[0] = Stack(DIRT, 3);
+        inventory.Slots[1] = Stack(PEARL, 4);
+
+        assert!(inventory.Merge(0, 1, &registry));
+        assert_eq!((inventory.Slots[0].Item.ItemID.ID, inventory.Slots[0].Count), (PEARL, 4));
+        assert_eq!((inventory.Slots[1].Item.ItemID.ID, inventory.Slots[1].Count), (DIRT, 3));
+    }
+
+    #[test]
+    fn Swap() {
+        let mut inventory = Inventory::New(2, 2).unwrap();
+        inventory.Slots[0] = Stack(DIRT, 3);
+
+        inventory.Swap(0, 1).unwrap();
+        assert!(inventory.Slots[0].IsEmpty());
+        assert_eq!(inventory.Slots[1].Count, 3);
+        assert!(inventory.Swap(0, 2).is_err());
+    }
+
+    #[test]
+    fn HotbarSelectAndScroll() {
+        let mut inventory = Inventory::Player();
+        assert_eq!(inventory.SelectedSlot(), 0);
+
+        inventory.Select(4).unwrap();
+        assert_eq!(inventory.SelectedSlot(), 4);
+        assert!(inventory.Select(HOTBAR_SIZE).is_err());
+        assert_eq!(inventory.SelectedSlot(), 4);
+
+        inventory.Scroll(-5);
+        assert_eq!(inventory.SelectedSlot(), HOTBAR_SIZE - 1);
+        inventory.Scroll(1);
+        assert_eq!(inventory.SelectedSlot(), 0);
+        inventory.Scroll(HOTBAR_SIZE as i32 * 3 + 2);
+        assert_eq!(inventory.SelectedSlot(), 2);
+    }
+
+    #[test]
+    fn SelectedStack() {
+        let mut inventory = Inventory::Player();
+        inventory.Slots[1] = Stack(DIRT, 2);
+        assert!(inventory.Selected().is_none());
+
+        inventory.Scroll(1);
+        assert_eq!(inventory.Selected().unwrap().Count, 2);
+        assert_eq!(inventory.TakeSelected(5).unwrap().Count, 2);
+        assert!(inventory.Selected().is_none());
+
+        assert!(Inventory::New(0, 0).is_err());
+        assert!(Inventory::New(2, 3).is_err());
+    }
+
+    #[test]
+    fn InsertIntoChest() {
+        let registry = Registry();
+        let mut chest = Chest(1, 2);
+
+        let slots = ContainerSlots(&mut chest).unwrap();
+        assert_eq!(slots.len(), 2);
+        let leftover = InsertInto(slots, Stack(PEARL, 40), &registry).unwrap();
+        assert_eq!(leftover.Count, 8);
+
+        let contents = ContainerContents(&HashMap::from([("Items".to_owned(), chest)]));
+        assert_eq!(contents.iter().map(|stack| stack.Count).collect::<Vec<_>>(), vec![16, 16]);
+        assert!(ContainerSlots(&mut State::IntAttribute(3)).is_none());
+    }
+
+    #[test]
+    fn TransferToAndFromChest() {
+        let registry = Registry();
+        let mut inventory = Inventory::New(2, 2).unwrap();
+        let mut chest = Chest(1, 1);
+        inventory.Slots[0] = Stack(PEARL, 20);
+
+        //only one stack's worth fits in the chest, the rest stays behind
+        assert!(Transfer(&mut inventory.Slots, 0, ContainerSlots(&mut chest).unwrap(), &registry));
+        assert_eq!(inventory.Slots[0].Count, 4);
+        assert_eq!(ContainerSlots(&mut chest).unwrap()[0].Count, 16);
+        assert!(!Transfer(&mut inventory.Slots, 0, ContainerSlots(&mut chest).unwrap(), &registry));
+        assert!(!Transfer(&mut inventory.Slots, 1, ContainerSlots(&mut chest).unwrap(), &registry));
+
+        //and back out again, merging with what's left
+        assert!(Transfer(ContainerSlots(&mut chest).unwrap(), 0, &mut inventory.Slots, &registry));
+        assert_eq!(inventory.Slots[0].Count, 16);
+        assert_eq!(inventory.Slots[1].Count, 4);
+        assert!(ContainerSlots(&mut chest).unwrap()[0].IsEmpty());
+    }
+}
\ No newline at end of file
@@ -2,7 +2,7 @@
 
 
 //TODO ENUM for ID? Either u8 or INVALID similiar to an option
-#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct ItemID{
     pub ID: u8,
 }
@@ -12,7 +12,7 @@ impl ItemID{
         Self { ID: id }
     }
 }
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Item{
     pub ItemID: ItemID,
     pub Attributes: Option<HashMap<String, super::State>>
@@ -33,8 +33,26 @@ impl Item{
             Attributes: Some(itemRegistry.GetAttributesOfID(id).CustomAttributes.clone())
         }
     }
+
+    //Uses left before the item breaks, for tools that wear out
+    pub fn Durability(&self) -> Option<i32>{
+        self.Attributes.as_ref()?.get(DURABILITY)?.AsInt().cloned()
+    }
+
+    //Wears the item down. Returns true if that used it up. Items without a durability never wear out
+    pub fn Damage(&mut self, amount: u32) -> bool{
+        let durability = match self.Attributes.as_mut().and_then(|attributes| attributes.get_mut(DURABILITY)) {
+            Some(super::State::IntAttribute(val)) => val,
+            _ => return false
+        };
+        *durability = durability.saturating_sub(amount as i32).max(0);
+        *durability == 0
+    }
 }
-#[derive(Clone)]
+
+//The attribute a tool's remaining durability is kept in, per item
+pub const DURABILITY: &str = "Durability";
+#[derive(Clone, Serialize, Deserialize)]
 pub struct ItemStack{
     pub Item: Item,
     pub Count: u32,
@@ -48,8 +66,32 @@ impl ItemStack {
         }
     }
 
+    pub fn WithCount(item: Item, count: u32) -> Self{
+        Self {
+            Item: item,
+            Count: count
+        }
+    }
+
+    //Item ID 0 is reserved for empty slots, which is what fixed size containers are filled with
+    pub fn Empty() -> Self{
+        Self {
+            Item: Item { ItemID: ItemID::New(0), Attributes: None },
+            Count: 0
+        }
+    }
+
+    pub fn IsEmpty(&self) -> bool{
+        self.Count == 0 || self.Item.ItemID.ID == 0
+    }
+
+    //How many items this stack can hold
+    pub fn MaxCount(&self, itemRegistry: &ItemRegistry) -> u32{
+        itemRegistry.GetAttributesOfID(self.Item.ItemID.ID).StackSize
+    }
+
     pub fn Push(&mut self, item: &Item, itemRegistry: &ItemRegistry) -> bool{
-        if item.ItemID == self.Item.ItemID && self.Count <= itemRegistry.GetAttributesOfID(self.Item.ItemID.ID).StackSize{
+        if item.ItemID == self.Item.ItemID && self.Count < self.MaxCount(itemRegistry){
             self.Count += 1;
             return true;
         }
@@ -74,24 +116,23 @@ impl ItemStack {
 /// /////////////////////////////////////////////////////////////////////////////////////////////////////////////
 ////////////////////////////////////////////////////////////////////////////////////////////////////////////////
 //////////////////////////////////////////////////////////////////////////////////////////////////////////////// 
-use std::path::Path;
-use std::{collections::HashMap, fs::File};
-use serde_json::Value;
+use std::path::PathBuf;
+use std::collections::{HashMap, HashSet};
+use serde::{Serialize, Deserialize};
 use std::error::Error;
-use std::io::{BufReader, Write};
-use std::fs;
-use crate::Event::event::Event;
-use crate::Util::atlas::TextureAtlas;
+use crate::Util::atlas::{TextureAtlas, AtlasBuilder, AtlasCache, AtlasSource, ATLAS_PADDING};
 use crate::Util::resource;
-use super::{GenericError, State};
-use super::crafting::{CraftingRecipe, CraftingRegistry};
-use super::itemBehavior::ItemBehavior;
+use super::{GenericError, State, schema::ItemJson, idMap::{IdMap, ValidateIdentifier}, contentPack::ContentPacks};
+use super::itemBehavior::{ItemBehavior, DefaultBehavior, ItemBindingFunction};
 use super::block::Block;
+use super::mining::EFFECTIVE_TOOL_SPEED;
 
 
 
 #[derive(Clone)]
 pub struct ItemAttribute{
+    //Namespaced identifier (core:dirt). Unlike the numeric ID, this is the same in every world
+    pub Identifier: String,
     pub Name: String,
     //Custom attributes are for more lossly defined attributes. Not every attribute can be covered by this struct
     pub CustomAttributes: HashMap<String, State>,
@@ -99,19 +140,44 @@ pub struct ItemAttribute{
     pub StackSize: u32,
     //A block that can be placed upon the player right clicking with this item
     pub PlaceableBlock: Option<Block>,
-    //The item's texture. If NONE, then the null texture will be used
+    //Path of the item's texture, resolved against the content packs. If NONE, then the null texture will be used
     pub Texture: Option<String>,
+    //Seconds a furnace burns for on one of this item. Zero means it isn't fuel
+    pub FuelValue: f32,
+    //Tools break blocks faster and wear out. None for everything else
+    pub Tool: Option<ToolAttribute>,
+    //Hunger points restored by eating one. Zero means it can't be eaten
+    pub FoodValue: u32,
+    pub Saturation: f32,
+    pub Throwable: bool,
+}
+
+#[derive(Clone)]
+pub struct ToolAttribute{
+    //Like "shovel" or "pickaxe". Tools only help with blocks whose effective tool is the same type
+    pub Type: String,
+    pub Tier: u32,
+    //How many times faster than a bare hand it breaks the blocks it's effective on
+    pub MiningSpeed: f32,
+    //Blocks it can break before it's used up. Each item keeps what's left in its DURABILITY attribute
+    pub Durability: Option<u32>,
 }
 
 //Default implementation
 impl Default for ItemAttribute{
     fn default() -> Self {
         Self { 
+            Identifier: String::from(""),
             Name: String::from(""),
             StackSize: 64,
             CustomAttributes: HashMap::new(),
             PlaceableBlock: None,
-            Texture: None 
+            Texture: None,
+            FuelValue: 0f32,
+            Tool: None,
+            FoodValue: 0,
+            Saturation: 0f32,
+            Throwable: false,
         }
     }
 }
@@ -130,11 +196,11 @@ pub struct ItemRegistry{
     //TODO otherwise remove stringtoID hashmap and replace the u8 ID's with strings
     //TODO Consider changing the visibility of these too, as there are already a bunch of interface functions to access them
     pub ItemAttributes: HashMap<u8, ItemAttribute>,
-    pub ItemBehaviors: HashMap<u8, ItemBehavior>,
+    pub ItemBehaviors: HashMap<u8, Box<dyn ItemBehavior>>,
     StringToID: HashMap<String, u8>,
     NumRegisteredItems: u32,
     NumRegisteredTextures: u32,
-
+    NullTexture: String,
 }
 
 impl ItemRegistry{
@@ -145,17 +211,18 @@ impl ItemRegistry{
             StringToID: HashMap::new(),
             NumRegisteredItems: 0,
             NumRegisteredTextures: 0,
+            NullTexture: String::new(),
         }
     }
 
-    pub fn ReadItemAttributes(&mut self, craftingRegistry: &mut CraftingRegistry) -> Result<Vec<(u8, String)>, Box<dyn Error>> {
+    pub fn ReadItemAttributes(&mut self, packs: &ContentPacks, idMap: &mut IdMap) -> Result<Vec<(u8, String)>, Box<dyn Error>> {
          //Keep track of the number of blocks and textures for those blocks
          let mut itemCount = 0;
          let mut textureCount = 0;
 
           /*
             In the JSON of items, the user can define a BLOCK that the item can place. I want the user
-            to imply write down the name of their desired block instead of hunting down that block's ID.
+            to imply write down the identifier of their desired block instead of hunting down that block's ID.
             Now, in order to check if the user wrote down a valid block, we need to check that block against
             the block registry. This function is intended to be used in the 'ReadAttributes' function in mod.rs, 
             which will validate the block names given by the JSON files
@@ -165,68 +232,44 @@ impl ItemRegistry{
          //then we will use the u8 ID and attach the appropiate item to the appropiate item ID
          let mut placeBlocks: Vec<(u8, String)> = Vec::new();
 
-        //keep a list of all json files in the given directory
-        let mut jsonFiles: Vec<serde_json::Value> = Vec::new();
-
-        let path = std::path::Path::new("./minecraft_gl/assets/data/item/json/");
-        for file in fs::read_dir(path)
-           .map_err(|e| format!("Error! Could not find ./minecraft_gl/assets/data/item/json/ directory! The error:\n{}", e.to_string()))? {
-
-           let path = file
-           .map_err(|e| format!("Error! Could not retrieve file in ../../assets/data/item/json/ directory! The error:\n{}", e.to_string()))?.path();
+        //keep a list of all json files across the content packs, along with their paths for error messages
+        let mut jsonFiles: Vec<(PathBuf, ItemJson)> = packs.ReadItems().IntoResult()?;
+        self.NullTexture = ResolveTexture(packs, "nullTexture.png");
 
-           let file = File::open(path)
-           .map_err(|e| GenericError::NewBoxed(format!("Error! Could not open file of path in ../../assets/data/item/json/ directory! The error:\n{}", e.to_string())))?;
+        //Hand out runtime IDs in identifier order so a new world always gets the same IDs for the same content
+        jsonFiles.sort_by(|a, b| a.1.ID.cmp(&b.1.ID));
 
-           let json: Value = serde_json::from_reader(BufReader::new(file))?;
-           jsonFiles.push(json);
-        }
+        let mut identifiers: HashSet<String> = HashSet::new();
+        let mut assigned: Vec<(u8, PathBuf, ItemJson)> = Vec::with_capacity(jsonFiles.len());
+        for (path, json) in jsonFiles {
+            let file = path.display();
+            ValidateIdentifier(&json.ID)
+            .map_err(|e| format!("Invalid item identifier. {}. Error in {}", e, file))?;
 
-         /*
-            Sort the Json files by their ID. This is due to the running 'textureCount' variable, which is used to determine a blocks
-            position in the texture atlas. Block's with lower ID's should be first in the atlas, followed by higher ID's
-         */
-        jsonFiles.sort_by(|a, b| a["ID"].as_u64().unwrap().partial_cmp(&b["ID"].as_u64().unwrap()).unwrap());
-
-        for json in jsonFiles {
-            let id: u8;
-            if let Some(val) = json.get("ID") {
-                id = val.as_u64().unwrap() as u8;
-                if self.ItemAttributes.contains_key(&id) {
-                    return Err(GenericError::NewBoxed(format!("Duplicate item ID found! Conflict with block '{}' of id {}. The Json:\n\n{}", self.ItemAttributes[&id].Name, id, json.to_string())));
-                }
-            }
-            else {
-                return Err(GenericError::NewBoxed(format!("No 'ID' attribute found for item whilst reading item attributes. The Json:\n\n{}", json.to_string())));
+            //Disabled items still claim their identifier, so turning one on later can't cause a conflict
+            if ! identifiers.insert(json.ID.clone()) {
+                return Err(GenericError::NewBoxed(format!("Duplicate item identifier {} found! Error in {}", json.ID, file)));
             }
-          
-            let name: &str;
-            if let Some(val) = json.get("Name") {
-                name = val.as_str().unwrap();
-                if self.StringToID.contains_key(name) {
-                    return Err(GenericError::NewBoxed(format!("Duplicate item name found! Conflict with item '{}' of id {}. The Json:\n\n{}", self.ItemAttributes[&id].Name, id, json.to_string())));
-                }
 
-            }
-            else {
-                return Err(GenericError::NewBoxed(format!("No 'Name' attribute found for item of id {} whilst reading block attributes. The Json:\n\n{}", id, json.to_string())));
+            if ! json.Enabled {
+                continue;
             }
 
-            if let Some(val) = json.get("Enabled") {
-                if ! val.as_bool().unwrap() {
-                    continue;
-                }
-            }
-            else {
-                return Err(GenericError::NewBoxed(format!("No 'Enabled' attribute found for item of type {} and id {} whilst reading item attributes. The Json:\n{}", name, id, json.to_string())));
-            }
+            let id = idMap.AssignItem(&json.ID)?;
+            assigned.push((id, path, json));
+        }
+        assigned.sort_by_key(|(id, _, _)| *id);
 
+        for (id, path, json) in assigned {
+            let name = json.Name.as_str();
+            let file = path.display();
 
-           //add the name and id to the String -> BlockID hashmap
-           self.StringToID.insert(String::from(name), id);
+           //add the identifier and id to the String -> ItemID hashmap
+           self.StringToID.insert(json.ID.clone(), id);
 
            //construct the block attribute struct with default values
            let mut itemAttribs = ItemAttribute::default();
+           itemAttribs.Identifier = json.ID.clone();
            itemAttribs.Name = String::from(name); //add the name
             
             /*
@@ -235,61 +278,26 @@ impl ItemRegistry{
                 these highly specialized values. The idea is for the user to grab these attributes via their 
                 name in the block behavior functions later on defined
             */
-           if let Some(attributes) = json.get("Attributes") {
-                let obj = attributes.as_object().unwrap();
-
-                for pair in obj.iter() {
-
-                     /*
-                        his is for when the user wants to specify a data type, but doesn't want to provide a default value.
-                        Imagine a furnace, which uses a floating point number to represent the amount of fuel left inside of it.
-                        It won't make much sense for the user to give that a default value, so they can just specify a data type.
-                    */
-                    if pair.1.is_string() {
-                        let val = match State::StateType(pair.1.as_str().unwrap()) {
-                            Ok(val) => val,
-                            Err(msg) => return Err(GenericError::NewBoxed(format!("{}. Error orignated from Item type {} of Id {}", msg, name, id)))
-                        };
-                        itemAttribs.CustomAttributes.insert(pair.0.clone(), val);
-                    }
-                    //For containers of specific row and column dimensions
-                    else if pair.1.is_object() {
-                        let rows = pair.1["Rows"].as_u64().unwrap() as u32;
-                        let cols = pair.1["Cols"].as_u64().unwrap() as u32;
-                        let mut vec: Vec<ItemStack> = Vec::new();
-                        vec.reserve((rows * cols) as usize);
-                        itemAttribs.CustomAttributes.insert(pair.0.clone(), State::Container((vec, rows, cols)));
-                    }
-                    //integer types with a default value
-                    else if pair.1.is_i64() {
-                        let val = pair.1.as_i64().unwrap() as i32;
-                        itemAttribs.CustomAttributes.insert(pair.0.clone(), State::IntAttribute(val));
-                    }
-                    //floating point types with a default value//floating point types with a default value
-                    else if pair.1.is_f64() {
-                        let val = pair.1.as_f64().unwrap() as f32;
-                        itemAttribs.CustomAttributes.insert(pair.0.clone(), State::FloatAttribute(val));
-                    }
-                    //boolean types with a default value
-                    else if pair.1.is_boolean() {
-                        let val = pair.1.as_bool().unwrap();
-                        itemAttribs.CustomAttributes.insert(pair.0.clone(), State::BoolAttribute(val));
+            for (key, attribute) in &json.Attributes {
+                let val = attribute.ToState()
+                .map_err(|e| format!("{}. Error orignated from attribute '{}' of Item type {} of Id {} in {}", e, key, name, id, file))?;
+                itemAttribs.CustomAttributes.insert(key.clone(), val);
+            }
+
+            //Now start to retrieve the concrete attributes that every block must have...
+            if let Some(block) = &json.PlaceableBlock { placeBlocks.push((id, block.clone())) }
+
+            if let Some(tool) = ReadTool(&json).map_err(|e| format!("{} Item {} of id {} in {}", e, name, id, file))? {
+                //every tool starts out with its full durability, and FromID hands each new item a copy of it
+                if let Some(durability) = tool.Durability {
+                    if itemAttribs.CustomAttributes.insert(DURABILITY.to_owned(), State::IntAttribute(durability as i32)).is_some() {
+                        return Err(GenericError::NewBoxed(format!("The attribute '{}' is reserved for tools. Item {} of id {} in {}", DURABILITY, name, id, file)));
                     }
-                    else {
-                        return Err(GenericError::NewBoxed(format!("Error! Item attribute {} for Item {} is not a valid type!\n
-                        The only valid types are...\n
-                        List: {{Rows, Cols}}\n
-                        Integer: int\n
-                        Float: float\n
-                        Bool: boolean\n", pair.0, name)));
-                    }  
                 }
+                itemAttribs.Tool = Some(tool);
             }
 
-            //Now start to retrieve the concrete attributes that every block must have...
-            if let Some(block) = json.get("Placeable Block") { placeBlocks.push((id, block.as_str().unwrap().to_owned())) }
-            if let Some(size) = json.get("Stack Size") { 
-                let stackSize = size.as_u64().unwrap() as u32;
+            if let Some(stackSize) = json.StackSize { 
                 /*
                     Item stacks (the struct) is implemented such that it doesn't actually contain a list of items,
                     but rather a single item with a count attached to it. Because of this, items with unique data
@@ -300,46 +308,41 @@ impl ItemRegistry{
                 if stackSize > 1 && itemAttribs.CustomAttributes.len() > 0 {
                     return Err(GenericError::NewBoxed(
                     format!("Cannot have a stack size greater than 1 for an item of custom attributes. 
-                    Item {} of id {} with stack size {} is invalid", name, id, stackSize)));
+                    Item {} of id {} with stack size {} is invalid. Error in {}", name, id, stackSize, file)));
                 }
                 itemAttribs.StackSize = stackSize; 
             }
-            else if itemAttribs.CustomAttributes.len() > 0 {
-                //the default stack size is 64, so correct this if the item has attributes and a stack size wasn't defined
+            else if itemAttribs.CustomAttributes.len() > 0 || itemAttribs.Tool.is_some() {
+                //the default stack size is 64, so correct this if the item has attributes (or is a tool) and a stack size wasn't defined
                 itemAttribs.StackSize = 1;
             }
-            
-            //Get the crafting recipe for this item if there is one
-            if let Some(recipe) = json.get("Crafting Recipe") {
-                let array = recipe.as_array().unwrap();
-
-                //check if the crafting grid is a square...
-                let sqrt = f32::sqrt(array.len() as f32);
-                if sqrt != f32::floor(sqrt) {
-                    return Err(GenericError::NewBoxed(format!("Error! Crafting grid for item {} is not a square!", name)));
-                }
 
-                //If it is, then add the recipe to the crafting registry
-                craftingRegistry.AddRecipe(
-                        id, 
-                        CraftingRecipe::New(
-                                array.into_iter().map(|x| x.as_u64().unwrap() as u8).collect(),
-                                sqrt as u32,
-                                sqrt as u32
-                            )
-                            .map_err(|e| format!("Crafting recipe creaion for item {} of id {} failed. The error:\n{}", name, id, e.to_string()))? 
-               );
-                   
+
+            if let Some(fuel) = json.FuelValue {
+                if fuel < 0f32 {
+                    return Err(GenericError::NewBoxed(format!("The fuel value of item {} of id {} can't be negative. Error in {}", name, id, file)));
+                }
+                itemAttribs.FuelValue = fuel;
             }
 
-            if let Some(texture) = json.get("Texture") {
-                itemAttribs.Texture = Some(String::from(texture.as_str().unwrap()));
+            if let Some(food) = json.FoodValue {
+                itemAttribs.FoodValue = food;
+                itemAttribs.Saturation = json.Saturation.unwrap_or(0f32);
+                if itemAttribs.Saturation < 0f32 {
+                    return Err(GenericError::NewBoxed(format!("The saturation of item {} of id {} can't be negative. Error in {}", name, id, file)));
+                }
+            }
+            else if json.Saturation.is_some() {
+                return Err(GenericError::NewBoxed(format!("Item {} of id {} has a 'Saturation' but no 'Food Value'. Error in {}", name, id, file)));
             }
+            itemAttribs.Throwable = json.Throwable;
+
+            itemAttribs.Texture = json.Texture.as_ref().map(|tex| ResolveTexture(packs, tex));
             textureCount += 1; //the given texture or null texture
 
             //add the attribute to the attributes map
             self.ItemAttributes.insert(id, itemAttribs);
-            self.ItemBehaviors.insert(id, ItemBehavior::default());
+            self.ItemBehaviors.insert(id, Box::new(DefaultBehavior));
             itemCount += 1;
          }
          
@@ -349,138 +352,64 @@ impl ItemRegistry{
          Ok(placeBlocks)
     }
 
-    fn ValidatePreviousAtlas(&self, dims: u32, textureResolution: u32) -> Result<bool, String> {
-        //If a texture atlas already exsits...
-        if Path::new("./minecraft_gl/assets/data/item/atlas/atlas.png").exists() {
-
-               //The metaData file contains data about the atlas. We want to check if that data is the same as our atlas
-            let file = std::fs::File::open("./minecraft_gl/assets/data/item/atlas/metaData.json")
-            .map_err(|_| format!("Could not open metaData file of path {} for the item atlas.", "./minecraft_gl/assets/data/item/atlas/metaData.json"))?;
-            
-            let json: Value = serde_json::from_reader(BufReader::new(file))
-            .map_err(|_| format!("Could not read json meta data file for the item atlas!"))?;
-
-            //check if the dimensions are different
-            let rows = json.get("Rows").unwrap().as_u64().unwrap() as u32;
-            let cols = json.get("Cols").unwrap().as_u64().unwrap() as u32;
-            if rows != dims || cols != dims {
-                return Ok(false);
-            }
-
-            //check if the resolution is the same
-            let res = json.get("Texture Resolution").unwrap().as_u64().unwrap() as u32;
-            if textureResolution != res {
-                return Ok(false);
-            }
-
-            let items = json.get("Items").unwrap().as_array().unwrap();
-            let strItems: Vec<&str> = items.into_iter().map(|x| x.as_str().unwrap()).collect();
-            for string in self.StringToID.keys() {
-                
-                let mut found = false;
-                for string2 in &strItems {
-                    if string2 == string {
-                        found = true;
-                        break;
-                    }
-                }
-                
-                if ! found {
-                    return Ok(false);
-                }
-
-            }
-            return Ok(true);
-        }
-        Ok(false)
-    }
-
-    pub fn GenerateAtlas(&self, textureResolution: u32, display: &glium::Display) -> Result<TextureAtlas, String> {
-         //attemp to make a square image out of the atlas...
-        let dims = f32::ceil(f32::sqrt(self.NumRegisteredTextures as f32)) as u32;
+    /*
+        Packs the item textures onto an atlas. An item's texture ID is its item ID, so IDs nobody has
+        get an empty texture. Textures bigger than textureResolution are scaled down to fit it. The atlas
+        is only built again when one of its textures (or their order) changed since it was cached
+    */
+    pub fn GenerateAtlas(&self, textureResolution: u32, cache: &AtlasCache, display: &glium::Display) -> Result<TextureAtlas, String> {
+        let slots = self.ItemAttributes.keys().max().map_or(0, |id| *id as u32 + 1);
+        //If a texture doesn't exist, use the null texture     
+        let sources: Vec<AtlasSource> = (0..slots).map(|idx| AtlasSource {
+            Path: self.ItemAttributes.get(&(idx as u8)).map(|attributes| attributes.Texture.as_ref().unwrap_or(&self.NullTexture).as_str()),
+            Animation: None,
+            Sheet: None,
+        }).collect();
+        let key = AtlasCache::Key(&sources, Some(textureResolution), ATLAS_PADDING);
 
         //First check if the atlas already exists...
-        if self.ValidatePreviousAtlas(dims, textureResolution)? {
-            let res = resource::GetImageFromPath("./minecraft_gl/assets/data/item/atlas/atlas.png")
-            .map_err(|e| format!("Could not open the pre-existing item atlas in ./minecraft_gl/assets/item/atlas/atlas.png! The error:\n{}", e.to_string()))?;
-            
-            //open the json again, would be sloppy to return the rows and cols from validatePreviousAtlas()
-            let file = std::fs::File::open("./minecraft_gl/assets/data/item/atlas/metaData.json")
-            .map_err(|_| format!("Could not open metaData file of path {} for the item atlas.", "./minecraft_gl/assets/data/item/atlas/metaData.json"))?;
-            
-            let json: Value = serde_json::from_reader(BufReader::new(file))
-            .map_err(|_| "Could not open block atlas metadata json!")?;
-            let rows = json.get("Rows").unwrap().as_u64().unwrap() as u32;
-            let cols = json.get("Cols").unwrap().as_u64().unwrap() as u32;
-
-            return Ok(TextureAtlas::FromImage(res, rows, cols, textureResolution, display))
+        if let Some((image, layout)) = cache.Load("item", key) {
+            return TextureAtlas::FromLayout(image, layout, display);
         }
 
-        //the master texture atlas image. We will paste texture - sub images onto this
-        let mut img = image::RgbaImage::new(textureResolution * dims, textureResolution * dims);
-        
-        let dir = String::from("./minecraft_gl/assets/data/item/img/");
-        let mut keys: Vec<&u8> = self.ItemAttributes.keys().collect();
-        keys.sort();
-        for id in keys{
-             let idx = *id as u32;
-             //If a texture doesn't exist, use the null texture     
-             let path = if let Some(tex) = &self.ItemAttributes[id].Texture {
-                         let mut d = dir.clone();
-                         d.push_str(tex);
-                         d
-                    }
-                    else {
-                        String::from("./minecraft_gl/assets/data/item/img/nullTexture.png")
-                    };
-
-             //paste the texture onto the atlas
-            let mut texture = resource::GetImageFromPath(&path)
-            .map_err(|e| format!("Error! Could not read image texture for item '{}' of id '{}'. The error:\n{}", self.ItemAttributes[id].Name, idx, e.to_string()))?;
-            texture = image::DynamicImage::ImageRgba8(image::imageops::resize(&mut texture, textureResolution, textureResolution, image::imageops::FilterType::Nearest));
-            let coords = ((idx % dims) * textureResolution, (idx / dims) * textureResolution);
-            image::imageops::overlay(&mut img, &mut texture, coords.0, coords.1);
-  
-         }
+        let mut builder = AtlasBuilder::New(ATLAS_PADDING, Some(textureResolution));
+        for (idx, source) in sources.into_iter().enumerate() {
+            let path = match source.Path {
+                Some(val) => val,
+                None => {
+                    builder.Add(&image::DynamicImage::new_rgba8(1, 1));
+                    continue;
+                }
+            };
 
-        //sSave the image and some metadata about it
-        img.save("./minecraft_gl/assets/data/item/atlas/atlas.png")
-        .map_err(|e| format!("Could not save item atlas png! The error:\n{}", e.to_string()))?;
-        let mut file = File::create("./minecraft_gl/assets/data/item/atlas/metadata.json").map_err(|_| "Could not create item atlas metadata file!")?;
+            let texture = resource::GetImageFromPath(path)
+            .map_err(|e| format!("Error! Could not read image texture for item '{}' of id '{}'. The error:\n{}", self.ItemAttributes[&(idx as u8)].Name, idx, e.to_string()))?;
+            builder.Add(&texture);
+         }
 
-        //generate a list of all the item names
-        let mut items: Vec<&str> = Vec::with_capacity(self.NumRegisteredItems as usize);
-        for attrib in self.StringToID.keys() {
-            items.push(&attrib);
-        }
-         //generate a serialized json string of these values and write it to the file
-        let serialized = serde_json::to_string(&items).expect("Could not serialize block names to json fromat!");
-        let finalStr = format!("{{\n\"Items\": {},\n\"Rows\": {},\n\"Cols\": {},\n\"Texture Resolution\": {}\n}}", serialized, dims, dims, textureResolution);
-        file.write_all(finalStr.as_bytes()).expect("Could not write to item atlas metadata file!");
+        let (img, layout) = builder.Build();
+        cache.Store("item", key, &img, &layout)?;
          
-         Ok(TextureAtlas::FromImage(image::DynamicImage::ImageRgba8(img), dims, dims, textureResolution, display))
-    }
-
-    pub fn OnLeftClickWithName(&self, itemName: &str) {
-        let itemID = self.StringToID[itemName];
-        let behavior =  &self.ItemBehaviors[&itemID];
-        (behavior.OnLeftClick)(self.GetAttributesOfID(itemID))
-    }
-
-    pub fn OnLeftClickWithID(&self, itemID: u8) {
-        let behavior =  &self.ItemBehaviors[&itemID];
-        (behavior.OnLeftClick)(self.GetAttributesOfID(itemID))
+        TextureAtlas::FromLayout(image::DynamicImage::ImageRgba8(img), layout, display)
     }
 
-    pub fn OnRightClickWithName(&self, itemName: &str) -> Option<fn(attributes: &ItemAttribute, Event)> {
-        let itemID = self.StringToID[itemName];
-        let behavior = &self.ItemBehaviors[&itemID];
-        (behavior.OnRightClick)(self.GetAttributesOfID(itemID))
+    //Items without a behavior act like DefaultBehavior
+    pub fn GetBehavior(&self, itemID: u8) -> &dyn ItemBehavior {
+        match self.ItemBehaviors.get(&itemID) {
+            Some(behavior) => behavior.as_ref(),
+            None => &DefaultBehavior
+        }
     }
 
-    pub fn OnRightClickWithID(&self, itemID: u8) -> Option<fn(attributes: &ItemAttribute, Event)> {
-        let behavior = &self.ItemBehaviors[&itemID];
-        (behavior.OnRightClick)(self.GetAttributesOfID(itemID))
+    //Gives an item its behavior. Returns false if no item has the identifier
+    pub fn Bind(&mut self, itemName: &str, behavior: Box<dyn ItemBehavior>) -> bool {
+        match self.StringToID.get(itemName) {
+            Some(id) => {
+                self.ItemBehaviors.insert(*id, behavior);
+                true
+            },
+            None => false
+        }
     }
 
     pub fn GetAttributesOf(&self, itemID: u8) -> &ItemAttribute{
@@ -491,6 +420,7 @@ impl ItemRegistry{
         &self.ItemAttributes[&id]
     }
 
+    //Takes a namespaced identifier (core:dirt), not the display name
     pub fn NameToID(&self, itemName: &str) -> u8{
         //TODO Return Result<u8, &str> saying in the error that ID for {itemName} doesn't exist
         self.StringToID[itemName]
@@ -501,6 +431,41 @@ impl ItemRegistry{
     }
 
     pub fn InitBehaviors(&mut self){
-        
+        ItemBindingFunction(self);
     }
+}
+
+//The tool fields of an item json. They all need a 'Tool Type', and None means the item isn't a tool
+pub fn ReadTool(json: &ItemJson) -> Result<Option<ToolAttribute>, String> {
+    let toolType = match &json.ToolType {
+        Some(val) => val,
+        None => {
+            if json.ToolTier.is_some() || json.Durability.is_some() || json.MiningSpeed.is_some() {
+                return Err(format!("Error! 'Tool Tier', 'Durability' and 'Mining Speed' only apply to items with a 'Tool Type'."));
+            }
+            return Ok(None);
+        }
+    };
+
+    let tool = ToolAttribute {
+        Type: toolType.clone(),
+        Tier: json.ToolTier.unwrap_or(0),
+        MiningSpeed: json.MiningSpeed.unwrap_or(EFFECTIVE_TOOL_SPEED),
+        Durability: json.Durability,
+    };
+    if tool.Type.is_empty() {
+        return Err(format!("Error! The 'Tool Type' can't be empty."));
+    }
+    if tool.MiningSpeed <= 0f32 {
+        return Err(format!("Error! The 'Mining Speed' of a tool has to be greater than 0."));
+    }
+    if tool.Durability == Some(0) {
+        return Err(format!("Error! The 'Durability' of a tool has to be at least 1. Leave it out for a tool that never wears out."));
+    }
+    Ok(Some(tool))
+}
+
+//Finds an item texture in the content packs. Missing textures keep a path so the atlas can report them
+fn ResolveTexture(packs: &ContentPacks, texture: &str) -> String {
+    packs.ResolveFileOrCore(&format!("item/img/{}", texture)).to_string_lossy().into_owned()
 }
\ No newline at end of file
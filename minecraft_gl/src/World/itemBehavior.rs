@@ -1,33 +1,69 @@
-use crate::{World::item::ItemAttribute, Event::event::Event};
+use crate::World::{item::{Item, ItemAttribute, ItemRegistry}, block::{Block, BlockAttribute}};
 
+//What using an item does. The world carries it out, since the item can't see the world
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ItemUse{
+    Nothing,
+    //Place the block in front of the targeted one, using up one item
+    PlaceBlock(Block),
+    //Restore the player's hunger, using up one item. Refused when the player is full
+    Eat{ Food: u32, Saturation: f32 },
+    //Throw one item the way the player is looking
+    Throw,
+}
+
+/*
+    How a type of item reacts to being used. Like block behaviors every hook has a default,
+    and implementations are registered by item identifier in CreateBinding! below
+*/
+#[allow(unused)]
+pub trait ItemBehavior{
+    fn OnLeftClick(&self, attributes: &ItemAttribute, item: &Item){
+
+    }
 
-pub struct ItemBehavior{
-    //TODO add an OnCreate() function that handles initialization of some of the item fields in its attribute map
-    pub OnLeftClick: fn(attributes: &ItemAttribute),
-    pub OnRightClick: fn(attributes: &ItemAttribute) -> Option<fn(attributes: &ItemAttribute, Event)>,
-    pub CustomBehavor: Option<fn(attributes: &ItemAttribute, Event)>, //returned in onRightClick
+    //Right clicking with the item, when the block being looked at (if any) didn't react
+    fn OnUse(&self, attributes: &ItemAttribute, item: &Item) -> ItemUse{
+        DefaultOnUse(attributes)
+    }
+
+    //The item was just used to break a block. Returns true if that used the item up
+    fn OnBlockMined(&self, attributes: &ItemAttribute, item: &mut Item, block: &BlockAttribute) -> bool{
+        DefaultOnBlockMined(attributes, item, block)
+    }
 }
 
-impl Default for ItemBehavior{
-    fn default() -> Self {
-        #[allow(unused)]
-        fn onLeft(attributes: &ItemAttribute){
+//Items without a binding of their own
+pub struct DefaultBehavior;
 
-        }
-        #[allow(unused)]
-        fn onRight(attributes: &ItemAttribute) -> Option<fn(attributes: &ItemAttribute, Event)>{
-            None
-        }
+impl ItemBehavior for DefaultBehavior {}
 
-        Self { OnLeftClick: onLeft, OnRightClick: onRight, CustomBehavor: None }
+//Placeable items place their block, food is eaten and throwable items are thrown, in that order
+pub fn DefaultOnUse(attributes: &ItemAttribute) -> ItemUse {
+    if let Some(block) = attributes.PlaceableBlock {
+        return ItemUse::PlaceBlock(block);
     }
+    if attributes.FoodValue > 0 {
+        return ItemUse::Eat { Food: attributes.FoodValue, Saturation: attributes.Saturation };
+    }
+    if attributes.Throwable {
+        return ItemUse::Throw;
+    }
+    ItemUse::Nothing
+}
+
+//Tools lose a point of durability for every block that took any effort to break
+pub fn DefaultOnBlockMined(attributes: &ItemAttribute, item: &mut Item, block: &BlockAttribute) -> bool {
+    if attributes.Tool.is_none() || block.Toughness <= 0f32 {
+        return false;
+    }
+    item.Damage(1)
 }
 
 
 ////////////////////////////////////////////////////////////////////////////////////////////////////
 // ///////////////////////////////////////////////////////////////////////////////////////////////./
 
-// crate::CreateBinding!(ItemBindingFunction,
-
+crate::CreateBinding!(ItemBindingFunction, ItemRegistry,
 
-// )
\ No newline at end of file
+);
@@ -0,0 +1,450 @@
+use nalgebra as na;
+use super::block::{Block, BlockAttribute};
+use super::item::{Item, ItemRegistry, ItemStack};
+
+/*
+    The rules for breaking and placing blocks. How long a block takes to break comes from its toughness and what
+    the player is holding, and Mining keeps track of how far along the targeted block is. Nothing here touches
+    the chunks, input or the renderer, the world feeds it a target and a delta time every update
+*/
+
+//Seconds it takes to break a block of toughness 1 by hand
+pub const SECONDS_PER_TOUGHNESS: f32 = 1.5;
+//How many times faster a block breaks with its effective tool
+pub const EFFECTIVE_TOOL_SPEED: f32 = 4.0;
+//Number of cracked textures a block goes through while it's being broken
+pub const BREAK_STAGES: u32 = 10;
+//Blocks per second squared that dropped items fall with, and how fast a thrown item leaves the player's hand
+pub const DROP_GRAVITY: f32 = 20.0;
+pub const THROW_SPEED: f32 = 10.0;
+//How far away (in blocks) the player can reach
+pub const PLAYER_REACH: f32 = 5.0;
+//The player's collision box, measured from the camera. Blocks can't be placed where they'd overlap it
+pub const PLAYER_WIDTH: f32 = 0.6;
+pub const PLAYER_HEIGHT: f32 = 1.8;
+pub const PLAYER_EYE_HEIGHT: f32 = 1.62;
+
+/*
+    Seconds it takes to break a block while holding the given item (None is an empty hand).
+    A toughness of 0 breaks instantly, and a negative toughness can't be broken at all (think bedrock)
+*/
+pub fn BreakTime(attribute: &BlockAttribute, held: Option<&ItemStack>, itemRegistry: &ItemRegistry) -> Option<f32> {
+    if attribute.Toughness < 0f32 {
+        return None;
+    }
+
+    let speed = match held.filter(|stack| !stack.IsEmpty()) {
+        Some(stack) if IsEffective(attribute, stack, itemRegistry) => {
+            itemRegistry.GetAttributesOfID(stack.Item.ItemID.ID).Tool.as_ref().map_or(EFFECTIVE_TOOL_SPEED, |tool| tool.MiningSpeed)
+        },
+        _ => 1f32
+    };
+    Some(attribute.Toughness * SECONDS_PER_TOUGHNESS / speed)
+}
+
+/*
+    Whether an item is the right tool for a block. That's the block's EffectiveTool itself, or a tool of
+    the same type with at least its tier (an iron shovel works wherever a wooden shovel does)
+*/
+pub fn IsEffective(attribute: &BlockAttribute, held: &ItemStack, itemRegistry: &ItemRegistry) -> bool {
+    let effective = match &attribute.EffectiveTool {
+        Some(val) => val,
+        None => return false
+    };
+    if held.Item.ItemID == *effective {
+        return true;
+    }
+
+    let needed = &itemRegistry.GetAttributesOfID(effective.ID).Tool;
+    let have = &itemRegistry.GetAttributesOfID(held.Item.ItemID.ID).Tool;
+    match (needed, have) {
+        (Some(needed), Some(have)) => needed.Type == have.Type && have.Tier >= needed.Tier,
+        _ => false
+    }
+}
+
+//What a broken block leaves behind, not counting whatever its containers held
+pub fn BlockDrops(attribute: &BlockAttribute, itemRegistry: &ItemRegistry) -> Vec<ItemStack> {
+    match &attribute.DropItem {
+        Some(item) => vec![ItemStack::New(Item::FromID(item.ID, itemRegistry))],
+        None => Vec::new()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MiningProgress {
+    //Nothing is being mined, or the target can't be broken
+    Idle,
+    //Fraction of the way to breaking the block, from 0 up to 1
+    Mining(f32),
+    //The block is ready to break. It stays that way until Stop() is called, so a break that fails can be retried
+    Broken,
+}
+
+struct MiningTarget {
+    Position: (i32, i32, i32),
+    Block: Block,
+    Progress: f32,
+}
+
+pub struct Mining {
+    Target: Option<MiningTarget>,
+}
+
+impl Mining {
+    pub fn New() -> Self {
+        Self { Target: None }
+    }
+
+    /*
+        Mines the block at a position for another deltaTime seconds. Looking at a different block, or the
+        block being swapped out underneath, starts over. Progress is kept as a fraction, so switching
+        items halfway through only changes how fast the rest goes
+    */
+    pub fn Update(&mut self, position: (i32, i32, i32), block: Block, attribute: &BlockAttribute, held: Option<&ItemStack>, itemRegistry: &ItemRegistry, deltaTime: f32) -> MiningProgress {
+        let breakTime = match BreakTime(attribute, held, itemRegistry) {
+            Some(val) if block != Block::Air() && !attribute.Fluid => val,
+            _ => {
+                self.Stop();
+                return MiningProgress::Idle;
+            }
+        };
+
+        let sameTarget = self.Target.as_ref().map_or(false, |t| t.Position == position && t.Block == block);
+        if !sameTarget {
+            self.Target = Some(MiningTarget { Position: position, Block: block, Progress: 0f32 });
+        }
+
+        let target = self.Target.as_mut().unwrap();
+        target.Progress = if breakTime <= 0f32 { 1f32 } else { f32::min(target.Progress + deltaTime / breakTime, 1f32) };
+
+        if target.Progress >= 1f32 { MiningProgress::Broken } else { MiningProgress::Mining(target.Progress) }
+    }
+
+    pub fn Stop(&mut self) {
+        self.Target = None;
+    }
+
+    pub fn Target(&self) -> Option<(i32, i32, i32)> {
+        self.Target.as_ref().map(|t| t.Position)
+    }
+
+    //Whether the block at this position was the one being mined
+    pub fn IsTarget(&self, position: (i32, i32, i32)) -> bool {
+        self.Target() == Some(position)
+    }
+
+    pub fn Progress(&self) -> f32 {
+        self.Target.as_ref().map_or(0f32, |t| t.Progress)
+    }
+
+    //Which of the BREAK_STAGES cracked textures to draw over the targeted block
+    pub fn BreakStage(&self) -> Option<((i32, i32, i32), u32)> {
+        let target = self.Target.as_ref()?;
+        let stage = ((target.Progress * BREAK_STAGES as f32) as u32).min(BREAK_STAGES - 1);
+        Some((target.Position, stage))
+    }
+}
+
+//Whether a block at this position would overlap the collision box of a player whose eyes are at 'eye'
+pub fn PlayerOverlapsBlock(eye: na::Vector3<f32>, position: (i32, i32, i32)) -> bool {
+    let min = na::Vector3::new(eye.x - PLAYER_WIDTH / 2f32, eye.y - PLAYER_EYE_HEIGHT, eye.z - PLAYER_WIDTH / 2f32);
+    let max = na::Vector3::new(eye.x + PLAYER_WIDTH / 2f32, eye.y - PLAYER_EYE_HEIGHT + PLAYER_HEIGHT, eye.z + PLAYER_WIDTH / 2f32);
+    let block = na::Vector3::new(position.0 as f32, position.1 as f32, position.2 as f32);
+
+    //touching faces don't count, so a player can place the block they're standing on
+    (0..3).all(|axis| min[axis] < block[axis] + 1f32 && max[axis] > block[axis])
+}
+
+/*
+    How the player placed a block, for blocks that turn to face them or sit in the top half of the cell.
+    Directions are face IDs (0 +X, 1 -X, 2 +Y, 3 -Y, 4 +Z, 5 -Z)
+*/
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Placement {
+    //The face of the clicked block the new block was put against, pointing from that block to the new one
+    pub Face: u8,
+    //The horizontal direction the player was looking in
+    pub Facing: u8,
+    //Whether the block was put against the underside of a block, or the top half of a side
+    pub Upper: bool,
+}
+
+pub fn PlacementOf(hit: &BlockHit, eye: na::Vector3<f32>, direction: na::Vector3<f32>) -> Placement {
+    let offset = [hit.Previous.0 - hit.Position.0, hit.Previous.1 - hit.Position.1, hit.Previous.2 - hit.Position.2];
+    let axis = offset.iter().position(|v| *v != 0).unwrap_or(1);
+    let face = (axis * 2) as u8 + if offset[axis] < 0 { 1 } else { 0 };
+
+    let facing = if direction.x.abs() > direction.z.abs() {
+        if direction.x > 0f32 { 0 } else { 1 }
+    } else {
+        if direction.z > 0f32 { 4 } else { 5 }
+    };
+
+    let upper = match face {
+        2 => false,
+        3 => true,
+        //where the ray crosses the face it hit, which is the plane between the two cells
+        _ => {
+            let plane = hit.Position.max(hit.Previous);
+            let plane = [plane.0, plane.1, plane.2][axis] as f32;
+            let along = (plane - eye[axis]) / direction[axis];
+            let y = eye.y + direction.y * along;
+            along.is_finite() && y - hit.Previous.1 as f32 > 0.5f32
+        }
+    };
+    Placement { Face: face, Facing: facing, Upper: upper }
+}
+
+//An item stack lying in the world, like a block drop that didn't fit in the inventory or a thrown item
+#[derive(Clone)]
+pub struct DroppedItem {
+    pub Position: na::Vector3<f32>,
+    pub Velocity: na::Vector3<f32>,
+    pub Stack: ItemStack,
+    //Seconds until it can be picked up, so a thrown item doesn't land straight back in the inventory
+    pub PickupDelay: f32,
+}
+
+impl DroppedItem {
+    pub fn New(position: na::Vector3<f32>, stack: ItemStack) -> Self {
+        Self { Position: position, Velocity: na::Vector3::zeros(), Stack: stack, PickupDelay: 0f32 }
+    }
+
+    /*
+        Moves the item for deltaTime seconds, falling under gravity. It comes to a stop
+        as soon as it would move into a solid block, and stays put until that block is gone
+    */
+    pub fn Update(&mut self, deltaTime: f32, isSolid: impl Fn((i32, i32, i32)) -> bool) {
+        self.PickupDelay = f32::max(self.PickupDelay - deltaTime, 0f32);
+        self.Velocity.y -= DROP_GRAVITY * deltaTime;
+
+        let next = self.Position + self.Velocity * deltaTime;
+        if isSolid((next.x.floor() as i32, next.y.floor() as i32, next.z.floor() as i32)) {
+            self.Velocity = na::Vector3::zeros();
+            return;
+        }
+        self.Position = next;
+    }
+}
+
+//////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+/// //////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+/// //////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+/// //////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+
+//The block a ray hit, and the empty cell the ray passed through right before it (where a placed block would go)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BlockHit {
+    pub Position: (i32, i32, i32),
+    pub Previous: (i32, i32, i32),
+}
+
+/*
+    Walks the cells a ray passes through, in order, until one of them is solid or the ray is longer than reach.
+    The block at (x, y, z) fills the cube from (x, y, z) to (x + 1, y + 1, z + 1).
+    See 'A Fast Voxel Traversal Algorithm for Ray Tracing' by Amanatides and Woo
+*/
+pub fn Raycast(origin: na::Vector3<f32>, direction: na::Vector3<f32>, reach: f32, isSolid: impl Fn((i32, i32, i32)) -> bool) -> Option<BlockHit> {
+    let length = direction.norm();
+    if length == 0f32 || !length.is_finite() {
+        return None;
+    }
+    let dir = direction / length;
+
+    let mut cell = [origin.x.floor() as i32, origin.y.floor() as i32, origin.z.floor() as i32];
+    let mut step = [0i32; 3];
+    //distance along the ray to the next cell boundary on each axis, and between boundaries
+    let mut tMax = [f32::INFINITY; 3];
+    let mut tDelta = [f32::INFINITY; 3];
+
+    for axis in 0..3 {
+        if dir[axis] > 0f32 {
+            step[axis] = 1;
+            tMax[axis] = (cell[axis] as f32 + 1f32 - origin[axis]) / dir[axis];
+            tDelta[axis] = 1f32 / dir[axis];
+        }
+        else if dir[axis] < 0f32 {
+            step[axis] = -1;
+            tMax[axis] = (cell[axis] as f32 - origin[axis]) / dir[axis];
+            tDelta[axis] = -1f32 / dir[axis];
+        }
+    }
+
+    let mut previous = (cell[0], cell[1], cell[2]);
+    if isSolid(previous) {
+        return Some(BlockHit { Position: previous, Previous: previous });
+    }
+
+    loop {
+        let axis = if tMax[0] < tMax[1] {
+            if tMax[0] < tMax[2] { 0 } else { 2 }
+        } else if tMax[1] < tMax[2] { 1 } else { 2 };
+
+        if tMax[axis] > reach {
+            return None;
+        }
+        cell[axis] += step[axis];
+        tMax[axis] += tDelta[axis];
+
+        let current = (cell[0], cell[1], cell[2]);
+        if isSolid(current) {
+            return Some(BlockHit { Position: current, Previous: previous });
+        }
+        previous = current;
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::World::item::{ItemAttribute, ItemID, ToolAttribute};
+
+    const WOOD_SHOVEL: u8 = 1;
+    const IRON_SHOVEL: u8 = 2;
+    const IRON_PICKAXE: u8 = 3;
+    const STICK: u8 = 4;
+    const DIRT: Block = Block { ID: 1 };
+    const STONE: Block = Block { ID: 2 };
+
+    fn Registry() -> ItemRegistry {
+        let mut registry = ItemRegistry::New();
+        let tool = |kind: &str, tier: u32, speed: f32| Some(ToolAttribute { Type: kind.to_owned(), Tier: tier, MiningSpeed: speed, Durability: None });
+        for (id, tool) in [(WOOD_SHOVEL, tool("shovel", 0, 2f32)), (IRON_SHOVEL, tool("shovel", 2, 6f32)), (IRON_PICKAXE, tool("pickaxe", 2, 6f32)), (STICK, None)] {
+            registry.ItemAttributes.insert(id, ItemAttribute { StackSize: 1, Tool: tool, ..Default::default() });
+        }
+        registry
+    }
+
+    fn Holding(id: u8) -> ItemStack {
+        ItemStack::New(Item { ItemID: ItemID::New(id), Attributes: None })
+    }
+
+    fn Dirt(toughness: f32) -> BlockAttribute {
+        BlockAttribute { Toughness: toughness, EffectiveTool: Some(ItemID::New(WOOD_SHOVEL)), ..Default::default() }
+    }
+
+    fn AssertNear(a: Option<f32>, b: f32) {
+        let a = a.unwrap();
+        assert!((a - b).abs() < 1e-5, "{} != {}", a, b);
+    }
+
+    #[test]
+    fn BreakTimes() {
+        let items = Registry();
+        let dirt = Dirt(2f32);
+
+        AssertNear(BreakTime(&dirt, None, &items), 2f32 * SECONDS_PER_TOUGHNESS);
+        AssertNear(BreakTime(&dirt, Some(&ItemStack::Empty()), &items), 2f32 * SECONDS_PER_TOUGHNESS);
+        //the wrong kind of tool is no better than a hand
+        AssertNear(BreakTime(&dirt, Some(&Holding(IRON_PICKAXE)), &items), 2f32 * SECONDS_PER_TOUGHNESS);
+        AssertNear(BreakTime(&dirt, Some(&Holding(STICK)), &items), 2f32 * SECONDS_PER_TOUGHNESS);
+        //the effective tool, and a better one of the same type
+        AssertNear(BreakTime(&dirt, Some(&Holding(WOOD_SHOVEL)), &items), 2f32 * SECONDS_PER_TOUGHNESS / 2f32);
+        AssertNear(BreakTime(&dirt, Some(&Holding(IRON_SHOVEL)), &items), 2f32 * SECONDS_PER_TOUGHNESS / 6f32);
+
+        //an effective tool that isn't a tool at all
+        let stickBlock = BlockAttribute { EffectiveTool: Some(ItemID::New(STICK)), ..Default::default() };
+        AssertNear(BreakTime(&stickBlock, Some(&Holding(STICK)), &items), SECONDS_PER_TOUGHNESS / EFFECTIVE_TOOL_SPEED);
+        //an iron shovel is needed, so a wooden one doesn't count
+        let gravel = BlockAttribute { EffectiveTool: Some(ItemID::New(IRON_SHOVEL)), ..Default::default() };
+        AssertNear(BreakTime(&gravel, Some(&Holding(WOOD_SHOVEL)), &items), SECONDS_PER_TOUGHNESS);
+
+        AssertNear(BreakTime(&Dirt(0f32), None, &items), 0f32);
+        assert!(BreakTime(&Dirt(-1f32), Some(&Holding(IRON_SHOVEL)), &items).is_none());
+    }
+
+    #[test]
+    fn MiningProgresses() {
+        let items = Registry();
+        let dirt = Dirt(1f32);
+        let shovel = Holding(WOOD_SHOVEL);
+        let mut mining = Mining::New();
+        let pos = (4, 10, -2);
+
+        //0.75 seconds by hand
+        assert_eq!(mining.Update(pos, DIRT, &dirt, None, &items, 0.375), MiningProgress::Mining(0.25));
+        assert_eq!(mining.BreakStage(), Some((pos, 2)));
+        //half way through with a shovel, which is twice as fast
+        assert_eq!(mining.Update(pos, DIRT, &dirt, None, &items, 0.375), MiningProgress::Mining(0.5));
+        assert_eq!(mining.Update(pos, DIRT, &dirt, Some(&shovel), &items, 0.1875), MiningProgress::Mining(0.75));
+        assert_eq!(mining.Update(pos, DIRT, &dirt, Some(&shovel), &items, 10f32), MiningProgress::Broken);
+        assert_eq!(mining.Update(pos, DIRT, &dirt, Some(&shovel), &items, 0f32), MiningProgress::Broken);
+        assert_eq!(mining.BreakStage(), Some((pos, BREAK_STAGES - 1)));
+
+        //another position or block starts over
+        assert_eq!(mining.Update((4, 11, -2), DIRT, &dirt, None, &items, 0.375), MiningProgress::Mining(0.25));
+        assert_eq!(mining.Update((4, 11, -2), STONE, &dirt, None, &items, 0.375), MiningProgress::Mining(0.25));
+        assert!(mining.IsTarget((4, 11, -2)) && !mining.IsTarget(pos));
+
+        //air, fluids and unbreakable blocks can't be mined
+        let water = BlockAttribute { Fluid: true, ..Default::default() };
+        for (block, attribute) in [(Block::Air(), &dirt), (STONE, &water), (STONE, &Dirt(-1f32))] {
+            assert_eq!(mining.Update(pos, block, attribute, None, &items, 1f32), MiningProgress::Idle);
+            assert_eq!(mining.Target(), None);
+        }
+
+        assert_eq!(mining.Update(pos, DIRT, &Dirt(0f32), None, &items, 0f32), MiningProgress::Broken);
+        mining.Stop();
+        assert_eq!((mining.Target(), mining.Progress(), mining.BreakStage()), (None, 0f32, None));
+    }
+
+    //Solid blocks at the listed positions
+    fn Blocks(solid: &[(i32, i32, i32)]) -> impl Fn((i32, i32, i32)) -> bool + '_ {
+        move |pos| solid.contains(&pos)
+    }
+
+    #[test]
+    fn RaycastAlongEachAxis() {
+        let origin = na::Vector3::new(0.5f32, 0.5, 0.5);
+        let cases = [
+            ((1f32, 0f32, 0f32), (3, 0, 0), (2, 0, 0)),
+            ((-1f32, 0f32, 0f32), (-3, 0, 0), (-2, 0, 0)),
+            ((0f32, 1f32, 0f32), (0, 3, 0), (0, 2, 0)),
+            ((0f32, -1f32, 0f32), (0, -3, 0), (0, -2, 0)),
+            ((0f32, 0f32, 1f32), (0, 0, 3), (0, 0, 2)),
+            ((0f32, 0f32, -1f32), (0, 0, -3), (0, 0, -2)),
+        ];
+        for ((x, y, z), position, previous) in cases {
+            let solid = [position];
+            let hit = Raycast(origin, na::Vector3::new(x, y, z) * 7f32, PLAYER_REACH, Blocks(&solid));
+            assert_eq!(hit, Some(BlockHit { Position: position, Previous: previous }), "looking along {:?}", (x, y, z));
+            //out of reach
+            assert_eq!(Raycast(origin, na::Vector3::new(x, y, z), 2f32, Blocks(&solid)), None);
+        }
+    }
+
+    #[test]
+    fn RaycastDiagonalsAndNegativeCoordinates() {
+        //looking down and towards -X and -Z from inside a negative cell hits the ground through the side of a block
+        let origin = na::Vector3::new(-2.2f32, 1.7, -5.6);
+        let direction = na::Vector3::new(-1f32, -1f32, -0.5f32);
+        let ground: Vec<(i32, i32, i32)> = (-10..=0).flat_map(|x| (-10..=0).map(move |z| (x, -1, z))).collect();
+        let hit = Raycast(origin, direction, PLAYER_REACH, Blocks(&ground)).unwrap();
+        assert_eq!(hit.Position.1, -1);
+        assert_eq!(hit.Previous.1, 0);
+
+        //the cells a ray walks through are all next to each other
+        let visited = std::cell::RefCell::new(Vec::new());
+        Raycast(origin, direction, PLAYER_REACH, |pos| { visited.borrow_mut().push(pos); false });
+        let visited = visited.into_inner();
+        assert_eq!(visited[0], (-3, 1, -6));
+        for pair in visited.windows(2) {
+            let (a, b) = (pair[0], pair[1]);
+            assert_eq!((a.0 - b.0).abs() + (a.1 - b.1).abs() + (a.2 - b.2).abs(), 1, "{:?} to {:?}", a, b);
+            assert!(b.0 <= a.0 && b.1 <= a.1 && b.2 <= a.2);
+        }
+    }
+
+    #[test]
+    fn RaycastFromInsideABlock() {
+        let origin = na::Vector3::new(-0.5f32, 3.25, 7.9);
+        let solid = [(-1, 3, 7)];
+        let hit = Raycast(origin, na::Vector3::new(0f32, 0f32, -1f32), PLAYER_REACH, Blocks(&solid));
+        assert_eq!(hit, Some(BlockHit { Position: (-1, 3, 7), Previous: (-1, 3, 7) }));
+
+        assert_eq!(Raycast(origin, na::Vector3::zeros(), PLAYER_REACH, Blocks(&solid)), None);
+        assert_eq!(Raycast(origin, na::Vector3::new(f32::NAN, 0f32, 0f32), PLAYER_REACH, Blocks(&solid)), None);
+    }
+}
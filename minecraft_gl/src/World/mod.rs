@@ -1,14 +1,28 @@
 pub mod crafting;
+pub mod smelting;
 pub mod block;
 pub mod blockBehavior;
+pub mod blockModel;
+pub mod colormap;
 pub mod item;
+pub mod inventory;
+pub mod hunger;
+pub mod dayCycle;
+pub mod mining;
 pub mod itemBehavior;
 pub mod chunk;
 pub mod world;
+pub mod chunkScheduler;
+pub mod schema;
+pub mod validate;
+pub mod idMap;
+pub mod contentPack;
 mod biomeGenerator;
-use std::{io::BufReader, collections::HashMap, marker::PhantomData};
+use std::collections::HashMap;
+use serde::{Serialize, Deserialize};
 use self::{item::{ItemRegistry, ItemStack, ItemID}, 
-           block::{BlockRegistry, Block}, crafting::CraftingRegistry, 
+           schema::BiomeBlockJson, idMap::IdMap, contentPack::ContentPacks,
+           block::{BlockRegistry, Block}, crafting::CraftingRegistry, smelting::SmeltingRegistry, 
            biomeGenerator::{BiomeGenerator, Biome, GenerationData, 
            HeightModifier, ForestGenerator}
           };
@@ -54,7 +68,8 @@ impl std::fmt::Display for GenericError {
 /// //////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
 /// //////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
 
-#[derive(Clone)]
+//Saved with the chunk the block is in, so items refer to the world's runtime IDs
+#[derive(Clone, Serialize, Deserialize)]
 pub enum State {
     Container((Vec<ItemStack>, u32, u32)),
     DynamicContainer(Vec<ItemStack>),
@@ -111,25 +126,23 @@ impl State{
 //////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
 //// //////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
 
+/*
+    Generates a function that binds a behavior (a type implementing BlockBehavior or ItemBehavior) to each identifier
+    in a block or item registry. Identifiers the loaded packs don't have are skipped, since packs don't have to ship everything
+*/
 #[macro_export]
 macro_rules! CreateBinding {
     (
-        $bindingFunctionName:ident,
-        $(  
-            pub fn $funcName:ident ($( $arg_name:ident : $arg_ty:ty ),* $(,)?)
-             { $($code:tt)* } 
-        )*
+        $bindingFunctionName:ident, $registry:ty,
+        $(
+            $blockName:literal => $behavior:expr
+        ),* $(,)?
     ) => {
 
-         $(
-            pub fn $funcName ($($arg_name : $arg_ty),*) 
-            { $($code)* }
-            
-         )*
-
-         pub fn $bindingFunctionName (registry: &mut BlockRegistry) {
+         #[allow(unused)]
+         pub fn $bindingFunctionName (registry: &mut $registry) {
             $(
-                $funcName (registry);
+                registry.Bind($blockName, Box::new($behavior));
             )*
          }
     };
@@ -140,9 +153,18 @@ macro_rules! CreateBinding {
 //////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
 //// //////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
 
-pub fn ReadAttributes(blockRegistry: &mut BlockRegistry, itemRegistry: &mut ItemRegistry, craftingRegistry: &mut CraftingRegistry) -> Result<(), Box<dyn std::error::Error>>{
-    let dataBlock = blockRegistry.ReadBlockAttributes()?;
-    let dataItem = itemRegistry.ReadItemAttributes(craftingRegistry)?;
+/*
+    The id map holds the runtime IDs a world has already handed out. New content gets added to it,
+    so the caller should save it with the world afterwards
+*/
+pub fn ReadAttributes(blockRegistry: &mut BlockRegistry, itemRegistry: &mut ItemRegistry, craftingRegistry: &mut CraftingRegistry, 
+                      smeltingRegistry: &mut SmeltingRegistry, packs: &ContentPacks, idMap: &mut IdMap) -> Result<(), Box<dyn std::error::Error>>{
+    let dataBlock = blockRegistry.ReadBlockAttributes(packs, idMap)?;
+    let dataItem = itemRegistry.ReadItemAttributes(packs, idMap)?;
+    craftingRegistry.ReadRecipes(packs, itemRegistry)?;
+    smeltingRegistry.ReadSmeltingRecipes(packs, itemRegistry)?;
+    blockRegistry.InitBehaviors();
+    itemRegistry.InitBehaviors();
 
     for dropItem in dataBlock.0 {
         if itemRegistry.HasItem(dropItem.1.as_str()) {
@@ -152,7 +174,7 @@ pub fn ReadAttributes(blockRegistry: &mut BlockRegistry, itemRegistry: &mut Item
         }
         else {
             return Err(GenericError::NewBoxed(
-            format!("Invalid drop item for block {} of id {}. Item registry has no item called '{}'", 
+            format!("Invalid drop item for block {} of id {}. Item registry has no item with the identifier '{}'", 
             blockRegistry.GetAttributesOfID(dropItem.0).Name, dropItem.0, dropItem.1)));
         }
     }
@@ -165,7 +187,7 @@ pub fn ReadAttributes(blockRegistry: &mut BlockRegistry, itemRegistry: &mut Item
         }
         else {
             return Err(GenericError::NewBoxed(
-            format!("Invalid 'effective tool' item for block {} of id {}. Item registry has no item called '{}'", 
+            format!("Invalid 'effective tool' item for block {} of id {}. Item registry has no item with the identifier '{}'", 
             blockRegistry.GetAttributesOfID(effectiveTool.0).Name, effectiveTool.0, effectiveTool.1)));
         }
     }
@@ -179,7 +201,7 @@ pub fn ReadAttributes(blockRegistry: &mut BlockRegistry, itemRegistry: &mut Item
         }
         else {
             return Err(GenericError::NewBoxed(
-            format!("Invalid placeable block for item {} of id {}. Block registry has no item called '{}'", 
+            format!("Invalid placeable block for item {} of id {}. Block registry has no block with the identifier '{}'", 
             itemRegistry.GetAttributesOfID(placeBlock.0).Name, placeBlock.0, placeBlock.1)));
         }
     }
@@ -191,137 +213,56 @@ pub fn ReadAttributes(blockRegistry: &mut BlockRegistry, itemRegistry: &mut Item
 //////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
 //// //////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
 
-pub fn ReadBiomeGenerators(blockRegistry: &BlockRegistry) -> Result<HashMap<Biome, Box<dyn BiomeGenerator + Send>>, Box<dyn std::error::Error>> {
+pub fn ReadBiomeGenerators(blockRegistry: &BlockRegistry, packs: &ContentPacks, seed: u64) -> Result<HashMap<Biome, Box<dyn BiomeGenerator + Send + Sync>>, Box<dyn std::error::Error>> {
     //TODO implement the capacity for item and block registries
-    let path = std::path::Path::new("./minecraft_gl/assets/data/biome/");
-    let dir = std::fs::read_dir(path)
-    .map_err(|e| format!("Error! Could not find ./minecraft_gl/assets/data/biome/ directory! The error:\n{}", e.to_string()))?;
-    
-    let mut generators: HashMap<Biome, Box<dyn BiomeGenerator + Send>> = HashMap::with_capacity(dir.count());
-
-    for file in std::fs::read_dir(path).unwrap() {
-        let path = file
-        .map_err(|e| format!("Error! Could not retrieve file in ../../assets/data/block/json/ directory! The error:\n{}", e.to_string()))?.path();
-
-        let file = std::fs::File::open(path)
-        .map_err(|e| GenericError::NewBoxed(format!("Error! Could not open file of path in ../../assets/data/block/json/ directory! The error:\n{}", e.to_string())))?;
-
-        let json: serde_json::Value = serde_json::from_reader(BufReader::new(file))?;
-        
-        let mut genData = GenerationData {
-            Crust: Vec::new(),
-            Mantle: None,
-            Core: Block::Air(),
-            Ores: Vec::new(),
-            MantleRange: (0u32, 0u32),
-            HeightLevel: 0u32,
-            SurfaceAmplitude: 0u32,
-            SeaLevel: 0u32,
-            CaveModifier: HeightModifier::default(),
-            CaveCutoff: 0f32,
-            OreCutoff: 0f32,
+    let biomes = packs.ReadBiomes().IntoResult()?;
+    let mut generators: HashMap<Biome, Box<dyn BiomeGenerator + Send + Sync>> = HashMap::with_capacity(biomes.len());
+
+    for (path, json) in biomes {
+        let name = json.Name.as_str();
+        let file = path.display();
+
+        let genData = GenerationData {
+            Crust: ReadBlockList(&json.Crust, &file, "Crust", blockRegistry)?,
+            Mantle: match &json.Mantle {
+                Some(mantle) => Some(ReadBlock(mantle, &file, "Mantle", blockRegistry)?),
+                None => None
+            },
+            Core: ReadBlock(&json.Core, &file, "Core", blockRegistry)?,
+            Ores: ReadBlockList(&json.Ores.Blocks, &file, "Ores", blockRegistry)?,
+            MantleRange: (json.MantleMinLength, json.MantleMaxLength),
+            HeightLevel: json.HeightLevel,
+            SurfaceAmplitude: json.SurfaceAmplitude,
+            SeaLevel: json.SeaLevel,
+            Temperature: json.Temperature,
+            Humidity: json.Humidity,
+            CaveModifier: ReadHeightModifier(json.Cave.MinHeight, json.Cave.MaxHeight, json.Cave.Decay, 
+                                             json.Cave.Constant, json.Cave.Speed, &file, "Cave")?,
+            CaveCutoff: json.Cave.NoiseCutoff,
+            OreCutoff: json.Ores.NoiseCutoff,
+            Water: ReadBlock(&json.Water, &file, "Water", blockRegistry)?,
+            Log: ReadBlock(&json.Log, &file, "Log", blockRegistry)?,
+            Leaves: ReadBlock(&json.Leaves, &file, "Leaves", blockRegistry)?,
+            Flower: ReadBlock(&json.Flower, &file, "Flower", blockRegistry)?,
+            TallGrass: ReadBlock(&json.TallGrass, &file, "Tall Grass", blockRegistry)?,
         };
 
-        let name = json["Name"].as_str().unwrap();
-
-        if let Some(val) = json.get("Cave") {
-            if let Some(c) = val.get("Noise Cutoff") {
-                genData.CaveCutoff = c.as_f64().unwrap() as f32;
-            } else {
-                return Err(GenericError::NewBoxed(
-                    format!("The {} biome json has no property 'Noise Cuftoff' inside of 'Cave'. Fix the json file!", name)));
-            }
-            genData.CaveModifier = ReadHeightModifier(val, name, "Cave")?;
-        }  else {
-            return Err(GenericError::NewBoxed(
-                format!("The {} biome json has no property 'cave'. Fix the json file!", name)));
-        }
-
-        if let Some(val) = json.get("Height Level") {
-            genData.HeightLevel = val.as_u64().unwrap() as u32;
-        }  else {
-            return Err(GenericError::NewBoxed(
-                format!("The {} biome json has no property 'Height Level'. Fix the json file!", name)));
-        }
-
-        if let Some(val) = json.get("Surface Amplitude") {
-            genData.SurfaceAmplitude = val.as_u64().unwrap() as u32;
-        }  else {
-            return Err(GenericError::NewBoxed(
-                format!("The {} biome json has no property 'Surface Amplitude'. Fix the json file!", name)));
-        }
-
-        if let Some(val) = json.get("Sea Level") {
-            genData.SeaLevel = val.as_u64().unwrap() as u32;
-        }  else {
-            return Err(GenericError::NewBoxed(
-                format!("The {} biome json has no property 'Sea Level'. Fix the json file!", name)));
-        }
-
-        //Read the block data
-        if let Some(val) = json.get("Crust") {
-            genData.Crust = ReadBlockList(val, name, "Blocks", blockRegistry)?;
-        }  else {
-            return Err(GenericError::NewBoxed(
-                format!("The {} biome json has no property 'Blocks'. Fix the json file!", name)));
-        }
-
-        if let Some(val) = json.get("Mantle") {
-            genData.Mantle = Some(Block { ID: blockRegistry.NameToID(val.as_str().unwrap()).expect(
-                format!("could not find {} in block registry", val.as_str().unwrap()).as_ref()
-            ) });
-        }
-
-        if let Some(val) = json.get("Mantle Min Length") {
-            genData.MantleRange.0 = val.as_u64().unwrap() as u32;
-        }  else {
-            return Err(GenericError::NewBoxed(
-                format!("The {} biome json has no property 'Mantle Min Length'. Fix the json file!", name)));
-        }
-
-        if let Some(val) = json.get("Mantle Max Length") {
-            genData.MantleRange.1 = val.as_u64().unwrap() as u32;
-        }  else {
+        if genData.MantleRange.0 >= genData.MantleRange.1 {
             return Err(GenericError::NewBoxed(
-                format!("The {} biome json has no property 'Mantle Max Length'. Fix the json file!", name)));
+                format!("The 'Mantle Min Length' must be less than the 'Mantle Max Length'. Error in {}", file)));
         }
-
-        if let Some(val) = json.get("Core") {
-            genData.Core = Block { ID: blockRegistry.NameToID(val.as_str().unwrap()).expect(
-                format!("could not find {} in block registry", val.as_str().unwrap()).as_ref()
-            ) };
-        }  else {
+        if !(0f32..=1f32).contains(&genData.Temperature) || !(0f32..=1f32).contains(&genData.Humidity) {
             return Err(GenericError::NewBoxed(
-                format!("The {} biome json has no property 'Core'. Fix the json file!", name)));
+                format!("The 'Temperature' and 'Humidity' must be between 0 and 1. Error in {}", file)));
         }
-        //Read the ore data
-        if let Some(val) = json.get("Ores") {
-            if let Some(v) = val.get("Noise Cutoff") {
-                genData.OreCutoff = v.as_f64().unwrap() as f32;
-            } else {
-                return Err(GenericError::NewBoxed(
-                    format!("The {} biome json has no property 'Noise Cuftoff' inside of 'Ores'. Fix the json file!", name)));
-            }
-
-            if let Some(v) = val.get("Blocks") {
-                 genData.Ores = ReadBlockList(v, name, "Ore Blocks", blockRegistry)?;
-            } else {
-                return Err(GenericError::NewBoxed(
-                    format!("The {} biome json has no property 'Blocks' inside of 'Ores'. Fix the json file!", name)));
-            }
-        }  else {
-            return Err(GenericError::NewBoxed(
-                format!("The {} biome json has no property 'Blocks'. Fix the json file!", name)));
-        }
-
 
         match name {
             "Forest" => {
-                generators.insert(Biome::Forest, Box::new(ForestGenerator::New(genData)));
+                generators.insert(Biome::Forest, Box::new(ForestGenerator::New(genData, seed)));
             },
             _ => {
                 return Err(GenericError::NewBoxed(
-                    format!("The {} biome is not yet supported!", name)));
+                    format!("The {} biome is not yet supported! Error in {}", name, file)));
             }
         }
 
@@ -334,66 +275,44 @@ pub fn ReadBiomeGenerators(blockRegistry: &BlockRegistry) -> Result<HashMap<Biom
     Ok(generators)
 }
 
-fn ReadHeightModifier(json: &serde_json::Value, biomeName: &str, propertyName: &str) -> Result<HeightModifier, GenericError> {
+fn ReadHeightModifier(minHeight: f32, maxHeight: f32, decay: bool, constant: bool, speed: Option<f32>, 
+                      file: &std::path::Display, propertyName: &str) -> Result<HeightModifier, GenericError> {
     let mut modif = HeightModifier::default();
+    modif.MinHeight = minHeight;
+    modif.MaxHeight = maxHeight;
+    modif.Decay = decay;
+    modif.Constant = constant;
 
-    if let Some(val) = json.get("Min Height") {
-        modif.MinHeight = val.as_f64().unwrap() as f32;
-    } else {
-        return Err(GenericError::New(
-            format!("The {} object has no 'Min Height' property. Error occursed in {} biome json file", propertyName, biomeName)));
-    }
-
-    if let Some(val) = json.get("Max Height") {
-        modif.MaxHeight = val.as_f64().unwrap() as f32;
-    } else {
+    if modif.Decay && modif.Constant {
         return Err(GenericError::New(
-            format!("The {} object has no 'Max Height' property. Error occursed in {} biome json file", propertyName, biomeName)));
+            format!("The {} object cannot be both constant and decaying. Error in {}", propertyName, file)));
     }
 
-    if let Some(val) = json.get("Decay") {
-        modif.Decay = val.as_bool().unwrap();
-    } 
-
-    if let Some(val) = json.get("Constant") {
-        modif.Constant = val.as_bool().unwrap();
-        if modif.Decay && modif.Constant {
-            return Err(GenericError::New(
-                format!("The {} object cannot be both constant and decaying. Error occursed in {} biome json file", propertyName, biomeName)));
-        }
-    } 
-
-    if let Some(val) = json.get("Speed") {
-        modif.Speed = val.as_f64().unwrap() as f32;
+    if let Some(val) = speed {
+        modif.Speed = val;
         if modif.Constant {
             return Err(GenericError::New(
-                format!("The {} object cannot be constant and have a defined speed. Error occursed in {} biome json file", propertyName, biomeName)));
+                format!("The {} object cannot be constant and have a defined speed. Error in {}", propertyName, file)));
         }
     } 
 
     Ok(modif)
 }
 
-fn ReadBlockList(json: &serde_json::Value, biomeName: &str, propertyName: &str, blockRegistry: &BlockRegistry) -> Result<Vec<(Block, HeightModifier)>, GenericError>{
-    let arr = json.as_array().unwrap();
-    let mut vec: Vec<(Block, HeightModifier)> = Vec::with_capacity(arr.len());
-
-    for val in arr {
-        println!("VALUES\n\n{}", val.to_string());
-        if let Some(v) = val.get("Name") {
-            let name = v.as_str().unwrap();
-            let id = blockRegistry.NameToID(name).expect(
-                format!("could not find {} in block registry", name).as_ref()
-            );
-            println!("ABout to write push value my little kitten :3");
-            vec.push((Block {ID: id}, ReadHeightModifier(val, biomeName, propertyName)?));
-        }
-        else {
-            return Err(GenericError::New(format!("
-            Block object inside of the '{}' object must have the property 
-            'Name'. Error occured in {} biome json file", propertyName, biomeName)));
-        }
+fn ReadBlock(name: &str, file: &std::path::Display, propertyName: &str, blockRegistry: &BlockRegistry) -> Result<Block, GenericError> {
+    match blockRegistry.NameToID(name) {
+        Some(id) => Ok(Block { ID: id }),
+        None => Err(GenericError::New(
+            format!("The '{}' property refers to the block '{}', which is not in the block registry. Error in {}", propertyName, name, file)))
+    }
+}
+
+fn ReadBlockList(blocks: &Vec<BiomeBlockJson>, file: &std::path::Display, propertyName: &str, blockRegistry: &BlockRegistry) -> Result<Vec<(Block, HeightModifier)>, GenericError>{
+    let mut vec: Vec<(Block, HeightModifier)> = Vec::with_capacity(blocks.len());
+
+    for val in blocks {
+        let block = ReadBlock(&val.Name, file, propertyName, blockRegistry)?;
+        vec.push((block, ReadHeightModifier(val.MinHeight, val.MaxHeight, val.Decay, val.Constant, val.Speed, file, propertyName)?));
     }
-    println!("Heres the data my little kitten :3 {:?}", vec);
     Ok(vec)
-}
\ No newline at end of file
+}
@@ -0,0 +1,552 @@
+use std::collections::HashMap;
+use std::io::BufReader;
+use std::path::Path;
+use serde::{Deserialize, de::DeserializeOwned};
+use schemars::JsonSchema;
+use crate::Util::atlas::TextureAnimation;
+use super::{State, item::ItemStack, contentPack::PackManifest, colormap::Tint};
+
+/*
+    Typed layouts of the block, item and biome json files. Unknown keys are an error instead of
+    being silently dropped, so a typo like "speed" instead of "Speed" gets caught when the file is read.
+    The same structs generate a JSON Schema (see WriteJsonSchemas) so editors can validate the files too
+*/
+
+#[derive(Deserialize, JsonSchema, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct BlockJson {
+    pub Enabled: bool,
+    //Display name
+    pub Name: String,
+    //Namespaced identifier (core:grass). Other files refer to the block by this, the numeric ID is assigned per world
+    pub ID: String,
+
+    //Decoration blocks (tall grass, flowers) can be placed where the player stands. Without a model of their own they're drawn with cross.json
+    #[serde(default)]
+    pub Decor: bool,
+    //Fluids (water) can't be targeted, mined or placed against, and things fall through them
+    #[serde(default)]
+    pub Fluid: bool,
+    pub Texture: Option<String>,
+    //One texture per face, in the order +X, -X, +Y, -Y, +Z, -Z
+    pub Textures: Option<[FaceTextureJson; 6]>,
+    //A model file in block/model (like slab.json) to draw the block with instead of a cube
+    pub Model: Option<String>,
+    //Models drawn depending on the block's state. Every entry whose 'When' matches is drawn, along with 'Model'
+    #[serde(default)]
+    pub Models: Vec<ModelVariantJson>,
+    //Texture file names for the texture keys the models use. Keys that aren't here use 'Texture'
+    #[serde(rename = "Model Textures", default)]
+    pub ModelTextures: HashMap<String, String>,
+    //Colours the block by the climate it's in ("grass", "foliage" or "water"), see colormap.rs
+    pub Tint: Option<Tint>,
+    /*
+        Textures of this block (by file name, like in Textures) that are a vertical strip of animation frames.
+        Every face using one of them plays through its frames, like water and lava
+    */
+    #[serde(default)]
+    pub Animations: HashMap<String, AnimationJson>,
+
+    #[serde(default = "DefaultToughness")]
+    pub Toughness: f32,
+    #[serde(default = "DefaultFriction")]
+    pub Friction: f32,
+    pub DropItem: Option<String>,
+    pub EffectiveTool: Option<String>,
+
+    #[serde(default)]
+    pub Attributes: HashMap<String, AttributeJson>,
+}
+
+fn DefaultToughness() -> f32 { 1f32 }
+fn DefaultFriction() -> f32 { 1f32 }
+
+impl BlockJson {
+    //File names of every texture the block uses, in the order they're first mentioned
+    pub fn TextureNames(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.Texture.iter().map(|t| t.as_str()).collect();
+        let faces = self.Textures.iter().flatten().flat_map(|face| face.TextureNames());
+        let mut keys: Vec<&String> = self.ModelTextures.keys().collect();
+        keys.sort();
+        for name in faces.chain(keys.into_iter().map(|k| self.ModelTextures[k].as_str())) {
+            if !names.contains(&name) {
+                names.push(name);
+            }
+        }
+        names
+    }
+
+    //Whether the block is drawn with models instead of as a cube
+    pub fn HasModel(&self) -> bool {
+        self.Decor || self.Model.is_some() || !self.Models.is_empty()
+    }
+}
+
+#[derive(Deserialize, JsonSchema, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct ModelVariantJson {
+    //Block state values that all have to match. A missing state counts as 0 or false
+    #[serde(default)]
+    pub When: HashMap<String, StateValueJson>,
+    pub Model: String,
+    //Turns the model around the vertical axis by multiples of 90 degrees, +X towards +Z
+    #[serde(default)]
+    pub Rotation: u32,
+}
+
+#[derive(Deserialize, JsonSchema, Debug, Clone, Copy, PartialEq)]
+#[serde(untagged)]
+pub enum StateValueJson {
+    Bool(bool),
+    Int(i32),
+}
+
+impl StateValueJson {
+    pub fn Matches(&self, state: Option<&State>) -> bool {
+        match (self, state) {
+            (StateValueJson::Bool(val), Some(State::BoolAttribute(other))) => val == other,
+            (StateValueJson::Int(val), Some(State::IntAttribute(other))) => val == other,
+            (StateValueJson::Bool(val), None) => !val,
+            (StateValueJson::Int(val), None) => *val == 0,
+            _ => false
+        }
+    }
+}
+
+/*
+    A block model, in block/model. It's a list of boxes measured in 16ths of a block,
+    so a bottom slab is one element from [0, 0, 0] to [16, 8, 16]
+*/
+#[derive(Deserialize, JsonSchema, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct ModelJson {
+    pub Elements: Vec<ElementJson>,
+}
+
+#[derive(Deserialize, JsonSchema, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct ElementJson {
+    pub From: [u32; 3],
+    pub To: [u32; 3],
+    /*
+        Faces of the box by direction ("+X", "-X", "+Y", "-Y", "+Z", "-Z"). Directions that aren't here
+        aren't drawn. "Cross" draws the two diagonal planes through the box instead, like plants have
+    */
+    pub Faces: HashMap<String, ModelFaceJson>,
+}
+
+#[derive(Deserialize, JsonSchema, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct ModelFaceJson {
+    //A key into the block's 'Model Textures'
+    pub Texture: String,
+    //[u1, v1, u2, v2] of the texture in 16ths. Without it the face shows the part of the texture a full cube would there
+    pub UV: Option<[u32; 4]>,
+    #[serde(default)]
+    pub Rotation: u32,
+    //Faces on the edge of the block are hidden when a full block covers them, unless this is false
+    #[serde(default = "DefaultCull")]
+    pub Cull: bool,
+    #[serde(default = "DefaultTinted")]
+    pub Tint: bool,
+}
+
+fn DefaultCull() -> bool { true }
+
+/*
+    What one face of a block shows. Either just the texture's file name, or an object with exactly one of
+    'Texture' (turned by 'Rotation' degrees clockwise), 'Variants' or 'Connected'
+*/
+#[derive(Deserialize, JsonSchema, Debug, Clone)]
+#[serde(untagged)]
+pub enum FaceTextureJson {
+    Name(String),
+    Face(FaceJson),
+}
+
+#[derive(Deserialize, JsonSchema, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct FaceJson {
+    pub Texture: Option<String>,
+    #[serde(default)]
+    pub Rotation: u32,
+    //One of these is picked for every block, by its position, so the same block always looks the same
+    pub Variants: Option<Vec<VariantJson>>,
+    /*
+        A 4x4 sheet of tiles for glass-like blocks that join up with the same block next to them.
+        Tile n (left to right, then top to bottom) is used when the bits of n say which sides of the face
+        have the same block next to them: 1 up, 2 right, 4 down and 8 left
+    */
+    pub Connected: Option<String>,
+    //Whether the block's 'Tint' colours this face, like the top of a grass block but not its sides
+    #[serde(default = "DefaultTinted")]
+    pub Tint: bool,
+}
+
+fn DefaultTinted() -> bool { true }
+
+#[derive(Deserialize, JsonSchema, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct VariantJson {
+    pub Texture: String,
+    //How likely the variant is compared to the others. A variant of weight 2 shows up twice as often as one of weight 1
+    #[serde(default = "DefaultWeight")]
+    pub Weight: u32,
+    #[serde(default)]
+    pub Rotation: u32,
+}
+
+fn DefaultWeight() -> u32 { 1 }
+
+impl FaceTextureJson {
+    pub fn TextureNames(&self) -> Vec<&str> {
+        match self {
+            FaceTextureJson::Name(name) => vec![name.as_str()],
+            FaceTextureJson::Face(face) => {
+                let variants = face.Variants.iter().flatten().map(|v| v.Texture.as_str());
+                face.Texture.iter().chain(face.Connected.iter()).map(|t| t.as_str()).chain(variants).collect()
+            }
+        }
+    }
+
+    pub fn IsTinted(&self) -> bool {
+        !matches!(self, FaceTextureJson::Face(FaceJson { Tint: false, .. }))
+    }
+
+    //Whether a texture is used as a connected texture sheet on this face
+    pub fn IsConnected(&self, texture: &str) -> bool {
+        matches!(self, FaceTextureJson::Face(FaceJson { Connected: Some(name), .. }) if name == texture)
+    }
+
+    //Checks everything that doesn't need the texture files themselves
+    pub fn Validate(&self) -> Result<(), String> {
+        let face = match self {
+            FaceTextureJson::Name(_) => return Ok(()),
+            FaceTextureJson::Face(face) => face
+        };
+
+        let given = face.Texture.is_some() as u32 + face.Variants.is_some() as u32 + face.Connected.is_some() as u32;
+        if given != 1 {
+            return Err(format!("a face needs exactly one of 'Texture', 'Variants' or 'Connected'"));
+        }
+        if face.Texture.is_none() && face.Rotation != 0 {
+            return Err(format!("'Rotation' only goes with 'Texture'. Variants have a 'Rotation' of their own"));
+        }
+        QuarterTurns(face.Rotation)?;
+
+        if let Some(variants) = &face.Variants {
+            if variants.is_empty() {
+                return Err(format!("'Variants' can't be empty"));
+            }
+            for variant in variants {
+                if variant.Weight == 0 {
+                    return Err(format!("the variant {} has a 'Weight' of 0 and would never show up", variant.Texture));
+                }
+                QuarterTurns(variant.Rotation)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+//A rotation in degrees as clockwise quarter turns (0-3)
+pub fn QuarterTurns(degrees: u32) -> Result<u8, String> {
+    if degrees % 90 != 0 {
+        return Err(format!("a rotation has to be a multiple of 90 degrees, not {}", degrees));
+    }
+    Ok(((degrees / 90) % 4) as u8)
+}
+
+#[derive(Deserialize, JsonSchema, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct AnimationJson {
+    //Without it the frames are taken to be square, so a 16x64 strip is 4 frames
+    pub Frames: Option<u32>,
+    //Seconds each frame shows for
+    #[serde(rename = "Frame Time", default = "DefaultFrameTime")]
+    pub FrameTime: f32,
+    //Fade each frame into the next instead of cutting between them
+    #[serde(default)]
+    pub Interpolate: bool,
+}
+
+fn DefaultFrameTime() -> f32 { 0.1f32 }
+
+impl AnimationJson {
+    pub fn ToAnimation(&self) -> Result<TextureAnimation, String> {
+        if self.Frames == Some(0) {
+            return Err(format!("'Frames' has to be at least 1"));
+        }
+        if !(self.FrameTime > 0f32) || !self.FrameTime.is_finite() {
+            return Err(format!("'Frame Time' has to be above 0, not {}", self.FrameTime));
+        }
+        Ok(TextureAnimation { Frames: self.Frames, FrameTime: self.FrameTime, Interpolate: self.Interpolate })
+    }
+}
+
+#[derive(Deserialize, JsonSchema, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct ItemJson {
+    pub Enabled: bool,
+    pub Name: String,
+    pub ID: String,
+
+    pub Texture: Option<String>,
+    #[serde(rename = "Placeable Block")]
+    pub PlaceableBlock: Option<String>,
+    #[serde(rename = "Stack Size")]
+    pub StackSize: Option<u32>,
+    //Seconds a furnace burns for on one of these. Items without it can't be burned
+    #[serde(rename = "Fuel Value")]
+    pub FuelValue: Option<f32>,
+
+    /*
+        Tools break blocks faster when the block's EffectiveTool is a tool of the same type and a tier no higher
+        than theirs. A tool without a durability never wears out
+    */
+    #[serde(rename = "Tool Type")]
+    pub ToolType: Option<String>,
+    #[serde(rename = "Tool Tier")]
+    pub ToolTier: Option<u32>,
+    pub Durability: Option<u32>,
+    #[serde(rename = "Mining Speed")]
+    pub MiningSpeed: Option<f32>,
+
+    //Hunger points eating one restores, and how much saturation comes with them
+    #[serde(rename = "Food Value")]
+    pub FoodValue: Option<u32>,
+    pub Saturation: Option<f32>,
+    #[serde(default)]
+    pub Throwable: bool,
+
+    #[serde(default)]
+    pub Attributes: HashMap<String, AttributeJson>,
+}
+
+/*
+    Shaped recipes are a pattern of rows, where every character is a key into 'Key' and a space is an empty slot.
+    The pattern can be any size up to the crafting grid and is matched wherever it sits in the grid.
+    Shapeless recipes only care that the grid holds exactly these ingredients
+*/
+#[derive(Deserialize, JsonSchema, Debug)]
+#[serde(tag = "Type", deny_unknown_fields)]
+pub enum RecipeJson {
+    Shaped {
+        ID: String,
+        Pattern: Vec<String>,
+        Key: HashMap<char, String>,
+        Result: String,
+        #[serde(default = "DefaultCount")]
+        Count: u32,
+    },
+    Shapeless {
+        ID: String,
+        Ingredients: Vec<String>,
+        Result: String,
+        #[serde(default = "DefaultCount")]
+        Count: u32,
+    },
+}
+
+fn DefaultCount() -> u32 { 1 }
+
+impl RecipeJson {
+    pub fn ID(&self) -> &str {
+        match self { RecipeJson::Shaped { ID, .. } | RecipeJson::Shapeless { ID, .. } => ID }
+    }
+
+    pub fn Result(&self) -> &str {
+        match self { RecipeJson::Shaped { Result, .. } | RecipeJson::Shapeless { Result, .. } => Result }
+    }
+
+    pub fn Count(&self) -> u32 {
+        match self { RecipeJson::Shaped { Count, .. } | RecipeJson::Shapeless { Count, .. } => *Count }
+    }
+}
+
+//A furnace turns one 'Input' into 'Count' of 'Result' every 'Duration' seconds while it has fuel
+#[derive(Deserialize, JsonSchema, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct SmeltingJson {
+    pub ID: String,
+    pub Input: String,
+    pub Result: String,
+    #[serde(default = "DefaultCount")]
+    pub Count: u32,
+    pub Duration: f32,
+    #[serde(default)]
+    pub Experience: f32,
+}
+
+/*
+    A custom attribute is either a state type without a default value ("Int", "Float", "Bool", "Dynamic Container"),
+    a container of fixed dimensions, or a default value whose type is inferred
+*/
+#[derive(Deserialize, JsonSchema, Debug)]
+#[serde(untagged)]
+pub enum AttributeJson {
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    StateType(String),
+    Container(ContainerJson),
+}
+
+#[derive(Deserialize, JsonSchema, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct ContainerJson {
+    pub Rows: u32,
+    pub Cols: u32,
+}
+
+impl AttributeJson {
+    pub fn ToState(&self) -> Result<State, String> {
+        Ok(match self {
+            AttributeJson::Bool(val) => State::BoolAttribute(*val),
+            AttributeJson::Int(val) => State::IntAttribute(*val as i32),
+            AttributeJson::Float(val) => State::FloatAttribute(*val as f32),
+            AttributeJson::StateType(name) => State::StateType(name)?,
+            AttributeJson::Container(ContainerJson { Rows, Cols }) => {
+                //every slot is there from the start, empty ones hold ItemStack::Empty()
+                let vec: Vec<ItemStack> = vec![ItemStack::Empty(); (Rows * Cols) as usize];
+                State::Container((vec, *Rows, *Cols))
+            }
+        })
+    }
+}
+
+#[derive(Deserialize, JsonSchema, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct BiomeJson {
+    pub Name: String,
+    pub Cave: CaveJson,
+
+    #[serde(rename = "Height Level")]
+    pub HeightLevel: u32,
+    #[serde(rename = "Surface Amplitude")]
+    pub SurfaceAmplitude: u32,
+    #[serde(rename = "Sea Level")]
+    pub SeaLevel: u32,
+    //The climate (from 0 to 1) tinted blocks take their colour from. It drifts a little from place to place
+    #[serde(default = "DefaultClimate")]
+    pub Temperature: f32,
+    #[serde(default = "DefaultClimate")]
+    pub Humidity: f32,
+
+    pub Crust: Vec<BiomeBlockJson>,
+    pub Mantle: Option<String>,
+    #[serde(rename = "Mantle Min Length")]
+    pub MantleMinLength: u32,
+    #[serde(rename = "Mantle Max Length")]
+    pub MantleMaxLength: u32,
+    pub Core: String,
+    pub Ores: OresJson,
+
+    pub Water: String,
+    pub Log: String,
+    pub Leaves: String,
+    pub Flower: String,
+    #[serde(rename = "Tall Grass")]
+    pub TallGrass: String,
+}
+
+fn DefaultClimate() -> f32 { 0.5 }
+
+//serde can't combine flatten with deny_unknown_fields, so the height modifier fields are repeated here
+#[derive(Deserialize, JsonSchema, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct CaveJson {
+    #[serde(rename = "Noise Cutoff")]
+    pub NoiseCutoff: f32,
+    #[serde(rename = "Min Height")]
+    pub MinHeight: f32,
+    #[serde(rename = "Max Height")]
+    pub MaxHeight: f32,
+    #[serde(default)]
+    pub Decay: bool,
+    #[serde(default)]
+    pub Constant: bool,
+    pub Speed: Option<f32>,
+}
+
+#[derive(Deserialize, JsonSchema, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct BiomeBlockJson {
+    pub Name: String,
+    #[serde(rename = "Min Height")]
+    pub MinHeight: f32,
+    #[serde(rename = "Max Height")]
+    pub MaxHeight: f32,
+    #[serde(default)]
+    pub Decay: bool,
+    #[serde(default)]
+    pub Constant: bool,
+    pub Speed: Option<f32>,
+}
+
+#[derive(Deserialize, JsonSchema, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct OresJson {
+    #[serde(rename = "Noise Cutoff")]
+    pub NoiseCutoff: f32,
+    pub Blocks: Vec<BiomeBlockJson>,
+}
+
+//////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+//// //////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+//////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+//// //////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+
+//Reads a json file into one of the schemas. Errors name the file and the JSON pointer of the offending value
+pub fn ReadJsonFile<T: DeserializeOwned>(path: &Path) -> Result<T, String> {
+    let file = std::fs::File::open(path)
+    .map_err(|e| format!("Error! Could not open file {}! The error:\n{}", path.display(), e.to_string()))?;
+
+    let mut deserializer = serde_json::Deserializer::from_reader(BufReader::new(file));
+    serde_path_to_error::deserialize(&mut deserializer).map_err(|e| {
+        format!("Error! Invalid json in {} at '{}'. The error:\n{}", path.display(), JsonPointer(e.path()), e.inner())
+    })
+}
+
+//Converts the path serde_path_to_error tracks into a JSON pointer (RFC 6901)
+fn JsonPointer(path: &serde_path_to_error::Path) -> String {
+    use serde_path_to_error::Segment;
+
+    let mut pointer = String::new();
+    for segment in path.iter() {
+        pointer.push('/');
+        match segment {
+            Segment::Seq { index } => pointer.push_str(&index.to_string()),
+            Segment::Map { key } => pointer.push_str(&key.replace('~', "~0").replace('/', "~1")),
+            Segment::Enum { variant } => pointer.push_str(variant),
+            Segment::Unknown => pointer.push('?'),
+        }
+    }
+
+    if pointer.is_empty() { String::from("/") } else { pointer }
+}
+
+//Writes block.schema.json, item.schema.json, biome.schema.json, recipe.schema.json, smelting.schema.json, model.schema.json and pack.schema.json into the given directory
+pub fn WriteJsonSchemas(dir: &Path) -> Result<(), String> {
+    std::fs::create_dir_all(dir)
+    .map_err(|e| format!("Error! Could not create schema directory {}! The error:\n{}", dir.display(), e.to_string()))?;
+
+    let schemas = [
+        ("block.schema.json", schemars::schema_for!(BlockJson)),
+        ("item.schema.json", schemars::schema_for!(ItemJson)),
+        ("biome.schema.json", schemars::schema_for!(BiomeJson)),
+        ("recipe.schema.json", schemars::schema_for!(RecipeJson)),
+        ("smelting.schema.json", schemars::schema_for!(SmeltingJson)),
+        ("model.schema.json", schemars::schema_for!(ModelJson)),
+        ("pack.schema.json", schemars::schema_for!(PackManifest)),
+    ];
+
+    for (name, schema) in schemas {
+        let path = dir.join(name);
+        let serialized = serde_json::to_string_pretty(&schema)
+        .map_err(|e| format!("Error! Could not serialize the schema {}! The error:\n{}", name, e.to_string()))?;
+        std::fs::write(&path, serialized)
+        .map_err(|e| format!("Error! Could not write schema file {}! The error:\n{}", path.display(), e.to_string()))?;
+    }
+    Ok(())
+}
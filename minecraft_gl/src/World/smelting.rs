@@ -0,0 +1,345 @@
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use super::item::{Item, ItemID, ItemRegistry, ItemStack};
+use super::idMap::ValidateIdentifier;
+use super::contentPack::ContentPacks;
+use super::{GenericError, State};
+
+/*
+    Smelting recipes turn one item into another over time inside a furnace, burning fuel as they go.
+    The furnace itself is just a block whose custom attributes hold its slots and timers (see FURNACE_ATTRIBUTES),
+    and TickFurnace moves that state forward. It only touches the state map, so it runs the same with or without a renderer
+*/
+
+#[derive(Clone)]
+pub struct SmeltingRecipe{
+    pub Identifier: String,
+    pub Input: ItemID,
+    pub Output: ItemID,
+    pub Count: u32,
+    //Seconds of burning it takes to smelt one input
+    pub Duration: f32,
+    pub Experience: f32,
+}
+
+pub struct SmeltingRegistry{
+    Recipes: Vec<SmeltingRecipe>,
+    //Input item -> index into Recipes. An input only smelts into one thing
+    ByInput: HashMap<u8, usize>
+}
+
+impl SmeltingRegistry{
+    pub fn New() -> Self {
+        Self { Recipes: Vec::new(), ByInput: HashMap::new() }
+    }
+
+    pub fn AddRecipe(&mut self, recipe: SmeltingRecipe) -> Result<(), String> {
+        if recipe.Duration <= 0f32 {
+            return Err(format!("Error! The smelting recipe {} needs a duration greater than 0!", recipe.Identifier));
+        }
+        if recipe.Count == 0 {
+            return Err(format!("Error! The smelting recipe {} has to make at least one item!", recipe.Identifier));
+        }
+        if let Some(idx) = self.ByInput.get(&recipe.Input.ID) {
+            return Err(format!("Error! The smelting recipes {} and {} have the same input!", self.Recipes[*idx].Identifier, recipe.Identifier));
+        }
+
+        self.ByInput.insert(recipe.Input.ID, self.Recipes.len());
+        self.Recipes.push(recipe);
+        Ok(())
+    }
+
+    pub fn GetRecipeFor(&self, input: &ItemID) -> Option<&SmeltingRecipe> {
+        self.ByInput.get(&input.ID).map(|idx| &self.Recipes[*idx])
+    }
+
+    pub fn Recipes(&self) -> &Vec<SmeltingRecipe> {
+        &self.Recipes
+    }
+
+    //Has to run after the item registry is read, since recipes refer to items by identifier
+    pub fn ReadSmeltingRecipes(&mut self, packs: &ContentPacks, itemRegistry: &ItemRegistry) -> Result<(), Box<dyn Error>> {
+        let mut seen: HashSet<String> = HashSet::new();
+
+        for (path, json) in packs.ReadSmelting().IntoResult()? {
+            ValidateIdentifier(&json.ID)
+            .map_err(|e| GenericError::NewBoxed(format!("Error! Invalid smelting recipe identifier in {}. {}", path.display(), e)))?;
+            if !seen.insert(json.ID.clone()) {
+                return Err(GenericError::NewBoxed(format!("Error! Duplicate smelting recipe identifier '{}' in {}", json.ID, path.display())));
+            }
+
+            let resolve = |name: &str, property: &str| -> Result<ItemID, Box<dyn Error>> {
+                if !itemRegistry.HasItem(name) {
+                    return Err(GenericError::NewBoxed(
+                    format!("Error! '{}' of the smelting recipe {} refers to the item '{}', which does not exist", property, json.ID, name)));
+                }
+                Ok(ItemID::New(itemRegistry.NameToID(name)))
+            };
+
+            let recipe = SmeltingRecipe {
+                Identifier: json.ID.clone(),
+                Input: resolve(&json.Input, "Input")?,
+                Output: resolve(&json.Result, "Result")?,
+                Count: json.Count,
+                Duration: json.Duration,
+                Experience: json.Experience,
+            };
+            self.AddRecipe(recipe).map_err(|e| format!("{} Error in {}", e, path.display()))?;
+        }
+        Ok(())
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+/// ////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+/// ////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+
+//The custom attributes a furnace block needs. The slots are 1x1 containers, the rest are floats
+pub const FURNACE_INPUT: &str = "Input";
+pub const FURNACE_FUEL: &str = "Fuel";
+pub const FURNACE_OUTPUT: &str = "Output";
+//Seconds left on the fuel that's burning, and how long that fuel lasted in total (for drawing the flame)
+pub const FURNACE_BURN_TIME: &str = "Burn Time";
+pub const FURNACE_BURN_DURATION: &str = "Burn Duration";
+//Seconds spent on the current input
+pub const FURNACE_PROGRESS: &str = "Progress";
+//Experience collected and not yet taken out
+pub const FURNACE_EXPERIENCE: &str = "Experience";
+
+pub const FURNACE_ATTRIBUTES: [&str; 7] = [FURNACE_INPUT, FURNACE_FUEL, FURNACE_OUTPUT, FURNACE_BURN_TIME,
+                                           FURNACE_BURN_DURATION, FURNACE_PROGRESS, FURNACE_EXPERIENCE];
+
+/*
+    Moves a furnace forward by deltaTime seconds. Fuel is only used up when there's something it can smelt
+    and room for the result. Progress is lost if the furnace runs out of fuel or the input is taken out.
+    A long deltaTime is stepped through one piece of fuel or one smelt at a time, so it ends up the same as many short ones.
+    Returns false if the state is missing one of the furnace attributes
+*/
+pub fn TickFurnace(state: &mut HashMap<String, State>, itemRegistry: &ItemRegistry, smeltingRegistry: &SmeltingRegistry, deltaTime: f32) -> bool {
+    if !FURNACE_ATTRIBUTES.iter().all(|key| state.contains_key(*key)) {
+        return false;
+    }
+
+    let mut dt = deltaTime.max(0f32);
+    loop {
+        let recipe = Slot(state, FURNACE_INPUT).and_then(|stack| smeltingRegistry.GetRecipeFor(&stack.Item.ItemID)).cloned();
+        let canSmelt = match &recipe {
+            Some(recipe) => match Slot(state, FURNACE_OUTPUT) {
+                None => true,
+                Some(out) => out.Item.ItemID == recipe.Output &&
+                             out.Count + recipe.Count <= itemRegistry.GetAttributesOfID(recipe.Output.ID).StackSize
+            },
+            None => false
+        };
+
+        //light a new piece of fuel
+        if Float(state, FURNACE_BURN_TIME) <= 0f32 && canSmelt {
+            let fuel = Slot(state, FURNACE_FUEL).map_or(0f32, |stack| itemRegistry.GetAttributesOfID(stack.Item.ItemID.ID).FuelValue);
+            if fuel > 0f32 {
+                TakeOne(state, FURNACE_FUEL);
+                SetFloat(state, FURNACE_BURN_TIME, fuel);
+                SetFloat(state, FURNACE_BURN_DURATION, fuel);
+            }
+        }
+
+        let burnTime = Float(state, FURNACE_BURN_TIME);
+        if burnTime <= 0f32 {
+            SetFloat(state, FURNACE_PROGRESS, 0f32);
+            return true;
+        }
+
+        match recipe {
+            Some(recipe) if canSmelt => {
+                //up to whichever comes first: the fuel running out, the input being done or the time running out
+                let progress = Float(state, FURNACE_PROGRESS);
+                let left = recipe.Duration - progress;
+                let step = dt.min(burnTime).min(left.max(0f32));
+                SetFloat(state, FURNACE_BURN_TIME, burnTime - step);
+                dt -= step;
+
+                if step < left {
+                    SetFloat(state, FURNACE_PROGRESS, progress + step);
+                }
+                else {
+                    SetFloat(state, FURNACE_PROGRESS, 0f32);
+                    TakeOne(state, FURNACE_INPUT);
+                    if let Some(State::Container((slots, _, _))) = state.get_mut(FURNACE_OUTPUT) {
+                        match slots.first_mut() {
+                            Some(out) if out.Count > 0 => out.Count += recipe.Count,
+                            _ => {
+                                slots.clear();
+                                slots.push(ItemStack { Item: Item::FromID(recipe.Output.ID, itemRegistry), Count: recipe.Count });
+                            }
+                        }
+                    }
+                    SetFloat(state, FURNACE_EXPERIENCE, Float(state, FURNACE_EXPERIENCE) + recipe.Experience);
+                }
+            },
+            //the fuel that's lit keeps burning with nothing to smelt
+            _ => {
+                let step = dt.min(burnTime);
+                SetFloat(state, FURNACE_BURN_TIME, burnTime - step);
+                SetFloat(state, FURNACE_PROGRESS, 0f32);
+                dt -= step;
+            }
+        }
+
+        if dt <= 0f32 {
+            return true;
+        }
+    }
+}
+
+//The stack in a 1x1 container, if it isn't empty
+fn Slot<'a>(state: &'a HashMap<String, State>, key: &str) -> Option<&'a ItemStack> {
+    state.get(key).and_then(|s| s.AsArray()).and_then(|(slots, _, _)| slots.first()).filter(|stack| stack.Count > 0)
+}
+
+fn TakeOne(state: &mut HashMap<String, State>, key: &str) {
+    if let Some(State::Container((slots, _, _))) = state.get_mut(key) {
+        if let Some(stack) = slots.first_mut() {
+            stack.Pop();
+            if stack.Count == 0 {
+                slots.clear();
+            }
+        }
+    }
+}
+
+fn Float(state: &HashMap<String, State>, key: &str) -> f32 {
+    state.get(key).and_then(|s| s.AsFloat()).cloned().unwrap_or(0f32)
+}
+
+fn SetFloat(state: &mut HashMap<String, State>, key: &str, val: f32) {
+    state.insert(key.to_owned(), State::FloatAttribute(val));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::World::item::ItemAttribute;
+
+    const ORE: u8 = 1;
+    const INGOT: u8 = 2;
+    const COAL: u8 = 3;
+    const STONE: u8 = 4;
+    //Each piece of coal smelts two and a half ores
+    const COAL_TIME: f32 = 25f32;
+    const SMELT_TIME: f32 = 10f32;
+
+    fn Registries() -> (ItemRegistry, SmeltingRegistry) {
+        let mut items = ItemRegistry::New();
+        for (id, stackSize, fuel) in [(ORE, 64, 0f32), (INGOT, 4, 0f32), (COAL, 64, COAL_TIME), (STONE, 64, 0f32)] {
+            items.ItemAttributes.insert(id, ItemAttribute { StackSize: stackSize, FuelValue: fuel, ..Default::default() });
+        }
+
+        let mut smelting = SmeltingRegistry::New();
+        smelting.AddRecipe(SmeltingRecipe {
+            Identifier: String::from("test:ingot"),
+            Input: ItemID::New(ORE),
+            Output: ItemID::New(INGOT),
+            Count: 1,
+            Duration: SMELT_TIME,
+            Experience: 0.5,
+        }).unwrap();
+        (items, smelting)
+    }
+
+    fn Furnace(input: u32, fuel: u32, output: Option<(u8, u32)>) -> HashMap<String, State> {
+        let slot = |stack: Option<(u8, u32)>| State::Container((
+            stack.filter(|(_, count)| *count > 0).map(|(id, count)| ItemStack::WithCount(Item { ItemID: ItemID::New(id), Attributes: None }, count)).into_iter().collect(), 1, 1));
+
+        let mut state = HashMap::new();
+        state.insert(FURNACE_INPUT.to_owned(), slot(Some((ORE, input))));
+        state.insert(FURNACE_FUEL.to_owned(), slot(Some((COAL, fuel))));
+        state.insert(FURNACE_OUTPUT.to_owned(), slot(output));
+        for key in [FURNACE_BURN_TIME, FURNACE_BURN_DURATION, FURNACE_PROGRESS, FURNACE_EXPERIENCE] {
+            state.insert(key.to_owned(), State::FloatAttribute(0f32));
+        }
+        state
+    }
+
+    fn Count(state: &HashMap<String, State>, key: &str) -> u32 {
+        Slot(state, key).map_or(0, |stack| stack.Count)
+    }
+
+    fn AssertNear(a: f32, b: f32) {
+        assert!((a - b).abs() < 1e-3, "{} != {}", a, b);
+    }
+
+    #[test]
+    fn OneSmelt() {
+        let (items, smelting) = Registries();
+        let mut state = Furnace(3, 1, None);
+
+        assert!(TickFurnace(&mut state, &items, &smelting, 4f32));
+        assert_eq!((Count(&state, FURNACE_INPUT), Count(&state, FURNACE_FUEL), Count(&state, FURNACE_OUTPUT)), (3, 0, 0));
+        AssertNear(Float(&state, FURNACE_PROGRESS), 4f32);
+        AssertNear(Float(&state, FURNACE_BURN_TIME), COAL_TIME - 4f32);
+        AssertNear(Float(&state, FURNACE_BURN_DURATION), COAL_TIME);
+
+        TickFurnace(&mut state, &items, &smelting, 6f32);
+        assert_eq!((Count(&state, FURNACE_INPUT), Count(&state, FURNACE_OUTPUT)), (2, 1));
+        assert_eq!(Slot(&state, FURNACE_OUTPUT).unwrap().Item.ItemID.ID, INGOT);
+        AssertNear(Float(&state, FURNACE_PROGRESS), 0f32);
+        AssertNear(Float(&state, FURNACE_BURN_TIME), COAL_TIME - SMELT_TIME);
+        AssertNear(Float(&state, FURNACE_EXPERIENCE), 0.5);
+
+        let mut missing = Furnace(1, 1, None);
+        missing.remove(FURNACE_PROGRESS);
+        assert!(!TickFurnace(&mut missing, &items, &smelting, 1f32));
+    }
+
+    #[test]
+    fn LongTicksSmeltSeveral() {
+        let (items, smelting) = Registries();
+        let mut state = Furnace(5, 2, None);
+
+        //three smelts and half of a fourth, burning through the first coal and into the second
+        TickFurnace(&mut state, &items, &smelting, 35f32);
+        assert_eq!((Count(&state, FURNACE_INPUT), Count(&state, FURNACE_FUEL), Count(&state, FURNACE_OUTPUT)), (2, 0, 3));
+        AssertNear(Float(&state, FURNACE_PROGRESS), 5f32);
+        AssertNear(Float(&state, FURNACE_BURN_TIME), COAL_TIME * 2f32 - 35f32);
+        AssertNear(Float(&state, FURNACE_EXPERIENCE), 1.5);
+
+        //the same as ticking a frame at a time
+        let mut stepped = Furnace(5, 2, None);
+        for _ in 0..350 {
+            TickFurnace(&mut stepped, &items, &smelting, 0.1);
+        }
+        assert_eq!((Count(&stepped, FURNACE_INPUT), Count(&stepped, FURNACE_FUEL), Count(&stepped, FURNACE_OUTPUT)), (2, 0, 3));
+        AssertNear(Float(&stepped, FURNACE_PROGRESS), Float(&state, FURNACE_PROGRESS));
+        AssertNear(Float(&stepped, FURNACE_BURN_TIME), Float(&state, FURNACE_BURN_TIME));
+    }
+
+    #[test]
+    fn FuelRunsOutPartway() {
+        let (items, smelting) = Registries();
+        let mut state = Furnace(5, 1, None);
+
+        //two smelts, then the coal goes out half way through the third and its progress is lost
+        TickFurnace(&mut state, &items, &smelting, 100f32);
+        assert_eq!((Count(&state, FURNACE_INPUT), Count(&state, FURNACE_FUEL), Count(&state, FURNACE_OUTPUT)), (3, 0, 2));
+        AssertNear(Float(&state, FURNACE_PROGRESS), 0f32);
+        AssertNear(Float(&state, FURNACE_BURN_TIME), 0f32);
+    }
+
+    #[test]
+    fn FullOutputStopsSmelting() {
+        let (items, smelting) = Registries();
+
+        //room for one more ingot. The coal lit for it burns out without smelting anything else
+        let mut state = Furnace(5, 5, Some((INGOT, 3)));
+        TickFurnace(&mut state, &items, &smelting, 100f32);
+        assert_eq!((Count(&state, FURNACE_INPUT), Count(&state, FURNACE_FUEL), Count(&state, FURNACE_OUTPUT)), (4, 4, 4));
+        AssertNear(Float(&state, FURNACE_PROGRESS), 0f32);
+        AssertNear(Float(&state, FURNACE_BURN_TIME), 0f32);
+
+        //something else in the output, so no fuel is lit at all
+        let mut blocked = Furnace(5, 5, Some((STONE, 1)));
+        TickFurnace(&mut blocked, &items, &smelting, 100f32);
+        assert_eq!((Count(&blocked, FURNACE_INPUT), Count(&blocked, FURNACE_FUEL), Count(&blocked, FURNACE_OUTPUT)), (5, 5, 1));
+        AssertNear(Float(&blocked, FURNACE_BURN_TIME), 0f32);
+    }
+}
\ No newline at end of file
@@ -0,0 +1,391 @@
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use super::schema::{BlockJson, ItemJson, BiomeJson, BiomeBlockJson, RecipeJson, SmeltingJson};
+use super::idMap::{AIR_IDENTIFIER, ValidateIdentifier};
+use super::contentPack::{ContentPacks, JsonDir};
+use super::crafting::{ParsePattern, MAX_CRAFTING_GRID};
+use super::item::{ReadTool, DURABILITY};
+use super::block::{CONNECTED_SHEET_SIZE, ModelTexture};
+use super::blockModel::ReadBlockModel;
+use crate::Util::atlas::TextureAnimation;
+
+/*
+    Lints every block, item, biome, recipe and smelting json without starting the game. Unlike the registries, which
+    bail out on the first problem, every file is checked and every problem is reported so content
+    authors (and CI) get the full list in one go. Only what's left after the content packs override
+    each other is checked, the same content the game would load
+*/
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Severity {
+    Error,
+    //Works, but probably isn't what the author wanted (like a block falling back to the null texture)
+    Warning,
+}
+
+pub struct ValidationIssue {
+    pub Severity: Severity,
+    pub File: PathBuf,
+    pub Msg: String,
+}
+
+impl std::fmt::Display for ValidationIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let severity = match self.Severity { Severity::Error => "error", Severity::Warning => "warning" };
+        write!(f, "{}: {}: {}", severity, self.File.display(), self.Msg)
+    }
+}
+
+#[derive(Default)]
+pub struct ValidationReport {
+    pub Issues: Vec<ValidationIssue>,
+    pub FilesChecked: usize,
+}
+
+impl ValidationReport {
+    fn Error(&mut self, file: &Path, msg: String) {
+        self.Issues.push(ValidationIssue { Severity: Severity::Error, File: file.to_path_buf(), Msg: msg });
+    }
+
+    fn Warning(&mut self, file: &Path, msg: String) {
+        self.Issues.push(ValidationIssue { Severity: Severity::Warning, File: file.to_path_buf(), Msg: msg });
+    }
+
+    pub fn NumErrors(&self) -> usize {
+        self.Issues.iter().filter(|i| i.Severity == Severity::Error).count()
+    }
+
+    pub fn NumWarnings(&self) -> usize {
+        self.Issues.iter().filter(|i| i.Severity == Severity::Warning).count()
+    }
+}
+
+//Identifier -> enabled. Used to resolve the identifiers one file uses to refer to another
+struct NameTable {
+    Entries: HashMap<String, bool>,
+}
+
+impl NameTable {
+    fn Check(&self, name: &str, kind: &str, property: &str, file: &Path, report: &mut ValidationReport) {
+        match self.Entries.get(name) {
+            None => report.Error(file, format!("'{}' refers to the {} '{}', which does not exist", property, kind, name)),
+            Some(false) => report.Error(file, format!("'{}' refers to the {} '{}', which is disabled", property, kind, name)),
+            _ => {}
+        }
+    }
+}
+
+pub fn ValidateContent(packs: &ContentPacks) -> ValidationReport {
+    let mut report = ValidationReport::default();
+
+    let blocks: Vec<(PathBuf, BlockJson)> = Collect(packs.ReadBlocks(), &mut report);
+    let items: Vec<(PathBuf, ItemJson)> = Collect(packs.ReadItems(), &mut report);
+    let biomes: Vec<(PathBuf, BiomeJson)> = Collect(packs.ReadBiomes(), &mut report);
+    let recipes: Vec<(PathBuf, RecipeJson)> = Collect(packs.ReadRecipes(), &mut report);
+    let smelting: Vec<(PathBuf, SmeltingJson)> = Collect(packs.ReadSmelting(), &mut report);
+
+    let blockNames = BuildNameTable(blocks.iter().map(|(p, b)| (p, b.ID.as_str(), b.Enabled)), "block", &mut report);
+    let itemNames = BuildNameTable(items.iter().map(|(p, i)| (p, i.ID.as_str(), i.Enabled)), "item", &mut report);
+
+    for (path, block) in &blocks {
+        if block.ID == AIR_IDENTIFIER {
+            report.Error(path, format!("the identifier {} is reserved for the air block", AIR_IDENTIFIER));
+        }
+        if !block.Enabled {
+            continue;
+        }
+
+        if let Some(item) = &block.DropItem {
+            itemNames.Check(item, "item", "DropItem", path, &mut report);
+        }
+        if let Some(item) = &block.EffectiveTool {
+            itemNames.Check(item, "item", "EffectiveTool", path, &mut report);
+        }
+
+        for (key, attribute) in &block.Attributes {
+            if let Err(msg) = attribute.ToState() {
+                report.Error(path, format!("custom attribute '{}' is invalid: {}", key, msg));
+            }
+        }
+
+        if block.HasModel() {
+            if block.Textures.is_some() {
+                report.Error(path, format!("a block with a model can't have 'Textures', its textures go in 'Model Textures'"));
+            }
+            //A texture key can be used by many faces, so each is only reported once
+            let mut checked: HashSet<String> = HashSet::new();
+            let result = ReadBlockModel(packs, block, &mut |key| {
+                let texture = ModelTexture(block, key)?;
+                if checked.insert(texture.to_owned()) {
+                    CheckTexture(packs, "block/img", texture, path, &mut report);
+                }
+                Ok((0, false))
+            });
+            if let Err(msg) = result {
+                report.Error(path, format!("the model is invalid: {}", msg));
+            }
+        }
+        else if let Some(textures) = &block.Textures {
+            for (i, face) in textures.iter().enumerate() {
+                if let Err(msg) = face.Validate() {
+                    report.Error(path, format!("the texture of face {} is invalid: {}", i, msg));
+                }
+                for texture in face.TextureNames() {
+                    CheckTexture(packs, "block/img", texture, path, &mut report);
+                    if face.IsConnected(texture) {
+                        CheckConnectedSheet(packs, texture, path, &mut report);
+                        if block.Animations.contains_key(texture) {
+                            report.Error(path, format!("the connected texture {} can't be animated", texture));
+                        }
+                    }
+                }
+            }
+        }
+        else {
+            report.Warning(path, format!("no 'Textures' given, the null texture will be used"));
+        }
+
+        if let Some(tint) = block.Tint {
+            if packs.ResolveFile(&format!("block/colormap/{}", tint.FileName())).is_none() {
+                report.Warning(path, format!("there's no colormap block/colormap/{} in any content pack, so the block won't be tinted", tint.FileName()));
+            }
+        }
+
+        for (texture, animation) in &block.Animations {
+            if !block.TextureNames().contains(&texture.as_str()) {
+                report.Error(path, format!("'Animations' has the texture {}, which the block doesn't use", texture));
+            }
+            match animation.ToAnimation() {
+                Ok(val) => CheckAnimationStrip(packs, texture, &val, path, &mut report),
+                Err(msg) => report.Error(path, format!("the animation for {} is invalid: {}", texture, msg)),
+            }
+        }
+    }
+
+    for (path, item) in &items {
+        if !item.Enabled {
+            continue;
+        }
+
+        if let Some(block) = &item.PlaceableBlock {
+            blockNames.Check(block, "block", "Placeable Block", path, &mut report);
+        }
+
+        for (key, attribute) in &item.Attributes {
+            if let Err(msg) = attribute.ToState() {
+                report.Error(path, format!("custom attribute '{}' is invalid: {}", key, msg));
+            }
+        }
+        if item.StackSize.map_or(false, |s| s > 1) && !item.Attributes.is_empty() {
+            report.Error(path, format!("items with custom attributes can't have a stack size greater than 1"));
+        }
+        if item.FuelValue.map_or(false, |f| f < 0f32) {
+            report.Error(path, format!("'Fuel Value' can't be negative"));
+        }
+        if let Err(msg) = ReadTool(item) {
+            report.Error(path, msg);
+        }
+        if item.ToolType.is_some() && item.Durability.is_some() && item.Attributes.contains_key(DURABILITY) {
+            report.Error(path, format!("the attribute '{}' is reserved for tools", DURABILITY));
+        }
+        if item.FoodValue.is_none() && item.Saturation.is_some() {
+            report.Error(path, format!("'Saturation' only applies to items with a 'Food Value'"));
+        }
+        if item.Saturation.map_or(false, |s| s < 0f32) {
+            report.Error(path, format!("'Saturation' can't be negative"));
+        }
+
+        match &item.Texture {
+            Some(texture) => CheckTexture(packs, "item/img", texture, path, &mut report),
+            None => report.Warning(path, format!("no 'Texture' given, the null texture will be used")),
+        }
+    }
+
+    for (path, biome) in &biomes {
+        if biome.Name != "Forest" {
+            report.Error(path, format!("the {} biome is not yet supported", biome.Name));
+        }
+        if biome.MantleMinLength >= biome.MantleMaxLength {
+            report.Error(path, format!("'Mantle Min Length' must be less than 'Mantle Max Length'"));
+        }
+        if !(0f32..=1f32).contains(&biome.Temperature) || !(0f32..=1f32).contains(&biome.Humidity) {
+            report.Error(path, format!("'Temperature' and 'Humidity' must be between 0 and 1"));
+        }
+        if biome.Cave.Decay && biome.Cave.Constant {
+            report.Error(path, format!("'Cave' cannot be both constant and decaying"));
+        }
+        if biome.Cave.Constant && biome.Cave.Speed.is_some() {
+            report.Error(path, format!("'Cave' cannot be constant and have a defined speed"));
+        }
+
+        if let Some(mantle) = &biome.Mantle {
+            blockNames.Check(mantle, "block", "Mantle", path, &mut report);
+        }
+        blockNames.Check(&biome.Core, "block", "Core", path, &mut report);
+        blockNames.Check(&biome.Water, "block", "Water", path, &mut report);
+        blockNames.Check(&biome.Log, "block", "Log", path, &mut report);
+        blockNames.Check(&biome.Leaves, "block", "Leaves", path, &mut report);
+        blockNames.Check(&biome.Flower, "block", "Flower", path, &mut report);
+        blockNames.Check(&biome.TallGrass, "block", "Tall Grass", path, &mut report);
+        CheckBiomeBlocks(&biome.Crust, "Crust", &blockNames, path, &mut report);
+        CheckBiomeBlocks(&biome.Ores.Blocks, "Ores", &blockNames, path, &mut report);
+    }
+
+    let mut recipeNames: HashSet<&str> = HashSet::new();
+    for (path, recipe) in &recipes {
+        if let Err(msg) = ValidateIdentifier(recipe.ID()) {
+            report.Error(path, msg);
+        }
+        if !recipeNames.insert(recipe.ID()) {
+            report.Error(path, format!("duplicate recipe identifier '{}'", recipe.ID()));
+        }
+
+        itemNames.Check(recipe.Result(), "item", "Result", path, &mut report);
+        if recipe.Count() == 0 {
+            report.Error(path, format!("'Count' must be at least 1"));
+        }
+
+        match recipe {
+            RecipeJson::Shaped { Pattern, Key, .. } => {
+                match ParsePattern(Pattern, recipe.ID()) {
+                    Ok(slots) => {
+                        if slots.iter().all(|slot| slot.is_none()) {
+                            report.Error(path, format!("the pattern has no ingredients"));
+                        }
+                        for symbol in slots.iter().flatten() {
+                            if !Key.contains_key(symbol) {
+                                report.Error(path, format!("the pattern uses '{}', which is not in 'Key'", symbol));
+                            }
+                        }
+                    },
+                    Err(msg) => report.Error(path, msg),
+                }
+                for (symbol, item) in Key {
+                    if *symbol == ' ' {
+                        report.Error(path, format!("' ' is an empty slot and can't be used in 'Key'"));
+                    }
+                    itemNames.Check(item, "item", "Key", path, &mut report);
+                }
+            },
+            RecipeJson::Shapeless { Ingredients, .. } => {
+                let max = (MAX_CRAFTING_GRID * MAX_CRAFTING_GRID) as usize;
+                if Ingredients.is_empty() || Ingredients.len() > max {
+                    report.Error(path, format!("a shapeless recipe needs between 1 and {} ingredients, not {}", max, Ingredients.len()));
+                }
+                for item in Ingredients {
+                    itemNames.Check(item, "item", "Ingredients", path, &mut report);
+                }
+            }
+        }
+    }
+
+    let mut smeltingNames: HashSet<&str> = HashSet::new();
+    let mut smeltingInputs: HashMap<&str, &str> = HashMap::new();
+    for (path, recipe) in &smelting {
+        if let Err(msg) = ValidateIdentifier(&recipe.ID) {
+            report.Error(path, msg);
+        }
+        if !smeltingNames.insert(&recipe.ID) {
+            report.Error(path, format!("duplicate smelting recipe identifier '{}'", recipe.ID));
+        }
+        if let Some(other) = smeltingInputs.insert(&recipe.Input, &recipe.ID) {
+            report.Error(path, format!("'{}' is already smelted by '{}'", recipe.Input, other));
+        }
+
+        itemNames.Check(&recipe.Input, "item", "Input", path, &mut report);
+        itemNames.Check(&recipe.Result, "item", "Result", path, &mut report);
+        if recipe.Count == 0 {
+            report.Error(path, format!("'Count' must be at least 1"));
+        }
+        if recipe.Duration <= 0f32 {
+            report.Error(path, format!("'Duration' must be greater than 0"));
+        }
+        if recipe.Experience < 0f32 {
+            report.Error(path, format!("'Experience' can't be negative"));
+        }
+    }
+
+    report
+}
+
+fn CheckBiomeBlocks(blocks: &Vec<BiomeBlockJson>, property: &str, blockNames: &NameTable, path: &Path, report: &mut ValidationReport) {
+    for block in blocks {
+        blockNames.Check(&block.Name, "block", property, path, report);
+        if block.Decay && block.Constant {
+            report.Error(path, format!("'{}' block '{}' cannot be both constant and decaying", property, block.Name));
+        }
+        if block.Constant && block.Speed.is_some() {
+            report.Error(path, format!("'{}' block '{}' cannot be constant and have a defined speed", property, block.Name));
+        }
+    }
+}
+
+fn CheckTexture(packs: &ContentPacks, dir: &str, texture: &str, file: &Path, report: &mut ValidationReport) {
+    let relative = format!("{}/{}", dir, texture);
+    if packs.ResolveFile(&relative).is_none() {
+        report.Error(file, format!("the texture {} is not in any content pack and would fall back to the null texture", relative));
+    }
+}
+
+//An animated texture has to split into whole frames. Missing textures are already reported by CheckTexture
+fn CheckAnimationStrip(packs: &ContentPacks, texture: &str, animation: &TextureAnimation, file: &Path, report: &mut ValidationReport) {
+    let dims = match packs.ResolveFile(&format!("block/img/{}", texture)).and_then(|p| image::image_dimensions(p).ok()) {
+        Some(val) => val,
+        None => return
+    };
+    let frames = animation.Frames.unwrap_or(if dims.0 == 0 { 0 } else { dims.1 / dims.0 });
+    if frames == 0 || dims.1 % frames != 0 {
+        report.Error(file, format!("the animated texture {} is {}x{}, which can't be split into {} frames of equal height", texture, dims.0, dims.1, frames));
+    }
+}
+
+fn CheckConnectedSheet(packs: &ContentPacks, texture: &str, file: &Path, report: &mut ValidationReport) {
+    let dims = match packs.ResolveFile(&format!("block/img/{}", texture)).and_then(|p| image::image_dimensions(p).ok()) {
+        Some(val) => val,
+        None => return
+    };
+    if dims.0 % CONNECTED_SHEET_SIZE != 0 || dims.1 % CONNECTED_SHEET_SIZE != 0 {
+        report.Error(file, format!("the connected texture {} is {}x{}, which can't be split into {}x{} tiles of equal size", 
+                     texture, dims.0, dims.1, CONNECTED_SHEET_SIZE, CONNECTED_SHEET_SIZE));
+    }
+}
+
+//Flags malformed and duplicate identifiers. Disabled entries still claim their identifier, the same as in the registries
+fn BuildNameTable<'a>(entries: impl Iterator<Item = (&'a PathBuf, &'a str, bool)>, kind: &str, report: &mut ValidationReport) -> NameTable {
+    let mut table = NameTable { Entries: HashMap::new() };
+
+    for (path, identifier, enabled) in entries {
+        if let Err(msg) = ValidateIdentifier(identifier) {
+            report.Error(path, msg);
+        }
+
+        if table.Entries.contains_key(identifier) {
+            report.Error(path, format!("duplicate {} identifier '{}'", kind, identifier));
+        } else {
+            table.Entries.insert(identifier.to_owned(), enabled);
+        }
+    }
+    table
+}
+
+//Files that fail to parse are reported and skipped
+fn Collect<T>(dir: JsonDir<T>, report: &mut ValidationReport) -> Vec<(PathBuf, T)> {
+    report.FilesChecked += dir.FilesRead;
+    for (path, msg) in dir.Errors {
+        report.Error(&path, msg);
+    }
+    dir.Entries
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn CoreContentIsValid() {
+        let report = ValidateContent(&ContentPacks::Core().unwrap());
+        let issues: Vec<String> = report.Issues.iter().map(|issue| issue.to_string()).collect();
+        assert!(report.FilesChecked > 0);
+        assert!(issues.is_empty(), "{}", issues.join("\n"));
+    }
+}
@@ -1,49 +1,127 @@
 use nalgebra as na;
 use bracket_noise::prelude::FastNoise;
-use rand::Rng;
-use std::sync::mpsc;
+use rand::{Rng, SeedableRng, rngs::StdRng};
 
 use std::{collections::{HashSet, HashMap, VecDeque}, 
-          sync::{Arc, Mutex, mpsc::Receiver}, thread
+          sync::Arc, path::{Path, PathBuf}
          };
+use serde::{Serialize, Deserialize};
 
-use crate::{World::{block::BlockRegistry, 
-            chunk::{Chunk, CHUNK_BOUNDS_X, CHUNK_BOUNDS_Z}, 
-            item::ItemRegistry, crafting::CraftingRegistry, 
+use crate::{World::{block::{BlockRegistry, Block}, 
+            chunk::{Chunk, ChunkState, CHUNK_BOUNDS_X, CHUNK_BOUNDS_Y, CHUNK_BOUNDS_Z}, 
+            item::{ItemRegistry, ItemStack}, crafting::CraftingRegistry, smelting::SmeltingRegistry, blockBehavior::{BlockContext, BlockUse, Stepper, BlockBehavior}, 
+            inventory::{Inventory, ContainerSlots}, mining::{Mining, MiningProgress, DroppedItem, BlockHit, BlockDrops, Raycast, PlayerOverlapsBlock, Placement, PlacementOf, PLAYER_REACH, PLAYER_EYE_HEIGHT, THROW_SPEED}, itemBehavior::ItemUse, hunger::{Hunger, BREAK_EXHAUSTION}, dayCycle::DayCycle, 
             biomeGenerator::{BiomeGenerator, Biome, NoiseParameters}, 
-            ReadBiomeGenerators
-            }, Scene::camera::Camera
+            chunkScheduler::{ChunkScheduler, JobKind, FinishedJob, MeshResult, GatherAdjacent, NeighboursReady},
+            contentPack::ContentPacks, ReadBiomeGenerators, State
+            }, Scene::camera::Camera, Util::lodMeshHelper::{LodLevel, ADJACENT_OFFSETS}, Event::event::Event
            };
 
 
 const DEFAULT_RENDER_DISTANCE: usize = 1;
-const MAX_RENDER_DISTANCE: usize = 10;
+//Far chunks are meshed at a lower level of detail, so the render distance can go well past the full detail rings
+const MAX_RENDER_DISTANCE: usize = 32;
+//Neighbour updates handled per call, so blocks that keep changing each other can't stall the game
+const MAX_BLOCK_UPDATES: usize = 4096;
+//Offsets to the six blocks touching a block, in face ID order
+pub const NEIGHBOR_OFFSETS: [(i32, i32, i32); 6] = [(1, 0, 0), (-1, 0, 0), (0, 1, 0), (0, -1, 0), (0, 0, 1), (0, 0, -1)];
 
 pub struct World{
     pub Chunks: HashMap<na::Vector2<i32>, Arc<Chunk>>,
     pub RenderList: HashSet<*const Chunk>, //TODO change to basic list
 
-    //Used for syncing the workers
-    WorkerQueue: VecDeque<Vec<(bool, Chunk)>>,
+    //Generation and meshing jobs, handed out closest chunk first
+    Scheduler: ChunkScheduler,
+    //Meshes that came back while a worker still held the chunk. They're applied once it lets go
+    UnappliedMeshes: Vec<MeshResult>,
     RemovalQueue: VecDeque<na::Vector2<i32>>,
-    Reciever: Option<Receiver<Arc<Chunk>>>,
-    IsWorking: Arc<Mutex<bool>>,
 
     BlockRegistry: Arc<BlockRegistry>,
     ItemRegistry: ItemRegistry, //to be used
     CraftingRegistry: CraftingRegistry, //to be used
-
-    BiomeGenerators: Arc<Mutex<HashMap<Biome, Box<dyn BiomeGenerator + Send>>>>,
+    SmeltingRegistry: SmeltingRegistry,
+    pub PlayerInventory: Inventory,
+    pub PlayerHunger: Hunger,
+    //Progress on the block the player is breaking
+    Mining: Mining,
+    //Drops that didn't fit in the player's inventory
+    DroppedItems: Vec<DroppedItem>,
+    //The block whose screen is open, like a chest
+    OpenBlock: Option<OpenBlock>,
+    //Modified chunks are written here when they unload. Worlds without one (headless runs) aren't saved
+    SaveDir: Option<PathBuf>,
+    //The time of day, which moves forward with the block ticks
+    DayCycle: DayCycle,
+
+    //Seconds of block ticks owed to chunks a worker was holding when they were due
+    TickDebt: HashMap<na::Vector2<i32>, f32>,
+    //Blocks whose neighbour changed, paired with that neighbour. Handled breadth first
+    BlockUpdates: VecDeque<((i32, i32, i32), (i32, i32, i32))>,
+    //Set while the queue is being worked through, so changes made by the hooks only add to it
+    UpdatingBlocks: bool,
+    //The block under the player's feet the last time they moved
+    PlayerStep: Option<(i32, i32, i32)>,
+
+    BiomeGenerators: Arc<HashMap<Biome, Box<dyn BiomeGenerator + Send + Sync>>>,
     BiomeNoise: NoiseParameters, //to be used
     BiomeNoiseGenerator: FastNoise, //to be used
 
     RenderDistance: usize,
     TargetPosition: (i32, i32),
+    Seed: u64,
+}
+
+//Its behavior's OnScreenEvent gets the input until it says to close
+pub struct OpenBlock {
+    pub Position: (i32, i32, i32),
+}
+
+//A snapshot of where every chunk is in the pipeline
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ChunkStats {
+    pub Loaded: usize,
+    pub Meshed: usize,
+    pub Pending: usize,
+    pub InFlight: usize,
+    pub Vertices: usize,
+}
+
+impl std::fmt::Display for ChunkStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "loaded: {}, meshed: {}, pending: {}, in flight: {}, vertices: {}",
+               self.Loaded, self.Meshed, self.Pending, self.InFlight, self.Vertices)
+    }
 }
 
 impl World{
-    pub fn New(craftingRegistry: CraftingRegistry, blockRegistry: BlockRegistry, itemRegistry: ItemRegistry) -> Self{
-        let mut rng = rand::thread_rng();
+    /*
+        Opens the world saved in a directory. A new world gets a random seed, which is saved straight away
+        so chunks that were never modified (and so never saved) generate the same way next time
+    */
+    pub fn New(craftingRegistry: CraftingRegistry, smeltingRegistry: SmeltingRegistry, blockRegistry: BlockRegistry, itemRegistry: ItemRegistry, packs: &ContentPacks, saveDir: PathBuf) -> Self{
+        let seed = match WorldSeed::Load(&saveDir) {
+            Ok(Some(seed)) => seed.Seed,
+            Ok(None) => {
+                let seed = WorldSeed { Seed: rand::thread_rng().gen() };
+                if let Err(msg) = seed.Save(&saveDir) {
+                    println!("{}", msg);
+                }
+                seed.Seed
+            },
+            Err(msg) => {
+                panic!("Error! World construction failed due to failure to read 
+                        the world seed. The error:\n{}", msg)
+            }
+        };
+
+        let mut world = Self::NewSeeded(craftingRegistry, smeltingRegistry, blockRegistry, itemRegistry, packs, seed);
+        world.SetSaveDir(saveDir);
+        world
+    }
+
+    //The same seed always generates the same terrain
+    pub fn NewSeeded(craftingRegistry: CraftingRegistry, smeltingRegistry: SmeltingRegistry, blockRegistry: BlockRegistry, itemRegistry: ItemRegistry, packs: &ContentPacks, seed: u64) -> Self{
+        let mut rng = StdRng::seed_from_u64(seed);
         let noise =  NoiseParameters {
             Octaves: 6,
             Seed: rng.gen_range(0..10000),
@@ -52,7 +130,7 @@ impl World{
             Persistance: 0.5f32,
         };
     
-        let map = match ReadBiomeGenerators(&blockRegistry) {
+        let map = match ReadBiomeGenerators(&blockRegistry, packs, seed) {
             Ok(val) => val,
             Err(msg) => {
                 panic!("Error! World construction failed due to failure to read 
@@ -60,250 +138,238 @@ impl World{
             }
         };
 
+        let blockRegistry = Arc::new(blockRegistry);
+        let biomeGenerators = Arc::new(map);
    
         let mut self_ = Self{
             Chunks: HashMap::with_capacity( (DEFAULT_RENDER_DISTANCE * 2 + 1) * 
                                             (DEFAULT_RENDER_DISTANCE * 2 + 1)),
             RenderList: HashSet::new(),
 
-            WorkerQueue: VecDeque::new(),
+            Scheduler: ChunkScheduler::New(blockRegistry.clone(), biomeGenerators.clone()),
+            UnappliedMeshes: Vec::new(),
             RemovalQueue: VecDeque::new(),
-            Reciever: None,
-            IsWorking: Arc::new(Mutex::new(false)),
 
-            BlockRegistry: Arc::new(blockRegistry),
+            BlockRegistry: blockRegistry,
             ItemRegistry: itemRegistry,
             CraftingRegistry: craftingRegistry,
-
-            BiomeGenerators: Arc::new(Mutex::new(map)),
+            SmeltingRegistry: smeltingRegistry,
+            PlayerInventory: Inventory::Player(),
+            PlayerHunger: Hunger::New(),
+            Mining: Mining::New(),
+            DroppedItems: Vec::new(),
+            OpenBlock: None,
+            SaveDir: None,
+            DayCycle: DayCycle::default(),
+            TickDebt: HashMap::new(),
+            BlockUpdates: VecDeque::new(),
+            UpdatingBlocks: false,
+            PlayerStep: None,
+
+            BiomeGenerators: biomeGenerators,
             BiomeNoise: noise,
             BiomeNoiseGenerator: FastNoise::new(),
 
             RenderDistance: 0,
             TargetPosition: (0i32, 0i32),
+            Seed: seed,
         };
 
-        self_.RenderDistanceUpdate(DEFAULT_RENDER_DISTANCE);
+        self_.RenderDistanceUpdate(DEFAULT_RENDER_DISTANCE).expect("Error! The default render distance is above the max render distance!");
         self_
     }
 
     pub fn Update(&mut self, targetPos: (f32, f32), _: &Camera){
-        self.generationUpdate();
-
-        while self.RemovalQueue.len() > 0 {
-            let vec = self.RemovalQueue.front().unwrap().clone();
-            let mut exists = false;
-
-            
-            if let Some(chunk) = self.Chunks.get(&vec) {
-                exists = true;
-                self.RenderList.remove(&(chunk.as_ref() as *const Chunk));
-                self.RemovalQueue.pop_front().unwrap();
-            }
-            
-            if exists {
-                self.Chunks.remove(&vec).unwrap();
-            }
-    
+        while let Some(vec) = self.RemovalQueue.pop_front() {
+            self.UnloadChunk(vec);
         }
 
-        if self.Reciever.is_some() {
-            let recieve = self.Reciever.as_ref().unwrap().try_recv();
-            match recieve {
-                Ok(e) => {
-                    let pos = e.Position;
-                    let vec = na::Vector2::new(pos.0, pos.1);
-                    self.Chunks.insert(vec, e);
-                },
-                _ => {}
-            }
-        }
+        self.ReceiveChunks();
 
         let currChunkPos = ToChunkPos(targetPos);
         if currChunkPos != self.TargetPosition {
             self.TranslateChunks(self.TargetPosition, currChunkPos);
         }
         self.TargetPosition = currChunkPos;
+
+        self.Scheduler.Retarget(self.TargetPosition, self.GenerationRadius());
+        self.Scheduler.Dispatch(&self.Chunks);
         self.RenderListUpdate();
-        
-   
+        //whatever was left over from a long chain of updates
+        self.ProcessBlockUpdates();
     }
 
-    fn generationUpdate(&mut self) {
-        //first check if the current thread is still working
-        let b = self.IsWorking.lock().unwrap().to_owned();
-        if b || self.WorkerQueue.len() == 0 {
-            return;
-        }
-
-        //then make a new vector of adjacents to send to the thread
-        let work = self.WorkerQueue.pop_front().unwrap();
-        let mut buffer: Vec<(Chunk, bool, [Option<Arc<Chunk>>; 4])> = 
-                    Vec::with_capacity(work.len());
+    /*
+        Moves the time of day forward and ticks every block with dynamic state in the loaded chunks. A chunk a
+        worker is still holding can't be written to, so its time is saved up and handed over on the next tick it's free
+    */
+    pub fn TickBlocks(&mut self, deltaTime: f32){
+        self.DayCycle.Advance(deltaTime);
+        let mut debt = std::mem::take(&mut self.TickDebt);
+        let due: Vec<na::Vector2<i32>> = self.Chunks.iter().filter(|(_, c)| !c.DynamicState.is_empty()).map(|(pos, _)| *pos).collect();
+
+        for chunkPos in due {
+            let owed = debt.remove(&chunkPos).unwrap_or(0f32);
+            let blocks = match self.Chunks.get_mut(&chunkPos).and_then(Arc::get_mut) {
+                Some(chunk) => chunk.StatefulBlocks(),
+                None => {
+                    self.TickDebt.insert(chunkPos, deltaTime + owed);
+                    continue;
+                }
+            };
 
-        //add relevant adjacent chunks to the buffer
-        let d = [-1, 1];
-        for (remesh, chunk) in work.into_iter() {
-            let pos = chunk.Position;
+            for local in blocks {
+                let pos = (chunkPos.x * CHUNK_BOUNDS_X as i32 + local.0 as i32, local.1 as i32, chunkPos.y * CHUNK_BOUNDS_Z as i32 + local.2 as i32);
+                self.RunBlockHook(pos, |behavior, context, state| behavior.OnTick(context, state, deltaTime + owed));
+            }
+        }
+    }
 
-            let mut adj = [None, None, None, None];
-            for a in 0..2 {
+    /*
+        Chunks are generated one ring past the render distance. That margin ring is never meshed,
+        but it guarantees every chunk inside the render distance has all four neighbours decorated
+        before it is meshed, so its borders are built once against the final blocks of its neighbours
+    */
+    fn GenerationRadius(&self) -> i32 {
+        self.RenderDistance as i32 + 1
+    }
 
-                    let newPos = na::Vector2::new(pos.0 + d[a], pos.1);
-                    if self.Chunks.contains_key(&newPos) {
-                        adj[a] = Some(self.Chunks.get(&newPos).unwrap().clone());
+    fn ReceiveChunks(&mut self) {
+        for job in self.Scheduler.Poll() {
+            match job {
+                FinishedJob::Generated(mut chunk) => {
+                    let pos = chunk.Position;
+                    if !InRadius(pos, self.TargetPosition, self.GenerationRadius()) {
+                        continue;
                     }
-
-                    let newPos = na::Vector2::new(pos.0, pos.1 + d[a]);
-                    if self.Chunks.contains_key(&newPos) {
-                        adj[a + 2] = Some(self.Chunks.get(&newPos).unwrap().clone());
+                    if let Some(dir) = &self.SaveDir {
+                        if let Err(msg) = chunk.LoadSaved(dir, &self.BlockRegistry) {
+                            println!("{}\nThe chunk will be regenerated", msg);
+                        }
                     }
-                
-            }
+                    self.Chunks.insert(na::Vector2::new(pos.0, pos.1), Arc::new(chunk));
 
-            buffer.push((chunk, remesh, adj));
-        }
-
-        //spawn the thread
-        let (tx, rx) = mpsc::channel();
-        self.Reciever = Some(rx);
-
-        let biomeGens = self.BiomeGenerators.clone();
-        let blockReg = self.BlockRegistry.clone();
-        let isWorking = self.IsWorking.clone();
-        *isWorking.lock().unwrap() = true;
-
-        thread::spawn(move || {
-            //iterate through our buffer, generating chunk blocks
-            for (chunk, remesh, _) in &mut buffer {
-                //TODO add throttling
-                if ! *remesh {
-                    chunk.GenerateBlocks(biomeGens
-                                                   .lock()
-                                                   .unwrap()
-                                                   .get_mut(&Biome::Forest)
-                                                   .unwrap()
-                                        );
-                }
+                    //this may have been the last missing neighbour of a chunk waiting to be meshed
+                    self.TryScheduleMesh(pos);
+                    for offset in ADJACENT_OFFSETS {
+                        self.TryScheduleMesh((pos.0 + offset.0, pos.1 + offset.1));
+                    }
+                },
+                FinishedJob::Meshed(result) => self.UnappliedMeshes.push(result)
             }
+        }
 
-            //add any new adjacencies from the chunks we just created
-            //TODO find a way to optomize this because copying all these chunks is bad ):
-            let copy = buffer.clone();
-            for (chunk_, _, adj) in &mut buffer {
-                let pos = chunk_.Position;
-
-                for (chunk, _, _) in &copy {
-                    let targetPos = chunk.Position;
-
-                    for a in 0..2 {
-                            let newPos = na::Vector2::new(pos.0 + d[a], pos.1);
-                            if newPos.x == targetPos.0 && newPos.y == targetPos.1 {
-                                adj[a] = Some(Arc::new(chunk.clone()));
-                            }
-
-                            let newPos = na::Vector2::new(pos.0, pos.1 + d[a]);
-                            if newPos.x == targetPos.0 && newPos.y == targetPos.1 {
-                                adj[a + 2] = Some(Arc::new(chunk.clone()));
-                            }
-                    }
+        let results = std::mem::take(&mut self.UnappliedMeshes);
+        for result in results {
+            let radius = self.RenderDistance as i32;
+            let chunk = match self.Chunks.get_mut(&na::Vector2::new(result.Position.0, result.Position.1)) {
+                Some(chunk) => chunk,
+                None => continue
+            };
+
+            //a neighbour's mesh job may still hold a reference. Wait for it instead of cloning the whole chunk
+            let chunk = match Arc::get_mut(chunk) {
+                Some(chunk) => chunk,
+                None => {
+                    self.UnappliedMeshes.push(result);
+                    continue;
                 }
-            }
+            };
 
-            //now mesh the chunks
-            for (mut chunk, _, adj) in buffer.into_iter() {
-                chunk.GreedyMesh(&adj, &blockReg);
-                tx.send(Arc::new(chunk)).unwrap();
+            //left the render distance while it was being meshed, so it goes back to being part of the margin
+            if !InRadius(result.Position, self.TargetPosition, radius) {
+                chunk.Mesh = Vec::new();
+                chunk.Status = ChunkState::Decorated;
+                continue;
             }
+            chunk.Mesh = result.Mesh;
+            chunk.Lod = result.Lod;
+            chunk.Status = ChunkState::Meshed;
+        }
+    }
 
-            //set the bool to false or send a message to signify its all over
-            *isWorking.lock().unwrap() = false;
+    //Schedules a mesh for a chunk inside the render distance that hasn't been meshed yet, once its neighbours are ready
+    fn TryScheduleMesh(&mut self, pos: (i32, i32)) {
+        if !InRadius(pos, self.TargetPosition, self.RenderDistance as i32) {
+            return;
+        }
+        match self.Chunks.get(&na::Vector2::new(pos.0, pos.1)) {
+            Some(chunk) if chunk.Status < ChunkState::Meshed => {},
+            _ => return
+        }
 
-        });
+        if NeighboursReady(&GatherAdjacent(pos, &self.Chunks)) {
+            self.Scheduler.Schedule(pos, JobKind::Mesh);
+        }
     }
 
     fn TranslateChunks(&mut self, oldPos: (i32, i32), newPos: (i32, i32)){
-        let radius: i32 = self.RenderDistance.try_into().unwrap();
+        let radius = self.RenderDistance as i32;
+        let genRadius = self.GenerationRadius();
 
-        let inRadius = |pos: (i32, i32), center: (i32, i32) | {
-            return pos.0 >= center.0 - radius &&
-                   pos.0 <= center.0 + radius &&
-                   pos.1 >= center.1 - radius &&
-                   pos.1 <= center.1 + radius;
-        };
-
-        let mut vec = Vec::new();
-
-        for offY in -radius..=radius {
-            for offX in -radius..=radius {
+        for offY in -genRadius..=genRadius {
+            for offX in -genRadius..=genRadius {
 
                 let newPosOff = (newPos.0 + offX, newPos.1 + offY);
                 //First, add chunks that are in the newPos area and NOT in the oldPos area
-                if !inRadius(newPosOff, oldPos) {
-                    let chunk = Chunk::New(newPosOff, 0.0f32);
-                    vec.push((false, chunk));
+                if InRadius(newPosOff, newPos, genRadius) && !InRadius(newPosOff, oldPos, genRadius) {
+                    self.Scheduler.Schedule(newPosOff, JobKind::Generate);
                 }
 
                 let oldPosOff = (oldPos.0 + offX, oldPos.1 + offY);
                 //Next, for chunks in the old radius and not in the new one, add to the removal queue
-                if !inRadius(oldPosOff, newPos) {
+                if InRadius(oldPosOff, oldPos, genRadius) && !InRadius(oldPosOff, newPos, genRadius) {
                     self.RemovalQueue.push_back(na::Vector2::new(oldPosOff.0, oldPosOff.1));
                 }
 
             }
         }
 
-        //frontier chunks are adjacent to new chunks
-        //we need to remesh all frontier chunks
-        //We want these at the end of the buffer so we do another for loop
-        //find a better way of doing this
-        for offY in -radius..=radius {
-            for offX in -radius..=radius {
-                let newPosOff = (newPos.0 + offX, newPos.1 + offY);
-                let oldPosOff = (oldPos.0 + offX, oldPos.1 + offY);
+        for offY in -genRadius..=genRadius {
+            for offX in -genRadius..=genRadius {
+                let pos = (newPos.0 + offX, newPos.1 + offY);
+                let wasRendered = InRadius(pos, oldPos, radius);
+                let isRendered = InRadius(pos, newPos, radius);
 
-                if !inRadius(newPosOff, oldPos) {
-                    continue;
+                //Margin chunks that moved inside the render distance get meshed (if their neighbours are ready)
+                if isRendered && !wasRendered {
+                    self.TryScheduleMesh(pos);
                 }
-               
-                let mut exit = false;
-                let d = [-1_i32, 1];
-                for a in 0..2 {
-                    for b in 0..2 {
-                        let offPos = (newPosOff.0 + d[a], newPosOff.1 + d[b]);
-                        //check if this adjacent position is a NEW CHUNK
-                        if !inRadius(offPos, oldPos) {
-                            //if it is, remesh this little guy
-                            let v = na::Vector2::new(newPosOff.0, newPosOff.1);
-                            vec.push((true, self.Chunks.get(&v).unwrap().as_ref().clone()));
-
-                            exit = true;
-                            break;
-                        }
+                //and chunks that moved out to the margin drop their mesh
+                else if wasRendered && !isRendered && InRadius(pos, newPos, genRadius) {
+                    self.UnappliedMeshes.push(MeshResult { Position: pos, Lod: LodLevel::ForChunk(pos, newPos), Mesh: Vec::new() });
+                }
+                //Chunks that stayed rendered but crossed a level of detail ring (or border a chunk that did) need a new mesh
+                else if isRendered && wasRendered {
+                    let lodChanged = LodLevel::ForChunk(pos, oldPos) != LodLevel::ForChunk(pos, newPos) ||
+                                     LodLevel::ForNeighbours(pos, oldPos) != LodLevel::ForNeighbours(pos, newPos);
+                    let meshed = self.Chunks.get(&na::Vector2::new(pos.0, pos.1))
+                                 .map_or(false, |c| c.Status == ChunkState::Meshed);
+                    if lodChanged && meshed {
+                        self.Scheduler.Schedule(pos, JobKind::Mesh);
                     }
-                    if exit {break;}
                 }
-                if exit {break};
-
             }
         }
-
-        self.WorkerQueue.push_back(vec);
-
-        //TODO just send a message to the thread to quit its execution
-        //TODO do this later. This is an optimization. It is not neccesary
-
     }
 
     pub fn RenderListUpdate(&mut self){
         let extents: i32 = self.RenderDistance.try_into().unwrap();
 
+        //chunks get swapped out when they're remeshed, so rebuild the list instead of keeping stale pointers around
+        self.RenderList.clear();
         for a in -extents..=extents {
             for b in -extents..=extents {
                 let pos = na::Vector2::new(a + self.TargetPosition.0, b + self.TargetPosition.1);
+                if !InRadius((pos.x, pos.y), self.TargetPosition, extents) {
+                    continue;
+                }
 
-                if let Some(chunk) = self.Chunks.get(&pos){
-                    self.RenderList.insert(chunk.as_ref() as *const Chunk);
+                match self.Chunks.get(&pos) {
+                    Some(chunk) if chunk.Status == ChunkState::Meshed => {
+                        self.RenderList.insert(chunk.as_ref() as *const Chunk);
+                    },
+                    _ => {}
                 }
      
             }
@@ -312,64 +378,909 @@ impl World{
     }
 
 
-    pub fn RenderDistanceUpdate(&mut self, renderDistance: usize){
-        if renderDistance >= MAX_RENDER_DISTANCE {
-            eprintln!("Error! Cannot change render distance to {} 
-                      since it is {} above the max render distance of {}!",
-                      renderDistance, renderDistance - MAX_RENDER_DISTANCE,
-                      MAX_RENDER_DISTANCE);
-            return;
+    /*
+        Grows or shrinks the render distance around the current target. Chunks that enter the
+        generation radius are queued, chunks that leave it are unloaded, and chunks that cross the
+        render distance are meshed or dropped back to the unmeshed margin. The old border ring never
+        needs to be remeshed, since it was meshed against a decorated margin ring in the first place
+    */
+    pub fn RenderDistanceUpdate(&mut self, renderDistance: usize) -> Result<(), String> {
+        if renderDistance > MAX_RENDER_DISTANCE {
+            return Err(format!("Error! Cannot change render distance to {} since it is above the max render distance of {}!",
+                               renderDistance, MAX_RENDER_DISTANCE));
         }
-        else if renderDistance == self.RenderDistance {
-            return;
+        else if renderDistance == self.RenderDistance && !self.Chunks.is_empty() {
+            return Ok(());
         }
 
-        let increase = renderDistance > self.RenderDistance;
-
         let target = self.TargetPosition;
-        let mut newChunks = Vec::with_capacity({
-            let sq = (renderDistance * 2 + 1) * (renderDistance * 2 + 1);
-            let sq2 = (self.RenderDistance * 2 + 1) * (self.RenderDistance * 2 + 1);
-            let rem = sq - sq2;
-            rem.max(0)
-        });
+        let oldRadius = self.RenderDistance as i32;
+        let oldGenRadius = self.GenerationRadius();
+        self.RenderDistance = renderDistance;
+        let radius = self.RenderDistance as i32;
+        let genRadius = self.GenerationRadius();
+
+        //anything loaded past the new generation radius is unloaded, wherever it came from
+        let outside: Vec<na::Vector2<i32>> = self.Chunks.keys()
+                                             .filter(|pos| !InRadius((pos.x, pos.y), target, genRadius))
+                                             .cloned()
+                                             .collect();
+        for pos in outside {
+            self.UnloadChunk(pos);
+        }
 
-        let extents: i32 = renderDistance.try_into().unwrap();
+        let extents = i32::max(oldGenRadius, genRadius);
         for a in -extents..=extents {
             for b in -extents..=extents {
+                let pos = (a + target.0, b + target.1);
+                //jobs queued past the new radius are dropped by Retarget below
+                if !InRadius(pos, target, genRadius) {
+                    continue;
+                }
 
-                let pos = na::Vector2::new(a + target.0, b + target.1);
-
-                if self.RenderDistance == 0 || 
-                   (pos.x < -extents || pos.x > extents) && 
-                   (pos.y < -extents || pos.y > extents) 
-                {
-                     if increase {
-                        let chunk = Chunk::New((pos.x, pos.y), 0.0f32);
-                        newChunks.push((false, chunk));
-                     } 
-                     else {
-                        self.Chunks.remove(&pos);
-                     }
+                if !self.Chunks.contains_key(&na::Vector2::new(pos.0, pos.1)) {
+                    self.Scheduler.Schedule(pos, JobKind::Generate);
+                    continue;
+                }
+
+                let wasRendered = InRadius(pos, target, oldRadius);
+                let isRendered = InRadius(pos, target, radius);
+                if isRendered && !wasRendered {
+                    self.TryScheduleMesh(pos);
                 }
-                else if pos.x.abs() == extents && pos.y.abs() == extents{
-                    //Remesh all chunks on the ORIGINAL outer ring
-                    //TODO remesh the inner chunks
+                else if wasRendered && !isRendered {
+                    self.UnappliedMeshes.push(MeshResult { Position: pos, Lod: LodLevel::ForChunk(pos, target), Mesh: Vec::new() });
                 }
+            }
+        }
 
+        self.Scheduler.Retarget(target, genRadius);
+        Ok(())
+    }
+
+    //Changes the render distance by delta, clamped to the allowed range
+    pub fn RenderDistanceStep(&mut self, delta: i32) {
+        let renderDistance = (self.RenderDistance as i32 + delta).clamp(0, MAX_RENDER_DISTANCE as i32) as usize;
+        //can't fail, the value was clamped
+        self.RenderDistanceUpdate(renderDistance).ok();
+    }
+
+    fn UnloadChunk(&mut self, pos: na::Vector2<i32>) {
+        if let Some(chunk) = self.Chunks.remove(&pos) {
+            if let (true, Some(dir)) = (chunk.Modified, &self.SaveDir) {
+                if let Err(msg) = chunk.Save(dir) {
+                    println!("{}", msg);
+                }
             }
         }
+        self.Scheduler.Cancel((pos.x, pos.y));
+    }
 
-        self.RenderDistance = renderDistance;
-        self.WorkerQueue.push_back(newChunks);
+    //Picks up the time of day saved with the world
+    pub fn SetSaveDir(&mut self, saveDir: PathBuf) {
+        match DayCycle::Load(&saveDir) {
+            Ok(cycle) => self.DayCycle = cycle,
+            Err(msg) => println!("{}", msg)
+        }
+        self.SaveDir = Some(saveDir);
     }
 
-    
+    //Writes the time of day and every loaded chunk that changed. Unloaded chunks were already saved on their way out
+    pub fn Save(&self) -> Result<(), String> {
+        let dir = match &self.SaveDir {
+            Some(dir) => dir,
+            None => return Ok(())
+        };
+        self.DayCycle.Save(dir)?;
+        for chunk in self.Chunks.values().filter(|c| c.Modified) {
+            chunk.Save(dir)?;
+        }
+        Ok(())
+    }
+
+    pub fn GetBlock(&self, pos: (i32, i32, i32)) -> Option<Block> {
+        let (chunkPos, local) = ToChunkCoordinate(pos)?;
+        self.Chunks.get(&chunkPos).map(|chunk| chunk.GetBlockAt(local))
+    }
 
+    /*
+        Places a block into air or a fluid (which it replaces) and runs its OnPlace, and OnPlacedBy if the player placed it.
+        Fails if the chunk isn't loaded
+    */
+    pub fn PlaceBlock(&mut self, pos: (i32, i32, i32), block: Block, placement: Option<&Placement>) -> bool {
+        let existing = match self.GetBlock(pos) {
+            Some(existing) => existing,
+            None => return false
+        };
+        if self.BlockRegistry.IsSolid(existing) {
+            return false;
+        }
+
+        //a fluid's OnBreak still runs and whatever it held is dropped where it was.
+        //Its neighbours only hear about the block that replaced it
+        if existing != Block::Air() {
+            let center = na::Vector3::new(pos.0 as f32 + 0.5f32, pos.1 as f32 + 0.5f32, pos.2 as f32 + 0.5f32);
+            for stack in self.RemoveBlock(pos).unwrap_or_default() {
+                self.DroppedItems.push(DroppedItem::New(center, stack));
+            }
+        }
+
+        let placed = match self.ChunkAt(pos) {
+            Some((chunk, local)) => chunk.EmplaceBlock(local, &block),
+            None => false
+        };
+        if placed {
+            self.RunBlockHook(pos, |behavior, context, state| behavior.OnPlace(context, state));
+            if let Some(placement) = placement {
+                self.RunBlockHook(pos, |behavior, context, state| behavior.OnPlacedBy(context, state, placement));
+            }
+            self.BlockChanged(pos);
+        }
+        placed
+    }
 
+    //Replaces the state of a block, like a behavior changing a block other than its own
+    pub fn SetBlockState(&mut self, pos: (i32, i32, i32), state: HashMap<String, State>) -> bool {
+        match self.ChunkAt(pos) {
+            Some((chunk, local)) if chunk.GetBlockAt(local) != Block::Air() => chunk.SetBlockState(local, state),
+            _ => return false
+        }
+        self.Remesh(pos);
+        true
+    }
+
+    //Breaks a block and returns what its OnBreak spilled, or None if there's nothing loaded there to break
+    pub fn BreakBlock(&mut self, pos: (i32, i32, i32)) -> Option<Vec<ItemStack>> {
+        let drops = self.RemoveBlock(pos)?;
+        self.BlockChanged(pos);
+        Some(drops)
+    }
+
+    //Runs a block's OnBreak and replaces it with air, without telling anything it changed
+    fn RemoveBlock(&mut self, pos: (i32, i32, i32)) -> Option<Vec<ItemStack>> {
+        match self.GetBlock(pos) {
+            Some(block) if block != Block::Air() => {},
+            _ => return None
+        }
+
+        //the chunk is loaded, so neither this nor the ChunkAt below can fail
+        let drops = self.RunBlockHook(pos, |behavior, context, state| behavior.OnBreak(context, state)).unwrap_or_default();
+        let (chunk, local) = self.ChunkAt(pos)?;
+        //the hook may have broken or replaced the block itself
+        chunk.DestroyBlock(local);
+        if self.OpenBlock.as_ref().map_or(false, |open| open.Position == pos) {
+            self.OpenBlock = None;
+        }
+        Some(drops)
+    }
+
+    //Whether the block at a position is loaded and solid (see BlockRegistry::IsSolid)
+    pub fn IsSolidBlock(&self, pos: (i32, i32, i32)) -> bool {
+        self.GetBlock(pos).is_some_and(|block| self.BlockRegistry.IsSolid(block))
+    }
+
+    //The first solid block along a ray within the player's reach. Air and fluids don't stop the ray, and neither do unloaded chunks
+    pub fn TargetBlock(&self, origin: na::Vector3<f32>, direction: na::Vector3<f32>) -> Option<BlockHit> {
+        Raycast(origin, direction, PLAYER_REACH, |pos| self.IsSolidBlock(pos))
+    }
+
+    /*
+        Keeps mining the block at a position with whatever is in the selected hotbar slot.
+        Once it breaks, its drops and whatever its containers held go to the player's inventory,
+        and what doesn't fit is dropped in the world where the block was
+    */
+    pub fn MineBlock(&mut self, pos: (i32, i32, i32), deltaTime: f32) -> MiningProgress {
+        if !self.IsSolidBlock(pos) {
+            self.Mining.Stop();
+            return MiningProgress::Idle;
+        }
+
+        if !self.Mining.IsTarget(pos) {
+            let hit = self.PlayerInventory.Selected().map_or(ItemStack::Empty().Item, |stack| stack.Item.clone());
+            self.RunBlockHook(pos, |behavior, context, state| behavior.OnLeftClick(context, state, &hit));
+        }
+
+        //the hook can change the block, in which case mining starts over on whatever is there now
+        let block = self.GetBlock(pos).unwrap_or(Block::Air());
+        let attribute = self.BlockRegistry.GetAttributesOf(&block);
+        let held = self.PlayerInventory.Selected();
+        let progress = self.Mining.Update(pos, block, attribute, held, &self.ItemRegistry, deltaTime);
+        if progress != MiningProgress::Broken {
+            return progress;
+        }
+
+        let mut drops = BlockDrops(self.BlockRegistry.GetAttributesOf(&block), &self.ItemRegistry);
+        match self.BreakBlock(pos) {
+            Some(spilled) => drops.extend(spilled),
+            //nothing could be broken after all. It's only Broken once it actually is, so stay at full progress and try again next update
+            None => return MiningProgress::Mining(self.Mining.Progress())
+        }
+        self.Mining.Stop();
+        self.WearSelectedItem(&block);
+        self.PlayerHunger.Exhaust(BREAK_EXHAUSTION);
+
+        let center = na::Vector3::new(pos.0 as f32 + 0.5f32, pos.1 as f32 + 0.5f32, pos.2 as f32 + 0.5f32);
+        for stack in drops {
+            if let Some(leftover) = self.PlayerInventory.Insert(stack, &self.ItemRegistry) {
+                self.DroppedItems.push(DroppedItem::New(center, leftover));
+            }
+        }
+        MiningProgress::Broken
+    }
+
+    //Runs the selected item's OnBlockMined for a block it just broke, and takes the item away if that used it up
+    fn WearSelectedItem(&mut self, block: &Block) {
+        let slot = self.PlayerInventory.SelectedSlot();
+        let stack = match self.PlayerInventory.Slots.get_mut(slot) {
+            Some(stack) if !stack.IsEmpty() => stack,
+            _ => return
+        };
+
+        let id = stack.Item.ItemID.ID;
+        let blockAttribute = self.BlockRegistry.GetAttributesOf(block);
+        if self.ItemRegistry.GetBehavior(id).OnBlockMined(self.ItemRegistry.GetAttributesOfID(id), &mut stack.Item, blockAttribute) {
+            self.PlayerInventory.TakeSelected(1);
+        }
+    }
+
+    pub fn StopMining(&mut self) {
+        self.Mining.Stop();
+    }
+
+    pub fn GetMining(&self) -> &Mining {
+        &self.Mining
+    }
+
+    pub fn GetDroppedItems(&self) -> &Vec<DroppedItem> {
+        &self.DroppedItems
+    }
+
+    //Moves dropped items along. Ones that fell out of the bottom of the world are gone
+    pub fn UpdateDroppedItems(&mut self, deltaTime: f32) {
+        let mut dropped = std::mem::take(&mut self.DroppedItems);
+        for item in dropped.iter_mut() {
+            item.Update(deltaTime, |pos| self.IsSolidBlock(pos));
+        }
+        dropped.retain(|item| item.Position.y >= 0f32);
+        self.DroppedItems = dropped;
+    }
+
+    //Moves dropped items within radius of a position into the player's inventory, as far as they fit
+    pub fn PickUpItems(&mut self, position: na::Vector3<f32>, radius: f32) {
+        let itemRegistry = &self.ItemRegistry;
+        let inventory = &mut self.PlayerInventory;
+        self.DroppedItems.retain_mut(|dropped| {
+            if dropped.PickupDelay > 0f32 || (dropped.Position - position).norm() > radius {
+                return true;
+            }
+            match inventory.Insert(dropped.Stack.clone(), itemRegistry) {
+                Some(leftover) => {
+                    dropped.Stack = leftover;
+                    true
+                },
+                None => false
+            }
+        });
+    }
+
+    /*
+        Uses the selected item through its OnUse, with 'hit' being the block looked at, if any.
+        Placeable items put their block in the cell in front of the hit face, as long as it's empty and wouldn't end up
+        inside the player (the block's OnPlace sets up its state). Food is eaten unless the player is full,
+        and throwable items are thrown along 'direction'. Each uses up one item. Returns true if anything happened
+    */
+    pub fn UseSelectedItem(&mut self, hit: Option<&BlockHit>, eye: na::Vector3<f32>, direction: na::Vector3<f32>) -> bool {
+        let (itemID, item) = match self.PlayerInventory.Selected() {
+            Some(stack) => (stack.Item.ItemID.ID, stack.Item.clone()),
+            None => return false
+        };
+
+        match self.ItemRegistry.GetBehavior(itemID).OnUse(self.ItemRegistry.GetAttributesOfID(itemID), &item) {
+            ItemUse::PlaceBlock(block) => {
+                let hit = match hit {
+                    //if the ray started inside a block there's no face to place against
+                    Some(hit) if hit.Previous != hit.Position => hit,
+                    _ => return false
+                };
+                //decoration like flowers has no collision, so it can go where the player stands
+                if !self.BlockRegistry.GetAttributesOf(&block).Decor && PlayerOverlapsBlock(eye, hit.Previous) {
+                    return false;
+                }
+                if !self.PlaceBlock(hit.Previous, block, Some(&PlacementOf(hit, eye, direction))) {
+                    return false;
+                }
+            },
+            ItemUse::Eat { Food, Saturation } => {
+                if !self.PlayerHunger.Eat(Food, Saturation) {
+                    return false;
+                }
+            },
+            ItemUse::Throw => {
+                let direction = match direction.try_normalize(f32::EPSILON) {
+                    Some(val) => val,
+                    None => return false
+                };
+                if let Some(stack) = self.PlayerInventory.TakeSelected(1) {
+                    let mut thrown = DroppedItem::New(eye + direction * 0.5f32, stack);
+                    thrown.Velocity = direction * THROW_SPEED;
+                    thrown.PickupDelay = 1f32;
+                    self.DroppedItems.push(thrown);
+                }
+                return true;
+            },
+            ItemUse::Nothing => return false
+        }
+
+        self.PlayerInventory.TakeSelected(1);
+        true
+    }
+
+    /*
+        Uses a block through its OnUse, like opening a chest. Returns false if the block didn't react,
+        in which case the held item should be used on it instead
+    */
+    pub fn RightClickBlock(&mut self, pos: (i32, i32, i32)) -> bool {
+        match self.GetBlock(pos) {
+            Some(block) if block != Block::Air() => {},
+            _ => return false
+        }
+
+        match self.RunBlockHook(pos, |behavior, context, state| behavior.OnUse(context, state)) {
+            Some(BlockUse::OpenScreen) => {
+                self.OpenBlock = Some(OpenBlock { Position: pos });
+                true
+            },
+            Some(BlockUse::Used) => true,
+            Some(BlockUse::Pass) | None => false
+        }
+    }
+
+    //Hands an event to the open block's screen, closing it if the screen is done
+    pub fn OnOpenBlockEvent(&mut self, event: &Event) -> bool {
+        let pos = match &self.OpenBlock {
+            Some(open) => open.Position,
+            None => return false
+        };
+
+        let close = match self.GetBlock(pos) {
+            Some(block) if block != Block::Air() => {
+                //a chunk a worker holds can't be handed over, so the event is dropped and the screen stays open
+                self.RunBlockHook(pos, |behavior, context, state| behavior.OnScreenEvent(context, state, event)).unwrap_or(false)
+            },
+            _ => true
+        };
+        if close {
+            self.OpenBlock = None;
+        }
+        true
+    }
+
+    //Runs the hook of the block under the player's feet when they step onto a new one
+    pub fn UpdatePlayerPosition(&mut self, eye: na::Vector3<f32>) {
+        let feet = ((eye.x).floor() as i32, (eye.y - PLAYER_EYE_HEIGHT - 0.01f32).floor() as i32, (eye.z).floor() as i32);
+        let standingOn = if self.IsSolidBlock(feet) { Some(feet) } else { None };
+
+        if standingOn != self.PlayerStep {
+            self.PlayerStep = standingOn;
+            if let Some(pos) = standingOn {
+                self.StepOnBlock(pos, &Stepper::Player);
+            }
+        }
+    }
+
+    pub fn StepOnBlock(&mut self, pos: (i32, i32, i32), stepper: &Stepper) {
+        self.RunBlockHook(pos, |behavior, context, state| behavior.OnEntityStep(context, state, stepper));
+    }
+
+    pub fn GetOpenBlock(&self) -> Option<&OpenBlock> {
+        self.OpenBlock.as_ref()
+    }
+
+    pub fn CloseBlock(&mut self) {
+        self.OpenBlock = None;
+    }
+
+    /*
+        The slots of a container attribute (like a chest's "Items") of the open block.
+        Marks the chunk modified, since the caller is about to move items in or out
+    */
+    pub fn OpenedContainer(&mut self, attribute: &str) -> Option<&mut Vec<ItemStack>> {
+        let pos = self.OpenBlock.as_ref()?.Position;
+        let (chunk, local) = self.ChunkAt(pos)?;
+        chunk.Modified = true;
+        let state = chunk.GetBlockStateAt(local)?.get_mut(attribute)?;
+        ContainerSlots(state)
+    }
+
+    /*
+        Runs one of the behavior hooks of the block at a position, with its state taken out of the chunk for the
+        duration (see BlockContext) and put back in the same map. None if the chunk isn't loaded or a worker holds it
+    */
+    fn RunBlockHook<R>(&mut self, pos: (i32, i32, i32), hook: impl FnOnce(&dyn BlockBehavior, &mut BlockContext, &mut HashMap<String, State>) -> R) -> Option<R> {
+        let blockRegistry = self.BlockRegistry.clone();
+        let (chunk, local) = self.ChunkAt(pos)?;
+        let block = chunk.GetBlockAt(local);
+        let (mut state, store) = chunk.TakeBlockState(local).unwrap_or_default();
+
+        //A model can depend on the block's state, in which case changing the state has to redraw the block
+        let model = blockRegistry.GetAttributesOf(&block).Model.as_ref();
+        let drawn = model.map(|m| m.Selected(Some(&state)));
+
+        let mut context = BlockContext { World: self, Position: pos, Block: block, Attributes: blockRegistry.GetAttributesOf(&block) };
+        let result = hook(blockRegistry.GetBehavior(block.ID), &mut context, &mut state);
+
+        let redraw = model.map(|m| m.Selected(Some(&state))) != drawn;
+        if let Some((chunk, local)) = self.ChunkAt(pos) {
+            if chunk.GetBlockAt(local) == block {
+                chunk.PutBlockState(local, state, store);
+                if redraw {
+                    self.Remesh(pos);
+                }
+            }
+        }
+        Some(result)
+    }
+
+    //Runs OnNeighborChanged for queued blocks until the queue is empty or the per call limit is hit
+    fn ProcessBlockUpdates(&mut self) {
+        if self.UpdatingBlocks {
+            return;
+        }
+        self.UpdatingBlocks = true;
+
+        for _ in 0..MAX_BLOCK_UPDATES {
+            let (pos, neighbor) = match self.BlockUpdates.pop_front() {
+                Some(val) => val,
+                None => break
+            };
+            if self.GetBlock(pos).map_or(false, |block| block != Block::Air()) {
+                self.RunBlockHook(pos, |behavior, context, state| behavior.OnNeighborChanged(context, state, neighbor));
+            }
+        }
+        self.UpdatingBlocks = false;
+    }
+
+    pub fn GetBlockRegistry(&self) -> &BlockRegistry {
+        &self.BlockRegistry
+    }
+
+    pub fn GetItemRegistry(&self) -> &ItemRegistry {
+        &self.ItemRegistry
+    }
+
+    pub fn GetSmeltingRegistry(&self) -> &SmeltingRegistry {
+        &self.SmeltingRegistry
+    }
+
+    /*
+        The chunk a block is in, if it's loaded. A worker still meshing it keeps the copy it was given
+        and the edit goes to a copy of our own, which the remesh the edit schedules will pick up
+    */
+    fn ChunkAt(&mut self, pos: (i32, i32, i32)) -> Option<(&mut Chunk, (u32, u32, u32))> {
+        let (chunkPos, local) = ToChunkCoordinate(pos)?;
+        let slot = self.Chunks.get_mut(&chunkPos)?;
+        let before = Arc::as_ptr(slot);
+        let chunk = Arc::make_mut(slot);
+        if !std::ptr::eq(before, chunk) && self.RenderList.remove(&before) {
+            self.RenderList.insert(chunk as *const Chunk);
+        }
+        Some((chunk, local))
+    }
+
+    //Remeshes the chunk a changed block is in, and the six blocks touching it are told through OnNeighborChanged
+    fn BlockChanged(&mut self, pos: (i32, i32, i32)) {
+        self.Remesh(pos);
+
+        for offset in NEIGHBOR_OFFSETS {
+            self.BlockUpdates.push_back(((pos.0 + offset.0, pos.1 + offset.1, pos.2 + offset.2), pos));
+        }
+        self.ProcessBlockUpdates();
+    }
+
+    //Remeshes the chunk a block is in, and the neighbour it borders if it's on an edge
+    fn Remesh(&mut self, pos: (i32, i32, i32)) {
+        let (chunkPos, local) = match ToChunkCoordinate(pos) {
+            Some(val) => val,
+            None => return
+        };
+
+        let mut affected = vec![(chunkPos.x, chunkPos.y)];
+        if local.0 == 0 { affected.push((chunkPos.x - 1, chunkPos.y)); }
+        if local.0 == CHUNK_BOUNDS_X - 1 { affected.push((chunkPos.x + 1, chunkPos.y)); }
+        if local.2 == 0 { affected.push((chunkPos.x, chunkPos.y - 1)); }
+        if local.2 == CHUNK_BOUNDS_Z - 1 { affected.push((chunkPos.x, chunkPos.y + 1)); }
+
+        for pos in affected {
+            let meshed = self.Chunks.get(&na::Vector2::new(pos.0, pos.1)).map_or(false, |c| c.Status == ChunkState::Meshed);
+            if meshed {
+                self.Scheduler.Schedule(pos, JobKind::Mesh);
+            }
+        }
+    }
+
+    pub fn GetRenderDistance(&self) -> usize {
+        self.RenderDistance
+    }
+
+    pub fn GetTargetPosition(&self) -> (i32, i32) {
+        self.TargetPosition
+    }
+
+    pub fn GetSeed(&self) -> u64 {
+        self.Seed
+    }
+
+    pub fn GetDayCycle(&self) -> &DayCycle {
+        &self.DayCycle
+    }
+
+    //Whether every chunk job has come back from the workers and been applied
+    pub fn IsIdle(&self) -> bool {
+        self.Scheduler.NumPending() == 0 && self.Scheduler.NumInFlight() == 0 && self.UnappliedMeshes.is_empty()
+    }
+
+    pub fn GetChunkStats(&self) -> ChunkStats {
+        let meshed = self.Chunks.values().filter(|c| c.Status == ChunkState::Meshed);
+        ChunkStats {
+            Loaded: self.Chunks.len(),
+            Meshed: meshed.clone().count(),
+            Pending: self.Scheduler.NumPending(),
+            InFlight: self.Scheduler.NumInFlight(),
+            Vertices: meshed.map(|c| c.Mesh.len()).sum(),
+        }
+    }
+
+}
+
+const SEED_FILE: &str = "seed.json";
+
+//The seed a world was created with, saved next to its chunks
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(deny_unknown_fields)]
+struct WorldSeed {
+    Seed: u64,
 }
 
+impl WorldSeed {
+    //None if the world hasn't been created yet
+    fn Load(saveDir: &Path) -> Result<Option<Self>, String> {
+        let path = saveDir.join(SEED_FILE);
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let file = std::fs::File::open(&path)
+        .map_err(|e| format!("Error! Could not open the world seed {}! The error:\n{}", path.display(), e))?;
+        serde_json::from_reader(std::io::BufReader::new(file))
+        .map_err(|e| format!("Error! The world seed {} is corrupted! The error:\n{}", path.display(), e))
+    }
+
+    fn Save(&self, saveDir: &Path) -> Result<(), String> {
+        std::fs::create_dir_all(saveDir)
+        .map_err(|e| format!("Error! Could not create the save directory {}! The error:\n{}", saveDir.display(), e))?;
+
+        let path = saveDir.join(SEED_FILE);
+        let serialized = serde_json::to_string_pretty(self)
+        .map_err(|e| format!("Error! Could not serialize the world seed! The error:\n{}", e))?;
+        std::fs::write(&path, serialized)
+        .map_err(|e| format!("Error! Could not write the world seed {}! The error:\n{}", path.display(), e))
+    }
+}
+
+//The chunk a point on the ground is in, always the same one ToChunkCoordinate gives for the block there
 fn ToChunkPos(pos: (f32, f32)) -> (i32, i32){
-    (( ( pos.0 - if pos.0 < 0f32 {CHUNK_BOUNDS_X as f32} else {0f32} ) / CHUNK_BOUNDS_X as f32) as i32, 
-    ( ( pos.1 - if pos.1 < 0f32 {CHUNK_BOUNDS_Z as f32} else {0f32} ) / CHUNK_BOUNDS_Z as f32) as i32)
-}
\ No newline at end of file
+    let (chunkPos, _) = ToChunkCoordinate((pos.0.floor() as i32, 0, pos.1.floor() as i32)).unwrap();
+    (chunkPos.x, chunkPos.y)
+}
+
+//The chunk a block is in and its position inside that chunk. None if it's above or below the world
+pub fn ToChunkCoordinate(pos: (i32, i32, i32)) -> Option<(na::Vector2<i32>, (u32, u32, u32))> {
+    if pos.1 < 0 || pos.1 >= CHUNK_BOUNDS_Y as i32 {
+        return None;
+    }
+    let (bx, bz) = (CHUNK_BOUNDS_X as i32, CHUNK_BOUNDS_Z as i32);
+    Some((na::Vector2::new(pos.0.div_euclid(bx), pos.2.div_euclid(bz)),
+          (pos.0.rem_euclid(bx) as u32, pos.1 as u32, pos.2.rem_euclid(bz) as u32)))
+}
+
+//Chunks are loaded in a circle around the target instead of a square
+pub fn InRadius(pos: (i32, i32), center: (i32, i32), radius: i32) -> bool {
+    let d = (pos.0 - center.0, pos.1 - center.1);
+    d.0 * d.0 + d.1 * d.1 <= radius * radius
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::World::{idMap::IdMap, chunk::StateStore, blockBehavior::UPPER, ReadAttributes};
+
+    fn TestRegistries() -> (CraftingRegistry, SmeltingRegistry, BlockRegistry, ItemRegistry, ContentPacks) {
+        let packs = ContentPacks::Core().unwrap();
+        let (mut blocks, mut items, mut crafting, mut smelting) = (BlockRegistry::New(), ItemRegistry::New(), CraftingRegistry::New(), SmeltingRegistry::New());
+        ReadAttributes(&mut blocks, &mut items, &mut crafting, &mut smelting, &packs, &mut IdMap::default()).unwrap();
+        (crafting, smelting, blocks, items, packs)
+    }
+
+    //A world of the core content with nothing loaded past the default render distance, like a headless run
+    fn TestWorld() -> World {
+        let (crafting, smelting, blocks, items, packs) = TestRegistries();
+        World::NewSeeded(crafting, smelting, blocks, items, &packs, 1)
+    }
+
+    //The middle of a chunk, in block coordinates
+    fn CenterOf(chunk: (i32, i32)) -> (f32, f32) {
+        ((chunk.0 * CHUNK_BOUNDS_X as i32) as f32 + 7.5, (chunk.1 * CHUNK_BOUNDS_Z as i32) as f32 + 7.5)
+    }
+
+    //Updates the world until every job is done and everything inside the render distance is meshed
+    fn Settle(world: &mut World, target: (i32, i32)) {
+        let camera = Camera::New();
+        let start = std::time::Instant::now();
+        loop {
+            world.Update(CenterOf(target), &camera);
+            let stats = world.GetChunkStats();
+            let radius = world.GetRenderDistance() as i32;
+            let meshed = world.Chunks.iter().filter(|(pos, _)| InRadius((pos.x, pos.y), target, radius))
+                                            .all(|(_, chunk)| chunk.Status == ChunkState::Meshed);
+            if world.IsIdle() && meshed {
+                return;
+            }
+            assert!(start.elapsed().as_secs() < 120, "the world didn't settle: {}", stats);
+            std::thread::sleep(std::time::Duration::from_millis(5));
+        }
+    }
+
+    //Every chunk in the generation radius is loaded and nothing else, and only the ones in the render distance are meshed
+    fn AssertLoaded(world: &World, target: (i32, i32)) {
+        let radius = world.GetRenderDistance() as i32;
+        let genRadius = radius + 1;
+
+        let mut expected: Vec<(i32, i32)> = Vec::new();
+        for a in -genRadius..=genRadius {
+            for b in -genRadius..=genRadius {
+                let pos = (target.0 + a, target.1 + b);
+                if InRadius(pos, target, genRadius) {
+                    expected.push(pos);
+                }
+            }
+        }
+        let mut loaded: Vec<(i32, i32)> = world.Chunks.keys().map(|pos| (pos.x, pos.y)).collect();
+        expected.sort();
+        loaded.sort();
+        assert_eq!(loaded, expected, "render distance {} around {:?}", radius, target);
+
+        for (pos, chunk) in &world.Chunks {
+            let rendered = InRadius((pos.x, pos.y), target, radius);
+            assert_eq!(chunk.Status == ChunkState::Meshed, rendered, "chunk {:?} at render distance {}", pos, radius);
+        }
+    }
+
+    #[test]
+    fn RenderDistanceGrowsAndShrinksAroundTheTarget() {
+        let mut world = TestWorld();
+        let target = (9, -6);
+        Settle(&mut world, target);
+        assert_eq!(world.GetTargetPosition(), target);
+        AssertLoaded(&world, target);
+
+        for distance in [3, 5, 2, 0, 4] {
+            world.RenderDistanceUpdate(distance).unwrap();
+            //shrinking unloads straight away
+            assert!(world.Chunks.keys().all(|pos| InRadius((pos.x, pos.y), target, distance as i32 + 1)));
+            Settle(&mut world, target);
+            AssertLoaded(&world, target);
+        }
+    }
+
+    #[test]
+    fn RenderDistanceSteps() {
+        let mut world = TestWorld();
+        let target = (-4, 7);
+        Settle(&mut world, target);
+
+        world.RenderDistanceStep(2);
+        assert_eq!(world.GetRenderDistance(), 3);
+        Settle(&mut world, target);
+        AssertLoaded(&world, target);
+
+        world.RenderDistanceStep(-10);
+        assert_eq!(world.GetRenderDistance(), 0);
+        Settle(&mut world, target);
+        AssertLoaded(&world, target);
+
+        assert!(world.RenderDistanceUpdate(MAX_RENDER_DISTANCE + 1).is_err());
+        assert_eq!(world.GetRenderDistance(), 0);
+    }
+
+    #[test]
+    fn FluidsArentSolid() {
+        let mut world = TestWorld();
+        world.RenderDistanceUpdate(0).unwrap();
+        Settle(&mut world, (0, 0));
+
+        let water = Block { ID: world.GetBlockRegistry().NameToID("core:water").unwrap() };
+        let stone = Block { ID: world.GetBlockRegistry().NameToID("core:stone").unwrap() };
+        assert!(!world.GetBlockRegistry().IsSolid(water) && !world.GetBlockRegistry().IsSolid(Block::Air()));
+        assert!(world.GetBlockRegistry().IsSolid(stone));
+
+        //two blocks of water on top of the ground
+        let (x, z) = (3, 3);
+        let ground = (0..CHUNK_BOUNDS_Y as i32 - 3).rev().find(|y| world.IsSolidBlock((x, *y, z))).unwrap();
+        for y in [ground + 1, ground + 2] {
+            if world.GetBlock((x, y, z)) != Some(Block::Air()) {
+                world.BreakBlock((x, y, z)).unwrap();
+            }
+            assert!(world.PlaceBlock((x, y, z), water, None));
+        }
+
+        //looking down through the water hits the ground, with the water as the cell to place into
+        let eye = na::Vector3::new(x as f32 + 0.5, ground as f32 + 4.5, z as f32 + 0.5);
+        let hit = world.TargetBlock(eye, na::Vector3::new(0f32, -1f32, 0f32)).unwrap();
+        assert_eq!(hit, BlockHit { Position: (x, ground, z), Previous: (x, ground + 1, z) });
+        assert_eq!(world.MineBlock((x, ground + 1, z), 100f32), MiningProgress::Idle);
+        assert_eq!(world.GetBlock((x, ground + 1, z)), Some(water));
+
+        //items sink through it and land on the ground
+        world.DroppedItems.push(DroppedItem::New(eye, ItemStack::Empty()));
+        for _ in 0..200 {
+            world.UpdateDroppedItems(0.01);
+        }
+        assert_eq!(world.DroppedItems[0].Position.y.floor() as i32, ground + 1);
+
+        //placing a block into water replaces it
+        assert!(world.PlaceBlock(hit.Previous, stone, None));
+        assert_eq!(world.GetBlock(hit.Previous), Some(stone));
+    }
+
+    #[test]
+    fn HooksKeepStaticStateStatic() {
+        let mut world = TestWorld();
+        world.RenderDistanceUpdate(0).unwrap();
+        Settle(&mut world, (0, 0));
+
+        let (x, z) = (5, 5);
+        let ground = (0..CHUNK_BOUNDS_Y as i32).rev().find(|y| world.IsSolidBlock((x, *y, z))).unwrap();
+        let pos = (x, ground, z);
+        let value = |chunk: &Chunk| chunk.StaticState.values().next().and_then(|state| state.get("Value")).and_then(|v| v.AsFloat()).cloned();
+
+        let (chunk, local) = world.ChunkAt(pos).unwrap();
+        chunk.StaticState.clear();
+        chunk.DynamicState.clear();
+        chunk.PutBlockState(local, HashMap::from([(String::from("Value"), State::FloatAttribute(1f32))]), StateStore::Static);
+
+        world.RunBlockHook(pos, |_, _, state| state.insert(String::from("Value"), State::FloatAttribute(2f32))).unwrap();
+        let chunk = world.ChunkAt(pos).unwrap().0;
+        assert!(chunk.DynamicState.is_empty());
+        assert_eq!(value(chunk), Some(2f32));
+
+        assert!(world.SetBlockState(pos, HashMap::from([(String::from("Value"), State::FloatAttribute(3f32))])));
+        let chunk = world.ChunkAt(pos).unwrap().0;
+        assert!(chunk.DynamicState.is_empty());
+        assert_eq!(value(chunk), Some(3f32));
+
+        //a block without state that a hook gives some is ticked from then on
+        let below = (x, ground - 1, z);
+        world.RunBlockHook(below, |_, _, state| state.insert(String::from("Value"), State::FloatAttribute(4f32))).unwrap();
+        assert_eq!(world.ChunkAt(pos).unwrap().0.DynamicState.len(), 1);
+    }
+
+    #[test]
+    fn EditsDontWaitForWorkers() {
+        let mut world = TestWorld();
+        world.RenderDistanceUpdate(0).unwrap();
+        Settle(&mut world, (0, 0));
+
+        //a mesh job holding the chunk keeps the copy it was given
+        let held = world.Chunks[&na::Vector2::new(0, 0)].clone();
+        let (x, z) = (6, 6);
+        let ground = (0..CHUNK_BOUNDS_Y as i32).rev().find(|y| world.IsSolidBlock((x, *y, z))).unwrap();
+        let block = world.GetBlock((x, ground, z)).unwrap();
+
+        assert!(world.BreakBlock((x, ground, z)).is_some());
+        assert_eq!(world.GetBlock((x, ground, z)), Some(Block::Air()));
+        assert_eq!(held.GetBlockAt((x as u32, ground as u32, z as u32)), block);
+        assert!(world.RenderList.contains(&(world.Chunks[&na::Vector2::new(0, 0)].as_ref() as *const Chunk)));
+        assert!(!world.RenderList.contains(&(held.as_ref() as *const Chunk)));
+
+        assert!(world.PlaceBlock((x, ground, z), block, None));
+        assert_eq!(world.GetBlock((x, ground, z)), Some(block));
+    }
+
+    #[test]
+    fn DoorsArePlacedWholeOrNotAtAll() {
+        let mut world = TestWorld();
+        world.RenderDistanceUpdate(0).unwrap();
+        Settle(&mut world, (0, 0));
+
+        let door = Block { ID: world.GetBlockRegistry().NameToID("core:oak_door").unwrap() };
+        let water = Block { ID: world.GetBlockRegistry().NameToID("core:water").unwrap() };
+        let stone = Block { ID: world.GetBlockRegistry().NameToID("core:stone").unwrap() };
+        let placement = Placement { Face: 2, Facing: 4, Upper: false };
+        let upper = |world: &World, pos: (i32, i32, i32)| {
+            let (chunkPos, local) = ToChunkCoordinate(pos).unwrap();
+            world.Chunks[&chunkPos].GetBlockState(local).and_then(|state| state.get(UPPER)).and_then(|s| s.AsBool()).copied()
+        };
+
+        let (x, z) = (4, 9);
+        let ground = (0..CHUNK_BOUNDS_Y as i32 - 4).rev().find(|y| world.IsSolidBlock((x, *y, z))).unwrap();
+        let (bottom, top) = ((x, ground + 1, z), (x, ground + 2, z));
+        for pos in [bottom, top, (x, ground + 3, z)] {
+            if world.GetBlock(pos) != Some(Block::Air()) {
+                world.BreakBlock(pos).unwrap();
+            }
+        }
+
+        //the top half replaces the water above
+        assert!(world.PlaceBlock(top, water, None));
+        assert!(world.PlaceBlock(bottom, door, Some(&placement)));
+        assert_eq!((world.GetBlock(bottom), world.GetBlock(top)), (Some(door), Some(door)));
+        assert_eq!((upper(&world, bottom), upper(&world, top)), (Some(false), Some(true)));
+
+        //breaking either half breaks the other
+        world.BreakBlock(top).unwrap();
+        assert_eq!((world.GetBlock(bottom), world.GetBlock(top)), (Some(Block::Air()), Some(Block::Air())));
+
+        //without room for the top half nothing is left behind
+        assert!(world.PlaceBlock(top, stone, None));
+        world.PlaceBlock(bottom, door, Some(&placement));
+        assert_eq!((world.GetBlock(bottom), world.GetBlock(top)), (Some(Block::Air()), Some(stone)));
+    }
+
+    #[test]
+    fn MiningBreaksOnce() {
+        let mut world = TestWorld();
+        world.RenderDistanceUpdate(0).unwrap();
+        Settle(&mut world, (0, 0));
+
+        let (x, z) = (2, 12);
+        let ground = (0..CHUNK_BOUNDS_Y as i32).rev().find(|y| world.IsSolidBlock((x, *y, z))).unwrap();
+        let pos = (x, ground, z);
+
+        assert!(matches!(world.MineBlock(pos, 0f32), MiningProgress::Mining(_)));
+        assert!(world.Mining.IsTarget(pos));
+        assert_eq!(world.MineBlock(pos, 1000f32), MiningProgress::Broken);
+        assert_eq!(world.GetBlock(pos), Some(Block::Air()));
+        assert_eq!(world.Mining.Target(), None);
+        assert_eq!(world.MineBlock(pos, 1000f32), MiningProgress::Idle);
+    }
+
+    #[test]
+    fn ReopenedWorldsGenerateTheSameTerrain() {
+        let dir = std::env::temp_dir().join(format!("minecraft_gl_world_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let open = || {
+            let (crafting, smelting, blocks, items, packs) = TestRegistries();
+            let mut world = World::New(crafting, smelting, blocks, items, &packs, dir.clone());
+            world.RenderDistanceUpdate(0).unwrap();
+            Settle(&mut world, (0, 0));
+            world
+        };
+
+        //one chunk is changed and saved, its neighbour is left alone and so never saved
+        let mut world = open();
+        let (x, z) = (7, 7);
+        let ground = (0..CHUNK_BOUNDS_Y as i32).rev().find(|y| world.IsSolidBlock((x, *y, z))).unwrap();
+        world.BreakBlock((x, ground, z)).unwrap();
+        assert!(!world.Chunks[&na::Vector2::new(1, 0)].Modified);
+        let untouched = world.Chunks[&na::Vector2::new(1, 0)].Blocks.clone();
+        let seed = world.GetSeed();
+        world.Save().unwrap();
+        drop(world);
+
+        let world = open();
+        assert_eq!(world.GetSeed(), seed);
+        assert_eq!(world.GetBlock((x, ground, z)), Some(Block::Air()));
+        assert!(world.Chunks[&na::Vector2::new(1, 0)].Blocks == untouched);
+        drop(world);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn PointsAndBlocksAgreeOnTheirChunk() {
+        for (x, z) in [(0f32, 0f32), (15.9, 16.0), (-0.1, -16.0), (-16.0, -15.9), (-17.5, 31.0), (-32.0, -33.0)] {
+            let (chunkPos, _) = ToChunkCoordinate((x.floor() as i32, 0, z.floor() as i32)).unwrap();
+            assert_eq!(ToChunkPos((x, z)), (chunkPos.x, chunkPos.y), "({}, {})", x, z);
+        }
+        assert_eq!(ToChunkPos((-(CHUNK_BOUNDS_X as f32), CHUNK_BOUNDS_Z as f32)), (-1, 1));
+    }
+}
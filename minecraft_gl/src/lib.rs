@@ -0,0 +1,20 @@
+#![allow(non_snake_case)] 
+#![allow(dead_code)]
+#![feature(trace_macros)]
+#![feature(core_intrinsics)]
+#![feature(concat_idents)]
+#![feature(cstr_from_bytes_until_nul)]
+#![feature(const_type_id)]
+
+#[macro_use]
+pub extern crate glium;
+pub extern crate image;
+pub extern crate nalgebra;
+
+pub mod Core;
+pub mod Event;
+pub mod Util;
+pub mod Scene;
+pub mod Ecs;
+pub mod World;
+pub mod Renderer;
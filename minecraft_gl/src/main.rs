@@ -1,34 +1,68 @@
-
 #![allow(non_snake_case)] 
-#![allow(dead_code)]
-#![feature(trace_macros)]
-#![feature(core_intrinsics)]
-#![feature(concat_idents)]
-#![feature(cstr_from_bytes_until_nul)]
-#![feature(const_type_id)]
-
-#[macro_use]
-pub extern crate glium;
-pub extern crate image;
-pub extern crate nalgebra;
-
-mod Core;
-mod Event;
-mod Util;
-mod Scene;
-mod Ecs;
-mod World;
-mod Renderer;
+
+use std::path::PathBuf;
+use minecraft_gl::Core::{application::Application, headless::{self, HeadlessConfig}};
+use minecraft_gl::World::{schema, validate, contentPack::ContentPacks};
+use minecraft_gl::Util::resource::AssetPath;
 
 
 fn main() {
     std::env::set_var("RUST_BACKTRACE", "1");
-    let app = Core::application::Application::New();
-    app.Run();
-   
-}
 
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    //Lints all of the content and exits. Nonzero exit code if anything is wrong, for CI
+    if args.first().map_or(false, |a| a == "validate") {
+        let packs = LoadPacks(&args);
+        println!("{}", packs.Report());
 
+        let report = validate::ValidateContent(&packs);
+        for issue in &report.Issues {
+            println!("{}", issue);
+        }
+        println!("Checked {} files: {} errors, {} warnings", report.FilesChecked, report.NumErrors(), report.NumWarnings());
+        std::process::exit(if report.NumErrors() > 0 { 1 } else { 0 });
+    }
 
+    //Writes JSON Schemas for the block, item and biome files so editors can validate them
+    if let Some(idx) = args.iter().position(|a| a == "--export-schemas") {
+        let dir = args.get(idx + 1).map_or(AssetPath("data/schema"), PathBuf::from);
+        if let Err(msg) = schema::WriteJsonSchemas(&dir) {
+            eprintln!("{}", msg);
+            std::process::exit(1);
+        }
+        println!("Wrote json schemas to {}", dir.display());
+        return;
+    }
+    if args.iter().any(|a| a == "--headless") {
+        let config = match HeadlessConfig::FromArgs(&args) {
+            Ok(val) => val,
+            Err(msg) => {
+                eprintln!("{}", msg);
+                std::process::exit(2);
+            }
+        };
 
+        if let Err(msg) = headless::Run(config) {
+            eprintln!("{}", msg);
+            std::process::exit(1);
+        }
+        return;
+    }
 
+    let app = Application::New(LoadPacks(&args));
+    app.Run();
+   
+}
+
+//--pack DIR can be given any number of times. The packs load in that order, after the core pack
+fn LoadPacks(args: &[String]) -> ContentPacks {
+    let roots: Vec<PathBuf> = args.windows(2).filter(|w| w[0] == "--pack").map(|w| PathBuf::from(&w[1])).collect();
+    match ContentPacks::Load(&roots) {
+        Ok(val) => val,
+        Err(msg) => {
+            eprintln!("{}", msg);
+            std::process::exit(1);
+        }
+    }
+}